@@ -0,0 +1,86 @@
+//! run with `cargo bench`, dataset sizes are taken from IPV4_CLASSIFY_BENCH_SIZES
+//! e.g. IPV4_CLASSIFY_BENCH_SIZES=10000,1000000,10000000 - big ones take a while
+use std::{env, hint::black_box, str::FromStr};
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use ipv4_classify::{AddressTree, Subnet};
+
+fn sizes() -> Vec<usize> {
+    env::var("IPV4_CLASSIFY_BENCH_SIZES")
+        .unwrap_or_else(|_| "10000".to_string())
+        .split(',')
+        .map(|size| size.trim().parse().expect("bench size should be a number"))
+        .collect()
+}
+
+/// addresses clustered by 100 in /24 subnets
+fn dataset(size: usize) -> Vec<String> {
+    ipv4_classify::generate(size.div_ceil(100), 100, 24, 42).unwrap()
+}
+
+fn tree_of(addrs: &[String]) -> AddressTree {
+    let mut tree = AddressTree::new();
+    for addr in addrs {
+        tree.push(Subnet::from_str(addr).unwrap()).unwrap();
+    }
+    tree
+}
+
+fn parse(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse");
+    for size in sizes() {
+        let addrs = dataset(size);
+        group.bench_with_input(BenchmarkId::from_parameter(size), &addrs, |b, addrs| {
+            b.iter(|| {
+                for addr in addrs {
+                    black_box(Subnet::from_str(addr).unwrap());
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+fn push(c: &mut Criterion) {
+    let mut group = c.benchmark_group("push");
+    group.sample_size(10);
+    for size in sizes() {
+        let addrs = dataset(size);
+        group.bench_with_input(BenchmarkId::from_parameter(size), &addrs, |b, addrs| {
+            b.iter(|| black_box(tree_of(addrs)))
+        });
+    }
+    group.finish();
+}
+
+fn extend_sorted(c: &mut Criterion) {
+    let mut group = c.benchmark_group("extend_sorted");
+    group.sample_size(10);
+    for size in sizes() {
+        // normalized lists are sorted
+        let (addrs, _) = ipv4_classify::normalize_lines(dataset(size));
+        group.bench_with_input(BenchmarkId::from_parameter(size), &addrs, |b, addrs| {
+            b.iter(|| {
+                let mut tree = AddressTree::new();
+                tree.extend_sorted(addrs.iter().map(|addr| Subnet::from_str(addr).unwrap()))
+                    .unwrap();
+                black_box(tree)
+            })
+        });
+    }
+    group.finish();
+}
+
+fn get_subnets(c: &mut Criterion) {
+    let mut group = c.benchmark_group("get_subnets");
+    for size in sizes() {
+        let tree = tree_of(&dataset(size));
+        group.bench_with_input(BenchmarkId::from_parameter(size), &tree, |b, tree| {
+            b.iter(|| black_box(tree.get_subnets_map()))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, parse, push, extend_sorted, get_subnets);
+criterion_main!(benches);
@@ -0,0 +1,255 @@
+#[cfg(feature = "fs")]
+use std::path::Path;
+use std::{collections::HashMap, str::FromStr};
+
+#[cfg(feature = "fs")]
+use crate::content::FileContent;
+#[cfg(feature = "whois")]
+use crate::whois::{first_field, netblock_of, Whois};
+use crate::{io::addresses_in, parse_weighted_line, prefix_map::PrefixMap, Error, Subnet};
+
+/// the report text unless the user supplies their own template
+pub const DEFAULT_TEMPLATE: &str = "To: {email}
+Subject: Abuse from {netblock}
+
+Hello {org},
+
+the following {count} addresses of {netblock}, which you are responsible for,
+attacked our network:
+
+{addresses}
+
+Log lines attached as evidence:
+
+{evidence}
+";
+
+/// who to complain to about a network
+#[derive(Debug, Clone, PartialEq)]
+pub struct AbuseContact {
+    pub netblock: Subnet, // the network the contact is responsible for
+    pub org: String,
+    pub email: String,
+}
+
+/// known abuse contacts with an optional fallback to whois for the rest
+#[derive(Debug, Default)]
+pub struct AbuseContacts {
+    known: PrefixMap<(String, String)>, // org and email by network
+    #[cfg(feature = "whois")]
+    whois: Option<Whois>, // client to look the rest up with
+}
+
+impl AbuseContacts {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// # parse contacts formatted as: CIDR = org <email>
+    /// empty lines and lines starting with # are skipped
+    /// # returns
+    /// Err - if some line has no = or no <email>, or its CIDR can't be parsed
+    pub fn from_lines(lines: impl IntoIterator<Item = impl AsRef<str>>) -> Result<Self, Error> {
+        let mut contacts = Self::new();
+        for line in lines {
+            let line = line.as_ref().trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let parsed = line.split_once('=').and_then(|(subnet, contact)| {
+                let (org, email) = contact.trim().strip_suffix('>')?.rsplit_once('<')?;
+                Some((subnet.trim(), org.trim(), email.trim()))
+            });
+            let Some((subnet, org, email)) = parsed else {
+                return Err(Error::ParseError {
+                    input: line.to_string(),
+                    reason: "contact should look like CIDR = org <email>".to_string(),
+                });
+            };
+            contacts.insert(&Subnet::from_str(subnet)?, org, email);
+        }
+        Ok(contacts)
+    }
+
+    /// look up contacts missing in the list with the whois client, within its rate limit, e.g.
+    /// Whois::new(IANA_WHOIS).with_rate_limit(limits.get("whois"))
+    #[cfg(feature = "whois")]
+    pub fn with_whois(mut self, whois: Whois) -> Self {
+        self.whois = Some(whois);
+        self
+    }
+
+    /// add a contact, the last one wins for the same network
+    pub fn insert(&mut self, subnet: &Subnet, org: impl Into<String>, email: impl Into<String>) {
+        self.known.insert(subnet, (org.into(), email.into()));
+    }
+
+    /// # contact of the most specific network containing the address,
+    /// whois answers are remembered, so the rest of the network is never looked up again
+    /// # returns
+    /// None - if neither the list nor whois knows the contact, or the whois quota of the day is used up
+    /// Err - if the whois server can't be reached
+    pub fn contact_of(&mut self, addr: &Subnet) -> Result<Option<AbuseContact>, Error> {
+        if let Some((netblock, (org, email))) = self.known.lookup(addr) {
+            return Ok(Some(AbuseContact {
+                netblock,
+                org: org.clone(),
+                email: email.clone(),
+            }));
+        }
+        #[cfg(feature = "whois")]
+        if let Some(whois) = &mut self.whois {
+            let contact = match whois.answer_of(addr) {
+                Ok(answer) => answer.and_then(|response| parse_whois(&response, addr)),
+                // the rest is left unresolved rather than asked beyond the limit
+                Err(Error::QuotaExceeded(_)) => None,
+                Err(e) => return Err(e),
+            };
+            if let Some(contact) = &contact {
+                self.insert(&contact.netblock, &contact.org, &contact.email);
+            }
+            return Ok(contact);
+        }
+        Ok(None)
+    }
+
+    /// # group the addresses by the networks of their abuse contacts,
+    /// evidence lines go to the reports of the addresses they mention
+    /// empty address lines are skipped, the rest may have hits e.g. 1.2.3.4,573
+    /// # returns
+    /// Err - if some address line can't be parsed or whois can't be reached
+    pub fn report_lines(
+        &mut self,
+        lines: impl IntoIterator<Item = impl AsRef<str>>,
+        evidence: impl IntoIterator<Item = impl AsRef<str>>,
+    ) -> Result<Abuse, Error> {
+        let mut addrs = vec![];
+        for line in lines {
+            let line = line.as_ref().trim();
+            if !line.is_empty() {
+                addrs.push(parse_weighted_line(line)?.0);
+            }
+        }
+        addrs.sort_by_key(|addr| addr.bits);
+        addrs.dedup();
+
+        let mut abuse = Abuse {
+            reports: vec![],
+            unresolved: vec![],
+        };
+        let mut report_of = HashMap::new(); // address bits -> index of its report
+        for addr in addrs {
+            let Some(contact) = self.contact_of(&addr)? else {
+                abuse.unresolved.push(addr);
+                continue;
+            };
+            let i = match abuse
+                .reports
+                .iter()
+                .position(|report| report.contact == contact)
+            {
+                Some(i) => i,
+                None => {
+                    abuse.reports.push(AbuseReport {
+                        contact,
+                        addresses: vec![],
+                        evidence: vec![],
+                    });
+                    abuse.reports.len() - 1
+                }
+            };
+            report_of.insert(addr.bits, i);
+            abuse.reports[i].addresses.push(addr);
+        }
+        for line in evidence {
+            let line = line.as_ref().trim();
+            let mut reports: Vec<usize> = addresses_in(line)
+                .filter_map(|addr| report_of.get(&addr.bits).copied())
+                .collect();
+            reports.sort();
+            reports.dedup();
+            for i in reports {
+                abuse.reports[i].evidence.push(line.to_string());
+            }
+        }
+        Ok(abuse)
+    }
+}
+
+/// addresses grouped by the networks responsible for them
+#[derive(Debug, PartialEq)]
+pub struct Abuse {
+    pub reports: Vec<AbuseReport>, // in the order of their first address
+    pub unresolved: Vec<Subnet>,   // addresses nobody is known to be responsible for
+}
+
+/// everything to complain about to a single contact
+#[derive(Debug, PartialEq)]
+pub struct AbuseReport {
+    pub contact: AbuseContact,
+    pub addresses: Vec<Subnet>, // in address order
+    pub evidence: Vec<String>,  // log lines mentioning the addresses
+}
+
+impl AbuseReport {
+    /// # fill the template in, see DEFAULT_TEMPLATE for the placeholders
+    /// {addresses} and {evidence} become a line per item
+    pub fn render(&self, template: &str) -> String {
+        let addresses: Vec<String> = self.addresses.iter().map(Subnet::to_string).collect();
+        template
+            .replace("{email}", &self.contact.email)
+            .replace("{org}", &self.contact.org)
+            .replace("{netblock}", &self.contact.netblock.to_string())
+            .replace("{count}", &self.addresses.len().to_string())
+            .replace("{addresses}", &addresses.join("\n"))
+            .replace("{evidence}", &self.evidence.join("\n"))
+    }
+}
+
+/// same as AbuseContacts::report_lines for the addresses and the evidence read from the files
+#[cfg(feature = "fs")]
+pub fn find_abuse(
+    contacts: &mut AbuseContacts,
+    file_names: impl IntoIterator<Item = impl AsRef<Path>>,
+    evidence_files: impl IntoIterator<Item = impl AsRef<Path>>,
+) -> Result<Abuse, Error> {
+    let contents = file_names
+        .into_iter()
+        .map(|file_name| FileContent::read(file_name.as_ref()))
+        .collect::<Result<Vec<_>, _>>()?;
+    let evidence = evidence_files
+        .into_iter()
+        .map(|file_name| FileContent::read(file_name.as_ref()))
+        .collect::<Result<Vec<_>, _>>()?;
+    contacts.report_lines(
+        contents.iter().flat_map(|content| content.split('\n')),
+        evidence.iter().flat_map(|content| content.split('\n')),
+    )
+}
+
+/// # contact of a registry's answer, the formats of ARIN, RIPE, APNIC, AFRINIC and LACNIC are understood
+/// # returns
+/// None - if the answer has no network or abuse email
+#[cfg(feature = "whois")]
+pub(crate) fn parse_whois(response: &str, addr: &Subnet) -> Option<AbuseContact> {
+    let netblock = netblock_of(response, addr)?;
+    // RIPE puts it into a comment: % Abuse contact for '193.0.0.0 - 193.0.7.255' is 'abuse@ripe.net'
+    let comment = response
+        .lines()
+        .filter(|line| line.contains("Abuse contact for"))
+        .find_map(|line| line.trim_end().strip_suffix('\'')?.rsplit_once('\''))
+        .map(|(_, email)| email);
+    let email = first_field(response, &["OrgAbuseEmail", "abuse-mailbox"])
+        .or(comment)
+        .or_else(|| first_field(response, &["e-mail"]))?;
+    let org = first_field(
+        response,
+        &["OrgName", "org-name", "owner", "descr", "netname"],
+    )
+    .unwrap_or(email);
+    Some(AbuseContact {
+        netblock,
+        org: org.to_string(),
+        email: email.to_string(),
+    })
+}
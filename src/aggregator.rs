@@ -0,0 +1,135 @@
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::{AddressSource, AddressTree, CancelToken, Error};
+
+/// # classifies an endless source, e.g. FollowSource::stdin or a SyslogListener, handing out
+/// snapshots of the tree every N addresses and/or every so often instead of a single report at the end
+/// bound the tree with AddressTree::limit_memory to keep it from growing with the input
+pub struct Aggregator {
+    address_tree: AddressTree,
+    every_addresses: Option<usize>,
+    every: Option<Duration>,
+    decay: Option<f64>, // applied to the hits after every snapshot, see AddressTree::decay
+    ttl: Option<Duration>, // how long an address is kept since it was seen last
+    expired_at: i64,    // unix second of the last expiry, it's done at most once a second
+}
+
+impl Aggregator {
+    /// aggregate into the tree, e.g. a loaded one or one with a memory limit
+    pub fn new(address_tree: AddressTree) -> Self {
+        Self {
+            address_tree,
+            every_addresses: None,
+            every: None,
+            decay: None,
+            ttl: None,
+            expired_at: 0,
+        }
+    }
+
+    /// take a snapshot once N more addresses are pushed
+    pub fn every_addresses(mut self, addresses: usize) -> Self {
+        self.every_addresses = Some(addresses.max(1));
+        self
+    }
+
+    /// take a snapshot once the interval passes, it's checked between the batches of the source
+    pub fn every(mut self, interval: Duration) -> Self {
+        self.every = Some(interval);
+        self
+    }
+
+    /// # multiply hits of the leafs by the factor after every snapshot, so addresses not seen again fade out
+    /// # Panics
+    /// if the factor isn't within 0.0..=1.0
+    pub fn decay(mut self, factor: f64) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&factor),
+            "a decay factor should be within 0.0..=1.0"
+        );
+        self.decay = Some(factor);
+        self
+    }
+
+    /// # keep only the addresses seen within the TTL, older ones age out of the tree as the source goes,
+    /// the leafs are stamped with the time their batch arrives, see AddressTree::track_seen
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    pub fn tree(&self) -> &AddressTree {
+        &self.address_tree
+    }
+
+    pub fn into_tree(self) -> AddressTree {
+        self.address_tree
+    }
+
+    /// # push addresses of the source into the tree until it's exhausted or the token is cancelled,
+    /// the tree is handed to on_snapshot as often as asked and once more at the end if anything is new
+    /// # returns
+    /// Err - if the source fails, the tree refuses an address or on_snapshot fails
+    pub fn run(
+        &mut self,
+        mut source: impl AddressSource,
+        token: &CancelToken,
+        mut on_snapshot: impl FnMut(&AddressTree) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        let mut pushed = 0; // since the last snapshot
+        let mut last_snapshot = Instant::now();
+        while let Some(batch) = source.next_batch()? {
+            if token.is_cancelled() {
+                break;
+            }
+            self.expire();
+            for addr in batch {
+                self.address_tree.push(addr)?;
+                pushed += 1;
+                if self.every_addresses.is_some_and(|every| pushed >= every) {
+                    self.snapshot(&mut on_snapshot)?;
+                    (pushed, last_snapshot) = (0, Instant::now());
+                }
+            }
+            if self
+                .every
+                .is_some_and(|every| last_snapshot.elapsed() >= every)
+            {
+                if pushed > 0 {
+                    self.snapshot(&mut on_snapshot)?;
+                }
+                (pushed, last_snapshot) = (0, Instant::now());
+            }
+        }
+        if pushed > 0 {
+            on_snapshot(&self.address_tree)?;
+        }
+        Ok(())
+    }
+
+    /// stamp the leafs pushed from now on and drop the ones older than the TTL if there is one
+    fn expire(&mut self) {
+        let Some(ttl) = self.ttl else {
+            return;
+        };
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |now| now.as_secs() as i64);
+        self.address_tree.track_seen(now);
+        if now != self.expired_at {
+            self.address_tree.expire(now - ttl.as_secs() as i64);
+            self.expired_at = now;
+        }
+    }
+
+    fn snapshot(
+        &mut self,
+        on_snapshot: &mut impl FnMut(&AddressTree) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        on_snapshot(&self.address_tree)?;
+        if let Some(factor) = self.decay {
+            self.address_tree.decay(factor);
+        }
+        Ok(())
+    }
+}
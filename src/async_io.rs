@@ -0,0 +1,43 @@
+use std::path::Path;
+
+use crate::{
+    io::LineReading, AddressTree, CancelToken, Error, GroupBy, Observer, Pipeline, StderrLogger,
+    SubnetsMap,
+};
+
+/// async variant of find_subnets, files are read with tokio
+/// has to be called from within a tokio runtime
+pub async fn find_subnets_async(
+    file_names: impl IntoIterator<Item = impl AsRef<Path>>,
+) -> Result<SubnetsMap, Error> {
+    find_groups_async(file_names, &GroupBy::Subnet).await
+}
+
+/// async variant of find_groups, files are read with tokio
+/// has to be called from within a tokio runtime
+pub async fn find_groups_async(
+    file_names: impl IntoIterator<Item = impl AsRef<Path>>,
+    group_by: &GroupBy,
+) -> Result<SubnetsMap, Error> {
+    let mut address_tree = AddressTree::new();
+    let mut logger = StderrLogger;
+    let reading = LineReading {
+        lenient: false,
+        token: &CancelToken::new(),
+        pipeline: &Pipeline::new(),
+    };
+
+    for file_name in file_names {
+        let file_name = file_name.as_ref();
+        logger.on_file_started(file_name);
+        let content = tokio::fs::read_to_string(file_name).await?;
+        reading.push(
+            &mut address_tree,
+            file_name,
+            &content,
+            &mut logger,
+            &mut |_| true,
+        )?;
+    }
+    Ok(address_tree.get_groups_map(group_by))
+}
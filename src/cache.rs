@@ -0,0 +1,74 @@
+use crate::{prefix_map::PrefixMap, IpMetadata, Subnet};
+
+/// # metadata by announced prefix, so a lookup of one address answers for the rest of its prefix
+/// the hostname is of a single address, so it's never cached
+#[derive(Debug, Default)]
+pub struct PrefixCache {
+    known: PrefixMap<IpMetadata>,
+    len: usize,
+    hits: usize,
+    misses: usize,
+}
+
+impl PrefixCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// # metadata of the most specific cached prefix containing the address, counted as a hit or a miss
+    /// # returns
+    /// None - if no cached prefix contains the address
+    pub fn get(&mut self, addr: &Subnet) -> Option<IpMetadata> {
+        match self.known.lookup(addr) {
+            Some((_, ip)) => {
+                self.hits += 1;
+                Some(ip.clone())
+            }
+            None => {
+                self.misses += 1;
+                None
+            }
+        }
+    }
+
+    /// whether some cached prefix contains the address, it isn't counted as a lookup
+    pub fn contains(&self, addr: &Subnet) -> bool {
+        self.known.lookup(addr).is_some()
+    }
+
+    /// # remember the metadata for its announced prefix, the last one wins for the same prefix
+    /// # returns
+    /// false - if there is no announced prefix to remember it by
+    pub fn insert(&mut self, ip: &IpMetadata) -> bool {
+        let Some(prefix) = &ip.announced_prefix else {
+            return false;
+        };
+        let ip = IpMetadata {
+            hostname: None,
+            ..ip.clone()
+        };
+        if self.known.insert(prefix, ip).is_none() {
+            self.len += 1;
+        }
+        true
+    }
+
+    /// number of the cached prefixes
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// lookups answered from the cache
+    pub fn hits(&self) -> usize {
+        self.hits
+    }
+
+    /// lookups the cache had nothing for
+    pub fn misses(&self) -> usize {
+        self.misses
+    }
+}
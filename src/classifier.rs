@@ -0,0 +1,170 @@
+use std::{io::Write, str::FromStr};
+
+use crate::{
+    read_sources, AddressSource, AddressTree, CancelToken, Error, Exporters, GroupBy, Labels,
+    Metadata, Report, Scoring, Subnet, SubnetSet, SubnetsMap, WeightsMap,
+};
+
+/// enrichment a Classifier runs on the addresses found, e.g. Whois or RipeStat
+pub trait EnrichmentProvider {
+    /// # fill the metadata of the addresses in, values the metadata has already are kept
+    /// # returns
+    /// Err - if the provider can't be asked
+    fn enrich(&mut self, metadata: &mut Metadata, addrs: &[Subnet]) -> Result<(), Error>;
+}
+
+/// # sources, the tree, enrichment and exporters put together, e.g.
+/// Classifier::new().source(LineSource::stdin()).exclude_private().enrich(Whois::new(IANA_WHOIS)).run()?
+/// the addresses are grouped into subnets by default
+#[derive(Default)]
+pub struct Classifier {
+    sources: Vec<Box<dyn AddressSource>>, // read in the order they're added
+    excluded: Vec<Subnet>,                // addresses never pushed into the tree
+    group_by: GroupBy,
+    labels: Labels,
+    metadata: Metadata,
+    providers: Vec<Box<dyn EnrichmentProvider>>, // run in the order they're added
+    top: Option<usize>,
+    scoring: Option<Scoring>,
+}
+
+impl Classifier {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn source(mut self, source: impl AddressSource + 'static) -> Self {
+        self.sources.push(Box::new(source));
+        self
+    }
+
+    /// leave the addresses of the subnet out, e.g. own networks
+    pub fn exclude(mut self, subnet: Subnet) -> Self {
+        self.excluded.push(subnet);
+        self
+    }
+
+    /// leave out the private address space of RFC 1918
+    pub fn exclude_private(mut self) -> Self {
+        self.excluded.extend(Subnet::PRIVATE);
+        self
+    }
+
+    pub fn group_by(mut self, group_by: GroupBy) -> Self {
+        self.group_by = group_by;
+        self
+    }
+
+    pub fn labels(mut self, labels: Labels) -> Self {
+        self.labels = labels;
+        self
+    }
+
+    /// start the enrichment from the metadata, e.g. a CSV joined by the addresses
+    pub fn metadata(mut self, metadata: Metadata) -> Self {
+        self.metadata = metadata;
+        self
+    }
+
+    /// ask the provider about the addresses found, providers added earlier take precedence
+    pub fn enrich(mut self, provider: impl EnrichmentProvider + 'static) -> Self {
+        self.providers.push(Box::new(provider));
+        self
+    }
+
+    /// report only N subnets with the most addresses
+    pub fn top(mut self, top: usize) -> Self {
+        self.top = Some(top);
+        self
+    }
+
+    /// score the subnets and report the worst ones first, see Scoring
+    pub fn score(mut self, scoring: Scoring) -> Self {
+        self.scoring = Some(scoring);
+        self
+    }
+
+    /// # read the sources, group the addresses and enrich them
+    /// # returns
+    /// Err - if some source fails or some provider can't be asked
+    pub fn run(self) -> Result<Classified, Error> {
+        let excluded: SubnetSet = self.excluded.into_iter().collect();
+        let sources = self.sources.into_iter().map(|source| Excluding {
+            source,
+            excluded: &excluded,
+        });
+        let mut address_tree = AddressTree::new();
+        read_sources(&mut address_tree, sources, &CancelToken::new(), &mut ())?;
+        for (subnet, label) in self.labels.rules() {
+            address_tree.tag(&subnet, label);
+        }
+        let subnets = address_tree.get_groups_map(&self.group_by);
+        let mut metadata = self.metadata;
+        let addrs: Vec<Subnet> = subnets
+            .values()
+            .flatten()
+            .filter_map(|addr| Subnet::from_str(addr).ok())
+            .collect();
+        for mut provider in self.providers {
+            provider.enrich(&mut metadata, &addrs)?;
+        }
+        Ok(Classified {
+            address_hits: address_tree.get_hits_map(),
+            address_tree,
+            subnets,
+            labels: self.labels,
+            metadata,
+            top: self.top,
+            scoring: self.scoring,
+        })
+    }
+}
+
+/// what a Classifier found
+pub struct Classified {
+    pub address_tree: AddressTree,
+    pub subnets: SubnetsMap,
+    pub labels: Labels,
+    pub metadata: Metadata,
+    address_hits: WeightsMap,
+    top: Option<usize>,
+    scoring: Option<Scoring>,
+}
+
+impl Classified {
+    pub fn report(&self) -> Report<'_> {
+        Report::new(&self.subnets, &self.labels, &self.metadata)
+            .address_hits(Some(&self.address_hits))
+            .top(self.top)
+            .scoring(self.scoring.as_ref())
+    }
+
+    /// # write the report in the format, one of Exporters
+    /// # returns
+    /// Err - if there is no such format or the output can't be written
+    pub fn export(&self, format: &str, out: &mut dyn Write) -> Result<(), Error> {
+        Exporters::new().write(format, &self.report(), out)
+    }
+}
+
+/// the source without the excluded addresses
+struct Excluding<'a> {
+    source: Box<dyn AddressSource>,
+    excluded: &'a SubnetSet,
+}
+
+impl AddressSource for Excluding<'_> {
+    fn name(&self) -> &str {
+        self.source.name()
+    }
+
+    fn next_batch(&mut self) -> Result<Option<Vec<Subnet>>, Error> {
+        let batch = self.source.next_batch()?;
+        Ok(batch.map(|batch| {
+            batch
+                .into_iter()
+                .filter(|addr| !self.excluded.contains(addr))
+                .collect()
+        }))
+    }
+}
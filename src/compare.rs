@@ -0,0 +1,98 @@
+use std::fmt::{Display, Formatter};
+#[cfg(feature = "fs")]
+use std::path::Path;
+
+#[cfg(feature = "fs")]
+use crate::content::FileContent;
+use crate::{membership::Membership, Error, GroupBy};
+
+/// a group which got noticeably more addresses than in the baseline
+#[derive(Debug, PartialEq)]
+pub struct Growth {
+    pub group: String,
+    pub baseline: usize, // distinct addresses in the baseline, 0 for newly active groups
+    pub current: usize,  // distinct addresses now
+}
+
+impl Growth {
+    /// current to baseline ratio, infinite for newly active groups
+    pub fn factor(&self) -> f64 {
+        self.current as f64 / self.baseline as f64
+    }
+}
+
+impl Display for Growth {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+        if self.baseline == 0 {
+            write!(
+                f,
+                "{} subnet is new with {} addresses",
+                self.group, self.current
+            )
+        } else {
+            write!(
+                f,
+                "{} subnet grew from {} to {} addresses (x{:.1})",
+                self.group,
+                self.baseline,
+                self.current,
+                self.factor()
+            )
+        }
+    }
+}
+
+/// # spot groups which became more active than in the baseline, e.g. in recurring log analysis
+/// both lists are classified together, so their groups are the same
+/// # returns
+/// groups with more than factor times the baseline addresses, new ones first, then the fastest growing
+/// Err - if factor is below 1 or some line isn't a correct IP address, see parse_weighted_line
+pub fn compare_lines<L: AsRef<str>>(
+    baseline: impl IntoIterator<Item = L>,
+    current: impl IntoIterator<Item = L>,
+    group_by: &GroupBy,
+    factor: f64,
+) -> Result<Vec<Growth>, Error> {
+    if factor.is_nan() || factor < 1.0 {
+        return Err(Error::InvalidArgument(format!(
+            "growth factor {} should be at least 1",
+            factor
+        )));
+    }
+    let mut membership = Membership::new();
+    membership.add_lines(baseline)?;
+    membership.add_lines(current)?;
+    let mut grown: Vec<Growth> = membership
+        .counts(group_by)
+        .into_iter()
+        .map(|(group, counts)| Growth {
+            group,
+            baseline: counts[0],
+            current: counts[1],
+        })
+        .filter(|growth| growth.current as f64 > growth.baseline as f64 * factor)
+        .collect();
+    grown.sort_by(|g1, g2| {
+        g2.factor()
+            .total_cmp(&g1.factor())
+            .then(g2.current.cmp(&g1.current))
+            .then(g1.group.cmp(&g2.group))
+    });
+    Ok(grown)
+}
+
+/// same as compare_lines for the baseline and the current files
+#[cfg(feature = "fs")]
+pub fn find_growth(
+    baseline: impl AsRef<Path>,
+    current: impl AsRef<Path>,
+    group_by: &GroupBy,
+    factor: f64,
+) -> Result<Vec<Growth>, Error> {
+    compare_lines(
+        FileContent::read(baseline.as_ref())?.split('\n'),
+        FileContent::read(current.as_ref())?.split('\n'),
+        group_by,
+        factor,
+    )
+}
@@ -0,0 +1,424 @@
+use std::path::PathBuf;
+
+use crate::{
+    summary::ORG, Error, GroupBy, Labels, MergeLimits, Metadata, OutputFormat, Pipeline,
+    RateLimits, Scoring, Subnet, SubnetSet,
+};
+
+/// parsed tool's config
+#[derive(Debug)]
+pub struct Config {
+    pub file_names: Vec<PathBuf>,
+    pub top: Option<usize>,          // limit the report to N biggest subnets
+    pub group_by: GroupBy,           // top-level grouping of the report
+    pub lenient: bool,               // report bad lines and files instead of stopping at them
+    pub whitelist: SubnetSet,        // ranges no reported subnet may enclose
+    pub gaps: bool,                  // report unobserved ranges of the subnets instead of addresses
+    pub labels: Labels,              // names to tag subnets and addresses with
+    pub by_hits: bool,               // rank subnets by the hits of their addresses
+    pub scoring: Option<Scoring>,    // score the subnets and rank them by the score
+    pub metadata: Metadata,          // columns to join onto the addresses
+    pub whois: bool,                 // look up what the metadata lacks with whois
+    pub rate_limits: RateLimits,     // how often the enrichment providers may be asked
+    pub audit_log: Option<PathBuf>,  // file to write a JSON line per whois decision to
+    pub min_count: Option<usize>,    // skip subnets with fewer addresses
+    pub boundaries: Vec<Subnet>,     // prefixes no subnet may span across
+    pub root: Option<Subnet>,        // the subnet every address must be within, 0.0.0.0/0 if none
+    pub load: Option<PathBuf>,       // saved tree to start from instead of an empty one
+    pub save: Option<PathBuf>,       // file to save the tree to once everything is read
+    pub output_format: OutputFormat, // how the report is written
+    pub template: Option<PathBuf>, // Handlebars file to write the report through instead of the format
+    pub output: Option<PathBuf>,   // file to write the report to, text and ndjson go to stdout
+    pub manifest: Option<PathBuf>, // file to write the inputs, options and counts of the run to
+    pub memory_limit: Option<usize>, // bytes the tree may take before it's coarsened
+    pub max_children: Option<usize>, // children a tree node may have before they're split
+    pub views: Vec<GroupBy>,       // groupings to report one after another instead of group_by
+    pub untagged: bool,            // report only addresses without a label
+    pub merge_limits: MergeLimits, // the smallest and the sparsest subnets addresses are merged into
+    pub sources: bool,             // tell the files the addresses of every subnet came from
+    pub pipeline: Pipeline,        // rewrites of the input lines before they're parsed
+}
+
+impl Config {
+    /// config with default options for the given files
+    pub fn new(file_names: impl IntoIterator<Item = impl Into<PathBuf>>) -> Result<Config, Error> {
+        Self::builder().files(file_names).build()
+    }
+
+    pub fn builder() -> ConfigBuilder {
+        ConfigBuilder::default()
+    }
+
+    pub fn has_files(&self) -> bool {
+        !self.file_names.is_empty()
+    }
+
+    /// whether there is anything to report, i.e. files to read or a saved tree
+    pub fn has_input(&self) -> bool {
+        self.has_files() || self.load.is_some()
+    }
+}
+
+/// collects config options, nothing is validated or read until build()
+#[derive(Debug, Default)]
+pub struct ConfigBuilder {
+    file_names: Vec<PathBuf>,
+    top: Option<usize>,
+    group_by: GroupBy,
+    lenient: bool,
+    whitelist: SubnetSet,
+    gaps: bool,
+    labels: Labels,
+    by_hits: bool,
+    scoring: Option<Scoring>,
+    metadata: Metadata,
+    whois: bool,
+    rate_limits: RateLimits,
+    audit_log: Option<PathBuf>,
+    min_count: Option<usize>,
+    boundaries: Vec<Subnet>,
+    root: Option<Subnet>,
+    load: Option<PathBuf>,
+    save: Option<PathBuf>,
+    output_format: OutputFormat,
+    template: Option<PathBuf>,
+    output: Option<PathBuf>,
+    manifest: Option<PathBuf>,
+    memory_limit: Option<usize>,
+    max_children: Option<usize>,
+    views: Vec<GroupBy>,
+    untagged: bool,
+    merge_limits: MergeLimits,
+    sources: bool,
+    pipeline: Pipeline,
+}
+
+impl ConfigBuilder {
+    /// replace the list of files to read
+    pub fn files(mut self, file_names: impl IntoIterator<Item = impl Into<PathBuf>>) -> Self {
+        self.file_names = file_names.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// add one more file to read
+    pub fn file(mut self, file_name: impl Into<PathBuf>) -> Self {
+        self.file_names.push(file_name.into());
+        self
+    }
+
+    pub fn top(mut self, top: Option<usize>) -> Self {
+        self.top = top;
+        self
+    }
+
+    pub fn group_by(mut self, group_by: GroupBy) -> Self {
+        self.group_by = group_by;
+        self
+    }
+
+    pub fn lenient(mut self, lenient: bool) -> Self {
+        self.lenient = lenient;
+        self
+    }
+
+    pub fn whitelist(mut self, whitelist: SubnetSet) -> Self {
+        self.whitelist = whitelist;
+        self
+    }
+
+    pub fn gaps(mut self, gaps: bool) -> Self {
+        self.gaps = gaps;
+        self
+    }
+
+    pub fn labels(mut self, labels: Labels) -> Self {
+        self.labels = labels;
+        self
+    }
+
+    pub fn by_hits(mut self, by_hits: bool) -> Self {
+        self.by_hits = by_hits;
+        self
+    }
+
+    /// see Scoring
+    pub fn scoring(mut self, scoring: Option<Scoring>) -> Self {
+        self.scoring = scoring;
+        self
+    }
+
+    pub fn metadata(mut self, metadata: Metadata) -> Self {
+        self.metadata = metadata;
+        self
+    }
+
+    pub fn whois(mut self, whois: bool) -> Self {
+        self.whois = whois;
+        self
+    }
+
+    pub fn rate_limits(mut self, rate_limits: RateLimits) -> Self {
+        self.rate_limits = rate_limits;
+        self
+    }
+
+    pub fn audit_log(mut self, audit_log: Option<PathBuf>) -> Self {
+        self.audit_log = audit_log;
+        self
+    }
+
+    pub fn min_count(mut self, min_count: Option<usize>) -> Self {
+        self.min_count = min_count;
+        self
+    }
+
+    pub fn boundaries(mut self, boundaries: Vec<Subnet>) -> Self {
+        self.boundaries = boundaries;
+        self
+    }
+
+    /// see AddressTree::rooted_at
+    pub fn root(mut self, root: Option<Subnet>) -> Self {
+        self.root = root;
+        self
+    }
+
+    pub fn load(mut self, load: Option<PathBuf>) -> Self {
+        self.load = load;
+        self
+    }
+
+    pub fn save(mut self, save: Option<PathBuf>) -> Self {
+        self.save = save;
+        self
+    }
+
+    pub fn output_format(mut self, output_format: OutputFormat) -> Self {
+        self.output_format = output_format;
+        self
+    }
+
+    /// see Template, the report goes to stdout unless there is an output
+    pub fn template(mut self, template: Option<PathBuf>) -> Self {
+        self.template = template;
+        self
+    }
+
+    pub fn output(mut self, output: Option<PathBuf>) -> Self {
+        self.output = output;
+        self
+    }
+
+    pub fn manifest(mut self, manifest: Option<PathBuf>) -> Self {
+        self.manifest = manifest;
+        self
+    }
+
+    /// bytes the tree may take, see AddressTree::limit_memory
+    pub fn memory_limit(mut self, memory_limit: Option<usize>) -> Self {
+        self.memory_limit = memory_limit;
+        self
+    }
+
+    /// see AddressTree::limit_children
+    pub fn max_children(mut self, max_children: Option<usize>) -> Self {
+        self.max_children = max_children;
+        self
+    }
+
+    /// report the addresses grouped in several ways at once, e.g. /8, /16 and subnet
+    pub fn views(mut self, views: Vec<GroupBy>) -> Self {
+        self.views = views;
+        self
+    }
+
+    /// report only the addresses none of the labels covers
+    pub fn untagged(mut self, untagged: bool) -> Self {
+        self.untagged = untagged;
+        self
+    }
+
+    /// see AddressTree::set_merge_policy
+    pub fn merge_limits(mut self, merge_limits: MergeLimits) -> Self {
+        self.merge_limits = merge_limits;
+        self
+    }
+
+    /// see Provenance
+    pub fn sources(mut self, sources: bool) -> Self {
+        self.sources = sources;
+        self
+    }
+
+    pub fn pipeline(mut self, pipeline: Pipeline) -> Self {
+        self.pipeline = pipeline;
+        self
+    }
+
+    /// validate the options and make the config
+    /// # returns
+    /// Err - if some file doesn't exist (unless lenient) or an option is out of range
+    pub fn build(self) -> Result<Config, Error> {
+        for f in &self.file_names {
+            if !self.lenient && !f.exists() {
+                return Err(Error::FileNotFound(f.clone()));
+            }
+        }
+        if self.top == Some(0) {
+            return Err(Error::InvalidArgument("top should be at least 1".into()));
+        }
+        if let Some(load) = &self.load {
+            if !load.exists() {
+                return Err(Error::FileNotFound(load.clone()));
+            }
+            if !self.boundaries.is_empty() {
+                return Err(Error::InvalidArgument(
+                    "a saved tree keeps its own boundaries".into(),
+                ));
+            }
+            if self.root.is_some() {
+                return Err(Error::InvalidArgument(
+                    "a saved tree keeps its own root".into(),
+                ));
+            }
+        }
+        if let Some(template) = &self.template {
+            if !template.exists() {
+                return Err(Error::FileNotFound(template.clone()));
+            }
+            if self.output_format != OutputFormat::Text {
+                return Err(Error::InvalidArgument(
+                    "a template replaces the output format".into(),
+                ));
+            }
+        }
+        // a template writes to stdout or a file alike
+        let is_streamed = self.output_format.is_streamed();
+        if self.template.is_none() && is_streamed && self.output.is_some() {
+            return Err(Error::InvalidArgument(format!(
+                "{:?} output goes to stdout",
+                self.output_format
+            )));
+        }
+        if self.template.is_none() && !is_streamed && self.output.is_none() {
+            return Err(Error::InvalidArgument(format!(
+                "{:?} output needs a file to write to",
+                self.output_format
+            )));
+        }
+        if (self.output_format != OutputFormat::Text || self.template.is_some()) && self.gaps {
+            return Err(Error::InvalidArgument(
+                "gaps can only be reported as text".into(),
+            ));
+        }
+        if self.whois && self.output_format == OutputFormat::Ndjson {
+            return Err(Error::InvalidArgument(
+                "ndjson addresses are written before whois could be asked".into(),
+            ));
+        }
+        if self.audit_log.is_some() && !self.whois {
+            return Err(Error::InvalidArgument(
+                "there is nothing to log without whois".into(),
+            ));
+        }
+        // whois fills netname, country and org in
+        let has_column = |names: &[&str]| {
+            self.whois
+                || self
+                    .metadata
+                    .columns()
+                    .iter()
+                    .any(|column| names.iter().any(|name| column.eq_ignore_ascii_case(name)))
+        };
+        if self.output_format == OutputFormat::Countries && !has_column(&["country"]) {
+            return Err(Error::InvalidArgument(
+                "countries output needs metadata with a country column".into(),
+            ));
+        }
+        if self.output_format == OutputFormat::Orgs && !has_column(&ORG) {
+            return Err(Error::InvalidArgument(
+                "orgs output needs metadata with an org, organization or isp column".into(),
+            ));
+        }
+        for group_by in std::iter::once(&self.group_by).chain(&self.views) {
+            if let GroupBy::Column(name, _) = group_by {
+                if !has_column(&[name]) {
+                    return Err(Error::InvalidArgument(format!(
+                        "grouping by {} needs metadata with that column",
+                        name
+                    )));
+                }
+            }
+        }
+        if self.min_count == Some(0) {
+            return Err(Error::InvalidArgument(
+                "min count should be at least 1".into(),
+            ));
+        }
+        if !self.views.is_empty() {
+            if self.group_by != GroupBy::Subnet {
+                return Err(Error::InvalidArgument("views replace the grouping".into()));
+            }
+            if self.output_format != OutputFormat::Text {
+                return Err(Error::InvalidArgument(
+                    "views can only be reported as text".into(),
+                ));
+            }
+        }
+        if self.untagged && self.labels.is_empty() {
+            return Err(Error::InvalidArgument(
+                "untagged addresses need labels to tell them from the tagged ones".into(),
+            ));
+        }
+        if self.merge_limits.min_mask_len > 32 {
+            return Err(Error::MaskTooLong(self.merge_limits.min_mask_len));
+        }
+        if !(0.0..=1.0).contains(&self.merge_limits.min_density) {
+            return Err(Error::InvalidArgument(
+                "min density should be from 0 to 1".into(),
+            ));
+        }
+        if self.memory_limit == Some(0) {
+            return Err(Error::InvalidArgument(
+                "memory limit should be at least 1".into(),
+            ));
+        }
+        if self.max_children.is_some_and(|max| max < 2) {
+            return Err(Error::InvalidArgument(
+                "max children should be at least 2".into(),
+            ));
+        }
+
+        Ok(Config {
+            file_names: self.file_names,
+            top: self.top,
+            group_by: self.group_by,
+            lenient: self.lenient,
+            whitelist: self.whitelist,
+            gaps: self.gaps,
+            labels: self.labels,
+            by_hits: self.by_hits,
+            scoring: self.scoring,
+            metadata: self.metadata,
+            whois: self.whois,
+            rate_limits: self.rate_limits,
+            audit_log: self.audit_log,
+            min_count: self.min_count,
+            boundaries: self.boundaries,
+            root: self.root,
+            load: self.load,
+            save: self.save,
+            output_format: self.output_format,
+            template: self.template,
+            output: self.output,
+            manifest: self.manifest,
+            memory_limit: self.memory_limit,
+            max_children: self.max_children,
+            views: self.views,
+            untagged: self.untagged,
+            merge_limits: self.merge_limits,
+            sources: self.sources,
+            pipeline: self.pipeline,
+        })
+    }
+}
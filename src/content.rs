@@ -0,0 +1,49 @@
+use std::{ops::Deref, path::Path};
+
+use crate::Error;
+
+/// text of an input file
+/// with the mmap feature the file is memory-mapped instead of being copied into a String
+pub(crate) struct FileContent {
+    #[cfg(not(feature = "mmap"))]
+    text: String,
+    #[cfg(feature = "mmap")]
+    map: memmap2::Mmap,
+}
+
+impl FileContent {
+    #[cfg(not(feature = "mmap"))]
+    pub(crate) fn read(file_name: &Path) -> Result<Self, Error> {
+        Ok(Self {
+            text: std::fs::read_to_string(file_name)?,
+        })
+    }
+
+    /// # Errors
+    /// besides IO errors, the file has to be valid UTF-8 as read_to_string requires
+    #[cfg(feature = "mmap")]
+    pub(crate) fn read(file_name: &Path) -> Result<Self, Error> {
+        let file = std::fs::File::open(file_name)?;
+        // the map is read-only, changing the file underneath is the caller's problem as with any reader
+        let map = unsafe { memmap2::Mmap::map(&file)? };
+        if let Err(e) = std::str::from_utf8(&map) {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, e).into());
+        }
+        Ok(Self { map })
+    }
+}
+
+impl Deref for FileContent {
+    type Target = str;
+
+    #[cfg(not(feature = "mmap"))]
+    fn deref(&self) -> &str {
+        &self.text
+    }
+
+    #[cfg(feature = "mmap")]
+    fn deref(&self) -> &str {
+        // validated in read()
+        unsafe { std::str::from_utf8_unchecked(&self.map) }
+    }
+}
@@ -0,0 +1,136 @@
+use std::fmt::{Display, Formatter};
+#[cfg(feature = "fs")]
+use std::path::Path;
+
+#[cfg(feature = "fs")]
+use crate::content::FileContent;
+use crate::{io::tree_of_lines, membership::Membership, AddressTree, Error, GroupBy};
+
+/// # labeled datasets with a tree each, e.g. per data center or per day, to tell which subnets appear in which
+/// generalizes compare and overlap to any number of datasets
+#[derive(Default)]
+pub struct Datasets {
+    datasets: Vec<(String, AddressTree)>, // in the order they're added
+}
+
+impl Datasets {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// add the tree of a dataset, e.g. one loaded with AddressTree::load
+    pub fn add(&mut self, label: impl Into<String>, address_tree: AddressTree) {
+        self.datasets.push((label.into(), address_tree));
+    }
+
+    /// # classify the lines into a tree of their own and add it as a dataset, see parse_weighted_line
+    /// # returns
+    /// Err - if some line isn't a correct IP address
+    pub fn add_lines<L: AsRef<str>>(
+        &mut self,
+        label: impl Into<String>,
+        lines: impl IntoIterator<Item = L>,
+    ) -> Result<(), Error> {
+        self.add(label, tree_of_lines(lines)?);
+        Ok(())
+    }
+
+    pub fn labels(&self) -> impl Iterator<Item = &str> {
+        self.datasets.iter().map(|(label, _)| label.as_str())
+    }
+
+    pub fn tree_of(&self, label: &str) -> Option<&AddressTree> {
+        self.datasets
+            .iter()
+            .find(|(own, _)| own == label)
+            .map(|(_, address_tree)| address_tree)
+    }
+
+    /// # cross-tabulate the groups of all the datasets' addresses classified together
+    /// against the datasets, so the same subnet is a single row whichever dataset it comes from
+    /// # returns
+    /// rows present in the most datasets first, then the biggest ones
+    pub fn crosstab(&self, group_by: &GroupBy) -> CrossTab {
+        let mut membership = Membership::new();
+        for (_, address_tree) in &self.datasets {
+            // the union has no limits to refuse an address
+            let _ = membership.add(address_tree.leafs());
+        }
+        let mut rows: Vec<CrossRow> = membership
+            .counts(group_by)
+            .into_iter()
+            .map(|(group, counts)| CrossRow { group, counts })
+            .collect();
+        rows.sort_by(|r1, r2| {
+            r2.presence()
+                .cmp(&r1.presence())
+                .then(r2.total().cmp(&r1.total()))
+                .then(r1.group.cmp(&r2.group))
+        });
+        CrossTab {
+            labels: self.labels().map(str::to_string).collect(),
+            rows,
+        }
+    }
+}
+
+/// groups by datasets, see Datasets::crosstab
+#[derive(Debug, PartialEq)]
+pub struct CrossTab {
+    pub labels: Vec<String>, // of the datasets, the columns
+    pub rows: Vec<CrossRow>,
+}
+
+/// a group with the number of distinct addresses of every dataset in it
+#[derive(Debug, PartialEq)]
+pub struct CrossRow {
+    pub group: String,
+    pub counts: Vec<usize>, // in the order of the labels
+}
+
+impl CrossRow {
+    /// number of datasets with any address in the group
+    pub fn presence(&self) -> usize {
+        self.counts.iter().filter(|&&count| count > 0).count()
+    }
+
+    /// addresses of the group summed over the datasets, an address of several datasets counts in each
+    pub fn total(&self) -> usize {
+        self.counts.iter().sum()
+    }
+}
+
+/// a tab-separated table with a header: subnet, the labels and the number of datasets with the subnet
+impl Display for CrossTab {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+        write!(f, "subnet")?;
+        for label in &self.labels {
+            write!(f, "\t{}", label)?;
+        }
+        write!(f, "\tdatasets")?;
+        for row in &self.rows {
+            write!(f, "\n{}", row.group)?;
+            for count in &row.counts {
+                write!(f, "\t{}", count)?;
+            }
+            write!(f, "\t{}", row.presence())?;
+        }
+        Ok(())
+    }
+}
+
+/// # same as Datasets::crosstab with a dataset per labeled file, e.g. ("dc1", "dc1.txt")
+/// # returns
+/// Err - if some file can't be read or some line isn't a correct IP address
+#[cfg(feature = "fs")]
+pub fn find_crosstab(
+    files: impl IntoIterator<Item = (impl Into<String>, impl AsRef<Path>)>,
+    group_by: &GroupBy,
+) -> Result<CrossTab, Error> {
+    let mut datasets = Datasets::new();
+    for (label, file_name) in files {
+        let content = FileContent::read(file_name.as_ref())?;
+        datasets.add_lines(label, content.split('\n'))?;
+    }
+    Ok(datasets.crosstab(group_by))
+}
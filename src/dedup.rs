@@ -0,0 +1,63 @@
+use std::{collections::HashMap, path::Path};
+
+use roaring::RoaringBitmap;
+
+use crate::{
+    io::read_files, AddressTree, CancelToken, Error, GroupBy, StderrLogger, Subnet, SubnetsMap,
+};
+
+/// drops repeated addresses before they reach the tree, remembers how many times each one was seen
+/// only single addresses (/32) are tracked, wider subnets always pass
+#[derive(Debug, Default)]
+pub struct DuplicateFilter {
+    seen: RoaringBitmap,
+    repeats: HashMap<u32, usize>, // extra occurrences of the addresses seen more than once
+}
+
+impl DuplicateFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// # returns
+    /// true - if the subnet should go to the tree, i.e. it wasn't seen before
+    pub fn admit(&mut self, subnet: &Subnet) -> bool {
+        if subnet.mask_len < 32 || self.seen.insert(subnet.bits) {
+            return true;
+        }
+        *self.repeats.entry(subnet.bits).or_default() += 1;
+        false
+    }
+
+    /// how many times the address was seen, 0 for unseen addresses and wider subnets
+    pub fn count(&self, subnet: &Subnet) -> usize {
+        if subnet.mask_len < 32 || !self.seen.contains(subnet.bits) {
+            return 0;
+        }
+        1 + self.repeats.get(&subnet.bits).copied().unwrap_or(0)
+    }
+
+    /// total number of dropped repeats
+    pub fn duplicates(&self) -> usize {
+        self.repeats.values().sum()
+    }
+}
+
+/// # same as find_groups, but repeated addresses are dropped by the filter before the tree sees them
+/// pays off for heavily duplicated inputs such as firewall logs, query the filter for the counts afterwards
+pub fn find_groups_dedup(
+    file_names: impl IntoIterator<Item = impl AsRef<Path>>,
+    group_by: &GroupBy,
+    filter: &mut DuplicateFilter,
+) -> Result<SubnetsMap, Error> {
+    let mut address_tree = AddressTree::new();
+    read_files(
+        &mut address_tree,
+        file_names,
+        false,
+        &CancelToken::new(),
+        &mut StderrLogger,
+        |addr| filter.admit(addr),
+    )?;
+    Ok(address_tree.get_groups_map(group_by))
+}
@@ -0,0 +1,50 @@
+use std::{
+    fmt::{Display, Formatter},
+    str::FromStr,
+};
+
+use crate::{AddressClass, Subnet, SubnetSet};
+
+/// share of a subnet's address space present in the input
+/// dense subnets are much stronger block candidates than sparse ones
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Density {
+    pub present: u64, // distinct addresses found
+    pub size: u64,    // addresses in the subnet
+}
+
+impl Density {
+    /// # measure how much of the group is covered by its addresses
+    /// group is a subnet (e.g. 10.0.0.0/24) or a class name as reported with GroupBy::Class, e.g. private
+    /// # returns
+    /// None - if the group or some address can't be parsed
+    pub fn of(group: &str, addrs: &[String]) -> Option<Self> {
+        let size = match AddressClass::of_name(group) {
+            Some(class) => class.size(),
+            None => Subnet::from_str(group).ok()?.size(),
+        };
+        let present = SubnetSet::from_lines(addrs).ok()?.size();
+        Some(Self { present, size })
+    }
+
+    /// whether every address of the subnet is present
+    pub fn is_full(&self) -> bool {
+        self.present == self.size
+    }
+
+    pub fn ratio(&self) -> f64 {
+        self.present as f64 / self.size as f64
+    }
+}
+
+impl Display for Density {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+        write!(
+            f,
+            "{}/{} addresses ({:.1}%)",
+            self.present,
+            self.size,
+            self.ratio() * 100.0
+        )
+    }
+}
@@ -0,0 +1,330 @@
+use std::{
+    collections::HashMap,
+    fmt::{Display, Formatter},
+    net::IpAddr,
+    path::Path,
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
+
+use hickory_resolver::{
+    config::ResolverConfig, name_server::TokioConnectionProvider, ResolveError, TokioResolver,
+};
+use tokio::{sync::Semaphore, task::JoinSet};
+
+use crate::{Error, IpMetadata, Metadata, Subnet};
+
+/// # answers of the resolver kept between lookups and, saved to a file, between runs
+/// lines of the file: "ptr 192.0.2.1 host.example.org" ("ptr 192.0.2.1" if there is none)
+/// or "a host.example.org 192.0.2.1 192.0.2.2", answers don't expire, drop the file to refresh them
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct DnsCache {
+    hostnames: HashMap<u32, Option<String>>, // PTR answers by address
+    addrs: HashMap<String, Vec<Subnet>>,     // A answers by lowercase hostname
+}
+
+impl DnsCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// # parse the lines saved before, see Display
+    /// empty lines and lines starting with # are skipped
+    /// # returns
+    /// Err - if some line isn't a ptr or an a answer or its addresses can't be parsed
+    pub fn from_lines(lines: impl IntoIterator<Item = impl AsRef<str>>) -> Result<Self, Error> {
+        let mut cache = Self::new();
+        for line in lines {
+            let line = line.as_ref().trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            match fields[..] {
+                ["ptr", addr] => cache.insert_hostname(&Subnet::from_str(addr)?, None),
+                ["ptr", addr, hostname] => {
+                    cache.insert_hostname(&Subnet::from_str(addr)?, Some(hostname.to_string()))
+                }
+                ["a", hostname, ref addrs @ ..] => {
+                    let addrs = addrs
+                        .iter()
+                        .map(|addr| Subnet::from_str(addr))
+                        .collect::<Result<_, _>>()?;
+                    cache.insert_addrs(hostname, addrs);
+                }
+                _ => return Err(Error::ParseError {
+                    input: line.to_string(),
+                    reason:
+                        "answer should look like ptr ADDRESS [HOSTNAME] or a HOSTNAME ADDRESS..."
+                            .to_string(),
+                }),
+            }
+        }
+        Ok(cache)
+    }
+
+    /// # read the file saved before, no file is an empty cache
+    /// # returns
+    /// Err - if the file can't be read or parsed
+    pub fn load(file_name: impl AsRef<Path>) -> Result<Self, Error> {
+        match std::fs::read_to_string(file_name) {
+            Ok(content) => Self::from_lines(content.split('\n')),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::new()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn save(&self, file_name: impl AsRef<Path>) -> Result<(), Error> {
+        Ok(std::fs::write(file_name, self.to_string())?)
+    }
+
+    pub fn len(&self) -> usize {
+        self.hostnames.len() + self.addrs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// # the PTR answer of the address
+    /// # returns
+    /// None - if the address wasn't looked up, Some(None) if it has no hostname
+    pub fn hostname_of(&self, addr: &Subnet) -> Option<Option<&str>> {
+        self.hostnames.get(&addr.bits).map(Option::as_deref)
+    }
+
+    /// # the A answer of the hostname, hostnames are case-insensitive
+    /// # returns
+    /// None - if the hostname wasn't looked up
+    pub fn addrs_of(&self, hostname: &str) -> Option<&[Subnet]> {
+        self.addrs.get(&hostname.to_lowercase()).map(Vec::as_slice)
+    }
+
+    pub fn insert_hostname(&mut self, addr: &Subnet, hostname: Option<String>) {
+        self.hostnames.insert(addr.bits, hostname);
+    }
+
+    pub fn insert_addrs(&mut self, hostname: &str, addrs: Vec<Subnet>) {
+        self.addrs.insert(hostname.to_lowercase(), addrs);
+    }
+}
+
+/// the lines to save the cache as, sorted to keep the file diffable
+impl Display for DnsCache {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+        let mut hostnames: Vec<_> = self.hostnames.iter().collect();
+        hostnames.sort_by_key(|(bits, _)| **bits);
+        for (&bits, hostname) in hostnames {
+            let addr = std::net::Ipv4Addr::from(bits);
+            match hostname {
+                Some(hostname) => writeln!(f, "ptr {} {}", addr, hostname)?,
+                None => writeln!(f, "ptr {}", addr)?,
+            }
+        }
+        let mut addrs: Vec<_> = self.addrs.iter().collect();
+        addrs.sort_by_key(|&(hostname, _)| hostname);
+        for (hostname, addrs) in addrs {
+            write!(f, "a {}", hostname)?;
+            for addr in addrs {
+                write!(f, " {}", addr.to_string().trim_end_matches("/32"))?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// # resolves PTR records and hostnames concurrently through a DnsCache
+/// has to be used from within a tokio runtime, clones share the cache and the concurrency limit
+#[derive(Clone)]
+pub struct Resolver {
+    resolver: TokioResolver,
+    cache: Arc<Mutex<DnsCache>>,
+    limit: Arc<Semaphore>, // queries in flight
+}
+
+impl Resolver {
+    /// # resolver of the system's configuration, e.g. /etc/resolv.conf, public servers if it can't be read
+    /// # Panics
+    /// if max_concurrent is 0
+    pub fn new(max_concurrent: usize) -> Self {
+        assert!(
+            max_concurrent > 0,
+            "max concurrent queries should be at least 1"
+        );
+        let resolver = TokioResolver::builder_tokio()
+            .unwrap_or_else(|_| {
+                TokioResolver::builder_with_config(
+                    ResolverConfig::default(),
+                    TokioConnectionProvider::default(),
+                )
+            })
+            .build();
+        Self {
+            resolver,
+            cache: Arc::default(),
+            limit: Arc::new(Semaphore::new(max_concurrent)),
+        }
+    }
+
+    /// start from the answers of the cache, e.g. loaded from a file
+    pub fn with_cache(mut self, cache: DnsCache) -> Self {
+        self.cache = Arc::new(Mutex::new(cache));
+        self
+    }
+
+    /// the answers so far, e.g. to save them for the next run
+    pub fn cache(&self) -> DnsCache {
+        self.cache
+            .lock()
+            .expect("no lookup panics holding the lock")
+            .clone()
+    }
+
+    /// # PTR record of the address, from the cache if it's there
+    /// # returns
+    /// None - if the address has no PTR record
+    /// Err - if the DNS servers can't be reached
+    pub async fn hostname_of(&self, addr: &Subnet) -> Result<Option<String>, Error> {
+        if let Some(hostname) =
+            self.cached(|cache| cache.hostname_of(addr).map(|h| h.map(String::from)))
+        {
+            return Ok(hostname);
+        }
+        let ip = IpAddr::from(std::net::Ipv4Addr::from(addr.bits));
+        let hostname = {
+            let _permit = self
+                .limit
+                .acquire()
+                .await
+                .expect("the semaphore is never closed");
+            match self.resolver.reverse_lookup(ip).await {
+                Ok(lookup) => lookup
+                    .iter()
+                    .next()
+                    .map(|ptr| ptr.to_string().trim_end_matches('.').to_string()),
+                Err(e) if is_no_answer(&e) => None,
+                Err(e) => return Err(Box::new(e).into()),
+            }
+        };
+        self.update(|cache| cache.insert_hostname(addr, hostname.clone()));
+        Ok(hostname)
+    }
+
+    /// # A records of the hostname, from the cache if it's there
+    /// # returns
+    /// Err - if the DNS servers can't be reached
+    pub async fn addrs_of(&self, hostname: &str) -> Result<Vec<Subnet>, Error> {
+        if let Some(addrs) = self.cached(|cache| cache.addrs_of(hostname).map(<[Subnet]>::to_vec)) {
+            return Ok(addrs);
+        }
+        let addrs = {
+            let _permit = self
+                .limit
+                .acquire()
+                .await
+                .expect("the semaphore is never closed");
+            match self.resolver.ipv4_lookup(hostname).await {
+                Ok(lookup) => lookup
+                    .iter()
+                    .map(|a| Subnet::from_parts(a.0.octets(), 32))
+                    .collect(),
+                Err(e) if is_no_answer(&e) => vec![],
+                Err(e) => return Err(Box::new(e).into()),
+            }
+        };
+        self.update(|cache| cache.insert_addrs(hostname, addrs.clone()));
+        Ok(addrs)
+    }
+
+    /// # replace the hostnames of the lines with a line per their addresses, e.g. to classify a list of hosts
+    /// lines which are addresses, empty or don't resolve are kept as they are, so they're reported as usual
+    /// # returns
+    /// Err - if the DNS servers can't be reached
+    pub async fn resolve_lines(
+        &self,
+        lines: impl IntoIterator<Item = impl AsRef<str>>,
+    ) -> Result<Vec<String>, Error> {
+        let lines: Vec<String> = lines
+            .into_iter()
+            .map(|line| line.as_ref().to_string())
+            .collect();
+        let mut lookups = JoinSet::new();
+        for (i, line) in lines.iter().enumerate() {
+            let host = line.trim();
+            if host.is_empty() || host.starts_with('#') || Subnet::from_str(host).is_ok() {
+                continue;
+            }
+            let (resolver, host) = (self.clone(), host.to_string());
+            lookups.spawn(async move { (i, resolver.addrs_of(&host).await) });
+        }
+        let mut resolved = vec![None; lines.len()];
+        while let Some(lookup) = lookups.join_next().await {
+            let (i, addrs) = lookup.expect("lookups don't panic");
+            resolved[i] = Some(addrs?).filter(|addrs| !addrs.is_empty());
+        }
+        Ok(lines
+            .into_iter()
+            .zip(resolved)
+            .flat_map(|(line, addrs)| match addrs {
+                Some(addrs) => addrs
+                    .iter()
+                    .map(|addr| addr.to_string().trim_end_matches("/32").to_string())
+                    .collect(),
+                None => vec![line],
+            })
+            .collect())
+    }
+
+    /// # fill the hostname of the addresses in with their PTR records, values the metadata has already are kept
+    /// # returns
+    /// Err - if the DNS servers can't be reached
+    pub async fn enrich<'a>(
+        &self,
+        metadata: &mut Metadata,
+        addrs: impl IntoIterator<Item = &'a Subnet>,
+    ) -> Result<(), Error> {
+        let mut lookups = JoinSet::new();
+        for addr in addrs {
+            let known = metadata.ip_metadata_of(addr).and_then(|ip| ip.hostname);
+            if known.is_some() || addr.mask_len != 32 {
+                continue;
+            }
+            let (resolver, addr) = (self.clone(), addr.clone());
+            lookups.spawn(async move {
+                let hostname = resolver.hostname_of(&addr).await;
+                (addr, hostname)
+            });
+        }
+        while let Some(lookup) = lookups.join_next().await {
+            let (addr, hostname) = lookup.expect("lookups don't panic");
+            if let Some(hostname) = hostname? {
+                let ip = IpMetadata {
+                    hostname: Some(hostname),
+                    ..IpMetadata::default()
+                };
+                metadata.fill(&addr, &ip);
+            }
+        }
+        Ok(())
+    }
+
+    fn cached<T>(&self, get: impl FnOnce(&DnsCache) -> Option<T>) -> Option<T> {
+        get(&self
+            .cache
+            .lock()
+            .expect("no lookup panics holding the lock"))
+    }
+
+    fn update(&self, set: impl FnOnce(&mut DnsCache)) {
+        set(&mut self
+            .cache
+            .lock()
+            .expect("no lookup panics holding the lock"));
+    }
+}
+
+/// the name has no records, as opposed to the servers failing to answer
+fn is_no_answer(e: &ResolveError) -> bool {
+    e.is_no_records_found() || e.is_nx_domain()
+}
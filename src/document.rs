@@ -0,0 +1,178 @@
+use std::{io::Write, str::FromStr};
+
+use crate::{
+    export::SubnetRow, metadata::most_common, ndjson::json_string, prefix_map::PrefixMap, Error,
+    Report, Subnet,
+};
+
+/// version of the JSON document, bumped when a field is removed or changes its meaning, new fields don't bump it
+pub const JSON_VERSION: u32 = 1;
+
+/// # write the report as a single JSON document, the machine interface of the tool, e.g.
+/// {"version":1,"generator":"ipv4-classify 0.1.0","columns":["asn"],"count":2,"subnets":[
+/// {"subnet":"10.0.0.0/30","label":null,"kind":null,"class":"private","size":4,"density":0.5,"count":2,"hits":null,"score":null,
+/// "first_seen":null,"last_seen":null,"sources":null,"metadata":{"asn":"64500","country":null,"org":null},
+/// "addresses":[{"address":"10.0.0.1/32","class":"private","hits":1,"sources":null,"metadata":{"asn":"64500"}},...],"subnets":[]}]}
+/// subnets contained by other subnets of the report are nested into the most specific of them,
+/// subnet metadata are the most common values of its addresses, address metadata are the non-empty columns
+/// # returns
+/// Err - if the document can't be written
+pub fn write_json(report: &Report, out: &mut impl Write) -> Result<(), Error> {
+    let rows = report.rows();
+    let subnets: Vec<Option<Subnet>> = rows
+        .iter()
+        .map(|row| Subnet::from_str(row.name).ok())
+        .collect();
+    let mut indices = PrefixMap::default();
+    for (i, subnet) in subnets.iter().enumerate() {
+        if let Some(subnet) = subnet {
+            indices.insert(subnet, i);
+        }
+    }
+    // the most specific other subnet of the report containing the one of the row
+    let parent_of = |i: usize| {
+        let subnet = subnets[i].as_ref()?;
+        indices.lookup_parent(subnet).map(|(_, &parent)| parent)
+    };
+    let mut children: Vec<Vec<usize>> = vec![vec![]; rows.len()];
+    let mut roots = vec![];
+    for i in 0..rows.len() {
+        match parent_of(i) {
+            Some(parent) => children[parent].push(i),
+            None => roots.push(i),
+        }
+    }
+    let columns: Vec<String> = report
+        .metadata
+        .columns()
+        .iter()
+        .map(|column| json_string(Some(column)))
+        .collect();
+    write!(
+        out,
+        "{{\"version\":{},\"generator\":{},\"columns\":[{}],\"count\":{},\"subnets\":",
+        JSON_VERSION,
+        json_string(Some(concat!("ipv4-classify ", env!("CARGO_PKG_VERSION")))),
+        columns.join(","),
+        rows.iter().map(|row| row.addresses.len()).sum::<usize>()
+    )?;
+    write_subnets(report, &rows, &children, &roots, out)?;
+    writeln!(out, "}}")?;
+    out.flush()?;
+    Ok(())
+}
+
+/// the subnets of the indices along with their nested subnets as a JSON array
+fn write_subnets(
+    report: &Report,
+    rows: &[SubnetRow],
+    children: &[Vec<usize>],
+    indices: &[usize],
+    out: &mut impl Write,
+) -> Result<(), Error> {
+    write!(out, "[")?;
+    for (n, &i) in indices.iter().enumerate() {
+        let row = &rows[i];
+        let addrs: Vec<Option<Subnet>> = row
+            .addresses
+            .iter()
+            .map(|address| Subnet::from_str(address).ok())
+            .collect();
+        let ips: Vec<_> = addrs
+            .iter()
+            .flatten()
+            .filter_map(|addr| report.metadata.ip_metadata_of(addr))
+            .collect();
+        let most_common =
+            |values: Vec<String>| json_string(most_common(values.iter().map(String::as_str)));
+        let metadata = format!(
+            "{{\"asn\":{},\"country\":{},\"org\":{}}}",
+            most_common(
+                ips.iter()
+                    .filter_map(|ip| ip.asn.map(|asn| asn.to_string()))
+                    .collect()
+            ),
+            most_common(ips.iter().filter_map(|ip| ip.country.clone()).collect()),
+            most_common(ips.iter().filter_map(|ip| ip.org.clone()).collect()),
+        );
+        let addresses: Vec<String> = row
+            .addresses
+            .iter()
+            .zip(&addrs)
+            .map(|(address, addr)| {
+                let hits = report
+                    .address_hits
+                    .and_then(|hits| hits.get(address.as_str()))
+                    .map_or("null".to_string(), |hits| hits.to_string());
+                let sources = match (report.sources, addr) {
+                    (Some(sources), Some(addr)) => json_array(sources.sources_of(addr)),
+                    _ => "null".to_string(),
+                };
+                let class = addr.as_ref().and_then(Subnet::address_class);
+                format!(
+                    "{{\"address\":{},\"class\":{},\"hits\":{},\"sources\":{},\"metadata\":{}}}",
+                    json_string(Some(address)),
+                    json_string(class.map(|class| class.name())),
+                    hits,
+                    sources,
+                    address_metadata(report, addr.as_ref())
+                )
+            })
+            .collect();
+        write!(
+            out,
+            "{}{{\"subnet\":{},\"label\":{},\"kind\":{},\"class\":{},\"size\":{},\"density\":{},\"count\":{},\"hits\":{},\
+             \"score\":{},\"first_seen\":{},\"last_seen\":{},\"sources\":{},\"metadata\":{},\"addresses\":[{}],\"subnets\":",
+            if n == 0 { "" } else { "," },
+            json_string(Some(row.name)),
+            json_string(row.label),
+            json_string(row.kind.map(|kind| kind.to_string()).as_deref()),
+            json_string(row.class.map(|class| class.name())),
+            json_number(row.density.map(|density| density.size)),
+            row.density
+                .map_or("null".to_string(), |density| density.ratio().to_string()),
+            row.addresses.len(),
+            json_number(row.hits),
+            json_number(row.score),
+            json_number(row.seen.map(|seen| seen.first)),
+            json_number(row.seen.map(|seen| seen.last)),
+            row.sources.clone().map_or("null".to_string(), json_array),
+            metadata,
+            addresses.join(",")
+        )?;
+        write_subnets(report, rows, children, &children[i], out)?;
+        write!(out, "}}")?;
+    }
+    write!(out, "]")?;
+    Ok(())
+}
+
+/// non-empty metadata columns of the address, null if the metadata doesn't have it
+fn address_metadata(report: &Report, addr: Option<&Subnet>) -> String {
+    let Some(values) = addr.and_then(|addr| report.metadata.row_of(addr)) else {
+        return "null".to_string();
+    };
+    let fields: Vec<String> = report
+        .metadata
+        .columns()
+        .iter()
+        .zip(values)
+        .filter(|(_, value)| !value.is_empty())
+        .map(|(column, value)| {
+            format!("{}:{}", json_string(Some(column)), json_string(Some(value)))
+        })
+        .collect();
+    format!("{{{}}}", fields.join(","))
+}
+
+fn json_array(values: Vec<&str>) -> String {
+    let values: Vec<String> = values
+        .into_iter()
+        .map(|value| json_string(Some(value)))
+        .collect();
+    format!("[{}]", values.join(","))
+}
+
+fn json_number(value: Option<impl ToString>) -> String {
+    value.map_or("null".to_string(), |value| value.to_string())
+}
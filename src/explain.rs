@@ -0,0 +1,73 @@
+use std::fmt::{Display, Formatter};
+
+use crate::{Decision, Observer, Subnet};
+
+/// # collects the decisions of the tree about a subnet while the input is pushed, e.g.
+/// to tell why a reported subnet came out bigger or smaller than expected
+/// a decision is about the subnet if the pushed address is within it or overlaps it,
+/// or if an address from outside tried to join one within it
+pub struct Explainer {
+    subnet: Subnet,
+    steps: Vec<String>, // decisions in the order they were made
+}
+
+impl Explainer {
+    pub fn new(subnet: Subnet) -> Self {
+        Self {
+            subnet,
+            steps: vec![],
+        }
+    }
+
+    pub fn subnet(&self) -> &Subnet {
+        &self.subnet
+    }
+
+    pub fn steps(&self) -> &[String] {
+        &self.steps
+    }
+
+    fn overlaps(&self, subnet: &Subnet) -> bool {
+        self.subnet.contains(subnet) || subnet.contains(&self.subnet)
+    }
+}
+
+impl Observer for Explainer {
+    fn on_decision(&mut self, decision: &Decision) {
+        let joins_within = match decision {
+            Decision::Merged {
+                neighbour, common, ..
+            } => self.subnet.contains(neighbour) || self.subnet.contains(common),
+            Decision::Diverged { neighbour, .. }
+            | Decision::Bounded { neighbour, .. }
+            | Decision::Refused { neighbour, .. } => self.subnet.contains(neighbour),
+            Decision::Counted { .. } | Decision::Adopted { .. } => false,
+        };
+        if joins_within || self.overlaps(decision.new_subnet()) {
+            self.steps.push(decision.to_string());
+        }
+    }
+
+    fn on_tree_coarsened(&mut self, mask_len: u8) {
+        self.steps.push(format!(
+            "memory limit reached, subnets of /{} and longer were collapsed",
+            mask_len
+        ));
+    }
+}
+
+/// numbered decisions, one per line
+impl Display for Explainer {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+        if self.steps.is_empty() {
+            return write!(f, "nothing was pushed within {}", self.subnet);
+        }
+        for (n, step) in self.steps.iter().enumerate() {
+            if n > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}. {}", n + 1, step)?;
+        }
+        Ok(())
+    }
+}
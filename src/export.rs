@@ -0,0 +1,296 @@
+use std::{cmp::Ordering, str::FromStr};
+
+use crate::{
+    rank_subnets, rank_weighted, AddressClass, Density, Error, Labels, Metadata, NetworkKind,
+    Provenance, Scoring, Seen, SeenMap, Subnet, SubnetIndex, SubnetsMap, WeightsMap,
+};
+
+/// wider subnets are blocked address by address, e.g. the 0.0.0.0/0 of scattered addresses
+const WIDEST_RULE: u8 = 16;
+
+/// what the report is written as
+#[derive(Debug, Default, Clone, PartialEq)]
+pub enum OutputFormat {
+    /// human-readable text to stdout
+    #[default]
+    Text,
+    /// a JSON line per address to stdout as soon as it's read
+    Ndjson,
+    /// a JSON line per subnet of the report to stdout
+    NdjsonSubnets,
+    /// a single JSON document with the subnets nested by containment to stdout, see write_json
+    Json,
+    /// GeoJSON FeatureCollection with a point per located subnet
+    Geojson,
+    /// DNS Response Policy Zone with a rpz-ip rule per subnet
+    Rpz,
+    /// nft commands dropping traffic from the subnets with the evidence commented, for nft -f
+    Nft,
+    /// iptables-restore chain dropping traffic from the subnets with the evidence commented
+    Iptables,
+    /// ipset restore script of a hash:net set of the subnets with the evidence commented
+    Ipset,
+    /// text table of the addresses by country of the metadata to stdout
+    Countries,
+    /// subnets clustered by organization of the metadata as text to stdout
+    Orgs,
+    /// SQLite database with subnets, addresses and enrichment tables
+    #[cfg(feature = "sqlite")]
+    Sqlite,
+    /// Parquet file with a row per address
+    #[cfg(feature = "parquet")]
+    Parquet,
+}
+
+impl FromStr for OutputFormat {
+    type Err = Error;
+
+    /// parse one of: text, json, ndjson, ndjson-subnets, geojson, rpz, nft, iptables, ipset, countries, orgs,
+    /// sqlite, parquet
+    fn from_str(src: &str) -> Result<Self, Self::Err> {
+        match src {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            "ndjson" => Ok(OutputFormat::Ndjson),
+            "ndjson-subnets" => Ok(OutputFormat::NdjsonSubnets),
+            "geojson" => Ok(OutputFormat::Geojson),
+            "rpz" => Ok(OutputFormat::Rpz),
+            "nft" => Ok(OutputFormat::Nft),
+            "iptables" => Ok(OutputFormat::Iptables),
+            "ipset" => Ok(OutputFormat::Ipset),
+            "countries" => Ok(OutputFormat::Countries),
+            "orgs" => Ok(OutputFormat::Orgs),
+            #[cfg(feature = "sqlite")]
+            "sqlite" => Ok(OutputFormat::Sqlite),
+            #[cfg(not(feature = "sqlite"))]
+            "sqlite" => Err(Error::ParseError {
+                input: src.to_string(),
+                reason: "built without the sqlite feature".to_string(),
+            }),
+            #[cfg(feature = "parquet")]
+            "parquet" => Ok(OutputFormat::Parquet),
+            #[cfg(not(feature = "parquet"))]
+            "parquet" => Err(Error::ParseError {
+                input: src.to_string(),
+                reason: "built without the parquet feature".to_string(),
+            }),
+            _ => Err(Error::ParseError {
+                input: src.to_string(),
+                reason: "unknown output format".to_string(),
+            }),
+        }
+    }
+}
+
+impl OutputFormat {
+    /// name of the format as it's parsed, the exporter of it is registered under it, see Exporters
+    pub fn name(&self) -> &'static str {
+        match self {
+            OutputFormat::Text => "text",
+            OutputFormat::Ndjson => "ndjson",
+            OutputFormat::NdjsonSubnets => "ndjson-subnets",
+            OutputFormat::Json => "json",
+            OutputFormat::Geojson => "geojson",
+            OutputFormat::Rpz => "rpz",
+            OutputFormat::Nft => "nft",
+            OutputFormat::Iptables => "iptables",
+            OutputFormat::Ipset => "ipset",
+            OutputFormat::Countries => "countries",
+            OutputFormat::Orgs => "orgs",
+            #[cfg(feature = "sqlite")]
+            OutputFormat::Sqlite => "sqlite",
+            #[cfg(feature = "parquet")]
+            OutputFormat::Parquet => "parquet",
+        }
+    }
+
+    /// whether the report is firewall rules, their comments tell the files the addresses came from
+    pub fn is_firewall(&self) -> bool {
+        matches!(
+            self,
+            OutputFormat::Nft | OutputFormat::Iptables | OutputFormat::Ipset
+        )
+    }
+
+    /// whether the report goes to stdout rather than to a file
+    pub fn is_streamed(&self) -> bool {
+        matches!(
+            self,
+            OutputFormat::Text
+                | OutputFormat::Ndjson
+                | OutputFormat::NdjsonSubnets
+                | OutputFormat::Json
+                | OutputFormat::Nft
+                | OutputFormat::Iptables
+                | OutputFormat::Ipset
+                | OutputFormat::Countries
+                | OutputFormat::Orgs
+        )
+    }
+}
+
+/// everything a report is made of, for the exporters, see Report::new
+#[non_exhaustive]
+pub struct Report<'a> {
+    pub subnets: &'a SubnetsMap,
+    pub weights: Option<&'a WeightsMap>, // rank by hits if given
+    pub address_hits: Option<&'a WeightsMap>, // hits of the single addresses
+    pub seen: Option<&'a SeenMap>,       // first and last time the subnets were seen
+    pub sources: Option<&'a Provenance>, // files the addresses were read from
+    pub top: Option<usize>,              // only N subnets ranked the highest
+    pub scoring: Option<&'a Scoring>,    // score the subnets and rank them by the score if given
+    pub labels: &'a Labels,
+    pub metadata: &'a Metadata,
+}
+
+/// a subnet of the report as the exporters see it
+pub(crate) struct SubnetRow<'a> {
+    pub(crate) name: &'a str,
+    pub(crate) label: Option<&'a str>,
+    pub(crate) density: Option<Density>,
+    pub(crate) kind: Option<NetworkKind>, // the kind most of the addresses belong to
+    pub(crate) class: Option<AddressClass>, // the class all the addresses belong to
+    pub(crate) hits: Option<u64>,
+    pub(crate) score: Option<f64>, // 0 to 100 with a single decimal, see Scoring
+    pub(crate) seen: Option<Seen>,
+    pub(crate) sources: Option<Vec<&'a str>>, // files any of the addresses came from
+    pub(crate) top_source: Option<&'a str>,   // the file most of the addresses came from
+    pub(crate) addresses: &'a [String],
+}
+
+impl SubnetRow<'_> {
+    /// subnets a blocking rule is written for, the addresses if the row is too wide to block at once
+    /// or isn't a subnet at all, e.g. a class or a metadata value
+    pub(crate) fn rules(&self) -> Vec<Subnet> {
+        match Subnet::from_str(self.name) {
+            Ok(subnet) if subnet.mask_len >= WIDEST_RULE => vec![subnet],
+            _ => self
+                .addresses
+                .iter()
+                .filter_map(|addr| Subnet::from_str(addr).ok())
+                .collect(),
+        }
+    }
+}
+
+impl<'a> Report<'a> {
+    /// the subnets with nothing but their addresses, the optional columns are set one by one, e.g.
+    /// Report::new(&subnets, &labels, &metadata).top(Some(10))
+    pub fn new(subnets: &'a SubnetsMap, labels: &'a Labels, metadata: &'a Metadata) -> Self {
+        Self {
+            subnets,
+            weights: None,
+            address_hits: None,
+            seen: None,
+            sources: None,
+            top: None,
+            scoring: None,
+            labels,
+            metadata,
+        }
+    }
+
+    pub fn weights(mut self, weights: Option<&'a WeightsMap>) -> Self {
+        self.weights = weights;
+        self
+    }
+
+    pub fn address_hits(mut self, address_hits: Option<&'a WeightsMap>) -> Self {
+        self.address_hits = address_hits;
+        self
+    }
+
+    pub fn seen(mut self, seen: Option<&'a SeenMap>) -> Self {
+        self.seen = seen;
+        self
+    }
+
+    pub fn sources(mut self, sources: Option<&'a Provenance>) -> Self {
+        self.sources = sources;
+        self
+    }
+
+    pub fn top(mut self, top: Option<usize>) -> Self {
+        self.top = top;
+        self
+    }
+
+    /// see Scoring
+    pub fn scoring(mut self, scoring: Option<&'a Scoring>) -> Self {
+        self.scoring = scoring;
+        self
+    }
+
+    /// # index of all the subnets of the report, top ones or not, to look addresses up in, e.g.
+    /// report.subnet_index().subnet_of(&addr)
+    pub fn subnet_index(&self) -> SubnetIndex<'a> {
+        SubnetIndex::of(self.subnets)
+    }
+
+    /// subnets in the report's order along with everything known about them
+    pub(crate) fn rows(&self) -> Vec<SubnetRow<'a>> {
+        let ranked = match self.weights {
+            Some(weights) => rank_weighted(self.subnets, weights),
+            None => rank_subnets(self.subnets),
+        };
+        let top = self.top.unwrap_or(ranked.len());
+        // every subnet has to be scored before the top ones are known
+        let take = if self.scoring.is_some() {
+            ranked.len()
+        } else {
+            top
+        };
+        let mut rows: Vec<SubnetRow> = ranked
+            .into_iter()
+            .take(take)
+            .map(|(name, addresses)| {
+                let subnet = Subnet::from_str(name).ok();
+                let addrs: Vec<Subnet> = addresses
+                    .iter()
+                    .filter_map(|addr| Subnet::from_str(addr).ok())
+                    .collect();
+                SubnetRow {
+                    name,
+                    label: subnet.as_ref().and_then(|subnet| {
+                        self.labels
+                            .label_of(subnet)
+                            .or_else(|| subnet.special_range())
+                    }),
+                    density: Density::of(name, addresses),
+                    kind: NetworkKind::of_most(self.metadata, addresses),
+                    class: AddressClass::of_all(&addrs),
+                    hits: self
+                        .weights
+                        .map(|weights| weights.get(name).copied().unwrap_or_default()),
+                    score: None,
+                    seen: self.seen.and_then(|seen| seen.get(name).copied()),
+                    sources: self
+                        .sources
+                        .map(|sources| sources.sources_of_all(addresses)),
+                    top_source: self
+                        .sources
+                        .and_then(|sources| sources.top_source_of(addresses)),
+                    addresses,
+                }
+            })
+            .collect();
+        if let Some(scoring) = self.scoring {
+            let now = self
+                .seen
+                .and_then(|seen| seen.values().map(|seen| seen.last).max())
+                .unwrap_or_default();
+            for row in &mut rows {
+                let hits = row.hits.or_else(|| {
+                    let hits = self.address_hits?;
+                    Some(row.addresses.iter().filter_map(|addr| hits.get(addr)).sum())
+                });
+                let score = scoring.score(row.density, row.addresses.len(), hits, row.seen, now);
+                row.score = Some((score * 10.0).round() / 10.0);
+            }
+            // stable, so ties keep the order of the ranking
+            rows.sort_by(|r1, r2| r2.score.partial_cmp(&r1.score).unwrap_or(Ordering::Equal));
+            rows.truncate(top);
+        }
+        rows
+    }
+}
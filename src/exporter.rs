@@ -0,0 +1,142 @@
+use std::io::Write;
+
+use crate::{
+    firewall::{write_ipset, write_iptables, write_nft},
+    geojson::write_geojson,
+    io::write_report,
+    rpz::{serial_now, write_rpz},
+    write_json, write_ndjson_subnets, Error, Report,
+};
+
+/// writes a report in some format, register it in Exporters to make it one more output format
+pub trait Exporter {
+    /// name of the format, e.g. geojson as --output-format takes it
+    fn name(&self) -> &str;
+
+    /// # write the whole report to the output
+    /// # returns
+    /// Err - if the output can't be written
+    fn write(&self, report: &Report, out: &mut dyn Write) -> Result<(), Error>;
+}
+
+/// an exporter of this crate
+struct Builtin {
+    name: &'static str,
+    write: fn(&Report, &mut dyn Write) -> Result<(), Error>,
+}
+
+impl Exporter for Builtin {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn write(&self, report: &Report, out: &mut dyn Write) -> Result<(), Error> {
+        (self.write)(report, out)
+    }
+}
+
+/// exporters by their names, the formats of this crate are registered from the start,
+/// but the file-only ones, sqlite and parquet
+pub struct Exporters {
+    exporters: Vec<Box<dyn Exporter>>, // in the registration order
+}
+
+impl Default for Exporters {
+    fn default() -> Self {
+        let builtins = [
+            Builtin {
+                name: "text",
+                write: |report, out| Ok(write_report(report, out)?),
+            },
+            Builtin {
+                name: "json",
+                write: |report, mut out| write_json(report, &mut out),
+            },
+            Builtin {
+                name: "ndjson-subnets",
+                write: |report, mut out| write_ndjson_subnets(report, &mut out),
+            },
+            Builtin {
+                name: "geojson",
+                write: |report, mut out| write_geojson(report, &mut out),
+            },
+            Builtin {
+                name: "rpz",
+                write: |report, mut out| write_rpz(report, serial_now(), &mut out),
+            },
+            Builtin {
+                name: "nft",
+                write: |report, mut out| write_nft(report, &mut out),
+            },
+            Builtin {
+                name: "iptables",
+                write: |report, mut out| write_iptables(report, &mut out),
+            },
+            Builtin {
+                name: "ipset",
+                write: |report, mut out| write_ipset(report, &mut out),
+            },
+            Builtin {
+                name: "countries",
+                write: |report, out| Ok(writeln!(out, "{}", report.country_summary())?),
+            },
+            Builtin {
+                name: "orgs",
+                write: |report, out| {
+                    for org in report.org_clusters() {
+                        writeln!(out, "{}", org)?;
+                    }
+                    Ok(())
+                },
+            },
+        ];
+        let mut exporters = Self { exporters: vec![] };
+        for builtin in builtins {
+            exporters.register(builtin);
+        }
+        exporters
+    }
+}
+
+impl Exporters {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// add the exporter, it replaces the one registered under the same name before
+    pub fn register(&mut self, exporter: impl Exporter + 'static) {
+        self.exporters
+            .retain(|registered| registered.name() != exporter.name());
+        self.exporters.push(Box::new(exporter));
+    }
+
+    /// # exporter of the format
+    /// # returns
+    /// None - if nothing is registered under the name
+    pub fn get(&self, name: &str) -> Option<&dyn Exporter> {
+        self.exporters
+            .iter()
+            .find(|exporter| exporter.name() == name)
+            .map(Box::as_ref)
+    }
+
+    /// names of the formats in the registration order
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.exporters.iter().map(|exporter| exporter.name())
+    }
+
+    /// # write the report in the format
+    /// # returns
+    /// Err - if nothing is registered under the name or the output can't be written
+    pub fn write(&self, name: &str, report: &Report, out: &mut dyn Write) -> Result<(), Error> {
+        let exporter = self.get(name).ok_or_else(|| Error::ParseError {
+            input: name.to_string(),
+            reason: format!(
+                "unknown output format, try one of: {}",
+                self.names().collect::<Vec<_>>().join(", ")
+            ),
+        })?;
+        exporter.write(report, out)?;
+        Ok(out.flush()?)
+    }
+}
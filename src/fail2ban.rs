@@ -0,0 +1,28 @@
+use crate::{Error, Subnet};
+
+/// # jail.local snippet making fail2ban never ban the subnets, e.g. the known good ones
+/// fail2ban's own default of localhost is kept
+pub fn fail2ban_ignoreip(subnets: &[Subnet]) -> String {
+    let mut snippet = "[DEFAULT]\nignoreip = 127.0.0.1/8 ::1".to_string();
+    for subnet in subnets {
+        snippet += &format!("\n           {}", subnet);
+    }
+    snippet + "\n"
+}
+
+/// # fail2ban-client commands banning the subnets in the jail, a line per subnet
+/// # returns
+/// Err - if the jail name isn't safe to paste into a shell
+pub fn fail2ban_banip(jail: &str, subnets: &[Subnet]) -> Result<String, Error> {
+    let is_safe = |c: char| c.is_ascii_alphanumeric() || "-_.".contains(c);
+    if jail.is_empty() || !jail.chars().all(is_safe) {
+        return Err(Error::InvalidArgument(format!(
+            "jail name {:?} should consist of letters, digits, -, _ and .",
+            jail
+        )));
+    }
+    Ok(subnets
+        .iter()
+        .map(|subnet| format!("fail2ban-client set {} banip {}\n", jail, subnet))
+        .collect())
+}
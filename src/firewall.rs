@@ -0,0 +1,124 @@
+use std::{io::Write, path::Path};
+
+use crate::{export::SubnetRow, Error, Report, TimeBucket};
+
+/// nft takes the shortest comments of the three, 128 bytes
+const MAX_COMMENT_LEN: usize = 128;
+/// name of the nft table and chain, the iptables chain and the ipset set written
+const NAME: &str = "ipv4_classify";
+
+/// # write the report as nft commands dropping traffic from the subnets, for nft -f
+/// every rule is commented with the evidence, see evidence_of
+/// # returns
+/// Err - if the output can't be written
+pub(crate) fn write_nft(report: &Report, out: &mut impl Write) -> Result<(), Error> {
+    writeln!(out, "add table inet {}", NAME)?;
+    writeln!(
+        out,
+        "add chain inet {} input {{ type filter hook input priority 0; policy accept; }}",
+        NAME
+    )?;
+    writeln!(out, "flush chain inet {} input", NAME)?;
+    for row in report.rows() {
+        let evidence = evidence_of(&row);
+        for subnet in row.rules() {
+            writeln!(
+                out,
+                "add rule inet {} input ip saddr {} drop comment \"{}\"",
+                NAME, subnet, evidence
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// # write the report as an iptables-restore chain dropping traffic from the subnets,
+/// for iptables-restore --noflush, the chain is to be jumped to, e.g. from INPUT
+/// every rule is commented with the evidence, see evidence_of
+/// # returns
+/// Err - if the output can't be written
+pub(crate) fn write_iptables(report: &Report, out: &mut impl Write) -> Result<(), Error> {
+    let chain = NAME.to_uppercase();
+    writeln!(out, "# jump to it with: iptables -I INPUT -j {}", chain)?;
+    writeln!(out, "*filter")?;
+    writeln!(out, ":{} - [0:0]", chain)?;
+    for row in report.rows() {
+        let evidence = evidence_of(&row);
+        for subnet in row.rules() {
+            writeln!(
+                out,
+                "-A {} -s {} -m comment --comment \"{}\" -j DROP",
+                chain, subnet, evidence
+            )?;
+        }
+    }
+    writeln!(out, "COMMIT")?;
+    Ok(())
+}
+
+/// # write the report as an ipset restore script filling a hash:net set with the subnets
+/// every entry is commented with the evidence, see evidence_of
+/// # returns
+/// Err - if the output can't be written
+pub(crate) fn write_ipset(report: &Report, out: &mut impl Write) -> Result<(), Error> {
+    writeln!(out, "create {} hash:net comment -exist", NAME)?;
+    writeln!(out, "flush {}", NAME)?;
+    for row in report.rows() {
+        let evidence = evidence_of(&row);
+        for subnet in row.rules() {
+            writeln!(out, "add {} {} comment \"{}\"", NAME, subnet, evidence)?;
+        }
+    }
+    Ok(())
+}
+
+/// # why the subnet is blocked as key=value pairs, the ones not known are left out, e.g.
+/// subnet=203.0.113.0/24 addresses=12 class=bogon first_seen=2024-01-01 last_seen=2024-02-01 source=feed.txt
+/// first and last seen come from a tracked tree, the source is the file most of the addresses came from,
+/// the values are made safe for a quoted comment, the evidence is cut to MAX_COMMENT_LEN
+fn evidence_of(row: &SubnetRow) -> String {
+    // a group may be a metadata value, e.g. with GroupBy::Column
+    let mut evidence = format!(
+        "subnet={} addresses={}",
+        comment_safe(row.name),
+        row.addresses.len()
+    );
+    if let Some(class) = row.class {
+        evidence += &format!(" class={}", class);
+    }
+    if let Some(score) = row.score {
+        evidence += &format!(" score={}", score);
+    }
+    if let Some(hits) = row.hits {
+        evidence += &format!(" hits={}", hits);
+    }
+    if let Some(seen) = row.seen {
+        evidence += &format!(
+            " first_seen={} last_seen={}",
+            TimeBucket::Day.name_of(seen.first),
+            TimeBucket::Day.name_of(seen.last)
+        );
+    }
+    if let Some(source) = row.top_source {
+        let source = Path::new(source)
+            .file_name()
+            .map_or(source.into(), |name| name.to_string_lossy());
+        evidence += &format!(" source={}", comment_safe(&source));
+    }
+    while evidence.len() > MAX_COMMENT_LEN {
+        evidence.pop();
+    }
+    evidence
+}
+
+/// the value with _ instead of what would break the comment or the pairs: quotes, backslashes, spaces and control characters
+fn comment_safe(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| match c {
+            '"' | '\\' | ' ' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect()
+}
@@ -0,0 +1,70 @@
+use std::collections::HashSet;
+
+use crate::{Error, Subnet};
+
+/// # generate a synthetic list of addresses clustered into random subnets
+/// subnets - number of clusters, each is a random subnet with mask_len significant bits
+/// hosts_per_subnet - number of distinct addresses to pick from every cluster
+/// seed - same seed produces the same list, so it's fine to reference it in bug reports
+/// # returns
+/// Err - if the clusters can't fit that many hosts or there are too many clusters for the mask
+pub fn generate(
+    subnets: usize,
+    hosts_per_subnet: usize,
+    mask_len: u8,
+    seed: u64,
+) -> Result<Vec<String>, Error> {
+    if mask_len > 32 {
+        return Err(Error::MaskTooLong(mask_len));
+    }
+    let hosts_capacity = 1u64 << (32 - mask_len);
+    if hosts_per_subnet as u64 > hosts_capacity {
+        return Err(Error::InvalidArgument(format!(
+            "/{} can't fit {} hosts",
+            mask_len, hosts_per_subnet
+        )));
+    }
+    if subnets as u64 > 1u64 << mask_len {
+        return Err(Error::InvalidArgument(format!(
+            "there are less than {} /{} subnets",
+            subnets, mask_len
+        )));
+    }
+
+    let mut rng = XorShift::new(seed);
+    let mask = Subnet::new(0, 0, 0, 0, mask_len)?.mask;
+    let mut bases = HashSet::new();
+    let mut res = Vec::with_capacity(subnets * hosts_per_subnet);
+    while bases.len() < subnets {
+        let base = rng.next_u32() & mask;
+        if !bases.insert(base) {
+            continue;
+        }
+        let mut hosts = HashSet::new();
+        while hosts.len() < hosts_per_subnet {
+            let host = base | (rng.next_u32() & !mask);
+            if hosts.insert(host) {
+                let [o1, o2, o3, o4] = host.to_be_bytes();
+                res.push(Subnet::new(o1, o2, o3, o4, 32)?.to_string());
+            }
+        }
+    }
+    Ok(res)
+}
+
+/// tiny deterministic PRNG, so generated data doesn't depend on external crates' algorithms
+struct XorShift(u64);
+
+impl XorShift {
+    fn new(seed: u64) -> Self {
+        // zero state would produce only zeroes
+        Self(seed ^ 0x9E37_79B9_7F4A_7C15)
+    }
+
+    fn next_u32(&mut self) -> u32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 >> 32) as u32
+    }
+}
@@ -0,0 +1,75 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+    str::FromStr,
+};
+
+use crate::{metadata::most_common, ndjson::json_string, Error, Report, Subnet};
+
+/// metadata columns the coordinates are taken from, the first one present wins
+const LATITUDE: [&str; 2] = ["lat", "latitude"];
+const LONGITUDE: [&str; 3] = ["lon", "lng", "longitude"];
+
+/// # write the report as a GeoJSON FeatureCollection with a point per subnet
+/// the point is the average of the subnet's addresses coordinates taken from the metadata,
+/// asn and org are the most common metadata values of the addresses,
+/// subnets without any located address are left out as a point needs coordinates
+/// # returns
+/// Err - if the file can't be written
+pub fn export_geojson(report: &Report, file_name: impl AsRef<Path>) -> Result<(), Error> {
+    let mut out = BufWriter::new(File::create(file_name)?);
+    write_geojson(report, &mut out)?;
+    Ok(out.flush()?)
+}
+
+pub(crate) fn write_geojson(report: &Report, out: &mut impl Write) -> Result<(), Error> {
+    write!(out, "{{\"type\":\"FeatureCollection\",\"features\":[")?;
+    let mut separator = "\n";
+    for row in report.rows() {
+        let addrs: Vec<Subnet> = row
+            .addresses
+            .iter()
+            .filter_map(|address| Subnet::from_str(address).ok())
+            .collect();
+        let value = |addr: &Subnet, columns: &[&str]| {
+            columns
+                .iter()
+                .find_map(|column| report.metadata.value_of(addr, column))
+        };
+        let coordinates: Vec<(f64, f64)> = addrs
+            .iter()
+            .filter_map(|addr| {
+                let lat = value(addr, &LATITUDE)?.parse::<f64>().ok()?;
+                let lon = value(addr, &LONGITUDE)?.parse::<f64>().ok()?;
+                (lat.is_finite() && lon.is_finite()).then_some((lon, lat))
+            })
+            .collect();
+        if coordinates.is_empty() {
+            continue;
+        }
+        let n = coordinates.len() as f64;
+        let lon = coordinates.iter().map(|(lon, _)| lon).sum::<f64>() / n;
+        let lat = coordinates.iter().map(|(_, lat)| lat).sum::<f64>() / n;
+        let most_common =
+            |column| most_common(addrs.iter().filter_map(|addr| value(addr, &[column])));
+        write!(
+            out,
+            "{}{{\"type\":\"Feature\",\"geometry\":{{\"type\":\"Point\",\"coordinates\":[{},{}]}},\
+             \"properties\":{{\"cidr\":{},\"count\":{},\"asn\":{},\"org\":{},\"kind\":{},\"class\":{},\"score\":{}}}}}",
+            separator,
+            lon,
+            lat,
+            json_string(Some(row.name)),
+            row.addresses.len(),
+            json_string(most_common("asn")),
+            json_string(most_common("org")),
+            json_string(row.kind.map(|kind| kind.to_string()).as_deref()),
+            json_string(row.class.map(|class| class.name())),
+            row.score.map_or("null".to_string(), |score| score.to_string())
+        )?;
+        separator = ",\n";
+    }
+    writeln!(out, "\n]}}")?;
+    Ok(())
+}
@@ -0,0 +1,57 @@
+use std::{collections::HashMap, str::FromStr};
+
+use crate::{subnet::mask_of, Subnet, SubnetsMap};
+
+/// # reported subnets by address, built once to answer lots of lookups without walking the tree, see Report::subnet_index
+/// an address falls into the most specific reported subnet containing it,
+/// addresses of groups which aren't subnets, e.g. class A, fall into the group they're reported in
+#[derive(Debug, Default)]
+pub struct SubnetIndex<'a> {
+    subnets: HashMap<(u32, u8), &'a str>,
+    mask_lens: Vec<u8>,                     // of the subnets, the longest first
+    addresses: HashMap<(u32, u8), &'a str>, // of the groups which aren't subnets
+}
+
+impl<'a> SubnetIndex<'a> {
+    /// index the groups of the map, e.g. of AddressTree::get_groups_map
+    pub fn of(subnets: &'a SubnetsMap) -> Self {
+        let mut index = Self::default();
+        for (name, addrs) in subnets {
+            match Subnet::from_str(name) {
+                Ok(subnet) => {
+                    index.subnets.insert((subnet.bits, subnet.mask_len), name);
+                }
+                Err(_) => {
+                    for addr in addrs.iter().filter_map(|addr| Subnet::from_str(addr).ok()) {
+                        index.addresses.insert((addr.bits, addr.mask_len), name);
+                    }
+                }
+            }
+        }
+        index.mask_lens = index
+            .subnets
+            .keys()
+            .map(|&(_, mask_len)| mask_len)
+            .collect();
+        index.mask_lens.sort_unstable_by(|m1, m2| m2.cmp(m1));
+        index.mask_lens.dedup();
+        index
+    }
+
+    /// # the reported subnet (or group) the address (or subnet) falls into
+    /// # returns
+    /// None - if no reported subnet contains it
+    pub fn subnet_of(&self, addr: &Subnet) -> Option<&'a str> {
+        let found = self
+            .mask_lens
+            .iter()
+            .filter(|&&mask_len| mask_len <= addr.mask_len)
+            .find_map(|&mask_len| {
+                let bits = addr.bits & mask_of(mask_len);
+                self.subnets.get(&(bits, mask_len))
+            });
+        found
+            .or_else(|| self.addresses.get(&(addr.bits, addr.mask_len)))
+            .copied()
+    }
+}
@@ -0,0 +1,679 @@
+use std::{
+    cmp::Reverse,
+    io::{stdout, Write},
+    str::FromStr,
+};
+#[cfg(feature = "fs")]
+use std::{
+    fmt::{Display, Formatter},
+    path::{Path, PathBuf},
+};
+
+#[cfg(feature = "fs")]
+use crate::{
+    content::FileContent, find_groups_in, CancelToken, FileSource, Observer, Pipeline, StderrLogger,
+};
+use crate::{
+    AddressClass, AddressTree, Error, GroupBy, Labels, Metadata, Report, Subnet, SubnetSet,
+    SubnetsMap, TimeBucket, WeightsMap,
+};
+
+/// average length of a line with an address, used to guess the number of addresses from a file size
+#[cfg(feature = "fs")]
+const AVG_LINE_LEN: usize = 14;
+
+/// # parse a \n-separated list of IP addresses from the provided files into subnets
+/// # returns
+/// Err - if one of the files cannot be read, some line isn't a correct IP address or smth else went terribly wrong
+#[cfg(feature = "fs")]
+pub fn find_subnets(
+    file_names: impl IntoIterator<Item = impl AsRef<Path>>,
+) -> Result<SubnetsMap, Error> {
+    find_groups(file_names, &GroupBy::Subnet)
+}
+
+/// same as find_subnets, but addresses are grouped according to group_by
+#[cfg(feature = "fs")]
+pub fn find_groups(
+    file_names: impl IntoIterator<Item = impl AsRef<Path>>,
+    group_by: &GroupBy,
+) -> Result<SubnetsMap, Error> {
+    find_groups_in(file_names.into_iter().map(FileSource::new), group_by)
+}
+
+/// # read addresses from the files into a tree, for the analyses working on the tree itself
+/// # returns
+/// Err - if one of the files cannot be read or some line isn't a correct IP address
+#[cfg(feature = "fs")]
+pub fn read_tree(
+    file_names: impl IntoIterator<Item = impl AsRef<Path>>,
+) -> Result<AddressTree, Error> {
+    let mut address_tree = AddressTree::new();
+    read_files(
+        &mut address_tree,
+        file_names,
+        false,
+        &CancelToken::new(),
+        &mut StderrLogger,
+        |_| true,
+    )?;
+    Ok(address_tree)
+}
+
+/// a file or a line of it which couldn't be classified
+#[cfg(feature = "fs")]
+#[derive(Debug)]
+pub struct Failure {
+    pub file_name: PathBuf,
+    pub line_no: Option<usize>, // 1-based, None if the whole file failed
+    pub offset: Option<usize>,  // bytes before the line in the file
+    pub line: Option<String>,   // the offending line without surrounding whitespaces
+    pub reason: Error,
+}
+
+#[cfg(feature = "fs")]
+impl Failure {
+    /// the whole file couldn't be read
+    fn of_file(file_name: &Path, reason: Error) -> Self {
+        Self {
+            file_name: file_name.to_path_buf(),
+            line_no: None,
+            offset: None,
+            line: None,
+            reason,
+        }
+    }
+
+    /// line_no is 0-based as enumerate() gives it
+    fn of_line(file_name: &Path, line_no: usize, offset: usize, line: &str, reason: Error) -> Self {
+        Self {
+            file_name: file_name.to_path_buf(),
+            line_no: Some(line_no + 1),
+            offset: Some(offset),
+            line: Some(line.to_string()),
+            reason,
+        }
+    }
+}
+
+#[cfg(feature = "fs")]
+impl Display for Failure {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+        match (self.line_no, self.offset, &self.line) {
+            (Some(line_no), Some(offset), Some(line)) => write!(
+                f,
+                "{}:{} (byte {}): {} in line {:?}",
+                self.file_name.display(),
+                line_no,
+                offset,
+                self.reason,
+                line
+            ),
+            _ => write!(f, "{}: {}", self.file_name.display(), self.reason),
+        }
+    }
+}
+
+/// a failed line becomes Error::LineError, a failed file is its reason as it is
+#[cfg(feature = "fs")]
+impl From<Failure> for Error {
+    fn from(failure: Failure) -> Self {
+        match (failure.line_no, failure.offset, failure.line) {
+            (Some(line_no), Some(offset), Some(line)) => Error::LineError {
+                file_name: failure.file_name,
+                line_no,
+                offset,
+                line,
+                reason: Box::new(failure.reason),
+            },
+            _ => failure.reason,
+        }
+    }
+}
+
+/// # same as find_groups, but bad lines and unreadable files don't abort the run
+/// # returns
+/// subnets built from everything that could be read and parsed along with the list of failures
+#[cfg(feature = "fs")]
+pub fn find_groups_lenient(
+    file_names: impl IntoIterator<Item = impl AsRef<Path>>,
+    group_by: &GroupBy,
+) -> (SubnetsMap, Vec<Failure>) {
+    let mut address_tree = AddressTree::new();
+    let token = CancelToken::new();
+    match read_files(
+        &mut address_tree,
+        file_names,
+        true,
+        &token,
+        &mut StderrLogger,
+        |_| true,
+    ) {
+        Ok(failures) => (address_tree.get_groups_map(group_by), failures),
+        Err(_) => unreachable!("lenient reading collects errors instead of returning them"),
+    }
+}
+
+/// # same as find_groups or find_groups_lenient, but stops as soon as the token is cancelled
+/// # returns
+/// Ok - subnets built so far along with the list of failures (always empty if not lenient),
+/// check the token to find out whether the result is partial
+/// Err - if not lenient and some file cannot be read or some line isn't a correct IP address
+#[cfg(feature = "fs")]
+pub fn find_groups_cancellable(
+    file_names: impl IntoIterator<Item = impl AsRef<Path>>,
+    group_by: &GroupBy,
+    lenient: bool,
+    token: &CancelToken,
+) -> Result<(SubnetsMap, Vec<Failure>), Error> {
+    find_groups_observed(file_names, group_by, lenient, token, &mut StderrLogger)
+}
+
+/// # same as find_groups_cancellable, but progress goes to the observer instead of stderr
+/// the crate doesn't print anything on its own here
+#[cfg(feature = "fs")]
+pub fn find_groups_observed(
+    file_names: impl IntoIterator<Item = impl AsRef<Path>>,
+    group_by: &GroupBy,
+    lenient: bool,
+    token: &CancelToken,
+    observer: &mut dyn Observer,
+) -> Result<(SubnetsMap, Vec<Failure>), Error> {
+    let mut address_tree = AddressTree::new();
+    let failures = read_files(
+        &mut address_tree,
+        file_names,
+        lenient,
+        token,
+        observer,
+        |_| true,
+    )?;
+    Ok((address_tree.get_groups_map(group_by), failures))
+}
+
+/// # same as find_groups_cancellable, but no reported group encloses any of the whitelisted ranges
+/// groups are split as needed, see AddressTree::get_groups_map_except
+#[cfg(feature = "fs")]
+pub fn find_groups_except(
+    file_names: impl IntoIterator<Item = impl AsRef<Path>>,
+    group_by: &GroupBy,
+    whitelist: &SubnetSet,
+    lenient: bool,
+    token: &CancelToken,
+) -> Result<(SubnetsMap, Vec<Failure>), Error> {
+    let (address_tree, failures) = read_tree_cancellable(file_names, lenient, token)?;
+    Ok((
+        address_tree.get_groups_map_except(group_by, whitelist),
+        failures,
+    ))
+}
+
+/// same as read_tree, but lenient and cancellable as find_groups_cancellable
+#[cfg(feature = "fs")]
+pub fn read_tree_cancellable(
+    file_names: impl IntoIterator<Item = impl AsRef<Path>>,
+    lenient: bool,
+    token: &CancelToken,
+) -> Result<(AddressTree, Vec<Failure>), Error> {
+    let mut address_tree = AddressTree::new();
+    let failures = read_into_tree(&mut address_tree, file_names, lenient, token)?;
+    Ok((address_tree, failures))
+}
+
+/// same as read_tree_cancellable, but into a prepared tree e.g. AddressTree::with_boundaries
+#[cfg(feature = "fs")]
+pub fn read_into_tree(
+    address_tree: &mut AddressTree,
+    file_names: impl IntoIterator<Item = impl AsRef<Path>>,
+    lenient: bool,
+    token: &CancelToken,
+) -> Result<Vec<Failure>, Error> {
+    read_into_tree_observed(address_tree, file_names, lenient, token, &mut StderrLogger)
+}
+
+/// same as read_into_tree, but progress goes to the observer instead of stderr
+#[cfg(feature = "fs")]
+pub fn read_into_tree_observed(
+    address_tree: &mut AddressTree,
+    file_names: impl IntoIterator<Item = impl AsRef<Path>>,
+    lenient: bool,
+    token: &CancelToken,
+    observer: &mut dyn Observer,
+) -> Result<Vec<Failure>, Error> {
+    read_files(address_tree, file_names, lenient, token, observer, |_| true)
+}
+
+/// same as read_into_tree_observed, but every line goes through the pipeline before it's parsed
+#[cfg(feature = "fs")]
+pub fn read_into_tree_through(
+    address_tree: &mut AddressTree,
+    file_names: impl IntoIterator<Item = impl AsRef<Path>>,
+    lenient: bool,
+    token: &CancelToken,
+    observer: &mut dyn Observer,
+    pipeline: &Pipeline,
+) -> Result<Vec<Failure>, Error> {
+    read_files_through(
+        address_tree,
+        file_names,
+        lenient,
+        token,
+        observer,
+        |_| true,
+        pipeline,
+    )
+}
+
+/// push addresses from the files into the tree line by line until the token is cancelled
+/// lenient - collect failures instead of returning the first one
+/// admit - decides whether a parsed address goes to the tree, skipped ones still count as read
+#[cfg(feature = "fs")]
+pub(crate) fn read_files(
+    address_tree: &mut AddressTree,
+    file_names: impl IntoIterator<Item = impl AsRef<Path>>,
+    lenient: bool,
+    token: &CancelToken,
+    observer: &mut dyn Observer,
+    admit: impl FnMut(&Subnet) -> bool,
+) -> Result<Vec<Failure>, Error> {
+    read_files_through(
+        address_tree,
+        file_names,
+        lenient,
+        token,
+        observer,
+        admit,
+        &Pipeline::new(),
+    )
+}
+
+/// same as read_files, but every line goes through the pipeline before it's parsed
+#[cfg(feature = "fs")]
+fn read_files_through(
+    address_tree: &mut AddressTree,
+    file_names: impl IntoIterator<Item = impl AsRef<Path>>,
+    lenient: bool,
+    token: &CancelToken,
+    observer: &mut dyn Observer,
+    mut admit: impl FnMut(&Subnet) -> bool,
+    pipeline: &Pipeline,
+) -> Result<Vec<Failure>, Error> {
+    let reading = LineReading {
+        lenient,
+        token,
+        pipeline,
+    };
+    let mut failures = vec![];
+
+    for file_name in file_names {
+        let file_name = file_name.as_ref();
+        observer.on_file_started(file_name);
+        let content = match FileContent::read(file_name) {
+            Ok(content) => content,
+            Err(e) if lenient => {
+                failures.push(Failure::of_file(file_name, e));
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
+        failures.extend(reading.push(address_tree, file_name, &content, observer, &mut admit)?);
+        if token.is_cancelled() {
+            break;
+        }
+    }
+    Ok(failures)
+}
+
+/// how the lines of a file are pushed into the tree, the same for every way the files are read
+#[cfg(feature = "fs")]
+pub(crate) struct LineReading<'a> {
+    pub(crate) lenient: bool, // collect failures instead of returning the first one
+    pub(crate) token: &'a CancelToken, // stop at the next line once it's cancelled
+    pub(crate) pipeline: &'a Pipeline, // rewrites of the lines before they're parsed
+}
+
+#[cfg(feature = "fs")]
+impl LineReading<'_> {
+    /// # push the addresses of a \n-separated file content into the tree in the file's order
+    /// admit - decides whether a parsed address goes to the tree, skipped ones still count as read
+    /// # returns
+    /// the failures of the lines if lenient, the file isn't reported done if the token was cancelled
+    /// Err - the first failure otherwise
+    pub(crate) fn push(
+        &self,
+        address_tree: &mut AddressTree,
+        file_name: &Path,
+        content: &str,
+        observer: &mut dyn Observer,
+        admit: &mut dyn FnMut(&Subnet) -> bool,
+    ) -> Result<Vec<Failure>, Error> {
+        let mut failures = vec![];
+        address_tree.reserve(content.len() / AVG_LINE_LEN);
+        let mut addresses = 0;
+        let mut offset = 0;
+        for (line_no, line) in content.split('\n').enumerate() {
+            if self.token.is_cancelled() {
+                return Ok(failures);
+            }
+            let line_offset = offset;
+            offset += line.len() + 1;
+            let line = line.trim();
+            let processed;
+            let input = if self.pipeline.is_empty() {
+                line
+            } else {
+                processed = self.pipeline.apply(line);
+                processed.trim()
+            };
+            if input.is_empty() {
+                continue;
+            }
+            let reason = match parse_weighted_line(input) {
+                Ok((addr, hits)) => {
+                    observer.on_address_parsed(&addr);
+                    if !admit(&addr) {
+                        addresses += 1;
+                        continue;
+                    }
+                    match address_tree.push_weighted(addr.clone(), hits, observer) {
+                        Ok(_) => {
+                            observer.on_address_classified(&addr, address_tree);
+                            addresses += 1;
+                            continue;
+                        }
+                        Err(e) => e.into(),
+                    }
+                }
+                Err(e) => e,
+            };
+            let failure = Failure::of_line(file_name, line_no, line_offset, line, reason);
+            if !self.lenient {
+                return Err(failure.into());
+            }
+            failures.push(failure);
+        }
+        observer.on_file_done(file_name, addresses);
+        Ok(failures)
+    }
+}
+
+/// # parse a line with an address and an optional number of hits e.g. "1.2.3.4,573"
+/// # returns
+/// the address with its hits, 1 if the line has no number
+/// Err - if the address or the number can't be parsed
+pub fn parse_weighted_line(line: &str) -> Result<(Subnet, u64), Error> {
+    let Some((addr, hits)) = line.split_once(',') else {
+        return Ok((Subnet::from_str(line)?, 1));
+    };
+    let hits = hits.trim().parse().map_err(|e| Error::ParseError {
+        input: line.to_string(),
+        reason: format!("can't parse the number of hits: {}", e),
+    })?;
+    Ok((Subnet::from_str(addr.trim())?, hits))
+}
+
+/// # IPv4 addresses mentioned anywhere in a free-form text, e.g. a log line
+/// a dotted quad is taken as a whole word, so version numbers like 1.2.3.4.5 don't count
+pub(crate) fn addresses_in(text: &str) -> impl Iterator<Item = Subnet> + '_ {
+    text.split(|c: char| !c.is_ascii_digit() && c != '.')
+        .map(|word| word.trim_matches('.'))
+        .filter(|word| word.matches('.').count() == 3)
+        .filter_map(|word| Subnet::from_str(word).ok())
+}
+
+/// # parse IP addresses already available in memory into subnets
+/// empty lines are skipped, surrounding whitespaces are ignored
+/// # returns
+/// Err - if some line isn't a correct IP address
+pub fn classify_lines(
+    lines: impl IntoIterator<Item = impl AsRef<str>>,
+) -> Result<SubnetsMap, Error> {
+    group_lines(lines, &GroupBy::Subnet)
+}
+
+/// same as classify_lines, but addresses are grouped according to group_by
+pub fn group_lines(
+    lines: impl IntoIterator<Item = impl AsRef<str>>,
+    group_by: &GroupBy,
+) -> Result<SubnetsMap, Error> {
+    Ok(tree_of_lines(lines)?.get_groups_map(group_by))
+}
+
+/// same as group_lines, but groups come with their total hits, see parse_weighted_line
+pub fn weigh_lines(
+    lines: impl IntoIterator<Item = impl AsRef<str>>,
+    group_by: &GroupBy,
+) -> Result<(SubnetsMap, WeightsMap), Error> {
+    let address_tree = tree_of_lines(lines)?;
+    Ok((
+        address_tree.get_groups_map(group_by),
+        address_tree.get_weights_map(group_by),
+    ))
+}
+
+/// # classify the lines into a tree, see parse_weighted_line
+/// # returns
+/// Err - if some line can't be parsed
+pub(crate) fn tree_of_lines(
+    lines: impl IntoIterator<Item = impl AsRef<str>>,
+) -> Result<AddressTree, Error> {
+    let lines = lines.into_iter();
+    let mut address_tree = AddressTree::with_capacity(lines.size_hint().0);
+    for line in lines {
+        let line = line.as_ref().trim();
+        if line.is_empty() {
+            continue;
+        }
+        let (addr, hits) = parse_weighted_line(line)?;
+        address_tree.push_weighted(addr, hits, &mut ())?;
+    }
+    Ok(address_tree)
+}
+
+/// order subnets by the number of member addresses, biggest first
+/// subnets of the same size are ordered by name to keep the output stable
+pub fn rank_subnets(subnets: &SubnetsMap) -> Vec<(&String, &Vec<String>)> {
+    let mut ranked: Vec<(&String, &Vec<String>)> = subnets.iter().collect();
+    ranked.sort_by(|(s1, ips1), (s2, ips2)| ips2.len().cmp(&ips1.len()).then(s1.cmp(s2)));
+    ranked
+}
+
+/// drop subnets with fewer than min_count addresses, e.g. singletons which are mostly noise
+pub fn retain_min_count(subnets: &mut SubnetsMap, min_count: usize) {
+    subnets.retain(|_, ips| ips.len() >= min_count);
+}
+
+/// same as rank_subnets, but by total hits, then by the number of addresses
+pub fn rank_weighted<'a>(
+    subnets: &'a SubnetsMap,
+    weights: &WeightsMap,
+) -> Vec<(&'a String, &'a Vec<String>)> {
+    let hits = |s: &String| weights.get(s).copied().unwrap_or_default();
+    let mut ranked = rank_subnets(subnets);
+    // stable, so ties keep the order of rank_subnets
+    ranked.sort_by_key(|(s, _)| Reverse(hits(s)));
+    ranked
+}
+
+/// print subnets with their addresses to stdout
+/// top limits the report to the N subnets with the most addresses
+pub fn print_subnets(subnets: &SubnetsMap, top: Option<usize>) {
+    print_labeled_subnets(subnets, top, &Labels::new())
+}
+
+/// same as print_subnets, but subnets and addresses are tagged with their labels e.g. [corp]
+pub fn print_labeled_subnets(subnets: &SubnetsMap, top: Option<usize>, labels: &Labels) {
+    print_joined_subnets(subnets, None, top, labels, &Metadata::new())
+}
+
+/// same as print_labeled_subnets, but subnets are ranked by their hits, which are printed too
+pub fn print_weighted_subnets(
+    subnets: &SubnetsMap,
+    weights: &WeightsMap,
+    top: Option<usize>,
+    labels: &Labels,
+) {
+    print_joined_subnets(subnets, Some(weights), top, labels, &Metadata::new())
+}
+
+/// # print subnets tagged with labels, addresses also get their metadata columns e.g. username=alice
+/// subnets are ranked by their hits if weights are given, see print_weighted_subnets
+pub fn print_joined_subnets(
+    subnets: &SubnetsMap,
+    weights: Option<&WeightsMap>,
+    top: Option<usize>,
+    labels: &Labels,
+    metadata: &Metadata,
+) {
+    print_report(
+        &Report::new(subnets, labels, metadata)
+            .weights(weights)
+            .top(top),
+    )
+}
+
+/// same as print_joined_subnets for a report put together already
+pub fn print_report(report: &Report) {
+    write_report(report, &mut stdout().lock()).expect("failed printing to stdout")
+}
+
+/// the report as print_report prints it
+pub(crate) fn write_report(report: &Report, out: &mut dyn Write) -> std::io::Result<()> {
+    let rows = report.rows();
+    if rows.len() < report.subnets.len() {
+        writeln!(
+            out,
+            "top {} of {} subnets found:",
+            rows.len(),
+            report.subnets.len()
+        )?;
+    } else {
+        writeln!(out, "subnets found:")?;
+    }
+    for row in rows {
+        // subnets without a label of their own are tagged with their special range, e.g. [multicast]
+        let tag = row
+            .label
+            .map(|label| format!(" [{}]", label))
+            .unwrap_or_default();
+        let tag = match row.kind {
+            Some(kind) => format!("{}, {}", tag, kind),
+            None => tag,
+        };
+        // public is what most subnets are, only the special classes are worth a mention
+        let tag = match row.class {
+            Some(class) if class != AddressClass::Public => format!("{}, {}", tag, class),
+            _ => tag,
+        };
+        let hits = row
+            .hits
+            .map(|hits| format!(", {} hits", hits))
+            .unwrap_or_default();
+        let hits = match row.score {
+            Some(score) => format!("{}, score {}", hits, score),
+            None => hits,
+        };
+        let seen = row
+            .seen
+            .map(|seen| {
+                format!(
+                    ", seen {}..{} (age {} days)",
+                    TimeBucket::Day.name_of(seen.first),
+                    TimeBucket::Day.name_of(seen.last),
+                    seen.age_days()
+                )
+            })
+            .unwrap_or_default();
+        let seen = match &row.sources {
+            Some(sources) if !sources.is_empty() => {
+                format!("{}, from {}", seen, sources.join(", "))
+            }
+            _ => seen,
+        };
+        match row.density {
+            Some(density) if density.is_full() => writeln!(
+                out,
+                "{} subnet{}{}{}, full, {}",
+                row.name, tag, hits, seen, density
+            )?,
+            Some(density) => writeln!(
+                out,
+                "{} subnet{}{}{}, {}",
+                row.name, tag, hits, seen, density
+            )?,
+            None => writeln!(out, "{} subnet{}{}{}", row.name, tag, hits, seen)?,
+        }
+        for ip in row.addresses {
+            let addr = Subnet::from_str(ip).ok();
+            let tag = addr
+                .as_ref()
+                .and_then(|addr| report.labels.label_of(addr))
+                .map(|label| format!(" [{}]", label))
+                .unwrap_or_default();
+            let joined = addr
+                .as_ref()
+                .and_then(|addr| report.metadata.describe(addr))
+                .map(|row| format!(" {}", row))
+                .unwrap_or_default();
+            writeln!(out, "\t{}{}{}", ip, tag, joined)?;
+        }
+    }
+    Ok(())
+}
+
+/// print unobserved gaps of every subnet to stdout as the shortest list of subnets
+/// groups which aren't subnets (e.g. classes) are skipped, top is the same as in print_subnets
+pub fn print_gaps(subnets: &SubnetsMap, top: Option<usize>) {
+    let ranked = rank_subnets(subnets);
+    let top = top.unwrap_or(ranked.len());
+    println!("gaps found:");
+    for (subnet, ips) in ranked.into_iter().take(top) {
+        let (Ok(group), Ok(present)) = (Subnet::from_str(subnet), SubnetSet::from_lines(ips))
+        else {
+            continue;
+        };
+        let gaps = present.gaps_in(&group);
+        if gaps.is_empty() {
+            println!("{} subnet is full", subnet);
+        } else {
+            println!("{} subnet", subnet);
+            for gap in gaps {
+                println!("\t{}", gap);
+            }
+        }
+    }
+}
+
+/// # validate and canonicalize a \n-separated list of IP addresses and subnets from the file
+/// host bits are cleared (1.2.3.4/24 becomes 1.2.3.0/24), duplicates are dropped, the list is sorted
+/// # returns
+/// Ok((clean list, malformed lines with reasons))
+/// Err - if the file cannot be read
+#[cfg(feature = "fs")]
+pub fn normalize(file_name: impl AsRef<Path>) -> Result<(Vec<String>, Vec<String>), Error> {
+    Ok(normalize_lines(
+        FileContent::read(file_name.as_ref())?.split('\n'),
+    ))
+}
+
+/// same as normalize, but for lines already available in memory
+/// line numbers in the malformed list are 1-based positions in lines
+pub fn normalize_lines(
+    lines: impl IntoIterator<Item = impl AsRef<str>>,
+) -> (Vec<String>, Vec<String>) {
+    let mut subnets = vec![];
+    let mut malformed = vec![];
+    for (line_no, line) in lines.into_iter().enumerate() {
+        let line = line.as_ref().trim();
+        if line.is_empty() {
+            continue;
+        }
+        match Subnet::from_str(line) {
+            Ok(subnet) => subnets.push(subnet),
+            Err(e) => malformed.push(format!("line {}: {}", line_no + 1, e)),
+        }
+    }
+    subnets.sort_by_key(|s| (s.bits, s.mask_len));
+    subnets.dedup();
+    (subnets.iter().map(|s| s.to_string()).collect(), malformed)
+}
@@ -0,0 +1,95 @@
+use std::fmt::{Display, Formatter};
+
+use crate::{metadata::is_truthy, Metadata, Subnet};
+
+/// metadata columns flagging the address as mobile, hosting ones are of IpMetadata's privacy
+const MOBILE_FLAGS: [&str; 2] = ["mobile", "is_mobile"];
+/// metadata columns naming the type of the network, the first one present wins
+const TYPE_COLUMNS: [&str; 6] = [
+    "asn_type",
+    "asn.type",
+    "company_type",
+    "company.type",
+    "usage_type",
+    "type",
+];
+
+/// what kind of network an address likely belongs to, e.g. to tell a botnet of home routers from rented servers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NetworkKind {
+    Hosting, // datacenters, clouds and CDNs
+    Residential,
+    Mobile,
+    Business, // including education and government
+}
+
+impl NetworkKind {
+    /// # the kind of the address told by the metadata
+    /// hosting and mobile flags win over the type columns, e.g. asn_type of isp, hosting, business or edu
+    /// # returns
+    /// None - if the metadata has nothing known about it
+    pub fn of(metadata: &Metadata, addr: &Subnet) -> Option<NetworkKind> {
+        if metadata.ip_metadata_of(addr)?.privacy.hosting {
+            return Some(NetworkKind::Hosting);
+        }
+        let flagged = MOBILE_FLAGS
+            .iter()
+            .any(|column| metadata.value_of(addr, column).is_some_and(is_truthy));
+        if flagged {
+            return Some(NetworkKind::Mobile);
+        }
+        let value = TYPE_COLUMNS
+            .iter()
+            .find_map(|column| metadata.value_of(addr, column))?
+            .to_lowercase();
+        match value.as_str() {
+            "hosting" | "datacenter" | "data center" | "dch" | "cdn" => Some(NetworkKind::Hosting),
+            "isp" | "residential" => Some(NetworkKind::Residential),
+            "mobile" | "mob" | "cellular" => Some(NetworkKind::Mobile),
+            "business" | "com" | "edu" | "education" | "gov" | "government" => {
+                Some(NetworkKind::Business)
+            }
+            _ => None,
+        }
+    }
+
+    /// # the kind most of the addresses belong to, the first seen of equally common ones
+    /// # returns
+    /// None - if none of the addresses has a known kind
+    pub(crate) fn of_most<'a>(
+        metadata: &Metadata,
+        addrs: impl IntoIterator<Item = &'a String>,
+    ) -> Option<NetworkKind> {
+        let mut counts: Vec<(NetworkKind, usize)> = vec![];
+        for addr in addrs {
+            let Some(kind) = addr
+                .parse::<Subnet>()
+                .ok()
+                .and_then(|addr| NetworkKind::of(metadata, &addr))
+            else {
+                continue;
+            };
+            match counts.iter_mut().find(|(known, _)| *known == kind) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((kind, 1)),
+            }
+        }
+        // max_by_key takes the last of the equal ones
+        counts
+            .into_iter()
+            .rev()
+            .max_by_key(|&(_, count)| count)
+            .map(|(kind, _)| kind)
+    }
+}
+
+impl Display for NetworkKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+        f.write_str(match self {
+            NetworkKind::Hosting => "hosting",
+            NetworkKind::Residential => "residential",
+            NetworkKind::Mobile => "mobile",
+            NetworkKind::Business => "business",
+        })
+    }
+}
@@ -0,0 +1,59 @@
+use std::str::FromStr;
+
+use crate::{prefix_map::PrefixMap, Error, Subnet};
+
+/// user-supplied names of networks, e.g. 10.0.0.0/8 = corp
+/// an address gets the label of the most specific network it belongs to
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Labels {
+    rules: PrefixMap<String>,
+}
+
+impl Labels {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// # parse rules formatted as: CIDR = label
+    /// empty lines and lines starting with # are skipped
+    /// # returns
+    /// Err - if some rule has no = or its CIDR can't be parsed
+    pub fn from_lines(lines: impl IntoIterator<Item = impl AsRef<str>>) -> Result<Self, Error> {
+        let mut labels = Self::new();
+        for line in lines {
+            let line = line.as_ref().trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((subnet, label)) = line.split_once('=') else {
+                return Err(Error::ParseError {
+                    input: line.to_string(),
+                    reason: "rule should look like CIDR = label".to_string(),
+                });
+            };
+            labels.insert(&Subnet::from_str(subnet.trim())?, label.trim());
+        }
+        Ok(labels)
+    }
+
+    /// add a rule, the last one wins for the same network
+    pub fn insert(&mut self, subnet: &Subnet, label: impl Into<String>) {
+        self.rules.insert(subnet, label.into());
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// all the rules, the most specific networks first
+    pub fn rules(&self) -> impl Iterator<Item = (Subnet, &str)> {
+        self.rules
+            .iter()
+            .map(|(subnet, label)| (subnet, label.as_str()))
+    }
+
+    /// label of the most specific rule containing the subnet (or address), longest-prefix match
+    pub fn label_of(&self, subnet: &Subnet) -> Option<&str> {
+        self.rules.lookup(subnet).map(|(_, label)| label.as_str())
+    }
+}
@@ -1,345 +1,312 @@
-use std::{
-    cmp,
-    collections::HashMap,
-    error::Error,
-    fmt::{Debug, Display, Formatter},
-    fs,
-    mem::replace,
-    num::ParseIntError,
-    path::Path,
-};
-
-#[cfg(test)]
-mod test;
-
-/// parsed tool's config
-pub struct Config {
-    pub file_names: Vec<String>,
-}
-
-impl Config {
-    pub fn new(file_names: Vec<String>) -> Result<Config, Box<dyn Error>> {
-        for f in &file_names {
-            if !Path::new(f).exists() {
-                return Err(format!("file {} doesn't exist", f).into());
-            }
-        }
-
-        Ok(Config { file_names })
-    }
-
-    pub fn has_files(&self) -> bool {
-        !self.file_names.is_empty()
-    }
-}
-
-/// # parse a \n-separated list of IP addresses from the provided files into subnets
-/// # returns
-/// Err - if one of the files cannot be read, some line isn't a correct IP address or smth else went terribly wrong
-pub fn find_subnets(
-    file_names: Vec<String>,
-) -> Result<HashMap<String, Vec<String>>, Box<dyn Error>> {
-    let mut address_tree = AddressTree::new();
-
-    for file_name in file_names {
-        eprintln!("loading file {}", file_name);
-        let mut addrs = fs::read_to_string(&file_name)?
-            .split("\n")
-            .map(|el| el.trim())
-            .filter(|el| !el.is_empty())
-            .map(|str_addr| Subnet::from_str(str_addr))
-            .collect::<Result<Vec<Subnet>, Box<dyn Error>>>()?;
-
-        eprintln!("there are {} addresses in {}", addrs.len(), file_name);
-        while let Some(addr) = addrs.pop() {
-            match address_tree.push(addr) {
-                Ok(_) => (),
-                Err(addr) => {
-                    return Err(
-                        format!("address {} doesn't belong to IPv4 address space", addr).into(),
-                    )
-                }
-            }
-        }
-    }
-    println!("subnets found:");
-    let subnets = address_tree.get_subnets_map();
-    for (subnet, ips) in &subnets {
-        println!("{} subnet", subnet);
-        println!("\t{}", ips.join("\n\t"));
-    }
-    Ok(subnets)
-}
-
-/// IPv4 subnet representation
-/// consists of u32 and netmask
-#[derive(Debug, PartialEq)]
-struct Subnet {
-    bits: u32,    // IP address with significant bits representing the subnet
-    mask_len: u8, // number of significant bits in the bits
-    mask: u32,    // prebuilt number with leading significant bits set
-}
-
-impl Subnet {
-    /// root of all ipv4 addresses
-    pub fn root() -> Self {
-        Self {
-            bits: 0,
-            mask_len: 0,
-            mask: 0,
-        }
-    }
-
-    /// make subnet from octets & mask length
-    /// clear any bits set below the mask: e.g. 1.2.3.4/24 is acceptable but gets transformed to 1.2.3.0/24
-    pub fn new(o1: u8, o2: u8, o3: u8, o4: u8, mask_len: u8) -> Result<Self, Box<dyn Error>> {
-        if mask_len > 32 {
-            Err("mask len is > 32".into())
-        } else {
-            let mask = u32::MAX << (32 - mask_len);
-            Ok(Self {
-                bits: u32::from_be_bytes([o1, o2, o3, o4]) & mask,
-                mask_len: mask_len,
-                mask,
-            })
-        }
-    }
+#![cfg_attr(not(feature = "std"), no_std)]
+//! without the std feature only the Subnet math is available, it needs alloc only
 
-    /// parse string with netmask into a subnet
-    pub fn from_str(src: &str) -> Result<Self, Box<dyn Error>> {
-        let (addr, mask_len) = if src.contains("/") {
-            let split: Vec<&str> = src.split('/').collect();
-            if split.len() != 2 {
-                return Err("there are more than 1 / in the address".into());
-            }
-            if let Ok(mask_len) = split.get(1).unwrap().parse::<u8>() {
-                (*split.get(0).unwrap(), mask_len)
-            } else {
-                return Err(format!("can't parse netmask from {}", src).into());
-            }
-        } else {
-            (src, 32)
-        };
-        match addr
-            .split('.')
-            .map(|el| el.parse::<u8>())
-            .collect::<Result<Vec<u8>, ParseIntError>>()
-        {
-            Ok(octets) => {
-                if octets.len() != 4 {
-                    Err(format!("address {} doesn't have 4 dot-separated octets", addr).into())
-                } else {
-                    Self::new(octets[0], octets[1], octets[2], octets[3], mask_len)
-                }
-            }
-            Err(e) => Err(format!("unable to parse {:?}: {:?}", addr, e).into()),
-        }
-    }
+extern crate alloc;
 
-    /// check whether subnet includes other subnet
-    pub fn contains(&self, other: &Subnet) -> bool {
-        if self.mask_len > other.mask_len {
-            return false;
-        }
-        // let addr_number = u32::from_be_bytes(addr.octets());
-        return other.bits & self.mask == self.bits;
-    }
+#[cfg(feature = "std")]
+mod abuse;
+#[cfg(feature = "std")]
+mod aggregator;
+#[cfg(feature = "async")]
+mod async_io;
+#[cfg(feature = "std")]
+mod cache;
+#[cfg(feature = "std")]
+mod cancel;
+#[cfg(feature = "std")]
+mod classifier;
+#[cfg(feature = "std")]
+mod compare;
+#[cfg(feature = "std")]
+mod config;
+#[cfg(feature = "fs")]
+mod content;
+#[cfg(feature = "std")]
+mod crosstab;
+#[cfg(feature = "dedup")]
+mod dedup;
+#[cfg(feature = "std")]
+mod density;
+#[cfg(feature = "dns")]
+mod dns;
+#[cfg(feature = "std")]
+mod document;
+#[cfg(feature = "std")]
+mod explain;
+#[cfg(feature = "std")]
+mod export;
+#[cfg(feature = "std")]
+mod exporter;
+#[cfg(feature = "std")]
+mod fail2ban;
+#[cfg(feature = "std")]
+mod firewall;
+#[cfg(feature = "std")]
+mod generate;
+#[cfg(feature = "std")]
+mod geojson;
+#[cfg(feature = "std")]
+mod index;
+#[cfg(feature = "std")]
+mod io;
+#[cfg(feature = "std")]
+mod kind;
+#[cfg(feature = "std")]
+mod labels;
+#[cfg(feature = "manifest")]
+mod manifest;
+#[cfg(feature = "std")]
+mod membership;
+#[cfg(feature = "std")]
+mod metadata;
+#[cfg(feature = "std")]
+mod ndjson;
+#[cfg(feature = "std")]
+mod observer;
+#[cfg(feature = "std")]
+mod optimize;
+#[cfg(feature = "std")]
+mod overlap;
+#[cfg(feature = "parallel")]
+mod parallel;
+#[cfg(feature = "parquet")]
+mod parquet;
+#[cfg(feature = "persist")]
+mod persist;
+#[cfg(feature = "std")]
+mod pipeline;
+#[cfg(feature = "std")]
+mod policy;
+#[cfg(feature = "std")]
+mod prefix_map;
+#[cfg(feature = "std")]
+mod provenance;
+#[cfg(feature = "std")]
+mod rate;
+#[cfg(feature = "std")]
+mod redundant;
+#[cfg(feature = "ripestat")]
+mod ripestat;
+#[cfg(feature = "std")]
+mod routes;
+#[cfg(feature = "std")]
+mod rpz;
+#[cfg(feature = "schema")]
+mod schema;
+#[cfg(feature = "std")]
+mod score;
+#[cfg(feature = "serve")]
+mod serve;
+mod set;
+#[cfg(feature = "std")]
+mod source;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+mod subnet;
+#[cfg(feature = "std")]
+mod summary;
+#[cfg(feature = "serve")]
+mod syslog;
+#[cfg(feature = "template")]
+mod template;
+#[cfg(feature = "std")]
+mod timeline;
+#[cfg(feature = "std")]
+mod tree;
+#[cfg(feature = "whois")]
+mod whois;
 
-    /// find and return the closest common of the two subnets if exists
-    /// min_mask defines minimal (shortest) mask to look for
-    /// e.g. 10.0.0.0/24 and 10.128.0.0/24 are both of 10.0.0.0/8
-    /// if min_mask is 16 returns None for the above ranges,
-    /// as 8 is less than min_mask - it's the only case when None can be returned,
-    /// as default values for min_mask is 0, so 0.0.0.0/0 is the worst case
-    /// # Panics
-    /// if min_mask is bigger than any of the subnet masks
-    pub fn common_of(s1: &Subnet, s2: &Subnet, min_mask: Option<u8>) -> Option<Subnet> {
-        let min_mask = match min_mask {
-            Some(min_mask) => min_mask,
-            None => 0,
-        };
-        // get the shortest mask to start from
-        let mut curr_mask_len = cmp::min(s1.mask_len, s2.mask_len);
-        if min_mask > curr_mask_len {
-            panic!("min_mask {} is bigger than {}", min_mask, curr_mask_len);
-        }
-        let mut curr_mask = u32::MAX << (32 - curr_mask_len);
-        while curr_mask_len >= min_mask {
-            if s1.bits & curr_mask == s2.bits & curr_mask {
-                return Some(Subnet {
-                    bits: s1.bits & curr_mask,
-                    mask_len: curr_mask_len,
-                    mask: curr_mask,
-                });
-            }
-            curr_mask <<= 1;
-            curr_mask_len -= 1;
-        }
-        None
-    }
-}
-
-impl Display for Subnet {
-    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
-        f.write_str(&format!(
-            "{}.{}.{}.{}/{}",
-            (self.bits & (0xFF << 24)) >> 24,
-            (self.bits & (0xFF << 16)) >> 16,
-            (self.bits & (0xFF << 8)) >> 8,
-            self.bits & 0xFF,
-            self.mask_len
-        ))
-    }
-}
-
-#[derive(Debug)]
-struct AddressTree {
-    subnet: Subnet,
-    children: Option<Vec<AddressTree>>,
-}
-
-impl AddressTree {
-    /// make a new empty tree starting from 0.0.0.0/0
-    pub fn new() -> Self {
-        Self {
-            subnet: Subnet::root(),
-            children: None,
-        }
-    }
-
-    /// make a new empty tree starting at subnet
-    fn of(subnet: Subnet) -> Self {
-        Self {
-            subnet,
-            children: None,
-        }
-    }
-
-    /// try to place the supplied subnet in the tree
-    /// # Returns
-    /// Ok(()) - address was adopted by the tree
-    /// Err(new_subnet) - supplied subnet doesn't belond to the current tree
-    pub fn push(&mut self, new_subnet: Subnet) -> Result<(), Subnet> {
-        eprintln!("attempt to push {} to {}", new_subnet, self.subnet);
-        if self.subnet.contains(&new_subnet) {
-            if let Some(ref mut children) = self.children {
-                let mut to_consume = Some(new_subnet);
-                for ch in children.iter_mut() {
-                    eprintln!("processing subnet {}", ch.subnet);
-                    // check whether there's an address to take
-                    if let Some(new_subnet) = to_consume.take() {
-                        match ch.push(new_subnet) {
-                            Ok(_) => return Ok(()), // address found its place, nothing to do here
-                            Err(new_subnet) => {
-                                // it wasn't consumed - try to adopt
-                                match Subnet::common_of(
-                                    &ch.subnet,
-                                    &new_subnet,
-                                    Some(self.subnet.mask_len + 1),
-                                ) {
-                                    Some(new_intermediate) => {
-                                        eprintln!(
-                                            "address {} and {} are joined into {}",
-                                            new_subnet, ch.subnet, new_intermediate
-                                        );
-                                        ch.stepdown(new_intermediate, AddressTree::of(new_subnet));
-                                    }
-                                    None => to_consume = Some(new_subnet),
-                                }
-                            }
-                        }
-                    } else {
-                        // address was placed
-                        return Ok(());
-                    }
-                }
-                if let Some(new_subnet) = to_consume.take() {
-                    eprintln!("address {} settled in {}", new_subnet, self.subnet);
-                    children.push(AddressTree::of(new_subnet));
-                }
-            } else {
-                self.children = Some(vec![AddressTree::of(new_subnet)]);
-            }
-            Ok(())
-        } else {
-            Err(new_subnet)
-        }
-    }
-
-    fn stepdown(&mut self, new_subnet: Subnet, neighbour: AddressTree) {
-        let my_subnet = replace(&mut self.subnet, new_subnet);
-        let new_me = match self.children.take() {
-            Some(children) => AddressTree {
-                subnet: my_subnet,
-                children: Some(children),
-            },
-            None => AddressTree {
-                subnet: my_subnet,
-                children: None,
-            },
-        };
-
-        self.children = Some(vec![new_me, neighbour]);
-    }
-
-    /// extract vector of "subnets" - subnets that contain at least one tree leaf (IP address)
-    fn get_subnets(&self) -> Vec<&AddressTree> {
-        let mut res = vec![];
-        if let Some(ref children) = self.children {
-            if children.iter().any(|ch| ch.subnet.mask_len == 32) {
-                // chop the subtree at the first IP address in it
-                res.push(self);
-            } else {
-                for ch in children {
-                    res.append(&mut ch.get_subnets());
-                }
-            }
-        }
-        res
-    }
-
-    fn get_leafs(&self) -> Vec<&AddressTree> {
-        let mut res = vec![];
-        if let Some(ref children) = self.children {
-            for ch in children {
-                if ch.children.is_none() {
-                    res.push(ch);
-                } else {
-                    res.append(&mut ch.get_leafs());
-                }
-            }
-        }
-        res
-    }
+#[cfg(feature = "fs")]
+pub use abuse::find_abuse;
+#[cfg(feature = "std")]
+pub use abuse::{Abuse, AbuseContact, AbuseContacts, AbuseReport, DEFAULT_TEMPLATE};
+#[cfg(feature = "std")]
+pub use aggregator::Aggregator;
+#[cfg(feature = "async")]
+pub use async_io::{find_groups_async, find_subnets_async};
+#[cfg(feature = "std")]
+pub use cache::PrefixCache;
+#[cfg(feature = "std")]
+pub use cancel::CancelToken;
+#[cfg(feature = "std")]
+pub use classifier::{Classified, Classifier, EnrichmentProvider};
+#[cfg(feature = "fs")]
+pub use compare::find_growth;
+#[cfg(feature = "std")]
+pub use compare::{compare_lines, Growth};
+#[cfg(feature = "std")]
+pub use config::{Config, ConfigBuilder};
+#[cfg(feature = "fs")]
+pub use crosstab::find_crosstab;
+#[cfg(feature = "std")]
+pub use crosstab::{CrossRow, CrossTab, Datasets};
+#[cfg(feature = "dedup")]
+pub use dedup::{find_groups_dedup, DuplicateFilter};
+#[cfg(feature = "std")]
+pub use density::Density;
+#[cfg(feature = "dns")]
+pub use dns::{DnsCache, Resolver};
+#[cfg(feature = "std")]
+pub use document::{write_json, JSON_VERSION};
+#[cfg(feature = "std")]
+pub use explain::Explainer;
+#[cfg(feature = "std")]
+pub use export::{OutputFormat, Report};
+#[cfg(feature = "std")]
+pub use exporter::{Exporter, Exporters};
+#[cfg(feature = "std")]
+pub use fail2ban::{fail2ban_banip, fail2ban_ignoreip};
+#[cfg(feature = "std")]
+pub use generate::generate;
+#[cfg(feature = "std")]
+pub use geojson::export_geojson;
+#[cfg(feature = "std")]
+pub use index::SubnetIndex;
+#[cfg(feature = "std")]
+pub use io::{
+    classify_lines, group_lines, normalize_lines, parse_weighted_line, print_gaps,
+    print_joined_subnets, print_labeled_subnets, print_report, print_subnets,
+    print_weighted_subnets, rank_subnets, rank_weighted, retain_min_count, weigh_lines,
+};
+#[cfg(feature = "fs")]
+pub use io::{
+    find_groups, find_groups_cancellable, find_groups_except, find_groups_lenient,
+    find_groups_observed, find_subnets, normalize, read_into_tree, read_into_tree_observed,
+    read_into_tree_through, read_tree, read_tree_cancellable, Failure,
+};
+#[cfg(feature = "std")]
+pub use kind::NetworkKind;
+#[cfg(feature = "std")]
+pub use labels::Labels;
+#[cfg(feature = "manifest")]
+pub use manifest::{InputFile, Manifest};
+#[cfg(feature = "std")]
+pub use metadata::{IpMetadata, Metadata, Privacy};
+#[cfg(feature = "std")]
+pub use ndjson::{write_ndjson_subnets, JsonLog, NdjsonSink};
+#[cfg(feature = "std")]
+pub use observer::{Decision, Enrichment, Observer, StderrLogger, Verdict};
+#[cfg(feature = "fs")]
+pub use optimize::find_block_rules;
+#[cfg(feature = "std")]
+pub use optimize::{Plan, Target};
+#[cfg(feature = "fs")]
+pub use overlap::find_overlaps;
+#[cfg(feature = "std")]
+pub use overlap::{overlap_lines, Overlap};
+#[cfg(feature = "parallel")]
+pub use parallel::find_groups_parallel;
+#[cfg(feature = "parquet")]
+pub use parquet::export_parquet;
+#[cfg(feature = "std")]
+pub use pipeline::{Pipeline, PipelineStep};
+#[cfg(feature = "std")]
+pub use policy::{Merge, MergeLimits, MergePolicy};
+#[cfg(feature = "std")]
+pub use provenance::Provenance;
+#[cfg(feature = "std")]
+pub use rate::{RateLimit, RateLimits};
+#[cfg(feature = "fs")]
+pub use redundant::find_redundant;
+#[cfg(feature = "std")]
+pub use redundant::{redundant_lines, Entry, Redundant};
+#[cfg(all(feature = "ripestat", feature = "fs"))]
+pub use ripestat::find_announced_routes;
+#[cfg(feature = "ripestat")]
+pub use ripestat::{Announcement, RipeStat, RIPESTAT_URL};
+#[cfg(feature = "fs")]
+pub use routes::{find_mrt_routes, find_routes};
+#[cfg(feature = "std")]
+pub use routes::{Routed, RoutingTable};
+#[cfg(feature = "std")]
+pub use rpz::export_rpz;
+#[cfg(feature = "schema")]
+pub use schema::json_schema;
+#[cfg(feature = "std")]
+pub use score::Scoring;
+#[cfg(feature = "serve")]
+pub use serve::{serve, serve_with_syslog};
+pub use set::{Fit, SetOp, SubnetSet};
+#[cfg(feature = "fs")]
+pub use source::FileSource;
+#[cfg(feature = "std")]
+pub use source::{
+    find_groups_in, read_sources, AddressSource, FollowSource, LineSource, PcapSource,
+};
+#[cfg(feature = "sqlite")]
+pub use sqlite::export_sqlite;
+pub use subnet::{AddressClass, Subnet};
+#[cfg(feature = "std")]
+pub use summary::{normalize_org, Bucket, Country, CountrySummary, Org, Summary};
+#[cfg(feature = "serve")]
+pub use syslog::{extract_addresses, SyslogListener};
+#[cfg(feature = "template")]
+pub use template::Template;
+#[cfg(feature = "fs")]
+pub use timeline::find_timed_groups;
+#[cfg(feature = "std")]
+pub use timeline::{group_timed_lines, parse_timed_line, TimeBucket};
+#[cfg(feature = "std")]
+pub use tree::{AddressTree, GroupBy, PushError, Seen, SeenMap, SubnetsMap, WeightsMap};
+#[cfg(feature = "whois")]
+pub use whois::{Whois, WhoisNetwork, IANA_WHOIS};
 
-    /// make a human-readable map of subnets to all their addresses
-    fn get_subnets_map(&self) -> HashMap<String, Vec<String>> {
-        let subnets = self.get_subnets();
-        let mut res = HashMap::new();
+use alloc::string::String;
 
-        for s in subnets {
-            res.insert(
-                s.subnet.to_string(),
-                s.get_leafs()
-                    .iter()
-                    .map(|leaf| leaf.subnet.to_string())
-                    .collect(),
-            );
-        }
-        res
-    }
-}
+#[cfg(all(test, feature = "std"))]
+mod test;
 
-impl Display for AddressTree {
-    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
-        f.write_str(&format!("{}", self.subnet))?;
-        if let Some(ref children) = self.children {
-            f.write_str("=>[")?;
-            for ref ch in children {
-                <AddressTree as Display>::fmt(&ch, f)?;
-            }
-            f.write_str("]")?;
-        }
-        f.write_str(";")
-    }
+/// everything that can go wrong in the crate
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// input isn't a valid address, subnet or option value
+    #[error("unable to parse {input:?}: {reason}")]
+    ParseError { input: String, reason: String },
+    #[cfg(feature = "std")]
+    #[error(transparent)]
+    IoError(#[from] std::io::Error),
+    #[cfg(feature = "std")]
+    #[error("file {} doesn't exist", .0.display())]
+    FileNotFound(std::path::PathBuf),
+    /// a line of an input file which couldn't be classified, reason tells why
+    #[cfg(feature = "std")]
+    #[error("{}:{line_no} (byte {offset}): {reason} in line {line:?}", file_name.display())]
+    LineError {
+        file_name: std::path::PathBuf,
+        line_no: usize, // 1-based
+        offset: usize,  // bytes before the line in the file
+        line: String,
+        reason: alloc::boxed::Box<Error>,
+    },
+    #[error("mask len {0} is > 32")]
+    MaskTooLong(u8),
+    /// a subnet the tree refused, see AddressTree::push
+    #[cfg(feature = "std")]
+    #[error(transparent)]
+    PushError(#[from] PushError),
+    /// an enrichment provider refused to be asked more today, see RateLimit
+    #[cfg(feature = "std")]
+    #[error("the daily quota of {0} is used up")]
+    QuotaExceeded(String),
+    /// option values which are fine on their own, but don't make sense
+    #[error("{0}")]
+    InvalidArgument(String),
+    #[cfg(feature = "sqlite")]
+    #[error(transparent)]
+    SqliteError(#[from] rusqlite::Error),
+    #[cfg(feature = "parquet")]
+    #[error(transparent)]
+    ParquetError(#[from] ::parquet::errors::ParquetError),
+    #[cfg(feature = "dns")]
+    #[error(transparent)]
+    DnsError(#[from] alloc::boxed::Box<hickory_resolver::ResolveError>),
+    #[cfg(feature = "http")]
+    #[error(transparent)]
+    HttpError(#[from] alloc::boxed::Box<ureq::Error>),
+    #[cfg(feature = "template")]
+    #[error(transparent)]
+    TemplateError(#[from] alloc::boxed::Box<handlebars::TemplateError>),
+    #[cfg(feature = "template")]
+    #[error(transparent)]
+    RenderError(#[from] alloc::boxed::Box<handlebars::RenderError>),
 }
@@ -1,25 +1,1436 @@
-use std::error::Error;
+use std::{
+    env,
+    error::Error,
+    fs,
+    io::{stderr, stdout, BufWriter, Write},
+    path::PathBuf,
+    process,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
-use argparse::{ArgumentParser, List};
-use ipv4_classify::Config;
+use argparse::{ArgumentParser, List, Store, StoreOption, StoreTrue};
+use ipv4_classify::{
+    AbuseContacts, AddressTree, Aggregator, CancelToken, Config, Explainer, Exporters, Fit,
+    FollowSource, GroupBy, JsonLog, Labels, Manifest, MergeLimits, Metadata, NdjsonSink,
+    OutputFormat, PipelineStep, Provenance, RateLimits, Report, Scoring, SetOp, StderrLogger,
+    Subnet, SubnetSet, Target, TimeBucket, Whois,
+};
 
 fn main() -> Result<(), Box<dyn Error>> {
-    let mut file_names = vec![];
+    let args: Vec<String> = env::args().collect();
+    match args.get(1).map(String::as_str) {
+        Some("generate") => generate(subcommand_args(args)),
+        Some("normalize") => normalize(subcommand_args(args)),
+        Some("overlap") => overlap(subcommand_args(args)),
+        Some("redundant") => redundant(subcommand_args(args)),
+        Some("setop") => setop(subcommand_args(args)),
+        Some("optimize") => optimize(subcommand_args(args)),
+        Some("summary") => summary(subcommand_args(args)),
+        Some("compare") => compare(subcommand_args(args)),
+        Some("crosstab") => crosstab(subcommand_args(args)),
+        Some("allocate") => allocate(subcommand_args(args)),
+        Some("timeline") => timeline(subcommand_args(args)),
+        Some("route") => route(subcommand_args(args)),
+        Some("abuse") => abuse(subcommand_args(args)),
+        Some("stream") => stream(subcommand_args(args)),
+        #[cfg(feature = "serve")]
+        Some("serve") => serve(subcommand_args(args)),
+        _ => classify(),
+    }
+}
+
+/// glue program name and subcommand together, so subcommand's parser sees the rest as usual
+fn subcommand_args(mut args: Vec<String>) -> Vec<String> {
+    let command = args.remove(1);
+    args[0] = format!("{} {}", args[0], command);
+    args
+}
+
+/// default mode - read files and print subnets found
+fn classify() -> Result<(), Box<dyn Error>> {
+    let mut file_names: Vec<PathBuf> = vec![];
+    let mut top = None;
+    let mut group_by = GroupBy::Subnet;
+    let mut lenient = false;
+    let mut whitelist = None::<PathBuf>;
+    let mut gaps = false;
+    let mut labels = None::<PathBuf>;
+    let mut by_hits = false;
+    let mut by_score = false;
+    let mut score_weights = None::<Scoring>;
+    let mut metadata = None::<PathBuf>;
+    let mut whois = false;
+    let mut rate_limits = None::<PathBuf>;
+    let mut audit_log = None::<PathBuf>;
+    let mut min_count = None;
+    let mut boundaries = None::<PathBuf>;
+    let mut root = None::<Subnet>;
+    let mut load = None::<PathBuf>;
+    let mut save = None::<PathBuf>;
+    let mut output_format = OutputFormat::Text;
+    let mut output = None::<PathBuf>;
+    let mut schema = false;
+    let mut manifest = None::<PathBuf>;
+    let mut memory_limit = None::<usize>;
+    let mut max_children = None::<usize>;
+    let mut explain = None::<Subnet>;
+    #[cfg_attr(not(feature = "template"), allow(unused_mut))]
+    let mut template = None::<PathBuf>;
+    let mut views: Vec<GroupBy> = vec![];
+    let mut untagged = false;
+    let mut merge_limits = MergeLimits::default();
+    let mut sources = false;
+    let mut pipeline: Vec<PipelineStep> = vec![];
     {
         let mut arg_parser = ArgumentParser::new();
-        arg_parser.set_description("Sort out a long list of IPv4 addresses into subnets");
+        arg_parser.set_description(
+            "Sort out a long list of IPv4 addresses into subnets. See also \"generate -h\", \"normalize -h\", \"overlap -h\", \"redundant -h\", \"setop -h\", \"optimize -h\", \"summary -h\", \"compare -h\", \"crosstab -h\", \"allocate -h\", \"timeline -h\", \"route -h\", \"abuse -h\" and \"stream -h\"",
+        );
         arg_parser.refer(&mut file_names).add_option(
             &["-f", "--files"],
             List,
             "List of files with ipv4 addresses to read e.g. -f one.txt another.txt",
         );
+        arg_parser.refer(&mut top).add_option(
+            &["-t", "--top"],
+            StoreOption,
+            "Report only N subnets with the most addresses",
+        );
+        arg_parser.refer(&mut group_by).add_option(
+            &["-g", "--group-by"],
+            Store,
+            "Top-level grouping of the report: subnet (default), class (public, private, cgnat, multicast or bogon), prefix length e.g. /8 or a --metadata column e.g. column:asn",
+        );
+        arg_parser.refer(&mut lenient).add_option(
+            &["-l", "--lenient"],
+            StoreTrue,
+            "Skip bad lines and unreadable files reporting them at the end",
+        );
+        arg_parser.refer(&mut whitelist).add_option(
+            &["-w", "--whitelist"],
+            StoreOption,
+            "File with addresses and subnets no reported subnet may enclose",
+        );
+        arg_parser.refer(&mut gaps).add_option(
+            &["--gaps"],
+            StoreTrue,
+            "Report unobserved ranges (free space) of every subnet instead of its addresses",
+        );
+        arg_parser.refer(&mut labels).add_option(
+            &["--labels"],
+            StoreOption,
+            "File with rules like \"10.0.0.0/8 = corp\" to tag subnets and addresses with",
+        );
+        arg_parser.refer(&mut by_hits).add_option(
+            &["--by-hits"],
+            StoreTrue,
+            "Rank subnets by total hits of lines like \"1.2.3.4,573\" instead of the number of addresses",
+        );
+        arg_parser.refer(&mut by_score).add_option(
+            &["--by-score"],
+            StoreTrue,
+            "Score subnets from 0 to 100 by density, addresses, hits and recency and rank them by the score, worst first",
+        );
+        arg_parser.refer(&mut score_weights).add_option(
+            &["--score-weights"],
+            StoreOption,
+            "Weights of the score parts, implies --by-score e.g. density=2,hits=0,half-life=30 (days)",
+        );
+        arg_parser.refer(&mut metadata).add_option(
+            &["--metadata"],
+            StoreOption,
+            "CSV with a header and an address in the first column e.g. ip,username,device to join onto the addresses",
+        );
+        arg_parser.refer(&mut whois).add_option(
+            &["--whois"],
+            StoreTrue,
+            "Look up netname, country and org of the addresses missing in --metadata with whois, starting at IANA",
+        );
+        arg_parser.refer(&mut rate_limits).add_option(
+            &["--rate-limits"],
+            StoreOption,
+            "File with limits of the enrichment providers like \"whois = 1/s 5000/day\", lookups stop once a day's quota is used up",
+        );
+        arg_parser.refer(&mut audit_log).add_option(
+            &["--audit-log"],
+            StoreOption,
+            "File to write a JSON line per --whois decision to, e.g. cache hits and quota refusals",
+        );
+        arg_parser.refer(&mut min_count).add_option(
+            &["--min-count"],
+            StoreOption,
+            "Skip subnets with fewer than N addresses e.g. 2 to drop singletons",
+        );
+        arg_parser.refer(&mut boundaries).add_option(
+            &["--boundaries"],
+            StoreOption,
+            "File with prefixes no reported subnet may span across e.g. RIR allocations or sites",
+        );
+        arg_parser.refer(&mut root).add_option(
+            &["--root"],
+            StoreOption,
+            "Subnet all the addresses must be within e.g. 10.0.0.0/8 to analyze a single allocation",
+        );
+        arg_parser.refer(&mut load).add_option(
+            &["--load"],
+            StoreOption,
+            "Start from a tree saved with --save instead of an empty one, files are added on top",
+        );
+        arg_parser.refer(&mut save).add_option(
+            &["--save"],
+            StoreOption,
+            "Save the tree to the file to reuse it with --load without reading the input again",
+        );
+        arg_parser.refer(&mut output_format).add_option(
+            &["--output-format"],
+            Store,
+            "How to write the report: text (default), json (a single document with everything known about the subnets), ndjson (a line per address while reading), ndjson-subnets, countries (a table by the country column of --metadata), orgs (subnets by the org column of --metadata), nft, iptables or ipset (rules blocking the subnets commented with their address count, first and last seen with --load or --save and the file most addresses came from), geojson, rpz, sqlite or parquet, the last four need --output",
+        );
+        arg_parser.refer(&mut output).add_option(
+            &["--output"],
+            StoreOption,
+            "File to write the report to e.g. results.db, results.parquet, results.geojson or rpz.zone",
+        );
+        arg_parser.refer(&mut manifest).add_option(
+            &["--manifest"],
+            StoreOption,
+            "File to write a JSON manifest of the run to: hashes of the files read, the options and the counts, e.g. to reproduce the report",
+        );
+        arg_parser.refer(&mut schema).add_option(
+            &["--schema"],
+            StoreTrue,
+            "Print the JSON Schema of --output-format (json, ndjson or ndjson-subnets) and exit",
+        );
+        arg_parser.refer(&mut merge_limits.min_mask_len).add_option(
+            &["--min-mask"],
+            Store,
+            "Never merge addresses into subnets shorter than /N e.g. 16 for blocklists",
+        );
+        arg_parser.refer(&mut merge_limits.min_density).add_option(
+            &["--min-density"],
+            Store,
+            "Never merge addresses into subnets with less than this share of addresses e.g. 0.1",
+        );
+        arg_parser.refer(&mut pipeline).add_option(
+            &["--preprocess"],
+            List,
+            "Rewrite every line before it's parsed, in the given order: trim, strip-port, strip-brackets, lowercase, unmap-ipv6, extract=REGEX e.g. --preprocess strip-port strip-brackets unmap-ipv6",
+        );
+        arg_parser.refer(&mut sources).add_option(
+            &["--sources"],
+            StoreTrue,
+            "Tell the files the addresses of every subnet came from, ndjson lines always have their file",
+        );
+        arg_parser.refer(&mut untagged).add_option(
+            &["--untagged"],
+            StoreTrue,
+            "Report only the addresses none of the --labels rules covers",
+        );
+        arg_parser.refer(&mut views).add_option(
+            &["--views"],
+            List,
+            "Report the addresses grouped several ways one after another e.g. --views /8 /16 /24 subnet",
+        );
+        arg_parser.refer(&mut memory_limit).add_option(
+            &["--memory-limit"],
+            StoreOption,
+            "Collapse the deepest subnets once the tree takes N MiB instead of running out of memory, the report gets coarser",
+        );
+        arg_parser.refer(&mut max_children).add_option(
+            &["--max-children"],
+            StoreOption,
+            "Split up subnets with more than N direct children to keep the tree fast e.g. with --min-mask, the report stays the same",
+        );
+        #[cfg(feature = "template")]
+        arg_parser.refer(&mut template).add_option(
+            &["--template"],
+            StoreOption,
+            "Write the report through a Handlebars template file instead of --output-format, to stdout or --output, e.g. {{#each subnets}}{{subnet}} {{count}}\n{{/each}}",
+        );
+        arg_parser.refer(&mut explain).add_option(
+            &["--explain"],
+            StoreOption,
+            "Print the decisions which made up the subnet instead of the report, i.e. which addresses joined it at which prefix or why they stayed apart, e.g. --explain 10.0.0.0/24",
+        );
         arg_parser.parse_args_or_exit();
     }
-    let config = Config::new(file_names)?;
-    if config.has_files() {
-        ipv4_classify::find_subnets(config.file_names)?;
-        Ok(())
+    if schema {
+        println!("{}", ipv4_classify::json_schema(&output_format)?);
+        return Ok(());
+    }
+    // files besides the input ones the report depends on
+    let extra_files: Vec<PathBuf> = [&whitelist, &labels, &metadata, &boundaries]
+        .into_iter()
+        .flatten()
+        .cloned()
+        .collect();
+    let whitelist = match whitelist {
+        Some(file_name) => read_set(&file_name)?,
+        None => SubnetSet::new(),
+    };
+    let labels = match labels {
+        Some(file_name) => fs::read_to_string(&file_name)
+            .map_err(ipv4_classify::Error::from)
+            .and_then(|content| Labels::from_lines(content.split('\n')))
+            .map_err(|e| format!("{}: {}", file_name.display(), e))?,
+        None => Labels::new(),
+    };
+    let metadata = match metadata {
+        Some(file_name) => fs::read_to_string(&file_name)
+            .map_err(ipv4_classify::Error::from)
+            .and_then(|content| Metadata::from_lines(content.split('\n')))
+            .map_err(|e| format!("{}: {}", file_name.display(), e))?,
+        None => Metadata::new(),
+    };
+    let rate_limits = match rate_limits {
+        Some(file_name) => read_rate_limits(&file_name)?,
+        None => RateLimits::new(),
+    };
+    let boundaries = match boundaries {
+        Some(file_name) => read_prefixes(&file_name)?,
+        None => vec![],
+    };
+    let mut config = Config::builder()
+        .files(file_names)
+        .top(top)
+        .group_by(group_by)
+        .lenient(lenient)
+        .whitelist(whitelist)
+        .gaps(gaps)
+        .labels(labels)
+        .by_hits(by_hits)
+        .scoring((by_score || score_weights.is_some()).then(|| score_weights.unwrap_or_default()))
+        .metadata(metadata)
+        .whois(whois)
+        .rate_limits(rate_limits)
+        .audit_log(audit_log)
+        .min_count(min_count)
+        .boundaries(boundaries)
+        .root(root)
+        .load(load)
+        .save(save)
+        .output_format(output_format)
+        .template(template)
+        .output(output)
+        .manifest(manifest)
+        .views(views)
+        .untagged(untagged)
+        .merge_limits(merge_limits)
+        .sources(sources)
+        .pipeline(pipeline.into_iter().collect())
+        .memory_limit(memory_limit.map(|mib| mib.saturating_mul(1024 * 1024)))
+        .max_children(max_children)
+        .build()?;
+    if config.has_input() {
+        let mut manifest = config
+            .manifest
+            .is_some()
+            .then(|| Manifest::new(env::args().skip(1)));
+        if let Some(manifest) = &mut manifest {
+            let files = config
+                .file_names
+                .iter()
+                .chain(&config.load)
+                .chain(&extra_files);
+            for file_name in files {
+                match manifest.add_input(file_name) {
+                    // the file is reported as skipped along with the rest of the failures
+                    Err(_) if config.lenient => {}
+                    result => result.map_err(|e| format!("{}: {}", file_name.display(), e))?,
+                }
+            }
+        }
+        let token = CancelToken::new();
+        let handler_token = token.clone();
+        ctrlc::set_handler(move || handler_token.cancel())?;
+        let mut address_tree = match &config.load {
+            Some(file_name) => AddressTree::load(file_name)?,
+            None => {
+                AddressTree::rooted_at(config.root.unwrap_or_else(Subnet::root), config.boundaries)
+            }
+        };
+        if config.merge_limits != MergeLimits::default() {
+            address_tree.set_merge_policy(config.merge_limits);
+        }
+        for (subnet, label) in config.labels.rules() {
+            address_tree.tag(&subnet, label);
+        }
+        if let Some(memory_limit) = config.memory_limit {
+            address_tree.limit_memory(memory_limit);
+        }
+        if let Some(max_children) = config.max_children {
+            address_tree.limit_children(max_children);
+        }
+        // first and last seen only make sense across the runs sharing a saved tree
+        let track_seen = config.load.is_some() || config.save.is_some();
+        if track_seen {
+            let now = SystemTime::now().duration_since(UNIX_EPOCH)?;
+            address_tree.track_seen(now.as_secs() as i64);
+        }
+        if let Some(subnet) = explain {
+            // the input is pushed through the explainer instead of reported
+            let mut explainer = Explainer::new(subnet);
+            let failures = ipv4_classify::read_into_tree_through(
+                &mut address_tree,
+                config.file_names,
+                config.lenient,
+                &token,
+                &mut explainer,
+                &config.pipeline,
+            )?;
+            for failure in failures {
+                eprintln!("skipped {}", failure);
+            }
+            println!("{}", explainer);
+            let group_by = config.group_by.joined(&config.metadata);
+            let subnets = address_tree.get_groups_map_except(&group_by, &config.whitelist);
+            if !subnets.contains_key(&explainer.subnet().to_string()) {
+                eprintln!("{} isn't among the reported subnets", explainer.subnet());
+            }
+            return Ok(());
+        }
+        let mut provenance = None;
+        let failures = if config.output_format == OutputFormat::Ndjson {
+            let mut sink = NdjsonSink::new(stdout().lock());
+            let failures = ipv4_classify::read_into_tree_through(
+                &mut address_tree,
+                config.file_names,
+                config.lenient,
+                &token,
+                &mut sink,
+                &config.pipeline,
+            )?;
+            sink.finish()?;
+            failures
+        } else if config.sources || config.output_format.is_firewall() {
+            let mut observer = (StderrLogger, Provenance::new());
+            let failures = ipv4_classify::read_into_tree_through(
+                &mut address_tree,
+                config.file_names,
+                config.lenient,
+                &token,
+                &mut observer,
+                &config.pipeline,
+            )?;
+            provenance = Some(observer.1);
+            failures
+        } else {
+            ipv4_classify::read_into_tree_through(
+                &mut address_tree,
+                config.file_names,
+                config.lenient,
+                &token,
+                &mut StderrLogger,
+                &config.pipeline,
+            )?
+        };
+        if let Some(manifest) = &mut manifest {
+            manifest.failures = failures.len();
+            manifest.addresses = address_tree.get_hits_map().len();
+        }
+        for failure in failures {
+            eprintln!("skipped {}", failure);
+        }
+        if let Some(file_name) = &config.save {
+            if token.is_cancelled() {
+                eprintln!(
+                    "not saving the tree to {} as it's partial",
+                    file_name.display()
+                );
+            } else {
+                address_tree.save(file_name)?;
+            }
+        }
+        // a single view is the grouping itself
+        let views = match config.views.is_empty() {
+            true => std::slice::from_ref(&config.group_by),
+            false => config.views.as_slice(),
+        };
+        let mut views: Vec<GroupBy> = views.iter().map(|v| v.joined(&config.metadata)).collect();
+        let mut maps = address_tree.get_views_map_except(&views, &config.whitelist);
+        if config.whois {
+            let addrs: Vec<Subnet> = maps
+                .iter()
+                .flat_map(|subnets| subnets.values().flatten())
+                .filter_map(|addr| addr.parse().ok())
+                .collect();
+            let mut whois = Whois::new(ipv4_classify::IANA_WHOIS)
+                .with_rate_limit(config.rate_limits.get("whois"));
+            match &config.audit_log {
+                Some(file_name) => {
+                    let mut log = JsonLog::new(BufWriter::new(fs::File::create(file_name)?));
+                    whois.enrich_observed(&mut config.metadata, &addrs, &mut log)?;
+                    log.finish()?;
+                }
+                None => whois.enrich(&mut config.metadata, &addrs)?,
+            }
+            // column groups take in what whois has found
+            if views.iter().any(|v| matches!(v, GroupBy::Column(..))) {
+                views = views.iter().map(|v| v.joined(&config.metadata)).collect();
+                maps = address_tree.get_views_map_except(&views, &config.whitelist);
+            }
+        }
+        let address_hits = (!config.output_format.is_streamed()
+            || config.output_format == OutputFormat::Json
+            || config.scoring.is_some()
+            || config.template.is_some())
+        .then(|| address_tree.get_hits_map());
+        #[cfg_attr(not(feature = "template"), allow(unused_mut))]
+        let mut exporters = Exporters::new();
+        #[cfg(feature = "template")]
+        if let Some(file_name) = &config.template {
+            let template = fs::read_to_string(file_name)
+                .map_err(ipv4_classify::Error::from)
+                .and_then(|source| ipv4_classify::Template::new(&source))
+                .map_err(|e| format!("{}: {}", file_name.display(), e))?;
+            exporters.register(template);
+        }
+        // a template stands for the output format
+        let format_name = match config.template {
+            Some(_) => "template",
+            None => config.output_format.name(),
+        };
+        for (group_by, mut subnets) in views.iter().zip(maps) {
+            if !config.views.is_empty() {
+                println!("{} view:", group_by);
+            }
+            if config.untagged {
+                address_tree.retain_untagged(&mut subnets);
+            }
+            if let Some(min_count) = config.min_count {
+                ipv4_classify::retain_min_count(&mut subnets, min_count);
+            }
+            if let Some(manifest) = &mut manifest {
+                manifest.subnets += config
+                    .top
+                    .map_or(subnets.len(), |top| top.min(subnets.len()));
+            }
+            if config.output_format == OutputFormat::Ndjson {
+                // the addresses are streamed while reading
+            } else if config.gaps {
+                ipv4_classify::print_gaps(&subnets, config.top);
+            } else {
+                let weights = config
+                    .by_hits
+                    .then(|| address_tree.get_weights_map_except(group_by, &config.whitelist));
+                let seen = track_seen
+                    .then(|| address_tree.get_seen_map_except(group_by, &config.whitelist));
+                let report = Report::new(&subnets, &config.labels, &config.metadata)
+                    .weights(weights.as_ref())
+                    .address_hits(address_hits.as_ref())
+                    .seen(seen.as_ref())
+                    .sources(provenance.as_ref())
+                    .top(config.top)
+                    .scoring(config.scoring.as_ref());
+                match (&config.output_format, &config.output) {
+                    #[cfg(feature = "sqlite")]
+                    (OutputFormat::Sqlite, Some(file_name)) => {
+                        ipv4_classify::export_sqlite(&report, file_name)?
+                    }
+                    #[cfg(feature = "parquet")]
+                    (OutputFormat::Parquet, Some(file_name)) => {
+                        ipv4_classify::export_parquet(&report, file_name)?
+                    }
+                    (_, Some(file_name)) => {
+                        let mut out = BufWriter::new(fs::File::create(file_name)?);
+                        exporters.write(format_name, &report, &mut out)?
+                    }
+                    (_, None) => exporters.write(format_name, &report, &mut stdout().lock())?,
+                }
+            }
+        }
+        if let (Some(manifest), Some(file_name)) = (&manifest, &config.manifest) {
+            manifest.save(file_name)?;
+        }
+        if token.is_cancelled() {
+            Err("interrupted, the report above is partial".into())
+        } else {
+            Ok(())
+        }
     } else {
         Err("no files provided, try -h".into())
     }
 }
+
+/// generate subcommand - print random addresses clustered into subnets
+fn generate(args: Vec<String>) -> Result<(), Box<dyn Error>> {
+    let mut subnets = 10;
+    let mut hosts_per_subnet = 50;
+    let mut mask_len = 24;
+    let mut seed = 0;
+    {
+        let mut arg_parser = ArgumentParser::new();
+        arg_parser.set_description("Generate a synthetic list of IPv4 addresses");
+        arg_parser.refer(&mut subnets).add_option(
+            &["-s", "--subnets"],
+            Store,
+            "Number of subnets to spread addresses over (default 10)",
+        );
+        arg_parser.refer(&mut hosts_per_subnet).add_option(
+            &["-n", "--hosts-per-subnet"],
+            Store,
+            "Number of addresses in every subnet (default 50)",
+        );
+        arg_parser.refer(&mut mask_len).add_option(
+            &["-m", "--mask-len"],
+            Store,
+            "Mask length of the subnets, smaller values spread addresses wider (default 24)",
+        );
+        arg_parser.refer(&mut seed).add_option(
+            &["--seed"],
+            Store,
+            "Seed of the random generator, the same seed gives the same list (default 0)",
+        );
+        if let Err(code) = arg_parser.parse(args, &mut stdout(), &mut stderr()) {
+            process::exit(code);
+        }
+    }
+    for addr in ipv4_classify::generate(subnets, hosts_per_subnet, mask_len, seed)? {
+        println!("{}", addr);
+    }
+    Ok(())
+}
+
+/// normalize subcommand - lint and canonicalize a list of addresses and subnets
+fn normalize(args: Vec<String>) -> Result<(), Box<dyn Error>> {
+    let mut file_name = PathBuf::new();
+    let mut output = None::<String>;
+    {
+        let mut arg_parser = ArgumentParser::new();
+        arg_parser.set_description(
+            "Validate a list of IPv4 addresses and subnets, clear host bits, deduplicate and sort it",
+        );
+        arg_parser.refer(&mut file_name).required().add_option(
+            &["-f", "--file"],
+            Store,
+            "File to normalize",
+        );
+        arg_parser.refer(&mut output).add_option(
+            &["-o", "--output"],
+            StoreOption,
+            "File to write the clean list to instead of stdout",
+        );
+        if let Err(code) = arg_parser.parse(args, &mut stdout(), &mut stderr()) {
+            process::exit(code);
+        }
+    }
+    let (subnets, malformed) = ipv4_classify::normalize(&file_name)?;
+    match output {
+        Some(output) => fs::write(output, subnets.join("\n") + "\n")?,
+        None => subnets.iter().for_each(|s| println!("{}", s)),
+    }
+    for problem in &malformed {
+        eprintln!("{}: {}", file_name.display(), problem);
+    }
+    if malformed.is_empty() {
+        Ok(())
+    } else {
+        Err(format!(
+            "{} malformed entries in {}",
+            malformed.len(),
+            file_name.display()
+        )
+        .into())
+    }
+}
+
+/// overlap subcommand - find subnets shared by several lists of addresses
+fn overlap(args: Vec<String>) -> Result<(), Box<dyn Error>> {
+    let mut file_names: Vec<PathBuf> = vec![];
+    let mut group_by = GroupBy::Subnet;
+    {
+        let mut arg_parser = ArgumentParser::new();
+        arg_parser.set_description(
+            "Report subnets with addresses from more than one file, e.g. a blocklist and a threat feed",
+        );
+        arg_parser.refer(&mut file_names).required().add_option(
+            &["-f", "--files"],
+            List,
+            "Two or more files to compare, file names label the counts",
+        );
+        arg_parser.refer(&mut group_by).add_option(
+            &["-g", "--group-by"],
+            Store,
+            "Grouping to compare by: subnet (default), class or prefix length e.g. /24",
+        );
+        if let Err(code) = arg_parser.parse(args, &mut stdout(), &mut stderr()) {
+            process::exit(code);
+        }
+    }
+    let overlaps = ipv4_classify::find_overlaps(&file_names, &group_by)?;
+    if overlaps.is_empty() {
+        println!("no overlaps found");
+    } else {
+        println!("overlaps found:");
+        for overlap in overlaps {
+            println!("{}", overlap);
+        }
+    }
+    Ok(())
+}
+
+/// redundant subcommand - list duplicate and shadowed entries to clean up
+fn redundant(args: Vec<String>) -> Result<(), Box<dyn Error>> {
+    let mut file_names: Vec<PathBuf> = vec![];
+    {
+        let mut arg_parser = ArgumentParser::new();
+        arg_parser.set_description(
+            "Report entries which are duplicates or lie within another entry, e.g. to clean up firewall rules",
+        );
+        arg_parser.refer(&mut file_names).required().add_option(
+            &["-f", "--files"],
+            List,
+            "Files with addresses and subnets, checked together",
+        );
+        if let Err(code) = arg_parser.parse(args, &mut stdout(), &mut stderr()) {
+            process::exit(code);
+        }
+    }
+    let redundant = ipv4_classify::find_redundant(&file_names)?;
+    if redundant.is_empty() {
+        println!("no redundant entries found");
+    } else {
+        println!("redundant entries found:");
+        for entry in redundant {
+            println!("{}", entry);
+        }
+    }
+    Ok(())
+}
+
+/// setop subcommand - CIDR-aware set algebra between lists of addresses and subnets
+fn setop(args: Vec<String>) -> Result<(), Box<dyn Error>> {
+    let mut op = SetOp::Union;
+    let mut file_names: Vec<PathBuf> = vec![];
+    let mut max_entries = None::<usize>;
+    {
+        let mut arg_parser = ArgumentParser::new();
+        arg_parser.set_description(
+            "Combine lists of IPv4 addresses and subnets, print the result as the shortest list of subnets",
+        );
+        arg_parser.refer(&mut op).required().add_option(
+            &["--op"],
+            Store,
+            "Operation to apply left to right: union, intersect or subtract",
+        );
+        arg_parser.refer(&mut max_entries).add_option(
+            &["--max-entries"],
+            StoreOption,
+            "Merge the result into at most N subnets covering as few extra addresses as possible e.g. for a WAF IP set",
+        );
+        arg_parser.refer(&mut file_names).required().add_argument(
+            "files",
+            List,
+            "Files to combine e.g. ours.txt theirs.txt",
+        );
+        if let Err(code) = arg_parser.parse(args, &mut stdout(), &mut stderr()) {
+            process::exit(code);
+        }
+    }
+    let mut sets = file_names.iter().map(read_set);
+    let mut res = sets.next().expect("files are required")?;
+    for set in sets {
+        res = op.apply(&res, &set?);
+    }
+    if max_entries == Some(0) {
+        return Err("max entries should be at least 1".into());
+    }
+    match max_entries {
+        Some(max_entries) => {
+            let plan = res.compact(max_entries);
+            for subnet in &plan.rules {
+                println!("{}", subnet);
+            }
+            eprintln!(
+                "{} subnets cover {} addresses and {} more",
+                plan.rules.len(),
+                plan.covered,
+                plan.collateral
+            );
+        }
+        None => {
+            for subnet in res.subnets() {
+                println!("{}", subnet);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// optimize subcommand - pick a short block list for the observed addresses
+fn optimize(args: Vec<String>) -> Result<(), Box<dyn Error>> {
+    let mut file_names: Vec<PathBuf> = vec![];
+    let mut target = Target::default();
+    let mut coverage = None::<f64>;
+    let mut whitelist = None::<PathBuf>;
+    let mut jail = None::<String>;
+    let mut ignoreip = false;
+    {
+        let mut arg_parser = ArgumentParser::new();
+        arg_parser.set_description(
+            "Select subnets to block, trading the number of rules for unobserved addresses blocked by them",
+        );
+        arg_parser.refer(&mut file_names).required().add_option(
+            &["-f", "--files"],
+            List,
+            "List of files with observed ipv4 addresses e.g. -f one.txt another.txt",
+        );
+        arg_parser.refer(&mut target.max_rules).add_option(
+            &["-r", "--max-rules"],
+            StoreOption,
+            "Select no more than N rules",
+        );
+        arg_parser.refer(&mut coverage).add_option(
+            &["-c", "--coverage"],
+            StoreOption,
+            "Percent of observed addresses to cover with as few rules as possible e.g. 95",
+        );
+        arg_parser.refer(&mut target.max_collateral).add_option(
+            &["-d", "--collateral"],
+            Store,
+            "Number of unobserved addresses the rules may block (default 0)",
+        );
+        arg_parser.refer(&mut whitelist).add_option(
+            &["-w", "--whitelist"],
+            StoreOption,
+            "File with addresses and subnets which must never be blocked",
+        );
+        arg_parser.refer(&mut jail).add_option(
+            &["--fail2ban-jail"],
+            StoreOption,
+            "Print \"fail2ban-client set JAIL banip SUBNET\" commands instead of bare subnets",
+        );
+        arg_parser.refer(&mut ignoreip).add_option(
+            &["--fail2ban-ignoreip"],
+            StoreTrue,
+            "Print a jail.local [DEFAULT] section with the subnets in ignoreip instead of bare subnets",
+        );
+        if let Err(code) = arg_parser.parse(args, &mut stdout(), &mut stderr()) {
+            process::exit(code);
+        }
+    }
+    target.min_coverage = coverage.map(|percent| percent / 100.0);
+    if let Some(file_name) = whitelist {
+        target.whitelist = read_set(&file_name)?;
+    }
+    if jail.is_some() && ignoreip {
+        return Err("choose either banip commands or ignoreip".into());
+    }
+    let plan = ipv4_classify::find_block_rules(&file_names, &target)?;
+    if let Some(jail) = jail {
+        print!("{}", ipv4_classify::fail2ban_banip(&jail, &plan.rules)?);
+    } else if ignoreip {
+        print!("{}", ipv4_classify::fail2ban_ignoreip(&plan.rules));
+    } else {
+        for rule in &plan.rules {
+            println!("{}", rule);
+        }
+    }
+    eprintln!(
+        "{} rules cover {} of {} addresses ({:.1}%) blocking {} more",
+        plan.rules.len(),
+        plan.covered,
+        plan.observed,
+        plan.coverage() * 100.0,
+        plan.collateral
+    );
+    match target.min_coverage {
+        Some(min) if plan.coverage() < min => {
+            Err("coverage target can't be met within the limits".into())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// summary subcommand - show how addresses are spread over networks
+fn summary(args: Vec<String>) -> Result<(), Box<dyn Error>> {
+    let mut file_names: Vec<PathBuf> = vec![];
+    {
+        let mut arg_parser = ArgumentParser::new();
+        arg_parser.set_description(
+            "Show a prefix length histogram and how addresses spread over /8, /16 and /24 networks",
+        );
+        arg_parser.refer(&mut file_names).required().add_option(
+            &["-f", "--files"],
+            List,
+            "List of files with ipv4 addresses to read e.g. -f one.txt another.txt",
+        );
+        if let Err(code) = arg_parser.parse(args, &mut stdout(), &mut stderr()) {
+            process::exit(code);
+        }
+    }
+    println!("{}", ipv4_classify::read_tree(&file_names)?.summary());
+    Ok(())
+}
+
+/// serve subcommand - answer HTTP requests with the tree kept in memory
+#[cfg(feature = "serve")]
+fn serve(args: Vec<String>) -> Result<(), Box<dyn Error>> {
+    let mut address = "127.0.0.1:8080".to_string();
+    let mut file_names: Vec<PathBuf> = vec![];
+    let mut load = None::<PathBuf>;
+    let mut save = None::<PathBuf>;
+    let mut syslog = None::<String>;
+    {
+        let mut arg_parser = ArgumentParser::new();
+        arg_parser.set_description(
+            "Keep the tree in memory and serve POST /addresses, GET /lookup/1.2.3.4, GET /subnets and GET /metrics",
+        );
+        arg_parser.refer(&mut address).add_option(
+            &["-a", "--address"],
+            Store,
+            "Address to listen on (default 127.0.0.1:8080)",
+        );
+        arg_parser.refer(&mut file_names).add_option(
+            &["-f", "--files"],
+            List,
+            "List of files with ipv4 addresses to start with e.g. -f one.txt another.txt",
+        );
+        arg_parser.refer(&mut load).add_option(
+            &["--load"],
+            StoreOption,
+            "Start from a tree saved with --save",
+        );
+        arg_parser.refer(&mut save).add_option(
+            &["--save"],
+            StoreOption,
+            "Save the tree to the file on exit (Ctrl+C)",
+        );
+        arg_parser.refer(&mut syslog).add_option(
+            &["--syslog"],
+            StoreOption,
+            "Also listen for syslog messages over UDP and TCP e.g. 0.0.0.0:514 and push addresses found in them",
+        );
+        if let Err(code) = arg_parser.parse(args, &mut stdout(), &mut stderr()) {
+            process::exit(code);
+        }
+    }
+    let mut address_tree = match &load {
+        Some(file_name) => AddressTree::load(file_name)?,
+        None => AddressTree::new(),
+    };
+    let token = CancelToken::new();
+    ipv4_classify::read_into_tree(&mut address_tree, &file_names, false, &token)?;
+    let handler_token = token.clone();
+    ctrlc::set_handler(move || handler_token.cancel())?;
+    eprintln!("listening on {}", address);
+    let address_tree = match syslog {
+        Some(syslog) => {
+            eprintln!("receiving syslog on {}", syslog);
+            ipv4_classify::serve_with_syslog(&address, &syslog, address_tree, &token)?
+        }
+        None => ipv4_classify::serve(&address, address_tree, &token)?,
+    };
+    if let Some(file_name) = save {
+        address_tree.save(file_name)?;
+    }
+    Ok(())
+}
+
+/// stream subcommand - classify an endless input printing a report every so often
+fn stream(args: Vec<String>) -> Result<(), Box<dyn Error>> {
+    let mut every_lines = None::<usize>;
+    let mut every_seconds = None::<u64>;
+    let mut decay = None::<f64>;
+    let mut ttl = None::<u64>;
+    let mut memory_limit = None::<usize>;
+    let mut group_by = GroupBy::Subnet;
+    let mut top = None;
+    let mut output_format = OutputFormat::Text;
+    #[cfg(feature = "serve")]
+    let mut syslog = None::<String>;
+    {
+        let mut arg_parser = ArgumentParser::new();
+        arg_parser.set_description(
+            "Classify addresses of stdin as they come, e.g. from tail -f, printing a report of everything so far every N lines and/or seconds and once more at the end",
+        );
+        arg_parser.refer(&mut every_lines).add_option(
+            &["--every-lines"],
+            StoreOption,
+            "Print a report once N more addresses are read",
+        );
+        arg_parser.refer(&mut every_seconds).add_option(
+            &["--every-seconds"],
+            StoreOption,
+            "Print a report every N seconds if any address was read meanwhile",
+        );
+        arg_parser.refer(&mut decay).add_option(
+            &["--decay"],
+            StoreOption,
+            "Multiply hits of the addresses by this factor after every report e.g. 0.5, addresses left without hits are dropped, so ones not seen again fade out",
+        );
+        arg_parser.refer(&mut ttl).add_option(
+            &["--ttl"],
+            StoreOption,
+            "Drop addresses not seen for N seconds, so the reports only cover the recent ones along with their first and last seen times",
+        );
+        arg_parser.refer(&mut memory_limit).add_option(
+            &["--memory-limit"],
+            StoreOption,
+            "Collapse the deepest subnets once the tree takes N MiB, the reports get coarser",
+        );
+        arg_parser.refer(&mut group_by).add_option(
+            &["-g", "--group-by"],
+            Store,
+            "Top-level grouping of the reports: subnet (default), class or prefix length e.g. /8",
+        );
+        arg_parser.refer(&mut top).add_option(
+            &["-t", "--top"],
+            StoreOption,
+            "Report only N subnets with the most addresses",
+        );
+        arg_parser.refer(&mut output_format).add_option(
+            &["--output-format"],
+            Store,
+            "How to write the reports: text (default), json, ndjson-subnets, countries, orgs, nft, iptables, ipset, geojson or rpz",
+        );
+        #[cfg(feature = "serve")]
+        arg_parser.refer(&mut syslog).add_option(
+            &["--syslog"],
+            StoreOption,
+            "Listen for syslog messages over UDP and TCP e.g. 0.0.0.0:514 instead of reading stdin",
+        );
+        if let Err(code) = arg_parser.parse(args, &mut stdout(), &mut stderr()) {
+            process::exit(code);
+        }
+    }
+    let exporters = Exporters::new();
+    if exporters.get(output_format.name()).is_none() {
+        return Err(format!("{} output can't be streamed", output_format.name()).into());
+    }
+    if decay.is_some_and(|factor| !(0.0..=1.0).contains(&factor)) {
+        return Err("--decay should be within 0.0..=1.0".into());
+    }
+    let mut address_tree = AddressTree::new();
+    if let Some(mib) = memory_limit {
+        address_tree.limit_memory(mib.saturating_mul(1024 * 1024));
+    }
+    let mut aggregator = Aggregator::new(address_tree);
+    if let Some(lines) = every_lines {
+        aggregator = aggregator.every_addresses(lines);
+    }
+    if let Some(seconds) = every_seconds {
+        aggregator = aggregator.every(Duration::from_secs(seconds));
+    }
+    if let Some(factor) = decay {
+        aggregator = aggregator.decay(factor);
+    }
+    if let Some(seconds) = ttl {
+        aggregator = aggregator.ttl(Duration::from_secs(seconds));
+    }
+    let token = CancelToken::new();
+    let handler_token = token.clone();
+    ctrlc::set_handler(move || handler_token.cancel())?;
+    let (labels, metadata) = (Labels::new(), Metadata::new());
+    let mut snapshots = 0;
+    let on_snapshot = |address_tree: &AddressTree| {
+        snapshots += 1;
+        let subnets = address_tree.get_groups_map(&group_by);
+        let address_hits = address_tree.get_hits_map();
+        let seen = ttl.map(|_| address_tree.get_seen_map(&group_by));
+        let report = Report::new(&subnets, &labels, &metadata)
+            .address_hits(Some(&address_hits))
+            .seen(seen.as_ref())
+            .top(top);
+        let mut out = stdout().lock();
+        if output_format == OutputFormat::Text {
+            writeln!(out, "snapshot {}:", snapshots)?;
+        }
+        exporters.write(output_format.name(), &report, &mut out)
+    };
+    #[cfg(feature = "serve")]
+    if let Some(syslog) = syslog {
+        let listener = ipv4_classify::SyslogListener::bind(&syslog, &token)?;
+        eprintln!("receiving syslog on {}", listener.local_addr());
+        return Ok(aggregator.run(listener, &token, on_snapshot)?);
+    }
+    Ok(aggregator.run(FollowSource::stdin(), &token, on_snapshot)?)
+}
+
+/// route subcommand - assign addresses to the user's prefixes only
+fn route(args: Vec<String>) -> Result<(), Box<dyn Error>> {
+    let mut table = None::<PathBuf>;
+    let mut mrt = None::<PathBuf>;
+    #[cfg(feature = "ripestat")]
+    let mut ripestat = false;
+    #[cfg(feature = "ripestat")]
+    let mut rate_limits = None::<PathBuf>;
+    let mut file_names: Vec<PathBuf> = vec![];
+    {
+        let mut arg_parser = ArgumentParser::new();
+        arg_parser.set_description(
+            "Assign addresses to the longest matching prefix of a routing table or an allocation plan",
+        );
+        arg_parser.refer(&mut table).add_option(
+            &["-p", "--prefixes"],
+            StoreOption,
+            "File with a prefix per line e.g. 10.0.0.0/8, anything after the prefix is ignored",
+        );
+        arg_parser.refer(&mut mrt).add_option(
+            &["--mrt"],
+            StoreOption,
+            "Uncompressed MRT routing table dump e.g. a RouteViews or RIPE RIS RIB file instead of --prefixes",
+        );
+        #[cfg(feature = "ripestat")]
+        arg_parser.refer(&mut ripestat).add_option(
+            &["--ripestat"],
+            StoreTrue,
+            "Use the prefixes announced in BGP according to RIPEstat instead of --prefixes",
+        );
+        #[cfg(feature = "ripestat")]
+        arg_parser.refer(&mut rate_limits).add_option(
+            &["--rate-limits"],
+            StoreOption,
+            "File with limits of the enrichment providers like \"ripestat = 4/s 10000/day\"",
+        );
+        arg_parser.refer(&mut file_names).required().add_option(
+            &["-f", "--files"],
+            List,
+            "List of files with ipv4 addresses to read e.g. -f one.txt another.txt",
+        );
+        if let Err(code) = arg_parser.parse(args, &mut stdout(), &mut stderr()) {
+            process::exit(code);
+        }
+    }
+    if table.is_some() && mrt.is_some() {
+        return Err("--prefixes and --mrt can't be used together".into());
+    }
+    #[cfg(feature = "ripestat")]
+    if ripestat {
+        if table.is_some() || mrt.is_some() {
+            return Err("--ripestat can't be used with --prefixes or --mrt".into());
+        }
+        let limit = match rate_limits {
+            Some(file_name) => read_rate_limits(&file_name)?.get("ripestat"),
+            None => ipv4_classify::RateLimit::default(),
+        };
+        let mut ripestat = ipv4_classify::RipeStat::new().with_rate_limit(limit);
+        print!(
+            "{}",
+            ipv4_classify::find_announced_routes(&mut ripestat, &file_names)?
+        );
+        return Ok(());
+    }
+    let routed = match (table, mrt) {
+        (Some(table), _) => ipv4_classify::find_routes(&table, &file_names)?,
+        (None, Some(mrt)) => ipv4_classify::find_mrt_routes(&mrt, &file_names)?,
+        (None, None) => return Err("either --prefixes or --mrt is required".into()),
+    };
+    print!("{}", routed);
+    Ok(())
+}
+
+/// abuse subcommand - a report per network to send to its abuse contact
+fn abuse(args: Vec<String>) -> Result<(), Box<dyn Error>> {
+    let mut file_names: Vec<PathBuf> = vec![];
+    let mut evidence: Vec<PathBuf> = vec![];
+    let mut contacts = None::<PathBuf>;
+    let mut whois = false;
+    let mut rate_limits = None::<PathBuf>;
+    let mut template = None::<PathBuf>;
+    let mut output_dir = None::<PathBuf>;
+    {
+        let mut arg_parser = ArgumentParser::new();
+        arg_parser.set_description(
+            "Group offending addresses by the networks of their abuse contacts and write a report per network",
+        );
+        arg_parser.refer(&mut file_names).required().add_option(
+            &["-f", "--files"],
+            List,
+            "List of files with offending ipv4 addresses e.g. -f one.txt another.txt",
+        );
+        arg_parser.refer(&mut evidence).add_option(
+            &["-e", "--evidence"],
+            List,
+            "Log files to attach the lines mentioning the addresses from",
+        );
+        arg_parser.refer(&mut contacts).add_option(
+            &["-c", "--contacts"],
+            StoreOption,
+            "File with contacts like \"203.0.113.0/24 = Example Org <abuse@example.org>\"",
+        );
+        arg_parser.refer(&mut whois).add_option(
+            &["--whois"],
+            StoreTrue,
+            "Look up contacts missing in --contacts with whois, starting at IANA",
+        );
+        arg_parser.refer(&mut rate_limits).add_option(
+            &["--rate-limits"],
+            StoreOption,
+            "File with limits of the enrichment providers like \"whois = 1/s 5000/day\", networks left once a day's quota is used up are reported as having no contact",
+        );
+        arg_parser.refer(&mut template).add_option(
+            &["--template"],
+            StoreOption,
+            "File with the report text, {email}, {org}, {netblock}, {count}, {addresses} and {evidence} are filled in",
+        );
+        arg_parser.refer(&mut output_dir).add_option(
+            &["-o", "--output-dir"],
+            StoreOption,
+            "Directory to write a file per network to instead of printing the reports",
+        );
+        if let Err(code) = arg_parser.parse(args, &mut stdout(), &mut stderr()) {
+            process::exit(code);
+        }
+    }
+    let mut contacts = match contacts {
+        Some(file_name) => fs::read_to_string(&file_name)
+            .map_err(ipv4_classify::Error::from)
+            .and_then(|content| AbuseContacts::from_lines(content.split('\n')))
+            .map_err(|e| format!("{}: {}", file_name.display(), e))?,
+        None => AbuseContacts::new(),
+    };
+    if rate_limits.is_some() && !whois {
+        return Err("--rate-limits needs --whois".into());
+    }
+    if whois {
+        let limit = match rate_limits {
+            Some(file_name) => read_rate_limits(&file_name)?.get("whois"),
+            None => ipv4_classify::RateLimit::default(),
+        };
+        contacts =
+            contacts.with_whois(Whois::new(ipv4_classify::IANA_WHOIS).with_rate_limit(limit));
+    }
+    let template = match template {
+        Some(file_name) => {
+            fs::read_to_string(&file_name).map_err(|e| format!("{}: {}", file_name.display(), e))?
+        }
+        None => ipv4_classify::DEFAULT_TEMPLATE.to_string(),
+    };
+    let abuse = ipv4_classify::find_abuse(&mut contacts, &file_names, &evidence)?;
+    for report in &abuse.reports {
+        let text = report.render(&template);
+        match &output_dir {
+            Some(dir) => {
+                let netblock = report.contact.netblock.to_string().replace('/', "_");
+                fs::write(dir.join(format!("{}.txt", netblock)), text)?;
+            }
+            None => println!("{}", text),
+        }
+    }
+    for addr in &abuse.unresolved {
+        eprintln!("no abuse contact for {}", addr);
+    }
+    Ok(())
+}
+
+/// compare subcommand - highlight subnets which grew since the baseline
+fn compare(args: Vec<String>) -> Result<(), Box<dyn Error>> {
+    let mut baseline = PathBuf::new();
+    let mut current = PathBuf::new();
+    let mut group_by = GroupBy::Subnet;
+    let mut factor = 2.0;
+    {
+        let mut arg_parser = ArgumentParser::new();
+        arg_parser.set_description(
+            "Report subnets with more addresses than in the baseline, e.g. newly active networks",
+        );
+        arg_parser.refer(&mut baseline).required().add_option(
+            &["-b", "--baseline"],
+            Store,
+            "File with the addresses seen before",
+        );
+        arg_parser.refer(&mut current).required().add_option(
+            &["-c", "--current"],
+            Store,
+            "File with the addresses seen now",
+        );
+        arg_parser.refer(&mut group_by).add_option(
+            &["-g", "--group-by"],
+            Store,
+            "Grouping to compare by: subnet (default), class or prefix length e.g. /24",
+        );
+        arg_parser.refer(&mut factor).add_option(
+            &["--factor"],
+            Store,
+            "Report subnets with more than that many times the baseline addresses (default 2)",
+        );
+        if let Err(code) = arg_parser.parse(args, &mut stdout(), &mut stderr()) {
+            process::exit(code);
+        }
+    }
+    let grown = ipv4_classify::find_growth(&baseline, &current, &group_by, factor)?;
+    if grown.is_empty() {
+        println!("no growth found");
+    } else {
+        println!("growth found:");
+        for growth in grown {
+            println!("{}", growth);
+        }
+    }
+    Ok(())
+}
+
+/// crosstab subcommand - tell which subnets appear in which of several datasets
+fn crosstab(args: Vec<String>) -> Result<(), Box<dyn Error>> {
+    let mut datasets: Vec<String> = vec![];
+    let mut group_by = GroupBy::Subnet;
+    {
+        let mut arg_parser = ArgumentParser::new();
+        arg_parser.set_description(
+            "Tabulate subnets against datasets, e.g. per data center or per day, with the number of addresses of every dataset in every subnet",
+        );
+        arg_parser.refer(&mut datasets).required().add_option(
+            &["-f", "--files"],
+            List,
+            "Files with the datasets, optionally labeled e.g. -f dc1=dc1.txt dc2=dc2.txt, file names label the rest",
+        );
+        arg_parser.refer(&mut group_by).add_option(
+            &["-g", "--group-by"],
+            Store,
+            "Grouping to tabulate by: subnet (default), class or prefix length e.g. /24",
+        );
+        if let Err(code) = arg_parser.parse(args, &mut stdout(), &mut stderr()) {
+            process::exit(code);
+        }
+    }
+    let files = datasets
+        .iter()
+        .map(|dataset| match dataset.split_once('=') {
+            Some((label, file_name)) => (label, file_name),
+            None => (dataset.as_str(), dataset.as_str()),
+        });
+    println!("{}", ipv4_classify::find_crosstab(files, &group_by)?);
+    Ok(())
+}
+
+/// allocate subcommand - free blocks of a pool none of the used addresses are in
+fn allocate(args: Vec<String>) -> Result<(), Box<dyn Error>> {
+    let mut pool = Subnet::root();
+    let mut mask_len = 0u8;
+    let mut count = 1usize;
+    let mut fit = Fit::First;
+    let mut file_names: Vec<PathBuf> = vec![];
+    let mut load = None::<PathBuf>;
+    {
+        let mut arg_parser = ArgumentParser::new();
+        arg_parser.set_description(
+            "Find free blocks of a pool for new subnets, none of the used addresses or subnets are in them",
+        );
+        arg_parser.refer(&mut pool).required().add_option(
+            &["-p", "--pool"],
+            Store,
+            "Subnet to allocate from e.g. 10.0.0.0/8",
+        );
+        arg_parser.refer(&mut mask_len).required().add_option(
+            &["-m", "--mask"],
+            Store,
+            "Mask length of the blocks e.g. 24",
+        );
+        arg_parser.refer(&mut count).add_option(
+            &["-n", "--count"],
+            Store,
+            "Number of blocks to allocate one after another (default 1)",
+        );
+        arg_parser.refer(&mut fit).add_option(
+            &["--fit"],
+            Store,
+            "first (default) - the lowest free block, best - the one of the smallest free subnet it fits into",
+        );
+        arg_parser.refer(&mut file_names).add_option(
+            &["-f", "--files"],
+            List,
+            "List of files with the used addresses and subnets e.g. -f servers.txt networks.txt",
+        );
+        arg_parser.refer(&mut load).add_option(
+            &["--load"],
+            StoreOption,
+            "Start from a tree of the used addresses saved with --save",
+        );
+        if let Err(code) = arg_parser.parse(args, &mut stdout(), &mut stderr()) {
+            process::exit(code);
+        }
+    }
+    let mut address_tree = match &load {
+        Some(file_name) => AddressTree::load(file_name)?,
+        None => AddressTree::new(),
+    };
+    ipv4_classify::read_into_tree(&mut address_tree, &file_names, false, &CancelToken::new())?;
+    for n in 0..count {
+        let Some(block) = address_tree.allocate(&pool, mask_len, fit) else {
+            return Err(format!(
+                "{} has no free /{} block left, {} of {} allocated",
+                pool, mask_len, n, count
+            )
+            .into());
+        };
+        println!("{}", block);
+        // the next block is allocated around this one
+        address_tree.push(block)?;
+    }
+    Ok(())
+}
+
+/// timeline subcommand - classify timestamped addresses per hour or day
+fn timeline(args: Vec<String>) -> Result<(), Box<dyn Error>> {
+    let mut file_names: Vec<PathBuf> = vec![];
+    let mut bucket = TimeBucket::Day;
+    let mut group_by = GroupBy::Subnet;
+    let mut top = None;
+    {
+        let mut arg_parser = ArgumentParser::new();
+        arg_parser.set_description(
+            "Sort out timestamped IPv4 addresses into subnets for every hour or day, \
+             lines look like \"2024-05-01T13:45:00Z 1.2.3.4\" or \"1714571100,1.2.3.4\"",
+        );
+        arg_parser.refer(&mut file_names).required().add_option(
+            &["-f", "--files"],
+            List,
+            "List of files with timestamped ipv4 addresses to read e.g. -f one.log another.log",
+        );
+        arg_parser.refer(&mut bucket).add_option(
+            &["-b", "--bucket"],
+            Store,
+            "Time span of a report: hour or day (default)",
+        );
+        arg_parser.refer(&mut group_by).add_option(
+            &["-g", "--group-by"],
+            Store,
+            "Top-level grouping of the reports: subnet (default), class or prefix length e.g. /8",
+        );
+        arg_parser.refer(&mut top).add_option(
+            &["-t", "--top"],
+            StoreOption,
+            "Report only N subnets with the most addresses in every time span",
+        );
+        if let Err(code) = arg_parser.parse(args, &mut stdout(), &mut stderr()) {
+            process::exit(code);
+        }
+    }
+    for (name, subnets) in ipv4_classify::find_timed_groups(&file_names, &bucket, &group_by)? {
+        println!("{}:", name);
+        ipv4_classify::print_subnets(&subnets, top);
+    }
+    Ok(())
+}
+
+/// read a list of addresses and subnets, errors mention the file
+fn read_set(file_name: &PathBuf) -> Result<SubnetSet, Box<dyn Error>> {
+    fs::read_to_string(file_name)
+        .map_err(ipv4_classify::Error::from)
+        .and_then(|content| SubnetSet::from_lines(content.split('\n')))
+        .map_err(|e| format!("{}: {}", file_name.display(), e).into())
+}
+
+/// read a prefix per line skipping empty lines and # comments, errors mention the file
+fn read_prefixes(file_name: &PathBuf) -> Result<Vec<Subnet>, Box<dyn Error>> {
+    let content =
+        fs::read_to_string(file_name).map_err(|e| format!("{}: {}", file_name.display(), e))?;
+    content
+        .split('\n')
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            line.parse::<Subnet>()
+                .map_err(|e| format!("{}: {}", file_name.display(), e).into())
+        })
+        .collect()
+}
+
+/// read limits of the enrichment providers, errors mention the file
+fn read_rate_limits(file_name: &PathBuf) -> Result<RateLimits, Box<dyn Error>> {
+    fs::read_to_string(file_name)
+        .map_err(ipv4_classify::Error::from)
+        .and_then(|content| RateLimits::from_lines(content.split('\n')))
+        .map_err(|e| format!("{}: {}", file_name.display(), e).into())
+}
@@ -0,0 +1,125 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Read, Write},
+    path::{Path, PathBuf},
+};
+
+use sha2::{Digest, Sha256};
+
+use crate::{ndjson::json_string, Error};
+
+/// a file a run read, e.g. an address list, labels or metadata
+#[derive(Debug, Clone, PartialEq)]
+pub struct InputFile {
+    pub path: PathBuf, // as it was given
+    pub size: u64,
+    pub sha256: String, // lowercase hex
+}
+
+impl InputFile {
+    /// # hash the file
+    /// # returns
+    /// Err - if the file can't be read
+    pub fn hash(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let mut file = File::open(path.as_ref())?;
+        let mut hasher = Sha256::new();
+        let mut buffer = vec![0; 64 * 1024];
+        let mut size = 0;
+        loop {
+            let len = file.read(&mut buffer)?;
+            if len == 0 {
+                break;
+            }
+            hasher.update(&buffer[..len]);
+            size += len as u64;
+        }
+        let sha256 = hasher
+            .finalize()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+        Ok(Self {
+            path: path.as_ref().to_path_buf(),
+            size,
+            sha256,
+        })
+    }
+}
+
+/// # what a run read, how it was asked to and what it found, to reproduce or audit the report later
+/// there is no timestamp, so runs over the same inputs with the same options have the same manifest
+#[derive(Debug, Clone, PartialEq)]
+pub struct Manifest {
+    pub version: String,      // of the crate
+    pub options: Vec<String>, // e.g. the command line arguments
+    pub inputs: Vec<InputFile>,
+    pub addresses: usize, // distinct addresses classified
+    pub subnets: usize,   // subnets reported
+    pub failures: usize,  // lines and files skipped by a lenient run
+}
+
+impl Manifest {
+    pub fn new(options: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        Self {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            options: options.into_iter().map(Into::into).collect(),
+            inputs: vec![],
+            addresses: 0,
+            subnets: 0,
+            failures: 0,
+        }
+    }
+
+    /// # hash the file and add it to the inputs
+    /// # returns
+    /// Err - if the file can't be read
+    pub fn add_input(&mut self, path: impl AsRef<Path>) -> Result<(), Error> {
+        self.inputs.push(InputFile::hash(path)?);
+        Ok(())
+    }
+
+    /// # write the manifest as a JSON document, e.g.
+    /// {"version":"0.1.0","options":["-f","feed.txt"],"inputs":[{"path":"feed.txt","size":27,"sha256":"9f86d0..."}],
+    /// "addresses":3,"subnets":1,"failures":0}
+    /// # returns
+    /// Err - if the document can't be written
+    pub fn write_json(&self, out: &mut impl Write) -> Result<(), Error> {
+        let options: Vec<String> = self
+            .options
+            .iter()
+            .map(|option| json_string(Some(option)))
+            .collect();
+        let inputs: Vec<String> = self
+            .inputs
+            .iter()
+            .map(|input| {
+                format!(
+                    "{{\"path\":{},\"size\":{},\"sha256\":\"{}\"}}",
+                    json_string(Some(&input.path.display().to_string())),
+                    input.size,
+                    input.sha256
+                )
+            })
+            .collect();
+        writeln!(
+            out,
+            "{{\"version\":{},\"options\":[{}],\"inputs\":[{}],\"addresses\":{},\"subnets\":{},\"failures\":{}}}",
+            json_string(Some(&self.version)),
+            options.join(","),
+            inputs.join(","),
+            self.addresses,
+            self.subnets,
+            self.failures
+        )?;
+        Ok(())
+    }
+
+    /// # write the manifest to the file, see write_json
+    /// # returns
+    /// Err - if the file can't be written
+    pub fn save(&self, file_name: impl AsRef<Path>) -> Result<(), Error> {
+        let mut out = BufWriter::new(File::create(file_name)?);
+        self.write_json(&mut out)?;
+        Ok(out.flush()?)
+    }
+}
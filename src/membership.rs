@@ -0,0 +1,78 @@
+use std::collections::HashSet;
+
+use crate::{io::parse_weighted_line, AddressTree, Error, GroupBy, Subnet};
+
+/// # addresses of several sources classified together, remembering which source has which
+/// overlap, compare and crosstab split the groups back by source with it
+#[derive(Default)]
+pub(crate) struct Membership {
+    address_tree: AddressTree,        // the union of the sources
+    pushed: HashSet<(u32, u8)>,       // addresses already in the union
+    members: Vec<HashSet<(u32, u8)>>, // addresses of every source in the order they're added
+}
+
+impl Membership {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// # add the addresses of a source, the same address of different sources is a single leaf
+    /// # returns
+    /// Err - if the union refuses some address
+    pub(crate) fn add<'a>(
+        &mut self,
+        addrs: impl IntoIterator<Item = &'a Subnet>,
+    ) -> Result<(), Error> {
+        let mut member = HashSet::new();
+        for addr in addrs {
+            let key = (addr.bits, addr.mask_len);
+            member.insert(key);
+            if self.pushed.insert(key) {
+                self.address_tree.push(addr.clone())?;
+            }
+        }
+        self.members.push(member);
+        Ok(())
+    }
+
+    /// # add the lines of a source, see parse_weighted_line, hits are dropped
+    /// empty lines are skipped, surrounding whitespaces are ignored
+    /// # returns
+    /// Err - if some line isn't a correct IP address
+    pub(crate) fn add_lines<L: AsRef<str>>(
+        &mut self,
+        lines: impl IntoIterator<Item = L>,
+    ) -> Result<(), Error> {
+        let mut addrs = vec![];
+        for line in lines {
+            let line = line.as_ref().trim();
+            if !line.is_empty() {
+                addrs.push(parse_weighted_line(line)?.0);
+            }
+        }
+        self.add(&addrs)
+    }
+
+    /// # split every group of the union back by source
+    /// # returns
+    /// groups with the number of distinct addresses of every source in it, in the sources' order
+    pub(crate) fn counts(&self, group_by: &GroupBy) -> Vec<(String, Vec<usize>)> {
+        self.address_tree
+            .groups(group_by, None)
+            .into_iter()
+            .map(|(group, addrs)| {
+                let counts = self
+                    .members
+                    .iter()
+                    .map(|member| {
+                        addrs
+                            .iter()
+                            .filter(|addr| member.contains(&(addr.bits, addr.mask_len)))
+                            .count()
+                    })
+                    .collect();
+                (group, counts)
+            })
+            .collect()
+    }
+}
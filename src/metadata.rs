@@ -0,0 +1,355 @@
+use std::{collections::HashMap, mem::take, str::FromStr};
+
+use crate::{summary::ORG, Error, Subnet};
+#[cfg(any(feature = "whois", feature = "ripestat"))]
+use crate::{Enrichment, Observer, Verdict};
+
+/// columns the fields of IpMetadata are read from, the first one present wins, the first one is written
+const ASN: [&str; 2] = ["asn", "as"];
+const AS_NAME: [&str; 3] = ["as_name", "asn.name", "as_org"];
+const ANNOUNCED_PREFIX: [&str; 4] = ["announced_prefix", "prefix", "route", "asn.route"];
+const COUNTRY: [&str; 2] = ["country", "country_code"];
+const REGION: [&str; 2] = ["region", "state"];
+const CITY: [&str; 1] = ["city"];
+const NETNAME: [&str; 1] = ["netname"];
+const HOSTNAME: [&str; 3] = ["hostname", "rdns", "ptr"];
+const ANYCAST: [&str; 2] = ["anycast", "is_anycast"];
+const VPN: [&str; 3] = ["vpn", "privacy.vpn", "is_vpn"];
+const PROXY: [&str; 3] = ["proxy", "privacy.proxy", "is_proxy"];
+const TOR: [&str; 3] = ["tor", "privacy.tor", "is_tor"];
+const RELAY: [&str; 3] = ["relay", "privacy.relay", "is_relay"];
+const HOSTING: [&str; 3] = ["hosting", "privacy.hosting", "is_hosting"];
+
+/// what an anonymizing or hosting network an address is of, e.g. the privacy object of ipinfo
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Privacy {
+    pub vpn: bool,
+    pub proxy: bool,
+    pub tor: bool,
+    pub relay: bool, // e.g. iCloud Private Relay
+    pub hosting: bool,
+}
+
+/// # what is known about an address, every enrichment provider is turned into this shape
+/// see Metadata::ip_metadata_of and Metadata::fill to read and write it as columns
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct IpMetadata {
+    pub asn: Option<u32>,
+    pub as_name: Option<String>,
+    pub announced_prefix: Option<Subnet>, // the BGP prefix the address is routed by
+    pub country: Option<String>,
+    pub region: Option<String>,
+    pub city: Option<String>,
+    pub org: Option<String>,
+    pub netname: Option<String>, // the registry's name of the network
+    pub hostname: Option<String>,
+    pub is_anycast: bool,
+    pub privacy: Privacy,
+}
+
+/// user-supplied context of single addresses read from a CSV, e.g. ip,username,device
+/// the first column is the address, the rest are joined onto the address in reports
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Metadata {
+    columns: Vec<String>, // names of the joined columns, the address column excluded
+    rows: HashMap<u32, Vec<String>>, // values of the columns by address
+}
+
+impl Metadata {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// # parse a CSV with a header, the first column of every row is an IP address
+    /// fields may be double-quoted to hold commas, "" inside quotes is a quote,
+    /// empty lines and lines starting with # are skipped, missing trailing fields are empty
+    /// # returns
+    /// Err - if some address can't be parsed, isn't a single address or a row has too many fields
+    pub fn from_lines(lines: impl IntoIterator<Item = impl AsRef<str>>) -> Result<Self, Error> {
+        let mut metadata = Self::new();
+        let mut has_header = false;
+        for line in lines {
+            let line = line.as_ref().trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut fields = split_fields(line);
+            if !has_header {
+                fields.remove(0);
+                metadata.columns = fields;
+                has_header = true;
+                continue;
+            }
+            if fields.len() > metadata.columns.len() + 1 {
+                return Err(Error::ParseError {
+                    input: line.to_string(),
+                    reason: format!("expected at most {} fields", metadata.columns.len() + 1),
+                });
+            }
+            let addr = Subnet::from_str(&fields.remove(0))?;
+            if addr.mask_len != 32 {
+                return Err(Error::ParseError {
+                    input: line.to_string(),
+                    reason: "the first field should be a single address".to_string(),
+                });
+            }
+            fields.resize(metadata.columns.len(), String::new());
+            metadata.rows.insert(addr.bits, fields);
+        }
+        Ok(metadata)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    /// names of the joined columns in the CSV's order
+    pub fn columns(&self) -> &[String] {
+        &self.columns
+    }
+
+    /// values of the columns for the address, None for subnets and unknown addresses
+    pub fn row_of(&self, addr: &Subnet) -> Option<&[String]> {
+        if addr.mask_len != 32 {
+            return None;
+        }
+        self.rows.get(&addr.bits).map(Vec::as_slice)
+    }
+
+    /// # value of the column for the address, column names are case-insensitive
+    /// # returns
+    /// None - if there is no such column, row or the value is empty
+    pub fn value_of(&self, addr: &Subnet, column: &str) -> Option<&str> {
+        let index = self
+            .columns
+            .iter()
+            .position(|name| name.eq_ignore_ascii_case(column))?;
+        let value = self.row_of(addr)?.get(index)?;
+        (!value.is_empty()).then_some(value.as_str())
+    }
+
+    /// addresses with a non-empty value of the column, column names are case-insensitive
+    pub fn values_of<'a>(&'a self, column: &str) -> impl Iterator<Item = (Subnet, &'a str)> {
+        let index = self
+            .columns
+            .iter()
+            .position(|name| name.eq_ignore_ascii_case(column));
+        self.rows.iter().filter_map(move |(bits, row)| {
+            let value = row.get(index?)?;
+            let addr = Subnet::from_parts(bits.to_be_bytes(), 32);
+            (!value.is_empty()).then_some((addr, value.as_str()))
+        })
+    }
+
+    /// set the column of the address, the column and the row are added if there are none
+    /// subnets are ignored, as the metadata is of single addresses
+    pub fn set(&mut self, addr: &Subnet, column: &str, value: impl Into<String>) {
+        if addr.mask_len != 32 {
+            return;
+        }
+        let index = match self
+            .columns
+            .iter()
+            .position(|name| name.eq_ignore_ascii_case(column))
+        {
+            Some(index) => index,
+            None => {
+                self.columns.push(column.to_string());
+                self.rows
+                    .values_mut()
+                    .for_each(|row| row.push(String::new()));
+                self.columns.len() - 1
+            }
+        };
+        let row = self
+            .rows
+            .entry(addr.bits)
+            .or_insert_with(|| vec![String::new(); self.columns.len()]);
+        row[index] = value.into();
+    }
+
+    /// # the known columns of the address, e.g. asn of AS64500 or 64500, see IpMetadata
+    /// # returns
+    /// None - for subnets and unknown addresses
+    pub fn ip_metadata_of(&self, addr: &Subnet) -> Option<IpMetadata> {
+        self.row_of(addr)?;
+        let text = |columns: &[&str]| {
+            columns
+                .iter()
+                .find_map(|column| self.value_of(addr, column))
+                .map(String::from)
+        };
+        let flag = |columns: &[&str]| {
+            columns
+                .iter()
+                .any(|column| self.value_of(addr, column).is_some_and(is_truthy))
+        };
+        Some(IpMetadata {
+            asn: text(&ASN).and_then(|asn| parse_asn(&asn)),
+            as_name: text(&AS_NAME),
+            announced_prefix: text(&ANNOUNCED_PREFIX)
+                .and_then(|prefix| Subnet::from_str(&prefix).ok()),
+            country: text(&COUNTRY),
+            region: text(&REGION),
+            city: text(&CITY),
+            org: text(&ORG),
+            netname: text(&NETNAME),
+            hostname: text(&HOSTNAME),
+            is_anycast: flag(&ANYCAST),
+            privacy: Privacy {
+                vpn: flag(&VPN),
+                proxy: flag(&PROXY),
+                tor: flag(&TOR),
+                relay: flag(&RELAY),
+                hosting: flag(&HOSTING),
+            },
+        })
+    }
+
+    /// # set the fields the address has no value of yet, so the first provider wins
+    /// flags which are false are left out, as most providers don't tell false from unknown
+    pub fn fill(&mut self, addr: &Subnet, ip: &IpMetadata) {
+        let mut fill = |columns: &[&str], value: Option<String>| {
+            let known = columns
+                .iter()
+                .any(|column| self.value_of(addr, column).is_some());
+            if let (Some(value), false) = (value, known) {
+                self.set(addr, columns[0], value);
+            }
+        };
+        let flag = |value: bool| value.then(|| "true".to_string());
+        fill(&ASN, ip.asn.map(|asn| asn.to_string()));
+        fill(&AS_NAME, ip.as_name.clone());
+        fill(
+            &ANNOUNCED_PREFIX,
+            ip.announced_prefix.as_ref().map(Subnet::to_string),
+        );
+        fill(&COUNTRY, ip.country.clone());
+        fill(&REGION, ip.region.clone());
+        fill(&CITY, ip.city.clone());
+        fill(&ORG, ip.org.clone());
+        fill(&NETNAME, ip.netname.clone());
+        fill(&HOSTNAME, ip.hostname.clone());
+        fill(&ANYCAST, flag(ip.is_anycast));
+        fill(&VPN, flag(ip.privacy.vpn));
+        fill(&PROXY, flag(ip.privacy.proxy));
+        fill(&TOR, flag(ip.privacy.tor));
+        fill(&RELAY, flag(ip.privacy.relay));
+        fill(&HOSTING, flag(ip.privacy.hosting));
+    }
+
+    /// # human-readable non-empty columns of the address e.g. username=alice, device=laptop
+    /// # returns
+    /// None - if there is nothing to join
+    pub fn describe(&self, addr: &Subnet) -> Option<String> {
+        let pairs: Vec<String> = self
+            .columns
+            .iter()
+            .zip(self.row_of(addr)?)
+            .filter(|(_, value)| !value.is_empty())
+            .map(|(column, value)| format!("{}={}", column, value))
+            .collect();
+        (!pairs.is_empty()).then(|| pairs.join(", "))
+    }
+}
+
+/// # fill what the lookup finds in for the addresses the metadata lacks something of, telling the observer every decision
+/// lookup tells whether the provider's cache answered and what was found,
+/// once the daily quota is used up, the rest of the addresses are left as they are
+/// # returns
+/// Err - the first failure of the lookup
+#[cfg(any(feature = "whois", feature = "ripestat"))]
+pub(crate) fn enrich_with<'a>(
+    provider: &str,
+    metadata: &mut Metadata,
+    addrs: impl IntoIterator<Item = &'a Subnet>,
+    observer: &mut impl Observer,
+    is_complete: impl Fn(&IpMetadata) -> bool,
+    mut lookup: impl FnMut(&Subnet) -> (bool, Result<Option<IpMetadata>, Error>),
+) -> Result<(), Error> {
+    for addr in addrs {
+        if addr.mask_len != 32 {
+            continue;
+        }
+        let event = |cached, verdict| Enrichment {
+            provider,
+            addr,
+            cached,
+            verdict,
+        };
+        if metadata
+            .ip_metadata_of(addr)
+            .is_some_and(|known| is_complete(&known))
+        {
+            observer.on_enrichment(&event(false, Verdict::Known));
+            continue;
+        }
+        let (cached, found) = lookup(addr);
+        match found {
+            Ok(Some(ip)) => {
+                metadata.fill(addr, &ip);
+                observer.on_enrichment(&event(cached, Verdict::Found));
+            }
+            Ok(None) => observer.on_enrichment(&event(cached, Verdict::NotFound)),
+            Err(Error::QuotaExceeded(_)) => {
+                observer.on_enrichment(&event(cached, Verdict::QuotaExceeded));
+                break;
+            }
+            Err(e) => {
+                observer.on_enrichment(&event(cached, Verdict::Failed(e.to_string())));
+                return Err(e);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// true, 1 or yes in any case
+pub(crate) fn is_truthy(value: &str) -> bool {
+    ["true", "1", "yes"]
+        .iter()
+        .any(|truthy| value.eq_ignore_ascii_case(truthy))
+}
+
+/// AS number of e.g. 64500, AS64500 or "AS64500 Example Org" as ipinfo's org has it
+fn parse_asn(value: &str) -> Option<u32> {
+    let asn = value.split_whitespace().next()?;
+    let asn = match asn.get(..2) {
+        Some(prefix) if prefix.eq_ignore_ascii_case("as") => &asn[2..],
+        _ => asn,
+    };
+    asn.parse().ok()
+}
+
+/// split a CSV line into trimmed fields, unquoting them
+fn split_fields(line: &str) -> Vec<String> {
+    let mut fields = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(take(&mut field).trim().to_string()),
+            _ => field.push(c),
+        }
+    }
+    fields.push(field.trim().to_string());
+    fields
+}
+
+/// the value seen the most, the first seen of equally common ones
+pub(crate) fn most_common<'a>(values: impl Iterator<Item = &'a str>) -> Option<&'a str> {
+    let mut counts: HashMap<&str, (usize, usize)> = HashMap::new(); // value -> (count, first seen)
+    for (i, value) in values.enumerate() {
+        counts.entry(value).or_insert((0, i)).0 += 1;
+    }
+    counts
+        .into_iter()
+        .max_by_key(|&(_, (count, first))| (count, std::cmp::Reverse(first)))
+        .map(|(value, _)| value)
+}
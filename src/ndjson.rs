@@ -0,0 +1,165 @@
+use std::{
+    io::{self, Write},
+    path::Path,
+};
+
+use crate::{AddressTree, Enrichment, Error, Observer, Report, Subnet, Verdict};
+
+/// streams a JSON line per address as soon as it's classified, e.g.
+/// {"address":"10.0.0.1/32","class":"private","subnet":"10.0.0.0/30","source":"feed.txt"}
+/// the subnet is the one the address falls into at the moment, later addresses may widen it
+pub struct NdjsonSink<W: Write> {
+    out: W,
+    source: Option<String>,   // the file being read
+    error: Option<io::Error>, // the first failed write, nothing is written after it
+}
+
+impl<W: Write> NdjsonSink<W> {
+    pub fn new(out: W) -> Self {
+        Self {
+            out,
+            source: None,
+            error: None,
+        }
+    }
+
+    /// # flush the lines written so far
+    /// # returns
+    /// Err - if some line couldn't be written, e.g. the reading side of a pipe is gone
+    pub fn finish(mut self) -> Result<(), Error> {
+        match self.error.take() {
+            Some(e) => Err(e.into()),
+            None => Ok(self.out.flush()?),
+        }
+    }
+}
+
+impl<W: Write> Observer for NdjsonSink<W> {
+    fn on_file_started(&mut self, file_name: &Path) {
+        self.source = Some(file_name.display().to_string());
+    }
+
+    fn on_address_classified(&mut self, addr: &Subnet, address_tree: &AddressTree) {
+        if self.error.is_some() {
+            return;
+        }
+        let subnet = address_tree.subnet_of(addr).map(Subnet::to_string);
+        if let Err(e) = writeln!(
+            self.out,
+            "{{\"address\":\"{}\",\"class\":{},\"subnet\":{},\"source\":{}}}",
+            addr,
+            json_string(addr.address_class().map(|class| class.name())),
+            json_string(subnet.as_deref()),
+            json_string(self.source.as_deref())
+        ) {
+            self.error = Some(e);
+        }
+    }
+}
+
+/// writes a JSON line per enrichment decision to audit the run afterwards, e.g.
+/// {"provider":"whois","address":"193.0.0.1/32","cached":false,"verdict":"found","error":null}
+pub struct JsonLog<W: Write> {
+    out: W,
+    error: Option<io::Error>, // the first failed write, nothing is written after it
+}
+
+impl<W: Write> JsonLog<W> {
+    pub fn new(out: W) -> Self {
+        Self { out, error: None }
+    }
+
+    /// # flush the lines written so far
+    /// # returns
+    /// Err - if some line couldn't be written
+    pub fn finish(mut self) -> Result<(), Error> {
+        match self.error.take() {
+            Some(e) => Err(e.into()),
+            None => Ok(self.out.flush()?),
+        }
+    }
+}
+
+impl<W: Write> Observer for JsonLog<W> {
+    fn on_enrichment(&mut self, event: &Enrichment) {
+        if self.error.is_some() {
+            return;
+        }
+        let error = match &event.verdict {
+            Verdict::Failed(reason) => Some(reason.as_str()),
+            _ => None,
+        };
+        if let Err(e) = writeln!(
+            self.out,
+            "{{\"provider\":{},\"address\":\"{}\",\"cached\":{},\"verdict\":\"{}\",\"error\":{}}}",
+            json_string(Some(event.provider)),
+            event.addr,
+            event.cached,
+            event.verdict,
+            json_string(error)
+        ) {
+            self.error = Some(e);
+        }
+    }
+}
+
+/// # write a JSON line per subnet of the report in the report's order, e.g.
+/// {"subnet":"10.0.0.0/30","label":"corp","kind":"hosting","class":"private","count":2,"hits":null,"score":null,"sources":["feed.txt"],"addresses":["10.0.0.1/32","10.0.0.2/32"]}
+/// sources are null unless the report has them, kind is null unless the metadata tells it, see NetworkKind,
+/// class is null if the addresses are of different classes, see AddressClass,
+/// score is null unless the report is scored, see Scoring
+/// # returns
+/// Err - if some line can't be written
+pub fn write_ndjson_subnets(report: &Report, out: &mut impl Write) -> Result<(), Error> {
+    for row in report.rows() {
+        let addresses: Vec<String> = row
+            .addresses
+            .iter()
+            .map(|address| json_string(Some(address)))
+            .collect();
+        let sources = row.sources.as_ref().map_or("null".to_string(), |sources| {
+            let sources: Vec<String> = sources
+                .iter()
+                .map(|source| json_string(Some(source)))
+                .collect();
+            format!("[{}]", sources.join(","))
+        });
+        writeln!(
+            out,
+            "{{\"subnet\":{},\"label\":{},\"kind\":{},\"class\":{},\"count\":{},\"hits\":{},\"score\":{},\"sources\":{},\"addresses\":[{}]}}",
+            json_string(Some(row.name)),
+            json_string(row.label),
+            json_string(row.kind.map(|kind| kind.to_string()).as_deref()),
+            json_string(row.class.map(|class| class.name())),
+            row.addresses.len(),
+            row.hits.map_or("null".to_string(), |hits| hits.to_string()),
+            row.score.map_or("null".to_string(), |score| score.to_string()),
+            sources,
+            addresses.join(",")
+        )?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+/// quoted and escaped JSON string, null for None
+pub(crate) fn json_string(value: Option<&str>) -> String {
+    let Some(value) = value else {
+        return "null".to_string();
+    };
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
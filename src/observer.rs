@@ -0,0 +1,250 @@
+use std::{
+    fmt::{Display, Formatter},
+    path::Path,
+};
+
+use crate::{AddressTree, Subnet};
+
+/// hooks called while classifying, e.g. to drive a progress bar or collect metrics
+/// all methods do nothing by default, so implement only those of interest
+/// to stop early, keep a clone of the CancelToken and cancel it from a hook
+pub trait Observer {
+    fn on_file_started(&mut self, _file_name: &Path) {}
+
+    fn on_address_parsed(&mut self, _addr: &Subnet) {}
+
+    /// the address found its place in the tree, e.g. to look up the subnet it falls into by now
+    fn on_address_classified(&mut self, _addr: &Subnet, _address_tree: &AddressTree) {}
+
+    /// an intermediate subnet joining two or more addresses appeared in the tree
+    fn on_subnet_created(&mut self, _subnet: &Subnet) {}
+
+    /// the tree outgrew its memory limit, subtrees down to the mask length were collapsed, see AddressTree::limit_memory
+    fn on_tree_coarsened(&mut self, _mask_len: u8) {}
+
+    /// addresses - number of addresses taken from the file
+    fn on_file_done(&mut self, _file_name: &Path, _addresses: usize) {}
+
+    /// an enrichment provider decided about an address, e.g. to audit a long whois run, see JsonLog
+    fn on_enrichment(&mut self, _event: &Enrichment) {}
+
+    /// the tree decided about a pushed subnet, e.g. to tell why a grouping came out as it did, see Explainer
+    fn on_decision(&mut self, _decision: &Decision) {}
+}
+
+/// what an enrichment provider did about an address
+#[derive(Debug, Clone, PartialEq)]
+pub struct Enrichment<'a> {
+    pub provider: &'a str, // e.g. whois or ripestat
+    pub addr: &'a Subnet,
+    pub cached: bool, // the provider's cache answered, nothing was sent
+    pub verdict: Verdict,
+}
+
+/// the outcome of an enrichment lookup
+#[derive(Debug, Clone, PartialEq)]
+pub enum Verdict {
+    Known, // the metadata has it all, the provider wasn't asked
+    Found,
+    NotFound,
+    QuotaExceeded, // the rest of the addresses won't be looked up, see RateLimit
+    Failed(String),
+}
+
+impl Display for Verdict {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+        f.write_str(match self {
+            Verdict::Known => "known",
+            Verdict::Found => "found",
+            Verdict::NotFound => "not-found",
+            Verdict::QuotaExceeded => "quota-exceeded",
+            Verdict::Failed(_) => "failed",
+        })
+    }
+}
+
+/// why the tree put a pushed subnet (or address) where it did, in the order the tree decides,
+/// a subnet is offered to the children of a node one by one until one of them takes it
+#[derive(Debug, Clone, PartialEq)]
+pub enum Decision<'a> {
+    /// the tree has the subnet already, or a coarse leaf containing it, only the hits grew
+    Counted {
+        new_subnet: &'a Subnet,
+        leaf: &'a Subnet,
+    },
+    /// the subnet and the neighbour were joined into their common subnet, the neighbour stepped down into it
+    Merged {
+        new_subnet: &'a Subnet,
+        neighbour: &'a Subnet,
+        common: &'a Subnet,
+    },
+    /// the subnet and the neighbour share fewer than min_mask leading bits,
+    /// their common subnet would be the parent itself or wider
+    Diverged {
+        new_subnet: &'a Subnet,
+        neighbour: &'a Subnet,
+        min_mask: u8,
+    },
+    /// the common subnet would span across the boundary, see AddressTree::with_boundaries
+    Bounded {
+        new_subnet: &'a Subnet,
+        neighbour: &'a Subnet,
+        common: &'a Subnet,
+        boundary: &'a Subnet,
+    },
+    /// the merge policy refused the common subnet, see MergePolicy
+    Refused {
+        new_subnet: &'a Subnet,
+        neighbour: &'a Subnet,
+        common: &'a Subnet,
+    },
+    /// none of the parent's children took the subnet, so it became a child of its own
+    Adopted {
+        new_subnet: &'a Subnet,
+        parent: &'a Subnet,
+    },
+}
+
+impl Decision<'_> {
+    /// the pushed subnet the decision is about
+    pub fn new_subnet(&self) -> &Subnet {
+        match self {
+            Decision::Counted { new_subnet, .. }
+            | Decision::Merged { new_subnet, .. }
+            | Decision::Diverged { new_subnet, .. }
+            | Decision::Bounded { new_subnet, .. }
+            | Decision::Refused { new_subnet, .. }
+            | Decision::Adopted { new_subnet, .. } => new_subnet,
+        }
+    }
+}
+
+impl Display for Decision<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+        match self {
+            Decision::Counted { new_subnet, leaf } if new_subnet == leaf => {
+                write!(f, "{} is in the tree already, its hits grew", new_subnet)
+            }
+            Decision::Counted { new_subnet, leaf } => write!(
+                f,
+                "{} is within the collapsed {}, its hits grew",
+                new_subnet, leaf
+            ),
+            Decision::Merged {
+                new_subnet,
+                neighbour,
+                common,
+            } => write!(
+                f,
+                "{} joined {} into {}, their longest common prefix",
+                new_subnet, neighbour, common
+            ),
+            Decision::Diverged {
+                new_subnet,
+                neighbour,
+                min_mask,
+            } => write!(
+                f,
+                "{} stays apart from {}, they share fewer than {} leading bits",
+                new_subnet, neighbour, min_mask
+            ),
+            Decision::Bounded {
+                new_subnet,
+                neighbour,
+                common,
+                boundary,
+            } => write!(
+                f,
+                "{} stays apart from {}, {} would span across the boundary {}",
+                new_subnet, neighbour, common, boundary
+            ),
+            Decision::Refused {
+                new_subnet,
+                neighbour,
+                common,
+            } => write!(
+                f,
+                "{} stays apart from {}, the merge policy refused {}",
+                new_subnet, neighbour, common
+            ),
+            Decision::Adopted { new_subnet, parent } => {
+                write!(f, "{} became a child of {}", new_subnet, parent)
+            }
+        }
+    }
+}
+
+/// doesn't observe anything
+impl Observer for () {}
+
+/// both observers see every event, the first one first, e.g. a logger along with a collector
+impl<A: Observer, B: Observer> Observer for (A, B) {
+    fn on_file_started(&mut self, file_name: &Path) {
+        self.0.on_file_started(file_name);
+        self.1.on_file_started(file_name);
+    }
+
+    fn on_address_parsed(&mut self, addr: &Subnet) {
+        self.0.on_address_parsed(addr);
+        self.1.on_address_parsed(addr);
+    }
+
+    fn on_address_classified(&mut self, addr: &Subnet, address_tree: &AddressTree) {
+        self.0.on_address_classified(addr, address_tree);
+        self.1.on_address_classified(addr, address_tree);
+    }
+
+    fn on_subnet_created(&mut self, subnet: &Subnet) {
+        self.0.on_subnet_created(subnet);
+        self.1.on_subnet_created(subnet);
+    }
+
+    fn on_tree_coarsened(&mut self, mask_len: u8) {
+        self.0.on_tree_coarsened(mask_len);
+        self.1.on_tree_coarsened(mask_len);
+    }
+
+    fn on_file_done(&mut self, file_name: &Path, addresses: usize) {
+        self.0.on_file_done(file_name, addresses);
+        self.1.on_file_done(file_name, addresses);
+    }
+
+    fn on_enrichment(&mut self, event: &Enrichment) {
+        self.0.on_enrichment(event);
+        self.1.on_enrichment(event);
+    }
+
+    fn on_decision(&mut self, decision: &Decision) {
+        self.0.on_decision(decision);
+        self.1.on_decision(decision);
+    }
+}
+
+/// reports progress to stderr, that's what the crate does when no observer is supplied
+#[derive(Debug, Default)]
+pub struct StderrLogger;
+
+impl Observer for StderrLogger {
+    fn on_file_started(&mut self, file_name: &Path) {
+        eprintln!("loading file {}", file_name.display());
+    }
+
+    fn on_subnet_created(&mut self, subnet: &Subnet) {
+        eprintln!("subnet {} created", subnet);
+    }
+
+    fn on_tree_coarsened(&mut self, mask_len: u8) {
+        eprintln!(
+            "memory limit reached, subnets of /{} and longer were collapsed",
+            mask_len
+        );
+    }
+
+    fn on_file_done(&mut self, file_name: &Path, addresses: usize) {
+        eprintln!(
+            "there are {} addresses in {}",
+            addresses,
+            file_name.display()
+        );
+    }
+}
@@ -0,0 +1,227 @@
+#[cfg(feature = "fs")]
+use std::path::Path;
+use std::{cmp::Ordering, collections::BinaryHeap};
+
+#[cfg(feature = "fs")]
+use crate::{read_tree, Error};
+use crate::{tree::ROOT, AddressTree, Subnet, SubnetSet};
+
+/// same as AddressTree::block_rules for addresses read from the files
+#[cfg(feature = "fs")]
+pub fn find_block_rules(
+    file_names: impl IntoIterator<Item = impl AsRef<Path>>,
+    target: &Target,
+) -> Result<Plan, Error> {
+    Ok(read_tree(file_names)?.block_rules(target))
+}
+
+/// what a block list should achieve, see AddressTree::block_rules
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Target {
+    pub max_rules: Option<usize>,  // no more rules than that
+    pub min_coverage: Option<f64>, // share of observed addresses to cover e.g. 0.95, fewer rules are preferred
+    pub max_collateral: u64,       // unobserved addresses allowed to be blocked along the way
+    pub whitelist: SubnetSet,      // ranges which must never be blocked
+}
+
+/// block list selected by the optimizer
+#[derive(Debug, PartialEq)]
+pub struct Plan {
+    pub rules: Vec<Subnet>, // in address order
+    pub covered: u64,       // observed addresses blocked by the rules
+    pub observed: u64,      // all observed addresses
+    pub collateral: u64,    // unobserved addresses blocked by the rules
+}
+
+impl Plan {
+    /// share of observed addresses blocked by the rules
+    pub fn coverage(&self) -> f64 {
+        if self.observed == 0 {
+            return 1.0;
+        }
+        self.covered as f64 / self.observed as f64
+    }
+}
+
+/// merging the rules below node into a single rule costs that much collateral
+#[derive(Debug, PartialEq, Eq)]
+struct Candidate {
+    cost: u64,
+    saved: u64, // rules less after the merge
+    node: usize,
+}
+
+impl Ord for Candidate {
+    /// the cheapest collateral per rule saved is the greatest, so BinaryHeap pops it first
+    fn cmp(&self, other: &Self) -> Ordering {
+        (other.cost as u128 * self.saved as u128)
+            .cmp(&(self.cost as u128 * other.saved as u128))
+            .then(other.node.cmp(&self.node))
+    }
+}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl AddressTree {
+    /// # select subnets of the tree to block, answering "what should I put in my firewall?"
+    /// starts from a rule per leaf, then greedily merges rules into their common subnets,
+    /// the least collateral per rule saved first, as long as the collateral bound allows,
+    /// then drops the rules covering the fewest addresses to meet max_rules or to stay just above min_coverage
+    /// # returns
+    /// the best plan found, check its coverage as min_coverage may be out of reach within max_rules
+    pub fn block_rules(&self, target: &Target) -> Plan {
+        let count = self.node_count();
+        let mut parent = vec![None; count];
+        let mut order = vec![ROOT]; // parents go before their children
+        let mut i = 0;
+        while i < order.len() {
+            for ch in self.children_at(order[i]) {
+                parent[ch] = Some(order[i]);
+                order.push(ch);
+            }
+            i += 1;
+        }
+
+        let is_leaf = |at: usize| at != ROOT && self.children_at(at).is_empty();
+        let mut observed = vec![0u64; count]; // addresses of the leafs below, nested leafs count once
+        let mut leafs = vec![SubnetSet::new(); count]; // of the node, moved to the parent once it's counted
+        let mut rules = vec![0u64; count]; // selected rules below, inclusive
+        let mut spent = vec![0u64; count]; // collateral of the selected rules below
+        let mut selected = vec![false; count];
+        for &at in order.iter().rev() {
+            if is_leaf(at) {
+                leafs[at] = [self.subnet_at(at).clone()].into_iter().collect();
+                // whitelisted addresses stay observed, but uncovered
+                if !target.whitelist.overlaps(self.subnet_at(at)) {
+                    rules[at] = 1;
+                    selected[at] = true;
+                }
+            }
+            observed[at] = leafs[at].size();
+            if let Some(p) = parent[at] {
+                let below = core::mem::take(&mut leafs[at]);
+                leafs[p] = leafs[p].union(&below);
+                rules[p] += rules[at];
+            }
+        }
+
+        let candidate = |at: usize, rules: &[u64], spent: &[u64]| Candidate {
+            cost: self
+                .subnet_at(at)
+                .size()
+                .saturating_sub(observed[at] + spent[at]),
+            saved: rules[at].saturating_sub(1),
+            node: at,
+        };
+        let mut heap: BinaryHeap<Candidate> = order
+            .iter()
+            .filter(|&&at| !is_leaf(at) && !target.whitelist.overlaps(self.subnet_at(at)))
+            .map(|&at| candidate(at, &rules, &spent))
+            .collect();
+        let mut total_rules = rules[ROOT];
+        let mut collateral = 0;
+        while let Some(c) = heap.pop() {
+            if target
+                .max_rules
+                .is_some_and(|max| total_rules <= max as u64)
+                && target.min_coverage.is_none()
+            {
+                break;
+            }
+            let current = candidate(c.node, &rules, &spent);
+            if current != c {
+                // something below was merged meanwhile
+                heap.push(current);
+                continue;
+            }
+            if c.saved == 0 || collateral + c.cost > target.max_collateral {
+                continue;
+            }
+            let mut ancestors = vec![];
+            let mut up = parent[c.node];
+            while let Some(a) = up {
+                ancestors.push(a);
+                up = parent[a];
+            }
+            if ancestors.iter().any(|&a| selected[a]) {
+                continue;
+            }
+            selected[c.node] = true;
+            collateral += c.cost;
+            total_rules -= c.saved;
+            rules[c.node] = 1;
+            spent[c.node] += c.cost;
+            for a in ancestors {
+                rules[a] -= c.saved;
+                spent[a] += c.cost;
+            }
+        }
+
+        // the topmost selected nodes are the rules
+        let mut picked = vec![];
+        let mut stack = vec![ROOT];
+        while let Some(at) = stack.pop() {
+            if selected[at] {
+                picked.push(at);
+            } else {
+                stack.extend(self.children_at(at));
+            }
+        }
+        // the smallest first, ties are broken by address to keep the plan stable
+        picked.sort_by_key(|&at| (observed[at], self.subnet_at(at).bits));
+        let mut picked = &picked[..];
+        let total = observed[ROOT];
+        let mut covered: u64 = picked.iter().map(|&at| observed[at]).sum();
+        while let Some((&smallest, rest)) = picked.split_first() {
+            let too_many = target.max_rules.is_some_and(|max| picked.len() > max);
+            let enough = target.min_coverage.is_some_and(|min| {
+                total > 0 && (covered - observed[smallest]) as f64 / total as f64 >= min
+            });
+            if !too_many && !enough {
+                break;
+            }
+            covered -= observed[smallest];
+            picked = rest;
+        }
+
+        let mut picked: Vec<Subnet> = picked
+            .iter()
+            .map(|&at| self.subnet_at(at).clone())
+            .collect();
+        picked.sort_by_key(|s| (s.bits, s.mask_len));
+        // a leaf nested in another leaf may be picked along with it
+        let mut rules: Vec<Subnet> = Vec::with_capacity(picked.len());
+        for subnet in picked {
+            if !rules.last().is_some_and(|last| last.contains(&subnet)) {
+                rules.push(subnet);
+            }
+        }
+        let blocked: SubnetSet = rules.iter().cloned().collect();
+        let covered = blocked.intersect(&leafs[ROOT]).size();
+        Plan {
+            collateral: blocked.size() - covered,
+            rules,
+            covered,
+            observed: total,
+        }
+    }
+}
+
+impl SubnetSet {
+    /// # aggregate the set into at most max_entries subnets covering all of it, e.g. for WAF IP sets
+    /// or router tables capping the number of entries, subnets are merged the least extra addresses first
+    /// # returns
+    /// the plan with the subnets as rules, its collateral is the overshoot, i.e. the addresses covered beyond the set
+    pub fn compact(&self, max_entries: usize) -> Plan {
+        let tree: AddressTree = self.subnets().into_iter().collect();
+        tree.block_rules(&Target {
+            max_rules: Some(max_entries),
+            max_collateral: u64::MAX,
+            ..Target::default()
+        })
+    }
+}
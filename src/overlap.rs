@@ -0,0 +1,91 @@
+use std::fmt::{Display, Formatter};
+#[cfg(feature = "fs")]
+use std::path::Path;
+
+#[cfg(feature = "fs")]
+use crate::content::FileContent;
+use crate::{membership::Membership, Error, GroupBy};
+
+/// a group with addresses from more than one source
+#[derive(Debug, PartialEq)]
+pub struct Overlap {
+    pub group: String,
+    pub counts: Vec<(String, usize)>, // source label and its number of distinct addresses in the group
+}
+
+impl Display for Overlap {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+        write!(f, "{} subnet", self.group)?;
+        for (label, count) in &self.counts {
+            write!(f, "\n\t{}: {}", label, count)?;
+        }
+        Ok(())
+    }
+}
+
+/// # find groups shared by several labeled lists of addresses
+/// addresses of all the sources are classified together, then every group is split back by source
+/// # returns
+/// groups present in at least two sources, the most shared and the biggest first
+/// Err - if there are less than two sources or some line isn't a correct IP address, see parse_weighted_line
+pub fn overlap_lines<L: AsRef<str>>(
+    sources: impl IntoIterator<Item = (impl Into<String>, impl IntoIterator<Item = L>)>,
+    group_by: &GroupBy,
+) -> Result<Vec<Overlap>, Error> {
+    let mut labels: Vec<String> = vec![];
+    let mut membership = Membership::new();
+    for (label, lines) in sources {
+        labels.push(label.into());
+        membership.add_lines(lines)?;
+    }
+    if labels.len() < 2 {
+        return Err(Error::InvalidArgument(
+            "at least two sources are needed to find overlaps".into(),
+        ));
+    }
+
+    let mut overlaps: Vec<Overlap> = membership
+        .counts(group_by)
+        .into_iter()
+        .filter_map(|(group, counts)| {
+            let counts: Vec<(String, usize)> = labels
+                .iter()
+                .cloned()
+                .zip(counts)
+                .filter(|(_, count)| *count > 0)
+                .collect();
+            (counts.len() > 1).then_some(Overlap { group, counts })
+        })
+        .collect();
+    overlaps.sort_by(|o1, o2| {
+        let total = |o: &Overlap| o.counts.iter().map(|(_, count)| count).sum::<usize>();
+        o2.counts
+            .len()
+            .cmp(&o1.counts.len())
+            .then(total(o2).cmp(&total(o1)))
+            .then(o1.group.cmp(&o2.group))
+    });
+    Ok(overlaps)
+}
+
+/// same as overlap_lines, but every source is a file labeled by its name
+#[cfg(feature = "fs")]
+pub fn find_overlaps(
+    file_names: impl IntoIterator<Item = impl AsRef<Path>>,
+    group_by: &GroupBy,
+) -> Result<Vec<Overlap>, Error> {
+    let mut contents = vec![];
+    for file_name in file_names {
+        let file_name = file_name.as_ref();
+        contents.push((
+            file_name.display().to_string(),
+            FileContent::read(file_name)?,
+        ));
+    }
+    overlap_lines(
+        contents
+            .iter()
+            .map(|(label, content)| (label.as_str(), content.split('\n'))),
+        group_by,
+    )
+}
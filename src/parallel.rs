@@ -0,0 +1,46 @@
+use std::path::{Path, PathBuf};
+
+use rayon::prelude::*;
+
+use crate::{
+    content::FileContent, io::LineReading, AddressTree, CancelToken, Error, GroupBy, Pipeline,
+    StderrLogger, SubnetsMap,
+};
+
+/// # same as find_groups, but every file is parsed into its own tree on a rayon worker
+/// partial trees are merged at the end, so it pays off for several big files
+pub fn find_groups_parallel(
+    file_names: impl IntoIterator<Item = impl AsRef<Path>>,
+    group_by: &GroupBy,
+) -> Result<SubnetsMap, Error> {
+    let file_names: Vec<PathBuf> = file_names
+        .into_iter()
+        .map(|f| f.as_ref().to_path_buf())
+        .collect();
+    let reading = LineReading {
+        lenient: false,
+        token: &CancelToken::new(),
+        pipeline: &Pipeline::new(),
+    };
+    let mut trees = file_names
+        .par_iter()
+        .map(|file_name| {
+            let mut address_tree = AddressTree::new();
+            let content = FileContent::read(file_name)?;
+            reading.push(
+                &mut address_tree,
+                file_name,
+                &content,
+                &mut StderrLogger,
+                &mut |_| true,
+            )?;
+            Ok(address_tree)
+        })
+        .collect::<Result<Vec<AddressTree>, Error>>()?;
+
+    let mut address_tree = trees.pop().unwrap_or_else(AddressTree::new);
+    for tree in trees {
+        address_tree.merge(tree)?;
+    }
+    Ok(address_tree.get_groups_map(group_by))
+}
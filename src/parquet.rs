@@ -0,0 +1,126 @@
+use std::{fs::File, path::Path, str::FromStr, sync::Arc};
+
+use parquet::{
+    data_type::{ByteArray, ByteArrayType, DoubleType, Int64Type},
+    file::{properties::WriterProperties, writer::SerializedFileWriter},
+    schema::parser::parse_message_type,
+};
+
+use crate::{Error, NetworkKind, Report, Subnet};
+
+const SCHEMA: &str = "
+message address {
+    REQUIRED BYTE_ARRAY address (UTF8);
+    REQUIRED BYTE_ARRAY subnet (UTF8);
+    REQUIRED INT64 count;
+    OPTIONAL BYTE_ARRAY asn (UTF8);
+    OPTIONAL BYTE_ARRAY country (UTF8);
+    OPTIONAL BYTE_ARRAY kind (UTF8);
+    OPTIONAL BYTE_ARRAY class (UTF8);
+    OPTIONAL DOUBLE score;
+}
+";
+
+/// values of an optional column along with their definition levels
+#[derive(Default)]
+struct Optional {
+    values: Vec<ByteArray>,
+    levels: Vec<i16>,
+}
+
+impl Optional {
+    fn push(&mut self, value: Option<&str>) {
+        self.levels.push(value.is_some() as i16);
+        self.values.extend(value.map(ByteArray::from));
+    }
+}
+
+/// # write the report into a Parquet file, one row per address in the report's order
+/// count - hits of the address, 1 if they aren't in the report
+/// asn, country - of the address's IpMetadata, e.g. asn of AS64500 is 64500, null if there are none
+/// kind - of the address told by the metadata, see NetworkKind
+/// class - of the address by its range, see AddressClass
+/// score - of the address's subnet if the report is scored, see Scoring
+/// # returns
+/// Err - if the file can't be written
+pub fn export_parquet(report: &Report, file_name: impl AsRef<Path>) -> Result<(), Error> {
+    let mut addresses = vec![];
+    let mut subnets = vec![];
+    let mut counts = vec![];
+    let mut asns = Optional::default();
+    let mut countries = Optional::default();
+    let mut kinds = Optional::default();
+    let mut classes = Optional::default();
+    let (mut scores, mut score_levels) = (vec![], vec![]);
+    for row in report.rows() {
+        for address in row.addresses {
+            let addr = Subnet::from_str(address).ok();
+            let ip = addr
+                .as_ref()
+                .and_then(|addr| report.metadata.ip_metadata_of(addr))
+                .unwrap_or_default();
+            asns.push(ip.asn.map(|asn| asn.to_string()).as_deref());
+            countries.push(ip.country.as_deref());
+            let kind = addr
+                .as_ref()
+                .and_then(|addr| NetworkKind::of(report.metadata, addr));
+            kinds.push(kind.map(|kind| kind.to_string()).as_deref());
+            classes.push(
+                addr.as_ref()
+                    .and_then(Subnet::address_class)
+                    .map(|class| class.name()),
+            );
+            let hits = report
+                .address_hits
+                .and_then(|hits| hits.get(address).copied())
+                .unwrap_or(1);
+            counts.push(hits as i64);
+            addresses.push(ByteArray::from(address.as_str()));
+            subnets.push(ByteArray::from(row.name));
+            score_levels.push(row.score.is_some() as i16);
+            scores.extend(row.score);
+        }
+    }
+
+    let schema = Arc::new(parse_message_type(SCHEMA)?);
+    let properties = Arc::new(WriterProperties::builder().build());
+    let mut writer = SerializedFileWriter::new(File::create(file_name)?, schema, properties)?;
+    let mut row_group = writer.next_row_group()?;
+    for values in [&addresses, &subnets] {
+        let mut column = row_group
+            .next_column()?
+            .expect("the column is in the schema");
+        column
+            .typed::<ByteArrayType>()
+            .write_batch(values, None, None)?;
+        column.close()?;
+    }
+    let mut column = row_group
+        .next_column()?
+        .expect("the column is in the schema");
+    column
+        .typed::<Int64Type>()
+        .write_batch(&counts, None, None)?;
+    column.close()?;
+    for optional in [&asns, &countries, &kinds, &classes] {
+        let mut column = row_group
+            .next_column()?
+            .expect("the column is in the schema");
+        column.typed::<ByteArrayType>().write_batch(
+            &optional.values,
+            Some(&optional.levels),
+            None,
+        )?;
+        column.close()?;
+    }
+    let mut column = row_group
+        .next_column()?
+        .expect("the column is in the schema");
+    column
+        .typed::<DoubleType>()
+        .write_batch(&scores, Some(&score_levels), None)?;
+    column.close()?;
+    row_group.close()?;
+    writer.close()?;
+    Ok(())
+}
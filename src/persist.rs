@@ -0,0 +1,213 @@
+use std::{fs, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{tree::ROOT, AddressTree, Error, Seen, Subnet};
+
+/// bumped whenever the layout of Saved changes
+const FORMAT_VERSION: u8 = 3;
+
+/// the tree as it goes to the file
+#[derive(Serialize, Deserialize)]
+struct Saved {
+    version: u8,
+    boundaries: Vec<(u32, u8)>, // user's ones, the special ranges are there anyway
+    nodes: Vec<SavedNode>,      // depth-first, every node is followed by its children
+    seen: Vec<(u32, u8, i64, i64)>, // first and last time of the stamped leafs
+}
+
+/// the layout before the leafs were stamped
+#[derive(Deserialize)]
+struct SavedV1 {
+    version: u8,
+    boundaries: Vec<(u32, u8)>,
+    nodes: Vec<SavedNodeV2>,
+}
+
+/// the layout before the coarse leafs were marked
+#[derive(Deserialize)]
+struct SavedV2 {
+    version: u8,
+    boundaries: Vec<(u32, u8)>,
+    nodes: Vec<SavedNodeV2>,
+    seen: Vec<(u32, u8, i64, i64)>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedNode {
+    bits: u32,
+    mask_len: u8,
+    children: u32, // number of the following nodes which are children of this one
+    hits: u64,
+    coarse: bool, // a leaf the memory limit collapsed a subtree into
+}
+
+/// a node of the layouts before the coarse leafs were marked
+#[derive(Deserialize)]
+struct SavedNodeV2 {
+    bits: u32,
+    mask_len: u8,
+    children: u32,
+    hits: u64,
+}
+
+impl From<SavedNodeV2> for SavedNode {
+    fn from(node: SavedNodeV2) -> Self {
+        SavedNode {
+            bits: node.bits,
+            mask_len: node.mask_len,
+            children: node.children,
+            hits: node.hits,
+            coarse: false,
+        }
+    }
+}
+
+impl AddressTree {
+    /// # write the tree to the file in a compact binary format, see load
+    /// # returns
+    /// Err - if the file can't be written
+    pub fn save(&self, file_name: impl AsRef<Path>) -> Result<(), Error> {
+        Ok(fs::write(file_name, self.to_bytes())?)
+    }
+
+    /// # read the tree written by save, so the input doesn't need to be read once again
+    /// # returns
+    /// Err - if the file can't be read or isn't a saved tree
+    pub fn load(file_name: impl AsRef<Path>) -> Result<Self, Error> {
+        let file_name = file_name.as_ref();
+        if !file_name.exists() {
+            return Err(Error::FileNotFound(file_name.to_path_buf()));
+        }
+        Self::from_bytes(&fs::read(file_name)?).map_err(|e| match e {
+            Error::ParseError { reason, .. } => Error::ParseError {
+                input: file_name.display().to_string(),
+                reason,
+            },
+            e => e,
+        })
+    }
+
+    /// same as save, but to memory
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let special: Vec<Subnet> = Subnet::special_ranges().collect();
+        let mut nodes = vec![];
+        let mut stack = vec![ROOT];
+        while let Some(at) = stack.pop() {
+            let subnet = self.subnet_at(at);
+            let children = self.children_at(at);
+            nodes.push(SavedNode {
+                bits: subnet.bits,
+                mask_len: subnet.mask_len,
+                children: children.len() as u32,
+                hits: self.hits_at(at),
+                coarse: self.is_coarse_at(at),
+            });
+            stack.extend(children.iter().rev());
+        }
+        let saved = Saved {
+            version: FORMAT_VERSION,
+            boundaries: self
+                .boundaries()
+                .iter()
+                .filter(|b| !special.contains(b))
+                .map(|b| (b.bits, b.mask_len))
+                .collect(),
+            nodes,
+            seen: self
+                .seen()
+                .iter()
+                .map(|(&(bits, mask_len), seen)| (bits, mask_len, seen.first, seen.last))
+                .collect(),
+        };
+        postcard::to_allocvec(&saved).expect("the tree fits into memory once again")
+    }
+
+    /// # same as load, but from memory
+    /// every subnet is checked to be inside of its parent, so a damaged input can't make a broken tree
+    /// # returns
+    /// Err - if the bytes aren't a saved tree
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let invalid = |reason: &str| Error::ParseError {
+            input: "saved tree".to_string(),
+            reason: reason.to_string(),
+        };
+        // the version is the first byte whatever the layout is
+        let saved = match bytes.first() {
+            Some(1) => postcard::from_bytes::<SavedV1>(bytes).map(|v1| Saved {
+                version: v1.version,
+                boundaries: v1.boundaries,
+                nodes: v1.nodes.into_iter().map(SavedNode::from).collect(),
+                seen: vec![],
+            }),
+            Some(2) => postcard::from_bytes::<SavedV2>(bytes).map(|v2| Saved {
+                version: v2.version,
+                boundaries: v2.boundaries,
+                nodes: v2.nodes.into_iter().map(SavedNode::from).collect(),
+                seen: v2.seen,
+            }),
+            Some(&FORMAT_VERSION) => postcard::from_bytes::<Saved>(bytes),
+            Some(version) => {
+                return Err(invalid(&format!(
+                    "format version {} isn't supported",
+                    version
+                )))
+            }
+            None => return Err(invalid("it's empty")),
+        }
+        .map_err(|e| invalid(&e.to_string()))?;
+        let subnet_of = |bits: u32, mask_len: u8| {
+            let [o1, o2, o3, o4] = bits.to_be_bytes();
+            Subnet::new(o1, o2, o3, o4, mask_len)
+        };
+        let boundaries = saved
+            .boundaries
+            .iter()
+            .map(|&(bits, mask_len)| subnet_of(bits, mask_len))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut nodes = saved.nodes.into_iter();
+        let root = nodes.next().ok_or_else(|| invalid("the root is missing"))?;
+        let root_subnet = subnet_of(root.bits, root.mask_len)?;
+        if root_subnet.bits != root.bits {
+            return Err(invalid("the root has bits below its mask"));
+        }
+        let mut tree = AddressTree::rooted_at(root_subnet, boundaries);
+        let mark_coarse = |tree: &mut AddressTree, at: usize, node: &SavedNode| {
+            if !node.coarse {
+                return Ok(());
+            }
+            if node.children > 0 {
+                return Err(invalid("a coarse leaf has children"));
+            }
+            tree.set_coarse_at(at);
+            Ok(())
+        };
+        mark_coarse(&mut tree, ROOT, &root)?;
+        // the tree's own root, its children are on the stack
+        let mut stack = vec![(ROOT, root.children)];
+        while let Some((parent, left)) = stack.last_mut() {
+            if *left == 0 {
+                stack.pop();
+                continue;
+            }
+            *left -= 1;
+            let parent = *parent;
+            let node = nodes.next().ok_or_else(|| invalid("nodes are missing"))?;
+            let subnet = subnet_of(node.bits, node.mask_len)?;
+            if subnet.bits != node.bits || !tree.subnet_at(parent).contains(&subnet) {
+                return Err(invalid("a subnet isn't inside of its parent"));
+            }
+            let at = tree.add_child(parent, subnet, node.hits);
+            mark_coarse(&mut tree, at, &node)?;
+            stack.push((at, node.children));
+        }
+        if nodes.next().is_some() {
+            return Err(invalid("there are extra nodes"));
+        }
+        tree.recount_all();
+        for (bits, mask_len, first, last) in saved.seen {
+            tree.set_seen(&subnet_of(bits, mask_len)?, Seen { first, last });
+        }
+        Ok(tree)
+    }
+}
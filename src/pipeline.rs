@@ -0,0 +1,152 @@
+use std::{
+    fmt::{Debug, Formatter},
+    str::FromStr,
+};
+
+#[cfg(feature = "regex")]
+use regex::Regex;
+
+use crate::Error;
+
+/// a single rewrite of an input line before it's parsed, see Pipeline
+#[derive(Clone)]
+pub enum PipelineStep {
+    /// surrounding whitespaces
+    Trim,
+    /// :port after an address, e.g. 1.2.3.4:443 or [::ffff:1.2.3.4]:443
+    StripPort,
+    /// surrounding [], e.g. of [1.2.3.4]
+    StripBrackets,
+    /// e.g. of hostnames, so the same host isn't counted twice
+    Lowercase,
+    /// the ::ffff: prefix of an IPv6-mapped IPv4 address, e.g. ::ffff:1.2.3.4
+    UnmapIpv6,
+    /// the first capture group (or the whole match) of the regex, a line without a match becomes empty
+    #[cfg(feature = "regex")]
+    Extract(Regex),
+}
+
+impl PipelineStep {
+    fn apply(&self, line: String) -> String {
+        match self {
+            PipelineStep::Trim => line.trim().to_string(),
+            PipelineStep::StripPort => strip_port(&line).to_string(),
+            PipelineStep::StripBrackets => line
+                .strip_prefix('[')
+                .and_then(|line| line.strip_suffix(']'))
+                .map_or(line.clone(), String::from),
+            PipelineStep::Lowercase => line.to_lowercase(),
+            PipelineStep::UnmapIpv6 => {
+                let unmapped = ["::ffff:", "0:0:0:0:0:ffff:"].iter().find_map(|prefix| {
+                    let head = line.get(..prefix.len())?;
+                    head.eq_ignore_ascii_case(prefix)
+                        .then(|| line[prefix.len()..].to_string())
+                });
+                unmapped.unwrap_or(line)
+            }
+            #[cfg(feature = "regex")]
+            PipelineStep::Extract(regex) => regex
+                .captures(&line)
+                .and_then(|captures| captures.get(1).or_else(|| captures.get(0)))
+                .map_or(String::new(), |found| found.as_str().to_string()),
+        }
+    }
+}
+
+/// the line without the port, bracketed IPv6 keeps its brackets, e.g. [::ffff:1.2.3.4]:443 -> [::ffff:1.2.3.4]
+fn strip_port(line: &str) -> &str {
+    if line.starts_with('[') {
+        return match line.find("]:") {
+            Some(end) => &line[..=end],
+            None => line,
+        };
+    }
+    match line.split_once(':') {
+        // more than one colon is IPv6 without brackets, there is no port to tell
+        Some((addr, port)) if !port.contains(':') => addr,
+        _ => line,
+    }
+}
+
+/// parse one of: trim, strip-port, strip-brackets, lowercase, unmap-ipv6, extract=REGEX
+impl FromStr for PipelineStep {
+    type Err = Error;
+
+    fn from_str(src: &str) -> Result<Self, Self::Err> {
+        match src {
+            "trim" => Ok(PipelineStep::Trim),
+            "strip-port" => Ok(PipelineStep::StripPort),
+            "strip-brackets" => Ok(PipelineStep::StripBrackets),
+            "lowercase" => Ok(PipelineStep::Lowercase),
+            "unmap-ipv6" => Ok(PipelineStep::UnmapIpv6),
+            #[cfg(feature = "regex")]
+            _ if src.starts_with("extract=") => Regex::new(&src["extract=".len()..])
+                .map(PipelineStep::Extract)
+                .map_err(|e| Error::ParseError {
+                    input: src.to_string(),
+                    reason: e.to_string(),
+                }),
+            #[cfg(not(feature = "regex"))]
+            _ if src.starts_with("extract=") => Err(Error::ParseError {
+                input: src.to_string(),
+                reason: "built without the regex feature".to_string(),
+            }),
+            _ => Err(Error::ParseError {
+                input: src.to_string(),
+                reason: "unknown pipeline step".to_string(),
+            }),
+        }
+    }
+}
+
+impl Debug for PipelineStep {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+        match self {
+            PipelineStep::Trim => f.write_str("trim"),
+            PipelineStep::StripPort => f.write_str("strip-port"),
+            PipelineStep::StripBrackets => f.write_str("strip-brackets"),
+            PipelineStep::Lowercase => f.write_str("lowercase"),
+            PipelineStep::UnmapIpv6 => f.write_str("unmap-ipv6"),
+            #[cfg(feature = "regex")]
+            PipelineStep::Extract(regex) => write!(f, "extract={}", regex),
+        }
+    }
+}
+
+/// # rewrites input lines step by step before they're parsed, e.g. to read 1.2.3.4:443 from access logs
+/// an empty pipeline leaves the lines as they are, lines left empty are skipped
+#[derive(Debug, Default, Clone)]
+pub struct Pipeline {
+    steps: Vec<PipelineStep>,
+}
+
+impl Pipeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// add a step after the ones added already
+    pub fn then(mut self, step: PipelineStep) -> Self {
+        self.steps.push(step);
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    /// the line after all the steps
+    pub fn apply(&self, line: &str) -> String {
+        self.steps
+            .iter()
+            .fold(line.to_string(), |line, step| step.apply(line))
+    }
+}
+
+impl FromIterator<PipelineStep> for Pipeline {
+    fn from_iter<I: IntoIterator<Item = PipelineStep>>(steps: I) -> Self {
+        Self {
+            steps: steps.into_iter().collect(),
+        }
+    }
+}
@@ -0,0 +1,68 @@
+use std::fmt::Debug;
+
+use crate::{AddressTree, Density, Subnet};
+
+/// decides whether the tree joins a pushed subnet with a neighbour into their common subnet,
+/// e.g. blocklists want small dense subnets, while IPAM is fine with big sparse ones
+/// a refused subnet becomes a sibling of its neighbour instead
+pub trait MergePolicy: Debug + Send + Sync {
+    fn should_merge(&self, merge: &Merge) -> bool;
+}
+
+/// a join the tree is about to make, see MergePolicy
+pub struct Merge<'a> {
+    tree: &'a AddressTree,
+    at: usize, // the neighbour node
+    pub common: &'a Subnet,
+    pub new_subnet: &'a Subnet,
+}
+
+impl<'a> Merge<'a> {
+    pub(crate) fn new(
+        tree: &'a AddressTree,
+        at: usize,
+        common: &'a Subnet,
+        new_subnet: &'a Subnet,
+    ) -> Self {
+        Self {
+            tree,
+            at,
+            common,
+            new_subnet,
+        }
+    }
+
+    /// the subnet (or address) the new one would be joined with
+    pub fn neighbour(&self) -> &Subnet {
+        self.tree.subnet_at(self.at)
+    }
+
+    /// number of addresses (or subnets) the common subnet would hold, the neighbour's subtree is walked
+    pub fn addresses(&self) -> usize {
+        self.tree.leafs_of(self.at).count() + 1
+    }
+
+    /// share of the common subnet's space its addresses would take, the neighbour's subtree is walked
+    pub fn density(&self) -> Density {
+        let present = self.tree.leafs_of(self.at).map(Subnet::size).sum::<u64>();
+        Density {
+            present: present + self.new_subnet.size(),
+            size: self.common.size(),
+        }
+    }
+}
+
+/// merges only into subnets of at least min_mask_len bits taken by at least min_density of addresses,
+/// the default limits merge everything as the tree does without a policy
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct MergeLimits {
+    pub min_mask_len: u8,
+    pub min_density: f64, // 0.0 to 1.0
+}
+
+impl MergePolicy for MergeLimits {
+    fn should_merge(&self, merge: &Merge) -> bool {
+        merge.common.mask_len >= self.min_mask_len
+            && (self.min_density <= 0.0 || merge.density().ratio() >= self.min_density)
+    }
+}
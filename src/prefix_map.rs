@@ -0,0 +1,98 @@
+use std::collections::HashMap;
+
+use crate::{subnet::mask_of, Subnet};
+
+/// values keyed by networks with the longest-prefix match lookup
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct PrefixMap<T> {
+    by_mask_len: Vec<(u8, HashMap<u32, T>)>, // the longest masks first
+}
+
+impl<T> Default for PrefixMap<T> {
+    fn default() -> Self {
+        Self {
+            by_mask_len: vec![],
+        }
+    }
+}
+
+impl<T> PrefixMap<T> {
+    /// # add a network, the last value wins for the same network
+    /// # returns
+    /// the replaced value if the network was there already
+    pub(crate) fn insert(&mut self, subnet: &Subnet, value: T) -> Option<T> {
+        let i = match self
+            .by_mask_len
+            .binary_search_by(|(mask_len, _)| subnet.mask_len.cmp(mask_len))
+        {
+            Ok(i) => i,
+            Err(i) => {
+                self.by_mask_len
+                    .insert(i, (subnet.mask_len, HashMap::new()));
+                i
+            }
+        };
+        self.by_mask_len[i].1.insert(subnet.bits, value)
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.by_mask_len.is_empty()
+    }
+
+    /// all the networks with their values, the longest masks first
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (Subnet, &T)> {
+        self.by_mask_len.iter().flat_map(|(mask_len, values)| {
+            values.iter().map(|(bits, value)| {
+                let network = Subnet {
+                    bits: *bits,
+                    mask_len: *mask_len,
+                    mask: mask_of(*mask_len),
+                };
+                (network, value)
+            })
+        })
+    }
+
+    /// the widest network containing the subnet (or address) but the subnet itself, with its value
+    pub(crate) fn covering(&self, subnet: &Subnet) -> Option<(Subnet, &T)> {
+        self.by_mask_len
+            .iter()
+            .rev()
+            .filter(|(mask_len, _)| *mask_len < subnet.mask_len)
+            .find_map(|(mask_len, values)| {
+                let mask = mask_of(*mask_len);
+                let network = Subnet {
+                    bits: subnet.bits & mask,
+                    mask_len: *mask_len,
+                    mask,
+                };
+                values.get(&network.bits).map(|value| (network, value))
+            })
+    }
+
+    /// the most specific network containing the subnet (or address) with its value
+    pub(crate) fn lookup(&self, subnet: &Subnet) -> Option<(Subnet, &T)> {
+        self.lookup_within(subnet, subnet.mask_len)
+    }
+
+    /// same as lookup, but the subnet itself doesn't count, e.g. to nest subnets into their parents
+    pub(crate) fn lookup_parent(&self, subnet: &Subnet) -> Option<(Subnet, &T)> {
+        self.lookup_within(subnet, subnet.mask_len.checked_sub(1)?)
+    }
+
+    /// the most specific network containing the subnet with a mask no longer than max_mask_len
+    fn lookup_within(&self, subnet: &Subnet, max_mask_len: u8) -> Option<(Subnet, &T)> {
+        self.by_mask_len
+            .iter()
+            .filter(|(mask_len, _)| *mask_len <= max_mask_len)
+            .find_map(|(mask_len, values)| {
+                let mask = mask_of(*mask_len);
+                let network = Subnet {
+                    bits: subnet.bits & mask,
+                    mask_len: *mask_len,
+                    mask,
+                };
+                values.get(&network.bits).map(|value| (network, value))
+            })
+    }
+}
@@ -0,0 +1,94 @@
+use std::{collections::HashMap, path::Path};
+
+use crate::{AddressTree, Observer, Subnet, SubnetsMap};
+
+/// remembers the files every address was read from, e.g. to tell which feed contributed a subnet
+/// watch the reading with it, see read_into_tree_observed
+#[derive(Debug, Default)]
+pub struct Provenance {
+    sources: Vec<String>,                     // file names in the reading order
+    current: Option<usize>,                   // the file being read
+    files_of: HashMap<(u32, u8), Vec<usize>>, // indices of the sources of every address
+}
+
+impl Provenance {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// files the address (or subnet) was read from in the reading order
+    pub fn sources_of(&self, addr: &Subnet) -> Vec<&str> {
+        self.files_of
+            .get(&(addr.bits, addr.mask_len))
+            .map(|files| files.iter().map(|&i| self.sources[i].as_str()).collect())
+            .unwrap_or_default()
+    }
+
+    /// # files the addresses of every group came from
+    /// # returns
+    /// map of group -> file names in the reading order, groups of unknown origin get none
+    pub fn get_sources_map(&self, subnets: &SubnetsMap) -> SubnetsMap {
+        subnets
+            .iter()
+            .map(|(group, addrs)| {
+                let sources = self.sources_of_all(addrs).into_iter().map(String::from);
+                (group.clone(), sources.collect())
+            })
+            .collect()
+    }
+
+    /// files any of the addresses came from in the reading order
+    pub(crate) fn sources_of_all(&self, addrs: &[impl AsRef<str>]) -> Vec<&str> {
+        let mut files: Vec<usize> = addrs
+            .iter()
+            .filter_map(|addr| addr.as_ref().parse::<Subnet>().ok())
+            .filter_map(|addr| self.files_of.get(&(addr.bits, addr.mask_len)))
+            .flatten()
+            .copied()
+            .collect();
+        files.sort();
+        files.dedup();
+        files.iter().map(|&i| self.sources[i].as_str()).collect()
+    }
+
+    /// # the file most of the addresses came from
+    /// # returns
+    /// None - if none of the addresses was read from a file, the one read first wins a tie
+    pub(crate) fn top_source_of(&self, addrs: &[impl AsRef<str>]) -> Option<&str> {
+        let mut counts = vec![0usize; self.sources.len()];
+        for addr in addrs {
+            let Ok(addr) = addr.as_ref().parse::<Subnet>() else {
+                continue;
+            };
+            for &i in self
+                .files_of
+                .get(&(addr.bits, addr.mask_len))
+                .into_iter()
+                .flatten()
+            {
+                counts[i] += 1;
+            }
+        }
+        let (top, &count) = counts
+            .iter()
+            .enumerate()
+            .max_by_key(|&(i, count)| (count, std::cmp::Reverse(i)))?;
+        (count > 0).then(|| self.sources[top].as_str())
+    }
+}
+
+impl Observer for Provenance {
+    fn on_file_started(&mut self, file_name: &Path) {
+        self.sources.push(file_name.display().to_string());
+        self.current = Some(self.sources.len() - 1);
+    }
+
+    fn on_address_classified(&mut self, addr: &Subnet, _address_tree: &AddressTree) {
+        if let Some(current) = self.current {
+            let files = self.files_of.entry((addr.bits, addr.mask_len)).or_default();
+            if files.last() != Some(&current) {
+                files.push(current);
+            }
+        }
+    }
+}
@@ -0,0 +1,180 @@
+use std::{
+    collections::HashMap,
+    fmt::{Display, Formatter},
+    str::FromStr,
+    time::Duration,
+};
+#[cfg(any(feature = "whois", feature = "ripestat"))]
+use std::{thread, time::Instant};
+
+use crate::Error;
+
+#[cfg(any(feature = "whois", feature = "ripestat"))]
+const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// how often an enrichment provider may be asked, no limits by default
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct RateLimit {
+    pub per_second: Option<f64>, // requests are queued to keep to it
+    pub per_day: Option<u64>,    // requests are refused once it's used up, see Error::QuotaExceeded
+}
+
+/// parse space-separated limits e.g. "8/s 10000/day" or "0.5/s"
+impl FromStr for RateLimit {
+    type Err = Error;
+
+    fn from_str(src: &str) -> Result<Self, Self::Err> {
+        let error = |reason: &str| Error::ParseError {
+            input: src.to_string(),
+            reason: reason.to_string(),
+        };
+        let mut limit = RateLimit::default();
+        for part in src.split_whitespace() {
+            match part.split_once('/') {
+                Some((count, "s")) => {
+                    let count = count
+                        .parse::<f64>()
+                        .map_err(|_| error("can't parse requests per second"))?;
+                    if !(count > 0.0 && count.is_finite()) {
+                        return Err(error("requests per second should be above 0"));
+                    }
+                    // otherwise the wait between requests can't be a Duration
+                    if interval_of(count).is_none() {
+                        return Err(error("requests per second are too few to wait for"));
+                    }
+                    limit.per_second = Some(count);
+                }
+                Some((count, "day")) => {
+                    let count = count
+                        .parse::<u64>()
+                        .map_err(|_| error("can't parse requests per day"))?;
+                    limit.per_day = Some(count);
+                }
+                _ => return Err(error("limit should look like N/s or N/day")),
+            }
+        }
+        Ok(limit)
+    }
+}
+
+impl Display for RateLimit {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+        let parts: Vec<String> = [
+            self.per_second.map(|count| format!("{}/s", count)),
+            self.per_day.map(|count| format!("{}/day", count)),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        f.write_str(&parts.join(" "))
+    }
+}
+
+/// the wait between requests at the rate, None if it doesn't fit into a Duration
+fn interval_of(per_second: f64) -> Option<Duration> {
+    Duration::try_from_secs_f64(1.0 / per_second).ok()
+}
+
+/// rate limits of the enrichment providers by their names, e.g. ripestat or whois
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct RateLimits {
+    by_provider: HashMap<String, RateLimit>,
+}
+
+impl RateLimits {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// # parse limits formatted as: provider = N/s N/day, either of the limits may be left out
+    /// empty lines and lines starting with # are skipped
+    /// # returns
+    /// Err - if some line has no = or its limits can't be parsed
+    pub fn from_lines(lines: impl IntoIterator<Item = impl AsRef<str>>) -> Result<Self, Error> {
+        let mut limits = Self::new();
+        for line in lines {
+            let line = line.as_ref().trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((provider, limit)) = line.split_once('=') else {
+                return Err(Error::ParseError {
+                    input: line.to_string(),
+                    reason: "limit should look like provider = N/s N/day".to_string(),
+                });
+            };
+            limits.insert(provider.trim(), RateLimit::from_str(limit)?);
+        }
+        Ok(limits)
+    }
+
+    /// set the limit of the provider, the last one wins, provider names are case-insensitive
+    pub fn insert(&mut self, provider: &str, limit: RateLimit) {
+        self.by_provider.insert(provider.to_lowercase(), limit);
+    }
+
+    /// the limit of the provider, no limits if it isn't listed
+    pub fn get(&self, provider: &str) -> RateLimit {
+        self.by_provider
+            .get(&provider.to_lowercase())
+            .copied()
+            .unwrap_or_default()
+    }
+}
+
+/// keeps a provider's requests within its limit
+#[cfg(any(feature = "whois", feature = "ripestat"))]
+#[derive(Debug)]
+pub(crate) struct Limiter {
+    provider: &'static str,
+    limit: RateLimit,
+    last: Option<Instant>,        // the previous request
+    day_started: Option<Instant>, // the first request of the day being counted
+    today: u64,                   // requests since day_started
+}
+
+#[cfg(any(feature = "whois", feature = "ripestat"))]
+impl Limiter {
+    pub(crate) fn new(provider: &'static str, limit: RateLimit) -> Self {
+        Self {
+            provider,
+            limit,
+            last: None,
+            day_started: None,
+            today: 0,
+        }
+    }
+
+    /// # wait until the next request may be sent and count it
+    /// # returns
+    /// Err - if the quota of the day is used up, nothing is waited for then
+    pub(crate) fn acquire(&mut self) -> Result<(), Error> {
+        let now = Instant::now();
+        if self
+            .day_started
+            .is_none_or(|started| now.duration_since(started) >= DAY)
+        {
+            self.day_started = Some(now);
+            self.today = 0;
+        }
+        if self.limit.per_day.is_some_and(|quota| self.today >= quota) {
+            return Err(Error::QuotaExceeded(self.provider.to_string()));
+        }
+        if let (Some(per_second), Some(last)) = (self.limit.per_second, self.last) {
+            // a limit made by hand may have no interval, see RateLimit::from_str
+            let interval = interval_of(per_second).ok_or_else(|| {
+                Error::InvalidArgument(format!(
+                    "{}/s of {} is too few to wait for",
+                    per_second, self.provider
+                ))
+            })?;
+            let next = last + interval;
+            if next > now {
+                thread::sleep(next - now);
+            }
+        }
+        self.last = Some(Instant::now());
+        self.today += 1;
+        Ok(())
+    }
+}
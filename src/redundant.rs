@@ -0,0 +1,114 @@
+#[cfg(feature = "fs")]
+use std::path::Path;
+use std::{
+    fmt::{Display, Formatter},
+    str::FromStr,
+};
+
+#[cfg(feature = "fs")]
+use crate::content::FileContent;
+use crate::{prefix_map::PrefixMap, Error, Subnet};
+
+/// an address or a subnet of an input list along with where it comes from
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub source: String,
+    pub line_no: usize, // 1-based
+    pub subnet: Subnet,
+}
+
+impl Display for Entry {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+        write!(f, "{}:{} {}", self.source, self.line_no, self.subnet)
+    }
+}
+
+/// an entry which can be dropped from the lists without changing what they cover
+#[derive(Debug, PartialEq)]
+pub struct Redundant {
+    pub entry: Entry,
+    pub kept: Entry,    // the entry covering it
+    pub shadowed: bool, // false if it's an exact duplicate of the kept one
+}
+
+impl Display for Redundant {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+        let reason = if self.shadowed {
+            "shadowed by"
+        } else {
+            "duplicates"
+        };
+        write!(f, "{} {} {}", self.entry, reason, self.kept)
+    }
+}
+
+/// # find entries of labeled lists which are duplicates or lie within another entry, e.g. in firewall rules
+/// the first of the duplicates is kept, a shadowed entry is reported against the widest entry covering it
+/// empty lines and lines starting with # are skipped
+/// # returns
+/// redundant entries in the order of the sources and their lines
+/// Err - if some line isn't a correct address or subnet
+pub fn redundant_lines<L: AsRef<str>>(
+    sources: impl IntoIterator<Item = (impl Into<String>, impl IntoIterator<Item = L>)>,
+) -> Result<Vec<Redundant>, Error> {
+    let mut entries = vec![];
+    for (source, lines) in sources {
+        let source = source.into();
+        for (line_no, line) in lines.into_iter().enumerate() {
+            let line = line.as_ref().trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            entries.push(Entry {
+                source: source.clone(),
+                line_no: line_no + 1,
+                subnet: Subnet::from_str(line)?,
+            });
+        }
+    }
+
+    let mut first = PrefixMap::default(); // the first entry of every distinct subnet
+    for (i, entry) in entries.iter().enumerate() {
+        if first.lookup(&entry.subnet).map(|(subnet, _)| subnet) != Some(entry.subnet.clone()) {
+            first.insert(&entry.subnet, i);
+        }
+    }
+    Ok(entries
+        .iter()
+        .enumerate()
+        .filter_map(|(i, entry)| {
+            let (kept, shadowed) = match first.covering(&entry.subnet) {
+                Some((_, &kept)) => (kept, true),
+                None => match first.lookup(&entry.subnet) {
+                    Some((_, &kept)) if kept != i => (kept, false),
+                    _ => return None,
+                },
+            };
+            Some(Redundant {
+                entry: entry.clone(),
+                kept: entries[kept].clone(),
+                shadowed,
+            })
+        })
+        .collect())
+}
+
+/// same as redundant_lines, but every source is a file labeled by its name
+#[cfg(feature = "fs")]
+pub fn find_redundant(
+    file_names: impl IntoIterator<Item = impl AsRef<Path>>,
+) -> Result<Vec<Redundant>, Error> {
+    let mut contents = vec![];
+    for file_name in file_names {
+        let file_name = file_name.as_ref();
+        contents.push((
+            file_name.display().to_string(),
+            FileContent::read(file_name)?,
+        ));
+    }
+    redundant_lines(
+        contents
+            .iter()
+            .map(|(label, content)| (label.as_str(), content.split('\n'))),
+    )
+}
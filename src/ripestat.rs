@@ -0,0 +1,212 @@
+#[cfg(feature = "fs")]
+use std::path::Path;
+use std::{str::FromStr, time::Duration};
+
+use serde_json::Value;
+
+#[cfg(feature = "fs")]
+use crate::{content::FileContent, Routed};
+use crate::{
+    metadata::enrich_with,
+    rate::{Limiter, RateLimit},
+    EnrichmentProvider, Error, IpMetadata, Metadata, Observer, PrefixCache, RoutingTable, Subnet,
+};
+
+/// network-info endpoint of the RIPEstat data API, free and without an API key
+pub const RIPESTAT_URL: &str = "https://stat.ripe.net/data/network-info/data.json";
+/// RIPEstat asks its users to tell who they are
+const SOURCE_APP: &str = "ipv4-classify";
+const RIPESTAT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// a prefix announced in BGP along with the networks originating it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Announcement {
+    pub prefix: Subnet,
+    pub origins: Vec<u32>, // origin ASNs, more than one is a MOAS prefix
+}
+
+/// the origin is the ASN only if there is a single one
+impl From<Announcement> for IpMetadata {
+    fn from(announcement: Announcement) -> Self {
+        Self {
+            asn: match announcement.origins[..] {
+                [asn] => Some(asn),
+                _ => None,
+            },
+            announced_prefix: Some(announcement.prefix),
+            ..Self::default()
+        }
+    }
+}
+
+/// looks up announced prefixes of addresses in RIPEstat, e.g. to verify the subnets against the routing
+/// answers are cached by their prefixes, so an address of a prefix looked up already isn't looked up again
+#[derive(Debug)]
+pub struct RipeStat {
+    url: String,
+    agent: ureq::Agent,
+    cache: PrefixCache,
+    limiter: Limiter,
+}
+
+impl Default for RipeStat {
+    fn default() -> Self {
+        Self::with_url(RIPESTAT_URL)
+    }
+}
+
+impl RipeStat {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// ask another server speaking the same API, e.g. a mirror or a proxy
+    pub fn with_url(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            agent: ureq::AgentBuilder::new().timeout(RIPESTAT_TIMEOUT).build(),
+            cache: PrefixCache::new(),
+            limiter: Limiter::new("ripestat", RateLimit::default()),
+        }
+    }
+
+    /// keep the requests within the limit, answers from the cache don't count
+    pub fn with_rate_limit(mut self, limit: RateLimit) -> Self {
+        self.limiter = Limiter::new("ripestat", limit);
+        self
+    }
+
+    /// the answers so far, e.g. to tell how many lookups the cache saved
+    pub fn cache(&self) -> &PrefixCache {
+        &self.cache
+    }
+
+    /// # the most specific announced prefix containing the address
+    /// # returns
+    /// None - if nothing announces the address
+    /// Err - if RIPEstat can't be reached, its answer can't be parsed or the daily quota is used up
+    pub fn announcement_of(&mut self, addr: &Subnet) -> Result<Option<Announcement>, Error> {
+        self.limiter.acquire()?;
+        let resource = addr.to_string();
+        let response = self
+            .agent
+            .get(&self.url)
+            .query("resource", resource.trim_end_matches("/32"))
+            .query("sourceapp", SOURCE_APP)
+            .call()
+            .map_err(Box::new)?;
+        parse_network_info(&response.into_string()?)
+    }
+
+    /// # announced prefix and origin of the address, from the cache if its prefix is there
+    /// # returns
+    /// None - if nothing announces the address
+    /// Err - if RIPEstat can't be reached, its answer can't be parsed or the daily quota is used up
+    pub fn metadata_of(&mut self, addr: &Subnet) -> Result<Option<IpMetadata>, Error> {
+        if let Some(ip) = self.cache.get(addr) {
+            return Ok(Some(ip));
+        }
+        let ip = self.announcement_of(addr)?.map(IpMetadata::from);
+        if let Some(ip) = &ip {
+            self.cache.insert(ip);
+        }
+        Ok(ip)
+    }
+
+    /// # fill announced prefix and asn of the addresses in, values the metadata has already are kept
+    /// once the daily quota is used up, the rest of the addresses are left as they are
+    /// # returns
+    /// Err - if RIPEstat can't be reached
+    pub fn enrich<'a>(
+        &mut self,
+        metadata: &mut Metadata,
+        addrs: impl IntoIterator<Item = &'a Subnet>,
+    ) -> Result<(), Error> {
+        self.enrich_observed(metadata, addrs, &mut ())
+    }
+
+    /// same as enrich, but every decision is told to the observer, see Observer::on_enrichment
+    pub fn enrich_observed<'a>(
+        &mut self,
+        metadata: &mut Metadata,
+        addrs: impl IntoIterator<Item = &'a Subnet>,
+        observer: &mut impl Observer,
+    ) -> Result<(), Error> {
+        let is_complete = |ip: &IpMetadata| ip.announced_prefix.is_some() && ip.asn.is_some();
+        enrich_with("ripestat", metadata, addrs, observer, is_complete, |addr| {
+            let cached = self.cache.contains(addr);
+            (cached, self.metadata_of(addr))
+        })
+    }
+
+    /// # prefixes announcing the addresses of the lines, to route them with, see RoutingTable::route_lines
+    /// empty lines are skipped
+    /// # returns
+    /// Err - if some line isn't a correct IP address or RIPEstat can't be reached
+    pub fn routing_table(
+        &mut self,
+        lines: impl IntoIterator<Item = impl AsRef<str>>,
+    ) -> Result<RoutingTable, Error> {
+        let mut table = RoutingTable::new();
+        for line in lines {
+            let line = line.as_ref().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let addr = Subnet::from_str(line)?;
+            let prefix = self.metadata_of(&addr)?.and_then(|ip| ip.announced_prefix);
+            if let Some(prefix) = prefix {
+                table.insert(&prefix);
+            }
+        }
+        Ok(table)
+    }
+}
+
+impl EnrichmentProvider for RipeStat {
+    fn enrich(&mut self, metadata: &mut Metadata, addrs: &[Subnet]) -> Result<(), Error> {
+        RipeStat::enrich(self, metadata, addrs)
+    }
+}
+
+/// # announcement of a network-info answer, e.g. {"data": {"asns": ["3333"], "prefix": "193.0.0.0/21"}}
+/// # returns
+/// None - if the prefix is empty, so the address isn't announced
+pub(crate) fn parse_network_info(body: &str) -> Result<Option<Announcement>, Error> {
+    let error = |reason: &str| Error::ParseError {
+        input: body.chars().take(100).collect(),
+        reason: reason.to_string(),
+    };
+    let value: Value = serde_json::from_str(body).map_err(|e| error(&e.to_string()))?;
+    let data = &value["data"];
+    let prefix = match data["prefix"].as_str() {
+        Some("") => return Ok(None),
+        Some(prefix) => Subnet::from_str(prefix)?,
+        None => return Err(error("no data.prefix")),
+    };
+    let origins = data["asns"]
+        .as_array()
+        .ok_or_else(|| error("no data.asns"))?
+        .iter()
+        .map(|asn| match asn {
+            Value::String(asn) => asn.parse().ok(),
+            asn => asn.as_u64().and_then(|asn| u32::try_from(asn).ok()),
+        })
+        .collect::<Option<Vec<u32>>>()
+        .ok_or_else(|| error("ASNs should be numbers"))?;
+    Ok(Some(Announcement { prefix, origins }))
+}
+
+/// same as find_routes, but the prefixes are the ones RIPEstat says announce the addresses of the files
+#[cfg(feature = "fs")]
+pub fn find_announced_routes(
+    ripestat: &mut RipeStat,
+    file_names: impl IntoIterator<Item = impl AsRef<Path>>,
+) -> Result<Routed, Error> {
+    let mut contents = vec![];
+    for file_name in file_names {
+        contents.push(FileContent::read(file_name.as_ref())?);
+    }
+    let lines = || contents.iter().flat_map(|content| content.split('\n'));
+    ripestat.routing_table(lines())?.route_lines(lines())
+}
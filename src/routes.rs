@@ -0,0 +1,237 @@
+#[cfg(feature = "fs")]
+use std::path::Path;
+use std::{
+    fmt::{Display, Formatter},
+    io::{ErrorKind, Read},
+    str::FromStr,
+};
+
+#[cfg(feature = "fs")]
+use crate::content::FileContent;
+use crate::{prefix_map::PrefixMap, Error, Subnet};
+
+/// user-supplied prefixes e.g. a routing table or an allocation plan
+/// addresses are only assigned to these prefixes, no new subnets are made up
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct RoutingTable {
+    prefixes: PrefixMap<()>,
+    len: usize,
+}
+
+impl RoutingTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// # parse a prefix per line, anything after the first whitespace is ignored e.g. "10.0.0.0/8 via gw1"
+    /// empty lines and lines starting with # are skipped
+    /// # returns
+    /// Err - if some prefix can't be parsed
+    pub fn from_lines(lines: impl IntoIterator<Item = impl AsRef<str>>) -> Result<Self, Error> {
+        let mut table = Self::new();
+        for line in lines {
+            let line = line.as_ref().trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let prefix = line.split_whitespace().next().unwrap_or(line);
+            table.insert(&Subnet::from_str(prefix)?);
+        }
+        Ok(table)
+    }
+
+    /// # read the IPv4 prefixes of an MRT routing table dump (RFC 6396), e.g. a RouteViews or RIPE RIS RIB file
+    /// TABLE_DUMP and TABLE_DUMP_V2 unicast records are read, the rest are skipped, compressed dumps have to be unpacked first
+    /// # returns
+    /// Err - if the dump is truncated or a record can't be read
+    pub fn from_mrt(mut reader: impl Read) -> Result<Self, Error> {
+        let mut table = Self::new();
+        let mut header = [0; MRT_HEADER_LEN];
+        loop {
+            match reader.read_exact(&mut header) {
+                Ok(()) => (),
+                Err(e) if e.kind() == ErrorKind::UnexpectedEof => break,
+                Err(e) => return Err(e.into()),
+            }
+            let kind = u16::from_be_bytes([header[4], header[5]]);
+            let subtype = u16::from_be_bytes([header[6], header[7]]);
+            let len = u32::from_be_bytes([header[8], header[9], header[10], header[11]]);
+            let mut record = vec![];
+            reader.by_ref().take(len.into()).read_to_end(&mut record)?;
+            if record.len() != len as usize {
+                return Err(mrt_error("truncated record"));
+            }
+            if let Some(prefix) = mrt_prefix(kind, subtype, &record)? {
+                table.insert(&prefix);
+            }
+        }
+        Ok(table)
+    }
+
+    /// add a prefix, the same prefix twice is a single one
+    pub fn insert(&mut self, prefix: &Subnet) {
+        if self.prefixes.insert(prefix, ()).is_none() {
+            self.len += 1;
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// the most specific prefix containing the address (or subnet), longest-prefix match
+    pub fn lookup(&self, addr: &Subnet) -> Option<Subnet> {
+        self.prefixes.lookup(addr).map(|(prefix, _)| prefix)
+    }
+
+    /// # assign every address of the lines to its longest matching prefix
+    /// empty lines are skipped, surrounding whitespaces are ignored, repeated addresses are counted once
+    /// # returns
+    /// Err - if some line isn't a correct IP address
+    pub fn route_lines(
+        &self,
+        lines: impl IntoIterator<Item = impl AsRef<str>>,
+    ) -> Result<Routed, Error> {
+        let mut members = vec![];
+        let mut unmatched = vec![];
+        for line in lines {
+            let line = line.as_ref().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let addr = Subnet::from_str(line)?;
+            match self.lookup(&addr) {
+                Some(prefix) => members.push((prefix, addr)),
+                None => unmatched.push(addr),
+            }
+        }
+        Ok(Routed::of(members, unmatched))
+    }
+}
+
+/// timestamp, type, subtype and length of an MRT record
+const MRT_HEADER_LEN: usize = 12;
+const TABLE_DUMP: u16 = 12;
+const TABLE_DUMP_AFI_IPV4: u16 = 1;
+const TABLE_DUMP_V2: u16 = 13;
+const RIB_IPV4_UNICAST: u16 = 2;
+
+fn mrt_error(reason: &str) -> Error {
+    Error::ParseError {
+        input: "MRT dump".to_string(),
+        reason: reason.to_string(),
+    }
+}
+
+/// # prefix of an MRT record
+/// # returns
+/// None - if the record isn't an IPv4 unicast RIB entry
+fn mrt_prefix(kind: u16, subtype: u16, record: &[u8]) -> Result<Option<Subnet>, Error> {
+    let (octets, mask_len) = match (kind, subtype) {
+        // view, sequence number, prefix, prefix length, status, ...
+        (TABLE_DUMP, TABLE_DUMP_AFI_IPV4) => {
+            let fields = record
+                .get(4..9)
+                .ok_or_else(|| mrt_error("short TABLE_DUMP"))?;
+            ([fields[0], fields[1], fields[2], fields[3]], fields[4])
+        }
+        // sequence number, prefix length, as many prefix octets as the length needs, ...
+        (TABLE_DUMP_V2, RIB_IPV4_UNICAST) => {
+            let mask_len = *record.get(4).ok_or_else(|| mrt_error("short RIB entry"))?;
+            let len = usize::from(mask_len).div_ceil(8);
+            let prefix = record
+                .get(5..5 + len.min(4))
+                .ok_or_else(|| mrt_error("short RIB entry"))?;
+            let mut octets = [0; 4];
+            octets[..prefix.len()].copy_from_slice(prefix);
+            (octets, mask_len)
+        }
+        _ => return Ok(None),
+    };
+    let [o1, o2, o3, o4] = octets;
+    Subnet::new(o1, o2, o3, o4, mask_len).map(Some)
+}
+
+/// addresses assigned to the prefixes of a routing table
+#[derive(Debug, PartialEq)]
+pub struct Routed {
+    pub prefixes: Vec<(Subnet, Vec<Subnet>)>, // matched prefixes with their addresses, both in address order
+    pub unmatched: Vec<Subnet>,               // addresses no prefix contains, in address order
+}
+
+impl Routed {
+    fn of(mut members: Vec<(Subnet, Subnet)>, mut unmatched: Vec<Subnet>) -> Self {
+        let key = |s: &Subnet| (s.bits, s.mask_len);
+        members.sort_by_key(|(prefix, addr)| (key(prefix), key(addr)));
+        members.dedup();
+        let mut prefixes: Vec<(Subnet, Vec<Subnet>)> = vec![];
+        for (prefix, addr) in members {
+            match prefixes.last_mut() {
+                Some((last, addrs)) if *last == prefix => addrs.push(addr),
+                _ => prefixes.push((prefix, vec![addr])),
+            }
+        }
+        unmatched.sort_by_key(key);
+        unmatched.dedup();
+        Self {
+            prefixes,
+            unmatched,
+        }
+    }
+}
+
+impl Display for Routed {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+        writeln!(f, "prefixes matched:")?;
+        for (prefix, addrs) in &self.prefixes {
+            writeln!(f, "{} prefix, {} addresses", prefix, addrs.len())?;
+            for addr in addrs {
+                writeln!(f, "\t{}", addr)?;
+            }
+        }
+        if !self.unmatched.is_empty() {
+            writeln!(f, "{} addresses matched nothing:", self.unmatched.len())?;
+            for addr in &self.unmatched {
+                writeln!(f, "\t{}", addr)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// same as RoutingTable::route_lines for the prefixes and addresses read from the files
+#[cfg(feature = "fs")]
+pub fn find_routes(
+    table_file: impl AsRef<Path>,
+    file_names: impl IntoIterator<Item = impl AsRef<Path>>,
+) -> Result<Routed, Error> {
+    let table = RoutingTable::from_lines(FileContent::read(table_file.as_ref())?.split('\n'))?;
+    route_files(&table, file_names)
+}
+
+/// same as find_routes, but the prefixes are read from an MRT dump, see RoutingTable::from_mrt
+#[cfg(feature = "fs")]
+pub fn find_mrt_routes(
+    mrt_file: impl AsRef<Path>,
+    file_names: impl IntoIterator<Item = impl AsRef<Path>>,
+) -> Result<Routed, Error> {
+    let file = std::fs::File::open(mrt_file.as_ref())?;
+    let table = RoutingTable::from_mrt(std::io::BufReader::new(file))?;
+    route_files(&table, file_names)
+}
+
+#[cfg(feature = "fs")]
+fn route_files(
+    table: &RoutingTable,
+    file_names: impl IntoIterator<Item = impl AsRef<Path>>,
+) -> Result<Routed, Error> {
+    let mut contents = vec![];
+    for file_name in file_names {
+        contents.push(FileContent::read(file_name.as_ref())?);
+    }
+    table.route_lines(contents.iter().flat_map(|content| content.split('\n')))
+}
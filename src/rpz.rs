@@ -0,0 +1,59 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{Error, Report, Subnet};
+
+/// # write the report as a DNS Response Policy Zone answering NXDOMAIN for the subnets' addresses
+/// the serial is the current unix time, so a reload picks a newer export up
+/// # returns
+/// Err - if the file can't be written
+pub fn export_rpz(report: &Report, file_name: impl AsRef<Path>) -> Result<(), Error> {
+    let mut out = BufWriter::new(File::create(file_name)?);
+    write_rpz(report, serial_now(), &mut out)?;
+    Ok(out.flush()?)
+}
+
+/// serial of a zone written now
+pub(crate) fn serial_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(1, |elapsed| elapsed.as_secs())
+}
+
+pub(crate) fn write_rpz(report: &Report, serial: u64, out: &mut impl Write) -> Result<(), Error> {
+    writeln!(out, "$TTL 300")?;
+    writeln!(
+        out,
+        "@ IN SOA localhost. root.localhost. ({} 3600 600 86400 300)",
+        serial
+    )?;
+    writeln!(out, "  IN NS localhost.")?;
+    for row in report.rows() {
+        let comment = [
+            row.kind.map(|kind| kind.to_string()),
+            row.class.map(|class| class.to_string()),
+            row.score.map(|score| format!("score {}", score)),
+        ]
+        .into_iter()
+        .flatten()
+        .fold(
+            format!("; {}, {} addresses", row.name, row.addresses.len()),
+            |comment, word| format!("{}, {}", comment, word),
+        );
+        writeln!(out, "{}", comment)?;
+        for subnet in row.rules() {
+            writeln!(out, "{} CNAME .", rpz_ip(&subnet))?;
+        }
+    }
+    Ok(())
+}
+
+/// trigger name of the subnet, e.g. 24.0.113.0.203.rpz-ip for 203.0.113.0/24
+fn rpz_ip(subnet: &Subnet) -> String {
+    let [o1, o2, o3, o4] = subnet.bits.to_be_bytes();
+    format!("{}.{}.{}.{}.{}.rpz-ip", subnet.mask_len, o4, o3, o2, o1)
+}
@@ -0,0 +1,153 @@
+use std::collections::BTreeMap;
+
+use schemars::{generate::SchemaSettings, JsonSchema, Schema};
+
+use crate::{Error, OutputFormat};
+
+/// the json output, see write_json and JSON_VERSION
+#[derive(JsonSchema)]
+#[allow(dead_code)] // the shapes are only described, the outputs are written by hand
+struct JsonDocument {
+    /// version of the document, see JSON_VERSION
+    version: u32,
+    /// name and version of the tool which wrote the document
+    generator: String,
+    /// columns of the metadata the addresses were enriched with
+    columns: Vec<String>,
+    /// addresses of all the subnets
+    count: u64,
+    /// subnets none of the other subnets contains in the report's order
+    subnets: Vec<JsonSubnet>,
+}
+
+#[derive(JsonSchema)]
+#[allow(dead_code)]
+struct JsonSubnet {
+    subnet: String,
+    label: Option<String>,
+    /// the kind most of the addresses belong to
+    kind: Option<Kind>,
+    /// the address class all the addresses belong to, null for a mix
+    class: Option<Class>,
+    /// addresses in the subnet
+    size: Option<u64>,
+    /// share of the subnet's addresses found, 1.0 for a full one
+    density: Option<f64>,
+    /// addresses found
+    count: u64,
+    /// hits of the addresses if the report is ranked by them
+    hits: Option<u64>,
+    /// severity from 0 to 100 if the report is scored
+    score: Option<f64>,
+    /// unix time the subnet was seen first, across the runs sharing a saved tree
+    first_seen: Option<i64>,
+    /// unix time the subnet was seen last
+    last_seen: Option<i64>,
+    /// files any of the addresses came from
+    sources: Option<Vec<String>>,
+    /// the most common values of the addresses
+    metadata: SubnetMetadata,
+    addresses: Vec<JsonAddress>,
+    /// subnets this one is the most specific one to contain
+    subnets: Vec<JsonSubnet>,
+}
+
+#[derive(JsonSchema)]
+#[allow(dead_code)]
+struct SubnetMetadata {
+    asn: Option<String>,
+    country: Option<String>,
+    org: Option<String>,
+}
+
+#[derive(JsonSchema)]
+#[allow(dead_code)]
+struct JsonAddress {
+    address: String,
+    class: Option<Class>,
+    /// times the address was read
+    hits: Option<u64>,
+    /// files the address came from
+    sources: Option<Vec<String>>,
+    /// non-empty metadata columns of the address
+    metadata: Option<BTreeMap<String, String>>,
+}
+
+/// a line of the ndjson output, see NdjsonSink
+#[derive(JsonSchema)]
+#[allow(dead_code)]
+struct NdjsonAddress {
+    address: String,
+    class: Option<Class>,
+    /// the subnet the address falls into at the moment, later addresses may widen it
+    subnet: Option<String>,
+    /// the file the address was read from
+    source: Option<String>,
+}
+
+/// a line of the ndjson-subnets output, see write_ndjson_subnets
+#[derive(JsonSchema)]
+#[allow(dead_code)]
+struct NdjsonSubnet {
+    subnet: String,
+    label: Option<String>,
+    /// the kind most of the addresses belong to
+    kind: Option<Kind>,
+    /// the address class all the addresses belong to, null for a mix
+    class: Option<Class>,
+    count: u64,
+    hits: Option<u64>,
+    /// severity from 0 to 100 if the report is scored
+    score: Option<f64>,
+    sources: Option<Vec<String>>,
+    addresses: Vec<String>,
+}
+
+/// see NetworkKind
+#[derive(JsonSchema)]
+#[schemars(rename_all = "lowercase")]
+#[allow(dead_code)]
+enum Kind {
+    Hosting,
+    Residential,
+    Mobile,
+    Business,
+}
+
+/// see AddressClass
+#[derive(JsonSchema)]
+#[schemars(rename_all = "lowercase")]
+#[allow(dead_code)]
+enum Class {
+    Public,
+    Private,
+    Cgnat,
+    Multicast,
+    Bogon,
+}
+
+/// # JSON Schema of the output, of a single line for the line-based ones
+/// # returns
+/// Err - if the output isn't JSON, e.g. text or rpz
+pub fn json_schema(format: &OutputFormat) -> Result<String, Error> {
+    let schema = match format {
+        OutputFormat::Json => schema_of::<JsonDocument>(),
+        OutputFormat::Ndjson => schema_of::<NdjsonAddress>(),
+        OutputFormat::NdjsonSubnets => schema_of::<NdjsonSubnet>(),
+        format => {
+            return Err(Error::InvalidArgument(format!(
+                "{:?} output has no JSON schema, only json, ndjson and ndjson-subnets do",
+                format
+            )))
+        }
+    };
+    Ok(serde_json::to_string_pretty(&schema).expect("a schema is plain JSON"))
+}
+
+/// schema of what's written rather than what's read, so the fields written as null are still required
+fn schema_of<T: JsonSchema>() -> Schema {
+    SchemaSettings::draft2020_12()
+        .for_serialize()
+        .into_generator()
+        .into_root_schema_for::<T>()
+}
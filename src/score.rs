@@ -0,0 +1,119 @@
+use std::str::FromStr;
+
+use crate::{Density, Error, Seen};
+
+/// addresses scoring half of the count part
+const COUNT_PIVOT: f64 = 16.0;
+/// hits scoring half of the hits part
+const HITS_PIVOT: f64 = 100.0;
+
+/// # weights of the severity score of a subnet, the worst networks score up to 100, see Scoring::score
+/// density - share of the subnet's addresses found, a full /24 is worse than 3 scattered addresses
+/// count - addresses found, 16 of them score half
+/// hits - times the addresses were read, e.g. reports of a reputation feed, 100 of them score half
+/// recency - how recently the subnet was seen, halves every half_life_days
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Scoring {
+    pub density: f64,
+    pub count: f64,
+    pub hits: f64,
+    pub recency: f64,
+    pub half_life_days: f64,
+}
+
+impl Default for Scoring {
+    fn default() -> Self {
+        Self {
+            density: 1.0,
+            count: 1.0,
+            hits: 1.0,
+            recency: 1.0,
+            half_life_days: 7.0,
+        }
+    }
+}
+
+impl Scoring {
+    /// # score a subnet from 0 to 100 by the weighted average of its parts
+    /// the parts not known are left out, e.g. recency of a tree without seen stamps,
+    /// recency counts back from now, e.g. the latest last seen of the report so a replayed log scores the same
+    pub fn score(
+        &self,
+        density: Option<Density>,
+        count: usize,
+        hits: Option<u64>,
+        seen: Option<Seen>,
+        now: i64,
+    ) -> f64 {
+        let half_lives =
+            |seen: Seen| (now - seen.last).max(0) as f64 / 86_400.0 / self.half_life_days;
+        let parts = [
+            (self.density, density.map(|density| density.ratio())),
+            (self.count, Some(saturate(count as f64, COUNT_PIVOT))),
+            (
+                self.hits,
+                hits.map(|hits| saturate(hits as f64, HITS_PIVOT)),
+            ),
+            (self.recency, seen.map(|seen| 0.5f64.powf(half_lives(seen)))),
+        ];
+        let (sum, weights) = parts
+            .into_iter()
+            .filter_map(|(weight, part)| Some((weight, part?)))
+            .fold((0.0, 0.0), |(sum, weights), (weight, part)| {
+                (sum + weight * part, weights + weight)
+            });
+        if weights > 0.0 {
+            100.0 * sum / weights
+        } else {
+            0.0
+        }
+    }
+}
+
+/// 0.0 for nothing, 0.5 at the pivot and closer to 1.0 the more there is
+fn saturate(value: f64, pivot: f64) -> f64 {
+    value / (value + pivot)
+}
+
+impl FromStr for Scoring {
+    type Err = Error;
+
+    /// parse comma-separated weights, the ones left out keep their defaults, e.g.
+    /// density=2,hits=0,half-life=30
+    fn from_str(src: &str) -> Result<Self, Self::Err> {
+        let error = |reason: &str| Error::ParseError {
+            input: src.to_string(),
+            reason: reason.to_string(),
+        };
+        let mut scoring = Scoring::default();
+        for part in src
+            .split(',')
+            .map(str::trim)
+            .filter(|part| !part.is_empty())
+        {
+            let (name, value) = part
+                .split_once('=')
+                .ok_or_else(|| error("expected name=weight"))?;
+            let value = value
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .filter(|value| value.is_finite() && *value >= 0.0)
+                .ok_or_else(|| error("a weight should be a non-negative number"))?;
+            match name.trim() {
+                "density" => scoring.density = value,
+                "count" => scoring.count = value,
+                "hits" => scoring.hits = value,
+                "recency" => scoring.recency = value,
+                "half-life" if value > 0.0 => scoring.half_life_days = value,
+                "half-life" => return Err(error("half-life should be above 0")),
+                _ => {
+                    return Err(error(
+                        "unknown weight, one of: density, count, hits, recency, half-life",
+                    ))
+                }
+            }
+        }
+        Ok(scoring)
+    }
+}
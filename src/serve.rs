@@ -0,0 +1,254 @@
+use std::{
+    io::{self, Read},
+    str::FromStr,
+    time::Duration,
+};
+
+use serde_json::{json, Value};
+use tiny_http::{Header, Method, Response, Server};
+
+use crate::{io::parse_weighted_line, AddressTree, CancelToken, Error, Subnet, SyslogListener};
+
+/// how often the token is checked while waiting for requests
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+/// longer request bodies are refused with 413
+pub(crate) const MAX_BODY_LEN: u64 = 16 << 20;
+
+/// counters of the served requests, see Metrics::render
+#[derive(Debug, Default)]
+pub(crate) struct Metrics {
+    pub(crate) addresses_ingested: u64, // pushed with POST /addresses
+    pub(crate) lookups_served: u64,
+    pub(crate) requests_failed: u64, // answered with 4xx
+    pub(crate) syslog_messages: u64,
+    pub(crate) syslog_addresses: u64, // found in the syslog messages and pushed
+}
+
+impl Metrics {
+    /// # counters along with the tree's gauges in the Prometheus text format
+    /// subnets are counted on every call, it takes a walk over the tree
+    pub(crate) fn render(&self, address_tree: &AddressTree) -> String {
+        let metrics = [
+            (
+                "ipv4_classify_addresses_ingested_total",
+                "counter",
+                "Addresses pushed with POST /addresses",
+                self.addresses_ingested,
+            ),
+            (
+                "ipv4_classify_lookups_served_total",
+                "counter",
+                "Requests to GET /lookup",
+                self.lookups_served,
+            ),
+            (
+                "ipv4_classify_requests_failed_total",
+                "counter",
+                "Requests answered with a client error",
+                self.requests_failed,
+            ),
+            (
+                "ipv4_classify_syslog_messages_total",
+                "counter",
+                "Syslog messages received",
+                self.syslog_messages,
+            ),
+            (
+                "ipv4_classify_syslog_addresses_total",
+                "counter",
+                "Addresses found in the syslog messages",
+                self.syslog_addresses,
+            ),
+            (
+                "ipv4_classify_subnets",
+                "gauge",
+                "Subnets tracked by the tree",
+                address_tree.subnets_iter().count() as u64,
+            ),
+            (
+                "ipv4_classify_tree_nodes",
+                "gauge",
+                "Nodes of the tree, addresses and subnets made up by it",
+                address_tree.node_count() as u64,
+            ),
+        ];
+        let mut text = String::new();
+        for (name, kind, help, value) in metrics {
+            text += &format!("# HELP {name} {help}\n# TYPE {name} {kind}\n{name} {value}\n");
+        }
+        text
+    }
+}
+
+/// # keep the tree in memory and answer HTTP requests on the address until the token is cancelled
+/// POST /addresses - push a JSON list of addresses e.g. ["1.2.3.4", "1.2.3.5,10"]
+/// GET /lookup/1.2.3.4 - the subnet the address falls into and whether it's known
+/// GET /subnets - all the subnets with their addresses
+/// GET /metrics - counters and gauges for Prometheus
+/// # returns
+/// the tree with everything pushed while serving
+/// Err - if the address can't be listened on
+pub fn serve(
+    address: &str,
+    address_tree: AddressTree,
+    token: &CancelToken,
+) -> Result<AddressTree, Error> {
+    serve_fed(address, address_tree, None, token)
+}
+
+/// # same as serve, but addresses of syslog messages received over UDP and TCP feed the tree as well
+/// # returns
+/// Err - if either address can't be listened on
+pub fn serve_with_syslog(
+    address: &str,
+    syslog_address: &str,
+    address_tree: AddressTree,
+    token: &CancelToken,
+) -> Result<AddressTree, Error> {
+    let syslog = SyslogListener::bind(syslog_address, token)?;
+    serve_fed(address, address_tree, Some(&syslog), token)
+}
+
+fn serve_fed(
+    address: &str,
+    mut address_tree: AddressTree,
+    syslog: Option<&SyslogListener>,
+    token: &CancelToken,
+) -> Result<AddressTree, Error> {
+    let server = Server::http(address).map_err(|e| Error::IoError(io::Error::other(e)))?;
+    let json = Header::from_bytes("Content-Type", "application/json").expect("the header is valid");
+    let text = Header::from_bytes("Content-Type", "text/plain; version=0.0.4")
+        .expect("the header is valid");
+    let mut metrics = Metrics::default();
+    while !token.is_cancelled() {
+        if let Some(syslog) = syslog {
+            push_syslog(&mut address_tree, &mut metrics, syslog);
+        }
+        let Some(mut request) = server.recv_timeout(POLL_INTERVAL)? else {
+            continue;
+        };
+        if *request.method() == Method::Get && request.url() == "/metrics" {
+            let response =
+                Response::from_string(metrics.render(&address_tree)).with_header(text.clone());
+            let _ = request.respond(response);
+            continue;
+        }
+        let (status, value) = match read_body(request.as_reader()) {
+            Ok(body) => respond(
+                &mut address_tree,
+                &mut metrics,
+                request.method(),
+                request.url(),
+                &body,
+            ),
+            Err(refusal) => {
+                metrics.requests_failed += 1;
+                refusal
+            }
+        };
+        let response = Response::from_string(value.to_string())
+            .with_status_code(status)
+            .with_header(json.clone());
+        // the client may be gone already, it's not the server's problem
+        let _ = request.respond(response);
+    }
+    Ok(address_tree)
+}
+
+/// # read the request body up to MAX_BODY_LEN
+/// # returns
+/// Err - HTTP status and JSON body to answer with: 413 if the body is longer, 400 if it can't be read
+pub(crate) fn read_body(reader: impl Read) -> Result<String, (u16, Value)> {
+    let mut body = String::new();
+    match reader.take(MAX_BODY_LEN + 1).read_to_string(&mut body) {
+        Ok(len) if len as u64 > MAX_BODY_LEN => Err((
+            413,
+            json!({ "error": format!("body is longer than {} bytes", MAX_BODY_LEN) }),
+        )),
+        Ok(_) => Ok(body),
+        Err(e) => Err((400, json!({ "error": e.to_string() }))),
+    }
+}
+
+/// # answer a single request
+/// # returns
+/// HTTP status and JSON body
+pub(crate) fn respond(
+    address_tree: &mut AddressTree,
+    metrics: &mut Metrics,
+    method: &Method,
+    url: &str,
+    body: &str,
+) -> (u16, Value) {
+    let path = url.split('?').next().unwrap_or(url);
+    let (status, value) = match (method, path) {
+        (Method::Post, "/addresses") => match push_json(address_tree, body) {
+            Ok(added) => {
+                metrics.addresses_ingested += added as u64;
+                (200, json!({ "added": added }))
+            }
+            Err(e) => (400, json!({ "error": e.to_string() })),
+        },
+        (Method::Get, "/subnets") => (200, json!(address_tree.get_subnets_map())),
+        (Method::Get, _) if path.starts_with("/lookup/") => {
+            metrics.lookups_served += 1;
+            match Subnet::from_str(&path["/lookup/".len()..]) {
+                Ok(addr) => (
+                    200,
+                    json!({
+                        "address": addr.to_string(),
+                        "subnet": address_tree.subnet_of(&addr).map(Subnet::to_string),
+                        "known": address_tree.contains(&addr),
+                    }),
+                ),
+                Err(e) => (400, json!({ "error": e.to_string() })),
+            }
+        }
+        _ => (
+            404,
+            json!({ "error": format!("no such endpoint: {} {}", method, path) }),
+        ),
+    };
+    if (400..500).contains(&status) {
+        metrics.requests_failed += 1;
+    }
+    (status, value)
+}
+
+/// push addresses of a JSON list into the tree, all or nothing
+/// # returns
+/// number of the addresses pushed
+fn push_json(address_tree: &mut AddressTree, body: &str) -> Result<usize, Error> {
+    let lines: Vec<String> = serde_json::from_str(body).map_err(|e| Error::ParseError {
+        input: body.chars().take(100).collect(),
+        reason: e.to_string(),
+    })?;
+    let addrs = lines
+        .iter()
+        .map(|line| parse_weighted_line(line.trim()))
+        .collect::<Result<Vec<_>, _>>()?;
+    if let Some((addr, _)) = addrs
+        .iter()
+        .find(|(addr, _)| !address_tree.root().contains(addr))
+    {
+        return Err(address_tree.rejected(addr.clone()).into());
+    }
+    for (addr, hits) in addrs.iter().cloned() {
+        address_tree
+            .push_weighted(addr, hits, &mut ())
+            .expect("the root contains the address");
+    }
+    Ok(addrs.len())
+}
+
+/// push addresses of the syslog messages received by now into the tree
+fn push_syslog(address_tree: &mut AddressTree, metrics: &mut Metrics, syslog: &SyslogListener) {
+    for addrs in syslog.received() {
+        metrics.syslog_messages += 1;
+        for addr in addrs {
+            if address_tree.push(addr).is_ok() {
+                metrics.syslog_addresses += 1;
+            }
+        }
+    }
+}
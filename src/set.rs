@@ -0,0 +1,248 @@
+use alloc::{string::ToString, vec::Vec};
+use core::{cmp, str::FromStr};
+
+use crate::{Error, Subnet};
+
+/// set of addresses kept as sorted non-overlapping ranges, so set algebra respects CIDR boundaries
+/// e.g. 10.0.0.0/24 minus 10.0.0.128/25 is 10.0.0.0/25
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SubnetSet {
+    ranges: Vec<(u32, u32)>, // first and last address of every range, inclusive
+}
+
+impl SubnetSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// # parse a list of addresses and subnets
+    /// empty lines are skipped, surrounding whitespaces are ignored
+    /// # returns
+    /// Err - if some line isn't a correct IP address or subnet
+    pub fn from_lines(lines: impl IntoIterator<Item = impl AsRef<str>>) -> Result<Self, Error> {
+        let mut subnets = Vec::new();
+        for line in lines {
+            let line = line.as_ref().trim();
+            if !line.is_empty() {
+                subnets.push(Subnet::from_str(line)?);
+            }
+        }
+        Ok(subnets.into_iter().collect())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// number of addresses in the set
+    pub fn size(&self) -> u64 {
+        self.ranges
+            .iter()
+            .map(|&(start, end)| end as u64 - start as u64 + 1)
+            .sum()
+    }
+
+    /// check whether every address of the subnet is in the set
+    pub fn contains(&self, subnet: &Subnet) -> bool {
+        let (first, last) = range_of(subnet);
+        self.ranges
+            .iter()
+            .any(|&(start, end)| start <= first && last <= end)
+    }
+
+    /// check whether any address of the subnet is in the set
+    pub fn overlaps(&self, subnet: &Subnet) -> bool {
+        let (first, last) = range_of(subnet);
+        // the first range which doesn't end before the subnet
+        let i = self.ranges.partition_point(|&(_, end)| end < first);
+        self.ranges.get(i).is_some_and(|&(start, _)| start <= last)
+    }
+
+    /// addresses present in either set
+    pub fn union(&self, other: &SubnetSet) -> SubnetSet {
+        let mut ranges = self.ranges.clone();
+        ranges.extend_from_slice(&other.ranges);
+        Self::of_ranges(ranges)
+    }
+
+    /// addresses present in both sets
+    pub fn intersect(&self, other: &SubnetSet) -> SubnetSet {
+        let mut ranges = Vec::new();
+        let (mut i, mut j) = (0, 0);
+        while i < self.ranges.len() && j < other.ranges.len() {
+            let (start1, end1) = self.ranges[i];
+            let (start2, end2) = other.ranges[j];
+            let start = cmp::max(start1, start2);
+            let end = cmp::min(end1, end2);
+            if start <= end {
+                ranges.push((start, end));
+            }
+            // the range ending first can't intersect anything else
+            if end1 < end2 {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+        SubnetSet { ranges }
+    }
+
+    /// addresses of this set missing in the other one
+    pub fn subtract(&self, other: &SubnetSet) -> SubnetSet {
+        let mut ranges = Vec::new();
+        let mut j = 0;
+        for &(start, end) in &self.ranges {
+            let mut start = start as u64;
+            let end = end as u64;
+            // skip the holes which are over before this range starts
+            while j < other.ranges.len() && (other.ranges[j].1 as u64) < start {
+                j += 1;
+            }
+            let mut k = j;
+            while start <= end && k < other.ranges.len() && other.ranges[k].0 as u64 <= end {
+                let (hole_start, hole_end) = (other.ranges[k].0 as u64, other.ranges[k].1 as u64);
+                if hole_start > start {
+                    ranges.push((start as u32, (hole_start - 1) as u32));
+                }
+                start = cmp::max(start, hole_end + 1);
+                k += 1;
+            }
+            if start <= end {
+                ranges.push((start as u32, end as u32));
+            }
+        }
+        SubnetSet { ranges }
+    }
+
+    /// the shortest list of subnets covering exactly the set, in address order
+    pub fn subnets(&self) -> Vec<Subnet> {
+        let mut res = Vec::new();
+        for &(start, end) in &self.ranges {
+            let (mut start, end) = (start as u64, end as u64);
+            while start <= end {
+                // the biggest aligned block starting here which doesn't go past the end
+                let mut host_bits = cmp::min(start.trailing_zeros(), 32);
+                while start + (1 << host_bits) - 1 > end {
+                    host_bits -= 1;
+                }
+                let [o1, o2, o3, o4] = (start as u32).to_be_bytes();
+                res.push(Subnet::new(o1, o2, o3, o4, 32 - host_bits as u8).unwrap());
+                start += 1 << host_bits;
+            }
+        }
+        res
+    }
+
+    /// addresses of the subnet missing in the set as the shortest list of subnets, e.g. free space of an IPAM export
+    pub fn gaps_in(&self, subnet: &Subnet) -> Vec<Subnet> {
+        SubnetSet {
+            ranges: Vec::from([range_of(subnet)]),
+        }
+        .subtract(self)
+        .subnets()
+    }
+
+    /// # a free /mask_len block of the pool, i.e. none of its addresses is in the set, e.g. the next subnet to assign
+    /// # returns
+    /// None - if there is no free block that big in the pool or the block would be bigger than the pool
+    pub fn allocate(&self, pool: &Subnet, mask_len: u8, fit: Fit) -> Option<Subnet> {
+        if mask_len < pool.mask_len || mask_len > 32 {
+            return None;
+        }
+        // any free aligned block is within one of the gaps, as they are the biggest aligned ones
+        let mut gaps = self
+            .gaps_in(pool)
+            .into_iter()
+            .filter(|gap| gap.mask_len <= mask_len);
+        let gap = match fit {
+            Fit::First => gaps.next(),
+            Fit::Best => gaps.min_by_key(|gap| mask_len - gap.mask_len),
+        }?;
+        Some(Subnet::from_parts(gap.bits.to_be_bytes(), mask_len))
+    }
+
+    /// sort and merge overlapping or adjacent ranges
+    fn of_ranges(mut ranges: Vec<(u32, u32)>) -> Self {
+        ranges.sort_unstable();
+        let mut merged: Vec<(u32, u32)> = Vec::with_capacity(ranges.len());
+        for (start, end) in ranges {
+            match merged.last_mut() {
+                Some(last) if start as u64 <= last.1 as u64 + 1 => last.1 = cmp::max(last.1, end),
+                _ => merged.push((start, end)),
+            }
+        }
+        SubnetSet { ranges: merged }
+    }
+}
+
+/// first and last address of the subnet
+fn range_of(subnet: &Subnet) -> (u32, u32) {
+    (subnet.bits, subnet.bits | !subnet.mask)
+}
+
+impl FromIterator<Subnet> for SubnetSet {
+    fn from_iter<T: IntoIterator<Item = Subnet>>(subnets: T) -> Self {
+        Self::of_ranges(subnets.into_iter().map(|s| range_of(&s)).collect())
+    }
+}
+
+/// which free block SubnetSet::allocate picks
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Fit {
+    /// the lowest one
+    #[default]
+    First,
+    /// the lowest one of the smallest free subnet it fits into, so the bigger free subnets stay whole
+    Best,
+}
+
+impl FromStr for Fit {
+    type Err = Error;
+
+    /// parse one of: first, best
+    fn from_str(src: &str) -> Result<Self, Self::Err> {
+        match src {
+            "first" => Ok(Fit::First),
+            "best" => Ok(Fit::Best),
+            _ => Err(Error::ParseError {
+                input: src.to_string(),
+                reason: "unknown fit".to_string(),
+            }),
+        }
+    }
+}
+
+/// set operation to combine address lists with
+#[derive(Debug, Clone, PartialEq)]
+pub enum SetOp {
+    Union,
+    Intersect,
+    Subtract,
+}
+
+impl SetOp {
+    pub fn apply(&self, s1: &SubnetSet, s2: &SubnetSet) -> SubnetSet {
+        match self {
+            SetOp::Union => s1.union(s2),
+            SetOp::Intersect => s1.intersect(s2),
+            SetOp::Subtract => s1.subtract(s2),
+        }
+    }
+}
+
+impl FromStr for SetOp {
+    type Err = Error;
+
+    /// parse one of: union, intersect, subtract
+    fn from_str(src: &str) -> Result<Self, Self::Err> {
+        match src {
+            "union" => Ok(SetOp::Union),
+            "intersect" => Ok(SetOp::Intersect),
+            "subtract" => Ok(SetOp::Subtract),
+            _ => Err(Error::ParseError {
+                input: src.to_string(),
+                reason: "unknown set operation".to_string(),
+            }),
+        }
+    }
+}
@@ -0,0 +1,430 @@
+#[cfg(feature = "fs")]
+use std::path::PathBuf;
+use std::{
+    io::{self, BufRead, BufReader, ErrorKind, Read},
+    path::Path,
+    sync::mpsc::{self, Receiver, RecvTimeoutError},
+    thread,
+    time::Duration,
+};
+
+#[cfg(feature = "fs")]
+use crate::content::FileContent;
+use crate::{
+    io::parse_weighted_line, AddressTree, CancelToken, Error, GroupBy, Observer, StderrLogger,
+    Subnet, SubnetsMap,
+};
+
+/// addresses a source gives at once
+const BATCH_LEN: usize = 4096;
+/// how long a batch of a followed reader waits for its first line
+const FOLLOW_TIMEOUT: Duration = Duration::from_millis(200);
+/// the biggest packet pcap files are expected to have, a longer one is taken for a broken file
+const MAX_PACKET_LEN: usize = 256 * 1024;
+
+/// where addresses come from, e.g. a file, stdin or a network listener
+/// implement it to classify addresses of one more kind of input, see read_sources
+pub trait AddressSource {
+    /// name of the source for the observers and the errors, e.g. the file name
+    fn name(&self) -> &str;
+
+    /// # next addresses of the source
+    /// # returns
+    /// None - once the source is exhausted, an empty batch if it has nothing at the moment, but may have later
+    /// Err - if the source can't be read or some of its input isn't an address
+    fn next_batch(&mut self) -> Result<Option<Vec<Subnet>>, Error>;
+}
+
+impl<T: AddressSource + ?Sized> AddressSource for Box<T> {
+    fn name(&self) -> &str {
+        (**self).name()
+    }
+
+    fn next_batch(&mut self) -> Result<Option<Vec<Subnet>>, Error> {
+        (**self).next_batch()
+    }
+}
+
+/// # push addresses of the sources into the tree one source after another until the token is cancelled
+/// every source is told to the observer as a file, see Observer::on_file_started
+/// # returns
+/// Err - if some source fails or the tree refuses an address
+pub fn read_sources(
+    address_tree: &mut AddressTree,
+    sources: impl IntoIterator<Item = impl AddressSource>,
+    token: &CancelToken,
+    observer: &mut dyn Observer,
+) -> Result<(), Error> {
+    for mut source in sources {
+        let name = Path::new(source.name()).to_path_buf();
+        observer.on_file_started(&name);
+        let mut addresses = 0;
+        while let Some(batch) = source.next_batch()? {
+            if token.is_cancelled() {
+                return Ok(());
+            }
+            for addr in batch {
+                observer.on_address_parsed(&addr);
+                address_tree.push_observed(addr.clone(), observer)?;
+                observer.on_address_classified(&addr, address_tree);
+                addresses += 1;
+            }
+        }
+        observer.on_file_done(&name, addresses);
+    }
+    Ok(())
+}
+
+/// # address of a line of a line-based source, see parse_weighted_line, hits are dropped
+/// # returns
+/// None - if the line is empty
+/// Err - Error::LineError if the line isn't an address
+fn parse_line(
+    name: &str,
+    line_no: usize,
+    offset: usize,
+    line: &str,
+) -> Result<Option<Subnet>, Error> {
+    let line = line.trim();
+    if line.is_empty() {
+        return Ok(None);
+    }
+    match parse_weighted_line(line) {
+        Ok((addr, _)) => Ok(Some(addr)),
+        Err(reason) => Err(Error::LineError {
+            file_name: name.into(),
+            line_no: line_no + 1,
+            offset,
+            line: line.to_string(),
+            reason: Box::new(reason),
+        }),
+    }
+}
+
+/// a file with an address per line, it's read (or memory-mapped) on the first batch
+#[cfg(feature = "fs")]
+pub struct FileSource {
+    name: String,
+    path: PathBuf,
+    content: Option<FileContent>,
+    line_no: usize, // of the next line, 0-based
+    offset: usize,  // bytes before the next line
+}
+
+#[cfg(feature = "fs")]
+impl FileSource {
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            name: path.as_ref().display().to_string(),
+            path: path.as_ref().to_path_buf(),
+            content: None,
+            line_no: 0,
+            offset: 0,
+        }
+    }
+}
+
+#[cfg(feature = "fs")]
+impl AddressSource for FileSource {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn next_batch(&mut self) -> Result<Option<Vec<Subnet>>, Error> {
+        if self.content.is_none() {
+            self.content = Some(FileContent::read(&self.path)?);
+        }
+        let content = self.content.as_deref().unwrap_or_default();
+        if self.offset >= content.len() {
+            return Ok(None);
+        }
+        let mut batch = vec![];
+        for line in content[self.offset..].split('\n') {
+            if batch.len() == BATCH_LEN {
+                break;
+            }
+            let (line_no, offset) = (self.line_no, self.offset);
+            self.line_no += 1;
+            self.offset += line.len() + 1;
+            batch.extend(parse_line(&self.name, line_no, offset, line)?);
+        }
+        Ok(Some(batch))
+    }
+}
+
+/// lines of a reader with an address each, e.g. stdin or an HTTP response
+pub struct LineSource {
+    name: String,
+    reader: Box<dyn BufRead>,
+    line_no: usize, // of the next line, 0-based
+    offset: usize,  // bytes before the next line
+}
+
+impl LineSource {
+    pub fn new(name: impl Into<String>, reader: impl BufRead + 'static) -> Self {
+        Self {
+            name: name.into(),
+            reader: Box::new(reader),
+            line_no: 0,
+            offset: 0,
+        }
+    }
+
+    /// addresses piped to the program, e.g. from grep
+    pub fn stdin() -> Self {
+        Self::new("stdin", io::stdin().lock())
+    }
+
+    /// # body of the answer to a GET request, e.g. a published blocklist
+    /// # returns
+    /// Err - if the server can't be reached or answers with an error
+    #[cfg(feature = "http")]
+    pub fn http(url: &str) -> Result<Self, Error> {
+        let response = ureq::get(url)
+            .timeout(std::time::Duration::from_secs(30))
+            .call()
+            .map_err(Box::new)?;
+        Ok(Self::new(url, BufReader::new(response.into_reader())))
+    }
+}
+
+impl AddressSource for LineSource {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn next_batch(&mut self) -> Result<Option<Vec<Subnet>>, Error> {
+        let mut batch = vec![];
+        let mut line = String::new();
+        while batch.len() < BATCH_LEN {
+            line.clear();
+            let len = self.reader.read_line(&mut line)?;
+            if len == 0 {
+                return Ok((!batch.is_empty()).then_some(batch));
+            }
+            let (line_no, offset) = (self.line_no, self.offset);
+            self.line_no += 1;
+            self.offset += len;
+            batch.extend(parse_line(&self.name, line_no, offset, &line)?);
+        }
+        Ok(Some(batch))
+    }
+}
+
+/// # lines of a reader which may never end, e.g. stdin of tail -f, read in a background thread
+/// unlike LineSource a batch is whatever has arrived so far, so it doesn't wait for the reader
+/// to fill a whole batch, an empty batch means nothing has arrived for a while
+pub struct FollowSource {
+    name: String,
+    lines: Receiver<io::Result<String>>,
+    line_no: usize, // of the next line, 0-based
+    offset: usize,  // bytes before the next line
+}
+
+impl FollowSource {
+    pub fn new(name: impl Into<String>, reader: impl BufRead + Send + 'static) -> Self {
+        let (sender, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in reader.split(b'\n') {
+                let line = line.map(|line| String::from_utf8_lossy(&line).into_owned());
+                let failed = line.is_err();
+                if sender.send(line).is_err() || failed {
+                    return; // the source is dropped or the reader is broken
+                }
+            }
+        });
+        Self {
+            name: name.into(),
+            lines,
+            line_no: 0,
+            offset: 0,
+        }
+    }
+
+    /// addresses piped to the program as they come, e.g. from tail -f
+    pub fn stdin() -> Self {
+        Self::new("stdin", BufReader::new(io::stdin()))
+    }
+
+    /// address of the line, see parse_line
+    fn parse(&mut self, line: io::Result<String>) -> Result<Option<Subnet>, Error> {
+        let line = line?;
+        let (line_no, offset) = (self.line_no, self.offset);
+        self.line_no += 1;
+        self.offset += line.len() + 1;
+        parse_line(&self.name, line_no, offset, &line)
+    }
+}
+
+impl AddressSource for FollowSource {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn next_batch(&mut self) -> Result<Option<Vec<Subnet>>, Error> {
+        let first = match self.lines.recv_timeout(FOLLOW_TIMEOUT) {
+            Ok(line) => line,
+            Err(RecvTimeoutError::Timeout) => return Ok(Some(vec![])),
+            Err(RecvTimeoutError::Disconnected) => return Ok(None),
+        };
+        let mut batch: Vec<Subnet> = self.parse(first)?.into_iter().collect();
+        while batch.len() < BATCH_LEN {
+            let Ok(line) = self.lines.try_recv() else {
+                break;
+            };
+            batch.extend(self.parse(line)?);
+        }
+        Ok(Some(batch))
+    }
+}
+
+/// source addresses of the IPv4 packets of a pcap capture, e.g. of tcpdump -w
+/// Ethernet (with VLAN tags), Linux cooked, BSD loopback and raw IP captures are understood,
+/// pcapng isn't, a capture cut off in the middle of a packet ends there
+pub struct PcapSource {
+    name: String,
+    reader: Box<dyn Read>,
+    big_endian: bool,
+    link_type: u32,
+}
+
+impl PcapSource {
+    /// # read the capture's header
+    /// # returns
+    /// Err - if it isn't a pcap capture or its link type isn't understood
+    pub fn new(name: impl Into<String>, reader: impl Read + 'static) -> Result<Self, Error> {
+        let name = name.into();
+        let mut reader = BufReader::new(reader);
+        let mut header = [0; 24];
+        reader.read_exact(&mut header)?;
+        let error = |reason: &str| Error::ParseError {
+            input: name.clone(),
+            reason: reason.to_string(),
+        };
+        let big_endian = match header[..4] {
+            [0xd4, 0xc3, 0xb2, 0xa1] | [0x4d, 0x3c, 0xb2, 0xa1] => false,
+            [0xa1, 0xb2, 0xc3, 0xd4] | [0xa1, 0xb2, 0x3c, 0x4d] => true,
+            _ => return Err(error("not a pcap capture, pcapng isn't supported")),
+        };
+        let link_type = u32_of(&header[20..24], big_endian);
+        if !matches!(link_type, 0 | 1 | 101 | 113 | 228) {
+            return Err(error(&format!("unsupported link type {}", link_type)));
+        }
+        Ok(Self {
+            name,
+            reader: Box::new(reader),
+            big_endian,
+            link_type,
+        })
+    }
+
+    /// # open the capture file
+    /// # returns
+    /// Err - if the file can't be read, isn't a pcap capture or its link type isn't understood
+    #[cfg(feature = "fs")]
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let file = std::fs::File::open(path.as_ref())?;
+        Self::new(path.as_ref().display().to_string(), file)
+    }
+
+    /// # next packet of the capture
+    /// # returns
+    /// None - at the end of the capture
+    fn next_packet(&mut self) -> Result<Option<Vec<u8>>, Error> {
+        let mut header = [0; 16];
+        if let Err(e) = self.reader.read_exact(&mut header) {
+            return match e.kind() {
+                ErrorKind::UnexpectedEof => Ok(None),
+                _ => Err(e.into()),
+            };
+        }
+        let len = u32_of(&header[8..12], self.big_endian) as usize;
+        if len > MAX_PACKET_LEN {
+            return Err(Error::ParseError {
+                input: self.name.clone(),
+                reason: format!("packet of {} bytes, the capture is broken", len),
+            });
+        }
+        let mut packet = vec![0; len];
+        match self.reader.read_exact(&mut packet) {
+            Ok(()) => Ok(Some(packet)),
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// source address of the packet, None if it isn't an IPv4 one
+    fn source_of(&self, packet: &[u8]) -> Option<Subnet> {
+        let ip = match self.link_type {
+            // BSD loopback, the address family is in the host's byte order
+            0 => match packet.get(..4)? {
+                [2, 0, 0, 0] | [0, 0, 0, 2] => &packet[4..],
+                _ => return None,
+            },
+            // Ethernet, 802.1Q tags are skipped
+            1 => {
+                let mut at = 12;
+                while packet.get(at..at + 2)? == [0x81, 0x00] {
+                    at += 4;
+                }
+                match packet.get(at..at + 2)? {
+                    [0x08, 0x00] => &packet[at + 2..],
+                    _ => return None,
+                }
+            }
+            // Linux cooked capture
+            113 => match packet.get(14..16)? {
+                [0x08, 0x00] => &packet[16..],
+                _ => return None,
+            },
+            // raw IP
+            _ => packet,
+        };
+        if ip.first()? >> 4 != 4 {
+            return None;
+        }
+        let source: [u8; 4] = ip.get(12..16)?.try_into().ok()?;
+        Some(Subnet::from_parts(source, 32))
+    }
+}
+
+impl AddressSource for PcapSource {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn next_batch(&mut self) -> Result<Option<Vec<Subnet>>, Error> {
+        let mut batch = vec![];
+        while batch.len() < BATCH_LEN {
+            match self.next_packet()? {
+                Some(packet) => batch.extend(self.source_of(&packet)),
+                None if batch.is_empty() => return Ok(None),
+                None => break,
+            }
+        }
+        Ok(Some(batch))
+    }
+}
+
+fn u32_of(bytes: &[u8], big_endian: bool) -> u32 {
+    let bytes = bytes.try_into().expect("4 bytes");
+    match big_endian {
+        true => u32::from_be_bytes(bytes),
+        false => u32::from_le_bytes(bytes),
+    }
+}
+
+/// same as find_groups, but the addresses come from the sources, e.g. FileSource, LineSource::stdin or PcapSource
+pub fn find_groups_in(
+    sources: impl IntoIterator<Item = impl AddressSource>,
+    group_by: &GroupBy,
+) -> Result<SubnetsMap, Error> {
+    let mut address_tree = AddressTree::new();
+    read_sources(
+        &mut address_tree,
+        sources,
+        &CancelToken::new(),
+        &mut StderrLogger,
+    )?;
+    Ok(address_tree.get_groups_map(group_by))
+}
@@ -0,0 +1,102 @@
+use std::{path::Path, str::FromStr};
+
+use rusqlite::{params, Connection};
+
+use crate::{Error, Report, Subnet};
+
+const SCHEMA: &str = "
+DROP TABLE IF EXISTS enrichment;
+DROP TABLE IF EXISTS addresses;
+DROP TABLE IF EXISTS subnets;
+CREATE TABLE subnets (
+    id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE,
+    label TEXT,
+    kind TEXT,
+    class TEXT,
+    size INTEGER,
+    density REAL,
+    hits INTEGER,
+    score REAL,
+    addresses INTEGER NOT NULL,
+    first_seen INTEGER,
+    last_seen INTEGER,
+    age_days INTEGER
+);
+CREATE TABLE addresses (
+    address TEXT NOT NULL,
+    subnet_id INTEGER NOT NULL REFERENCES subnets (id),
+    label TEXT,
+    class TEXT
+);
+CREATE TABLE enrichment (
+    address TEXT NOT NULL,
+    name TEXT NOT NULL,
+    value TEXT NOT NULL
+);
+CREATE INDEX addresses_subnet ON addresses (subnet_id);
+CREATE INDEX enrichment_address ON enrichment (address);
+";
+
+/// # write the report into a SQLite database, tables of a previous export are replaced
+/// subnets - one row per subnet in the report's order, id is the rank
+/// addresses - one row per address referring to its subnet
+/// enrichment - metadata columns of the addresses, one row per non-empty value
+/// # returns
+/// Err - if the database can't be written
+pub fn export_sqlite(report: &Report, file_name: impl AsRef<Path>) -> Result<(), Error> {
+    let mut connection = Connection::open(file_name)?;
+    let transaction = connection.transaction()?;
+    transaction.execute_batch(SCHEMA)?;
+    {
+        let mut subnets = transaction.prepare(
+            "INSERT INTO subnets (id, name, label, kind, class, size, density, hits, score, addresses, first_seen, last_seen, age_days)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        )?;
+        let mut addresses = transaction.prepare(
+            "INSERT INTO addresses (address, subnet_id, label, class) VALUES (?, ?, ?, ?)",
+        )?;
+        let mut enrichment = transaction
+            .prepare("INSERT INTO enrichment (address, name, value) VALUES (?, ?, ?)")?;
+        let columns = report.metadata.columns();
+        for (id, row) in report.rows().into_iter().enumerate() {
+            subnets.execute(params![
+                id + 1,
+                row.name,
+                row.label,
+                row.kind.map(|kind| kind.to_string()),
+                row.class.map(|class| class.name()),
+                row.density.map(|density| density.size as i64),
+                row.density.map(|density| density.ratio()),
+                row.hits.map(|hits| hits as i64),
+                row.score,
+                row.addresses.len(),
+                row.seen.map(|seen| seen.first),
+                row.seen.map(|seen| seen.last),
+                row.seen.map(|seen| seen.age_days()),
+            ])?;
+            for address in row.addresses {
+                let addr = Subnet::from_str(address).ok();
+                let label = addr.as_ref().and_then(|addr| report.labels.label_of(addr));
+                let class = addr.as_ref().and_then(Subnet::address_class);
+                addresses.execute(params![
+                    address,
+                    id + 1,
+                    label,
+                    class.map(|class| class.name())
+                ])?;
+                let Some(values) = addr.as_ref().and_then(|addr| report.metadata.row_of(addr))
+                else {
+                    continue;
+                };
+                for (name, value) in columns.iter().zip(values) {
+                    if !value.is_empty() {
+                        enrichment.execute(params![address, name, value])?;
+                    }
+                }
+            }
+        }
+    }
+    transaction.commit()?;
+    Ok(())
+}
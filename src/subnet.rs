@@ -0,0 +1,444 @@
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::{
+    cmp,
+    fmt::{Debug, Display, Formatter},
+    net::Ipv4Addr,
+    num::ParseIntError,
+    str::FromStr,
+};
+
+use crate::Error;
+
+/// IPv4 subnet representation
+/// consists of u32 and netmask
+#[derive(Debug, Clone, PartialEq)]
+pub struct Subnet {
+    pub(crate) bits: u32, // IP address with significant bits representing the subnet
+    pub(crate) mask_len: u8, // number of significant bits in the bits
+    pub(crate) mask: u32, // prebuilt number with leading significant bits set
+}
+
+impl Subnet {
+    /// root of all ipv4 addresses
+    pub const fn root() -> Self {
+        Self {
+            bits: 0,
+            mask_len: 0,
+            mask: 0,
+        }
+    }
+
+    /// make subnet from octets & mask length
+    /// clear any bits set below the mask: e.g. 1.2.3.4/24 is acceptable but gets transformed to 1.2.3.0/24
+    pub const fn new(o1: u8, o2: u8, o3: u8, o4: u8, mask_len: u8) -> Result<Self, Error> {
+        if mask_len > 32 {
+            Err(Error::MaskTooLong(mask_len))
+        } else {
+            Ok(Self::from_parts([o1, o2, o3, o4], mask_len))
+        }
+    }
+
+    /// same as new, but for constants: e.g. const LAN: Subnet = Subnet::from_parts([192, 168, 0, 0], 16);
+    /// # Panics
+    /// if mask_len is > 32, which fails the build in a const context
+    pub const fn from_parts(octets: [u8; 4], mask_len: u8) -> Self {
+        assert!(mask_len <= 32, "mask len is > 32");
+        let mask = mask_of(mask_len);
+        Self {
+            bits: u32::from_be_bytes(octets) & mask,
+            mask_len,
+            mask,
+        }
+    }
+
+    /// 224.0.0.0/4
+    pub const MULTICAST: Subnet = Subnet::from_parts([224, 0, 0, 0], 4);
+    /// 100.64.0.0/10, shared address space
+    pub const CGNAT: Subnet = Subnet::from_parts([100, 64, 0, 0], 10);
+    /// private address space of RFC 1918
+    pub const PRIVATE: [Subnet; 3] = [
+        Subnet::from_parts([10, 0, 0, 0], 8),
+        Subnet::from_parts([172, 16, 0, 0], 12),
+        Subnet::from_parts([192, 168, 0, 0], 16),
+    ];
+
+    /// number of addresses in the subnet
+    pub fn size(&self) -> u64 {
+        1 << (32 - self.mask_len)
+    }
+
+    /// address at the index within the subnet as a /32, e.g. 10.0.0.5 is at 5 of 10.0.0.0/24
+    /// # returns
+    /// None - if the index is out of the subnet's size
+    pub fn addr_at(&self, index: u64) -> Option<Subnet> {
+        if index >= self.size() {
+            return None;
+        }
+        Some(Subnet {
+            bits: self.bits + index as u32,
+            mask_len: 32,
+            mask: u32::MAX,
+        })
+    }
+
+    /// index of the address (or the first address of a subnet) within the subnet, reverse of addr_at
+    /// # returns
+    /// None - if the subnet doesn't contain the address
+    pub fn index_of(&self, addr: &Subnet) -> Option<u64> {
+        self.contains(addr)
+            .then(|| u64::from(addr.bits - self.bits))
+    }
+
+    /// check whether subnet includes other subnet
+    pub fn contains(&self, other: &Subnet) -> bool {
+        if self.mask_len > other.mask_len {
+            return false;
+        }
+        // let addr_number = u32::from_be_bytes(addr.octets());
+        other.bits & self.mask == self.bits
+    }
+
+    /// check whether subnet includes the address
+    pub fn contains_addr(&self, addr: &Ipv4Addr) -> bool {
+        u32::from(*addr) & self.mask == self.bits
+    }
+
+    /// find and return the closest common of the two subnets if exists
+    /// min_mask defines minimal (shortest) mask to look for
+    /// e.g. 10.0.0.0/24 and 10.128.0.0/24 are both of 10.0.0.0/8
+    /// if min_mask is 16 returns None for the above ranges,
+    /// as 8 is less than min_mask - it's the only case when None can be returned,
+    /// as default values for min_mask is 0, so 0.0.0.0/0 is the worst case
+    /// # Panics
+    /// if min_mask is bigger than any of the subnet masks
+    pub fn common_of(s1: &Subnet, s2: &Subnet, min_mask: Option<u8>) -> Option<Subnet> {
+        let min_mask = min_mask.unwrap_or_default();
+        // the shortest mask is the longest possible common prefix
+        let shortest_mask_len = cmp::min(s1.mask_len, s2.mask_len);
+        if min_mask > shortest_mask_len {
+            panic!("min_mask {} is bigger than {}", min_mask, shortest_mask_len);
+        }
+        // leading bits which are the same in both subnets
+        let same_bits = (s1.bits ^ s2.bits).leading_zeros() as u8;
+        let mask_len = cmp::min(shortest_mask_len, same_bits);
+        if mask_len < min_mask {
+            return None;
+        }
+        let mask = mask_of(mask_len);
+        Some(Subnet {
+            bits: s1.bits & mask,
+            mask_len,
+            mask,
+        })
+    }
+}
+
+/// a /32 subnet equals its address
+impl PartialEq<Ipv4Addr> for Subnet {
+    fn eq(&self, addr: &Ipv4Addr) -> bool {
+        self.mask_len == 32 && self.bits == u32::from(*addr)
+    }
+}
+
+impl PartialEq<Subnet> for Ipv4Addr {
+    fn eq(&self, subnet: &Subnet) -> bool {
+        subnet == self
+    }
+}
+
+/// ranges which are never merged with their neighbours and their names
+pub(crate) const SPECIAL_RANGES: [(Subnet, &str); 2] =
+    [(Subnet::MULTICAST, "multicast"), (Subnet::CGNAT, "CGNAT")];
+
+impl Subnet {
+    /// name of the special range the subnet belongs to, e.g. multicast
+    pub fn special_range(&self) -> Option<&'static str> {
+        SPECIAL_RANGES
+            .iter()
+            .find(|(range, _)| range.contains(self))
+            .map(|(_, name)| *name)
+    }
+
+    /// the special ranges as subnets
+    #[cfg(feature = "std")]
+    pub(crate) fn special_ranges() -> impl Iterator<Item = Subnet> {
+        SPECIAL_RANGES.iter().map(|(range, _)| range.clone())
+    }
+}
+
+/// special-purpose kind of an address, e.g. to filter out what isn't routed on the internet
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AddressClass {
+    Public,
+    Private, // RFC 1918
+    Cgnat,   // shared address space of RFC 6598
+    Multicast,
+    Bogon, // never routed: this network, loopback, link-local, documentation, benchmarking and reserved
+}
+
+/// ranges of the classes but public, none of them overlaps another
+const CLASS_RANGES: [(Subnet, AddressClass); 14] = [
+    (Subnet::from_parts([0, 0, 0, 0], 8), AddressClass::Bogon),
+    (Subnet::from_parts([10, 0, 0, 0], 8), AddressClass::Private),
+    (Subnet::CGNAT, AddressClass::Cgnat),
+    (Subnet::from_parts([127, 0, 0, 0], 8), AddressClass::Bogon),
+    (
+        Subnet::from_parts([169, 254, 0, 0], 16),
+        AddressClass::Bogon,
+    ),
+    (
+        Subnet::from_parts([172, 16, 0, 0], 12),
+        AddressClass::Private,
+    ),
+    (Subnet::from_parts([192, 0, 0, 0], 24), AddressClass::Bogon),
+    (Subnet::from_parts([192, 0, 2, 0], 24), AddressClass::Bogon),
+    (
+        Subnet::from_parts([192, 168, 0, 0], 16),
+        AddressClass::Private,
+    ),
+    (Subnet::from_parts([198, 18, 0, 0], 15), AddressClass::Bogon),
+    (
+        Subnet::from_parts([198, 51, 100, 0], 24),
+        AddressClass::Bogon,
+    ),
+    (
+        Subnet::from_parts([203, 0, 113, 0], 24),
+        AddressClass::Bogon,
+    ),
+    (Subnet::MULTICAST, AddressClass::Multicast),
+    (Subnet::from_parts([240, 0, 0, 0], 4), AddressClass::Bogon),
+];
+
+impl AddressClass {
+    /// # the class all the addresses (or subnets) belong to, see Subnet::address_class
+    /// # returns
+    /// None - if they're of different classes or there are none
+    pub fn of_all<'a>(addrs: impl IntoIterator<Item = &'a Subnet>) -> Option<AddressClass> {
+        let mut classes = addrs.into_iter().map(Subnet::address_class);
+        let first = classes.next()??;
+        classes.all(|class| class == Some(first)).then_some(first)
+    }
+
+    /// name of the class as the outputs have it, e.g. cgnat
+    pub fn name(&self) -> &'static str {
+        match self {
+            AddressClass::Public => "public",
+            AddressClass::Private => "private",
+            AddressClass::Cgnat => "cgnat",
+            AddressClass::Multicast => "multicast",
+            AddressClass::Bogon => "bogon",
+        }
+    }
+}
+
+impl AddressClass {
+    /// every class, public first
+    pub const ALL: [AddressClass; 5] = [
+        AddressClass::Public,
+        AddressClass::Private,
+        AddressClass::Cgnat,
+        AddressClass::Multicast,
+        AddressClass::Bogon,
+    ];
+
+    /// # the class by its name, see AddressClass::name
+    /// # returns
+    /// None - if no class has the name
+    pub fn of_name(name: &str) -> Option<AddressClass> {
+        Self::ALL.into_iter().find(|class| class.name() == name)
+    }
+
+    /// number of addresses of the class, the public ones are what the other classes leave
+    pub fn size(&self) -> u64 {
+        let special = |class: Option<AddressClass>| {
+            CLASS_RANGES
+                .iter()
+                .filter(|(_, of)| class.is_none_or(|class| class == *of))
+                .map(|(range, _)| range.size())
+                .sum::<u64>()
+        };
+        match self {
+            AddressClass::Public => (1 << 32) - special(None),
+            _ => special(Some(*self)),
+        }
+    }
+}
+
+impl Display for AddressClass {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), core::fmt::Error> {
+        f.write_str(self.name())
+    }
+}
+
+impl Subnet {
+    /// # special-purpose class of the subnet, e.g. private for 10.0.0.0/24
+    /// # returns
+    /// None - if the subnet spans several classes, e.g. 0.0.0.0/0
+    pub fn address_class(&self) -> Option<AddressClass> {
+        let mut overlapped = false;
+        for (range, class) in &CLASS_RANGES {
+            if range.contains(self) {
+                return Some(*class);
+            }
+            overlapped |= self.contains(range);
+        }
+        (!overlapped).then_some(AddressClass::Public)
+    }
+
+    /// # the range of the class the subnet belongs to, e.g. 10.0.0.0/8 for 10.0.0.0/24
+    /// # returns
+    /// None - for public subnets, as those are what's left between the ranges, or ones spanning several classes
+    #[cfg(feature = "std")]
+    pub(crate) fn class_range(&self) -> Option<Subnet> {
+        CLASS_RANGES
+            .iter()
+            .find(|(range, _)| range.contains(self))
+            .map(|(range, _)| range.clone())
+    }
+}
+
+/// number with mask_len leading bits set
+pub(crate) const fn mask_of(mask_len: u8) -> u32 {
+    match u32::MAX.checked_shl(32 - mask_len as u32) {
+        Some(mask) => mask,
+        None => 0,
+    }
+}
+
+impl FromStr for Subnet {
+    type Err = Error;
+
+    /// parse string with netmask into a subnet
+    fn from_str(src: &str) -> Result<Self, Self::Err> {
+        let (addr, mask_len) = if src.contains("/") {
+            let split: Vec<&str> = src.split('/').collect();
+            if split.len() != 2 {
+                return Err(Error::ParseError {
+                    input: src.to_string(),
+                    reason: "there are more than 1 / in the address".to_string(),
+                });
+            }
+            if let Ok(mask_len) = split.get(1).unwrap().parse::<u8>() {
+                (*split.first().unwrap(), mask_len)
+            } else {
+                return Err(Error::ParseError {
+                    input: src.to_string(),
+                    reason: "can't parse netmask".to_string(),
+                });
+            }
+        } else {
+            (src, 32)
+        };
+        match addr
+            .split('.')
+            .map(|el| el.parse::<u8>())
+            .collect::<Result<Vec<u8>, ParseIntError>>()
+        {
+            Ok(octets) => {
+                if octets.len() != 4 {
+                    Err(Error::ParseError {
+                        input: addr.to_string(),
+                        reason: "doesn't have 4 dot-separated octets".to_string(),
+                    })
+                } else {
+                    Self::new(octets[0], octets[1], octets[2], octets[3], mask_len)
+                }
+            }
+            Err(e) => Err(Error::ParseError {
+                input: addr.to_string(),
+                reason: format!("{:?}", e),
+            }),
+        }
+    }
+}
+
+/// longest text form: 255.255.255.255/32
+const MAX_TEXT_LEN: usize = 18;
+
+/// append decimal digits of n to buf starting at pos
+/// # returns
+/// position right after the last digit
+fn write_decimal(buf: &mut [u8; MAX_TEXT_LEN], mut pos: usize, n: u8) -> usize {
+    if n >= 100 {
+        buf[pos] = b'0' + n / 100;
+        pos += 1;
+    }
+    if n >= 10 {
+        buf[pos] = b'0' + n / 10 % 10;
+        pos += 1;
+    }
+    buf[pos] = b'0' + n % 10;
+    pos + 1
+}
+
+impl Display for Subnet {
+    /// formats into a stack buffer, Display is the hot path of every report
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), core::fmt::Error> {
+        let mut buf = [0; MAX_TEXT_LEN];
+        let mut pos = 0;
+        for (i, octet) in self.bits.to_be_bytes().into_iter().enumerate() {
+            if i > 0 {
+                buf[pos] = b'.';
+                pos += 1;
+            }
+            pos = write_decimal(&mut buf, pos, octet);
+        }
+        buf[pos] = b'/';
+        pos = write_decimal(&mut buf, pos + 1, self.mask_len);
+        f.write_str(core::str::from_utf8(&buf[..pos]).expect("digits and separators are ascii"))
+    }
+}
+
+impl Subnet {
+    /// significant bits grouped by octets, e.g. 00001010 00000001/16 for 10.1.0.0/16
+    pub fn to_bit_string(&self) -> String {
+        let mut result = String::new();
+        for i in 0..self.mask_len {
+            if i > 0 && i % 8 == 0 {
+                result.push(' ');
+            }
+            result.push(if self.bits << i & 1 << 31 == 0 {
+                '0'
+            } else {
+                '1'
+            });
+        }
+        result.push('/');
+        result.push_str(&self.mask_len.to_string());
+        result
+    }
+
+    /// # parse the form of to_bit_string, spaces between bits are ignored, missing trailing bits are zeros
+    /// # returns
+    /// Err - if there are more bits than the netmask length, e.g. 101/1
+    pub fn from_bit_string(src: &str) -> Result<Self, Error> {
+        let error = |reason: &str| Error::ParseError {
+            input: src.to_string(),
+            reason: reason.to_string(),
+        };
+        let (bits, mask_len) = src
+            .split_once('/')
+            .ok_or_else(|| error("no / in the address"))?;
+        let mask_len = mask_len
+            .parse::<u8>()
+            .map_err(|_| error("can't parse netmask"))?;
+        let mut number = 0u32;
+        for (len, bit) in bits.chars().filter(|c| *c != ' ').enumerate() {
+            let bit = bit.to_digit(2).ok_or_else(|| error("bits aren't 0 or 1"))?;
+            if len == 32 {
+                return Err(error("more than 32 bits"));
+            }
+            // otherwise host bits would be dropped silently
+            if len >= mask_len as usize {
+                return Err(error("more bits than the netmask length"));
+            }
+            number |= bit << (31 - len);
+        }
+        let [o1, o2, o3, o4] = number.to_be_bytes();
+        Self::new(o1, o2, o3, o4, mask_len)
+    }
+}
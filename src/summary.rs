@@ -0,0 +1,275 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt::{Display, Formatter},
+    str::FromStr,
+};
+
+use crate::{metadata::most_common, rank_subnets, AddressTree, GroupBy, Report, Subnet};
+
+/// prefixes the addresses are bucketed by in the summary
+const BUCKET_PREFIXES: [u8; 3] = [8, 16, 24];
+
+/// how addresses of a tree are spread over the address space
+#[derive(Debug, PartialEq)]
+pub struct Summary {
+    pub addresses: usize,
+    pub prefix_lengths: BTreeMap<u8, usize>, // number of input entries per mask length, /32 are addresses
+    pub buckets: Vec<Bucket>,
+}
+
+/// addresses bucketed by a fixed prefix
+#[derive(Debug, PartialEq)]
+pub struct Bucket {
+    pub prefix: u8,
+    pub networks: usize, // networks of the prefix with at least one address
+    pub largest: Option<(String, usize)>, // the most populated network and its number of addresses
+}
+
+impl AddressTree {
+    /// # summarize how addresses are distributed over /8, /16 and /24 networks
+    /// gives a quick sense of whether hits are concentrated in a few networks or spread across the internet
+    pub fn summary(&self) -> Summary {
+        let mut prefix_lengths = BTreeMap::new();
+        let mut addresses = 0;
+        for leaf in self.leafs() {
+            *prefix_lengths.entry(leaf.mask_len).or_default() += 1;
+            addresses += 1;
+        }
+        let buckets = BUCKET_PREFIXES
+            .iter()
+            .map(|&prefix| {
+                let groups = self.groups(&GroupBy::Prefix(prefix), None);
+                Bucket {
+                    prefix,
+                    networks: groups.len(),
+                    // ties are broken by name to keep the summary stable
+                    largest: groups
+                        .into_iter()
+                        .map(|(network, addrs)| (network, addrs.len()))
+                        .max_by(|(n1, c1), (n2, c2)| c1.cmp(c2).then(n2.cmp(n1))),
+                }
+            })
+            .collect();
+        Summary {
+            addresses,
+            prefix_lengths,
+            buckets,
+        }
+    }
+}
+
+impl Display for Summary {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+        write!(f, "prefix lengths:")?;
+        for (mask_len, count) in &self.prefix_lengths {
+            write!(f, "\n\t/{}: {}", mask_len, count)?;
+        }
+        write!(f, "\ndistribution:")?;
+        for bucket in &self.buckets {
+            write!(f, "\n\t/{}: {} networks", bucket.prefix, bucket.networks)?;
+            if let Some((network, count)) = &bucket.largest {
+                write!(
+                    f,
+                    ", the largest is {} with {} of {} ({:.1}%)",
+                    network,
+                    count,
+                    self.addresses,
+                    *count as f64 / self.addresses as f64 * 100.0
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// metadata column the countries are taken from
+const COUNTRY: &str = "country";
+
+/// addresses of the report broken down by their country, the one-slide summary of an incident
+#[derive(Debug, PartialEq)]
+pub struct CountrySummary {
+    pub addresses: usize,
+    pub subnets: usize,
+    pub countries: Vec<Country>, // the most addresses first
+}
+
+/// addresses of a country and the subnets they're in
+#[derive(Debug, PartialEq)]
+pub struct Country {
+    pub name: Option<String>, // None for addresses without a country in the metadata
+    pub addresses: usize,
+    pub subnets: usize, // subnets with at least one address of the country
+}
+
+impl Report<'_> {
+    /// # break the addresses of the report down by the country column of the metadata
+    /// a subnet with addresses of several countries counts for each of them, top is ignored
+    pub fn country_summary(&self) -> CountrySummary {
+        let mut countries: HashMap<Option<&str>, (usize, HashSet<&str>)> = HashMap::new();
+        let mut addresses = 0;
+        for (subnet, addrs) in self.subnets {
+            for addr in addrs {
+                let country = Subnet::from_str(addr)
+                    .ok()
+                    .and_then(|addr| self.metadata.value_of(&addr, COUNTRY));
+                let (count, subnets) = countries.entry(country).or_default();
+                *count += 1;
+                subnets.insert(subnet);
+                addresses += 1;
+            }
+        }
+        let mut countries: Vec<Country> = countries
+            .into_iter()
+            .map(|(name, (addresses, subnets))| Country {
+                name: name.map(String::from),
+                addresses,
+                subnets: subnets.len(),
+            })
+            .collect();
+        // unknown goes last among equals, ties are broken by name to keep the summary stable
+        countries.sort_by(|c1, c2| {
+            c2.addresses
+                .cmp(&c1.addresses)
+                .then(c1.name.is_none().cmp(&c2.name.is_none()))
+                .then(c1.name.cmp(&c2.name))
+        });
+        CountrySummary {
+            addresses,
+            subnets: self.subnets.len(),
+            countries,
+        }
+    }
+}
+
+impl Display for CountrySummary {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+        write!(
+            f,
+            "{:<12} {:>10} {:>8} {:>7}",
+            "country", "addresses", "subnets", "share"
+        )?;
+        for country in &self.countries {
+            write!(
+                f,
+                "\n{:<12} {:>10} {:>8} {:>6.1}%",
+                country.name.as_deref().unwrap_or("unknown"),
+                country.addresses,
+                country.subnets,
+                country.addresses as f64 / self.addresses as f64 * 100.0
+            )?;
+        }
+        write!(
+            f,
+            "\n{:<12} {:>10} {:>8} {:>6.1}%",
+            "total", self.addresses, self.subnets, 100.0
+        )
+    }
+}
+
+/// metadata columns the organizations are taken from, the first one present wins
+pub(crate) const ORG: [&str; 3] = ["org", "organization", "isp"];
+
+/// legal form suffixes dropped from organization names, so "Example, Inc." and "Example Inc" are the same
+const ORG_SUFFIXES: [&str; 16] = [
+    "inc",
+    "incorporated",
+    "llc",
+    "ltd",
+    "limited",
+    "corp",
+    "corporation",
+    "co",
+    "gmbh",
+    "ag",
+    "bv",
+    "b.v",
+    "sa",
+    "s.a",
+    "plc",
+    "pte",
+];
+
+/// subnets of the report clustered by the organization most of their addresses belong to
+#[derive(Debug, PartialEq)]
+pub struct Org {
+    pub name: Option<String>, // None for subnets without an organization in the metadata
+    pub addresses: usize,
+    pub subnets: Vec<String>, // the most addresses first
+}
+
+/// # organization name without the legal form and punctuation around it, e.g. Example for "Example, Inc."
+pub fn normalize_org(name: &str) -> String {
+    let mut name = name.trim();
+    loop {
+        let trimmed = name.trim_end_matches(|c: char| c == '.' || c == ',' || c.is_whitespace());
+        let stripped = trimmed
+            .rsplit_once(char::is_whitespace)
+            .filter(|(_, last)| {
+                ORG_SUFFIXES
+                    .iter()
+                    .any(|suffix| last.eq_ignore_ascii_case(suffix))
+            });
+        match stripped {
+            Some((rest, _)) => name = rest,
+            None => return trimmed.split_whitespace().collect::<Vec<_>>().join(" "),
+        }
+    }
+}
+
+impl Report<'_> {
+    /// # cluster subnets of the report by the organization of the metadata, e.g. to spot a single hosting provider
+    /// a subnet goes to the most common organization of its addresses, names are compared normalized
+    /// and case-insensitive, see normalize_org, top is ignored
+    /// # returns
+    /// organizations with the most addresses first, the one of unknown subnets is the last among equals
+    pub fn org_clusters(&self) -> Vec<Org> {
+        let mut orgs: HashMap<Option<String>, Org> = HashMap::new();
+        // the biggest subnets go first, so do their subnets and spellings of the names
+        for (subnet, addrs) in rank_subnets(self.subnets) {
+            let names: Vec<String> = addrs
+                .iter()
+                .filter_map(|addr| Subnet::from_str(addr).ok())
+                .filter_map(|addr| {
+                    ORG.iter()
+                        .find_map(|column| self.metadata.value_of(&addr, column))
+                })
+                .map(normalize_org)
+                .filter(|name| !name.is_empty())
+                .collect();
+            let name = most_common(names.iter().map(String::as_str));
+            let org = orgs
+                .entry(name.map(str::to_lowercase))
+                .or_insert_with(|| Org {
+                    name: name.map(String::from),
+                    addresses: 0,
+                    subnets: vec![],
+                });
+            org.addresses += addrs.len();
+            org.subnets.push(subnet.clone());
+        }
+        let mut orgs: Vec<Org> = orgs.into_values().collect();
+        orgs.sort_by(|o1, o2| {
+            o2.addresses
+                .cmp(&o1.addresses)
+                .then(o1.name.is_none().cmp(&o2.name.is_none()))
+                .then(o1.name.cmp(&o2.name))
+        });
+        orgs
+    }
+}
+
+impl Display for Org {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+        write!(
+            f,
+            "{}: {} addresses in {} subnets",
+            self.name.as_deref().unwrap_or("unknown"),
+            self.addresses,
+            self.subnets.len()
+        )?;
+        for subnet in &self.subnets {
+            write!(f, "\n\t{}", subnet)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,205 @@
+use std::{
+    io::{self, BufRead, BufReader, ErrorKind, Read},
+    net::{SocketAddr, TcpListener, TcpStream, UdpSocket},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc::{self, Receiver, RecvTimeoutError, Sender},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+use crate::{io::addresses_in, AddressSource, CancelToken, Error, Subnet};
+
+/// how often the listening threads check the token
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+/// the biggest UDP datagram there may be
+const MAX_DATAGRAM_LEN: usize = 65_535;
+/// longer TCP lines are cut, the rest of them is dropped
+const MAX_LINE_LEN: usize = MAX_DATAGRAM_LEN;
+/// TCP clients served at once, more are disconnected right away
+const MAX_CONNECTIONS: usize = 64;
+
+/// receives syslog messages over both UDP and TCP on the same port in background threads
+/// TCP messages are expected one per line, octet-counted framing works as well as the count is no address
+pub struct SyslogListener {
+    name: String, // e.g. syslog 127.0.0.1:514
+    local_addr: SocketAddr,
+    messages: Receiver<Vec<Subnet>>, // addresses of every message received
+}
+
+impl SyslogListener {
+    /// # start listening on the address until the token is cancelled
+    /// # returns
+    /// Err - if the address can't be bound
+    pub fn bind(address: &str, token: &CancelToken) -> Result<Self, Error> {
+        let udp = UdpSocket::bind(address)?;
+        udp.set_read_timeout(Some(POLL_INTERVAL))?;
+        // the same port even if the address asks for any, e.g. 127.0.0.1:0
+        let local_addr = udp.local_addr()?;
+        let tcp = TcpListener::bind(local_addr)?;
+        tcp.set_nonblocking(true)?;
+        let (sender, messages) = mpsc::channel();
+        let (udp_sender, udp_token) = (sender.clone(), token.clone());
+        thread::spawn(move || receive_datagrams(udp, udp_sender, udp_token));
+        let token = token.clone();
+        thread::spawn(move || accept_connections(tcp, sender, token));
+        Ok(Self {
+            name: format!("syslog {}", local_addr),
+            local_addr,
+            messages,
+        })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// addresses of the messages received since the last call, a list per message
+    pub fn received(&self) -> impl Iterator<Item = Vec<Subnet>> + '_ {
+        self.messages.try_iter()
+    }
+}
+
+/// addresses of the messages received, a batch waits for the first message for a while,
+/// the listener is exhausted once the token is cancelled
+impl AddressSource for SyslogListener {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn next_batch(&mut self) -> Result<Option<Vec<Subnet>>, Error> {
+        let mut batch = match self.messages.recv_timeout(POLL_INTERVAL) {
+            Ok(addrs) => addrs,
+            Err(RecvTimeoutError::Timeout) => return Ok(Some(vec![])),
+            Err(RecvTimeoutError::Disconnected) => return Ok(None),
+        };
+        batch.extend(self.received().flatten());
+        Ok(Some(batch))
+    }
+}
+
+/// # IPv4 addresses mentioned in the message, the syslog header is skipped
+/// timestamp and hostname of RFC 3164 and RFC 5424 headers are the sender's, not the network's
+pub fn extract_addresses(message: &str) -> Vec<Subnet> {
+    addresses_in(strip_header(message)).collect()
+}
+
+/// the message without <PRI>, timestamp, hostname and, for RFC 5424, app name, process and message ids
+fn strip_header(message: &str) -> &str {
+    let Some((_, rest)) = message
+        .strip_prefix('<')
+        .and_then(|message| message.split_once('>'))
+    else {
+        return message;
+    };
+    match rest.strip_prefix("1 ") {
+        Some(rest) => skip_fields(rest, 5),
+        None => skip_fields(rest, 4), // Mmm dd hh:mm:ss hostname
+    }
+}
+
+fn skip_fields(mut line: &str, fields: usize) -> &str {
+    for _ in 0..fields {
+        line = line
+            .trim_start()
+            .split_once(' ')
+            .map_or("", |(_, rest)| rest);
+    }
+    line
+}
+
+fn is_timeout(e: &io::Error) -> bool {
+    matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut)
+}
+
+/// a message per datagram
+fn receive_datagrams(socket: UdpSocket, sender: Sender<Vec<Subnet>>, token: CancelToken) {
+    let mut buf = vec![0; MAX_DATAGRAM_LEN];
+    while !token.is_cancelled() {
+        match socket.recv(&mut buf) {
+            Ok(len) => {
+                let message = String::from_utf8_lossy(&buf[..len]);
+                if sender.send(extract_addresses(&message)).is_err() {
+                    return; // the listener is dropped
+                }
+            }
+            Err(e) if is_timeout(&e) => continue,
+            // e.g. an ICMP error of an earlier datagram, it's not fatal
+            Err(_) => thread::sleep(POLL_INTERVAL),
+        }
+    }
+}
+
+fn accept_connections(listener: TcpListener, sender: Sender<Vec<Subnet>>, token: CancelToken) {
+    let connections = Arc::new(AtomicUsize::new(0));
+    while !token.is_cancelled() {
+        match listener.accept() {
+            // dropping the stream closes the connection
+            Ok(_) if connections.load(Ordering::Relaxed) >= MAX_CONNECTIONS => continue,
+            Ok((stream, _)) => {
+                connections.fetch_add(1, Ordering::Relaxed);
+                let (sender, token) = (sender.clone(), token.clone());
+                let connections = connections.clone();
+                thread::spawn(move || {
+                    let _ = receive_lines(stream, sender, token);
+                    connections.fetch_sub(1, Ordering::Relaxed);
+                });
+            }
+            Err(_) => thread::sleep(POLL_INTERVAL),
+        }
+    }
+}
+
+/// a message per line until the client disconnects, a line over MAX_LINE_LEN is cut
+fn receive_lines(
+    stream: TcpStream,
+    sender: Sender<Vec<Subnet>>,
+    token: CancelToken,
+) -> io::Result<()> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(POLL_INTERVAL))?;
+    let mut reader = BufReader::new(stream);
+    let mut line = vec![];
+    let mut cut = false; // the rest of a line over MAX_LINE_LEN is being dropped
+    while !token.is_cancelled() {
+        if line.len() >= MAX_LINE_LEN {
+            if !cut
+                && sender
+                    .send(extract_addresses(&String::from_utf8_lossy(&line)))
+                    .is_err()
+            {
+                return Ok(());
+            }
+            line.clear();
+            cut = true;
+        }
+        let limit = (MAX_LINE_LEN - line.len()) as u64;
+        match (&mut reader).take(limit).read_until(b'\n', &mut line) {
+            Ok(0) => break,
+            // a line without \n is the last one or a too long one, the next read says so
+            Ok(_) if !line.ends_with(b"\n") => continue,
+            Ok(_) if cut => {
+                line.clear();
+                cut = false;
+            }
+            Ok(_) => {
+                if sender
+                    .send(extract_addresses(&String::from_utf8_lossy(&line)))
+                    .is_err()
+                {
+                    return Ok(());
+                }
+                line.clear();
+            }
+            // a partial line stays in the buffer until the rest arrives
+            Err(e) if is_timeout(&e) => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    if !line.is_empty() && !cut {
+        let _ = sender.send(extract_addresses(&String::from_utf8_lossy(&line)));
+    }
+    Ok(())
+}
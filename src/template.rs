@@ -0,0 +1,125 @@
+use std::{io::Write, str::FromStr};
+
+use handlebars::{no_escape, Handlebars};
+use serde_json::{json, Map, Value};
+
+use crate::{metadata::most_common, Error, Exporter, Report, Subnet};
+
+/// # exporter writing the report through a user's Handlebars template, e.g. a line per subnet
+/// {{#each subnets}}{{subnet}} {{count}} {{metadata.asn}}{{/each}}
+/// or a line per address
+/// {{#each subnets}}{{#each addresses}}{{address}},{{../subnet}}{{/each}}{{/each}}
+/// the template sees what the json output has: generator, columns, count and the subnets in the report's order
+/// with label, kind, class, size, density, count, hits, score, first_seen, last_seen, sources, metadata
+/// and the addresses with class, hits and metadata of their own, values aren't escaped
+pub struct Template {
+    handlebars: Handlebars<'static>,
+}
+
+/// the name the template is registered under in Handlebars
+const NAME: &str = "report";
+
+impl Template {
+    /// # compile the template
+    /// # returns
+    /// Err - if the template isn't valid Handlebars
+    pub fn new(source: &str) -> Result<Self, Error> {
+        let mut handlebars = Handlebars::new();
+        handlebars.register_escape_fn(no_escape);
+        handlebars
+            .register_template_string(NAME, source)
+            .map_err(Box::new)?;
+        Ok(Self { handlebars })
+    }
+}
+
+/// registered as template, see Exporters::register
+impl Exporter for Template {
+    fn name(&self) -> &str {
+        "template"
+    }
+
+    fn write(&self, report: &Report, out: &mut dyn Write) -> Result<(), Error> {
+        self.handlebars
+            .render_to_write(NAME, &context_of(report), &mut *out)
+            .map_err(Box::new)?;
+        out.flush()?;
+        Ok(())
+    }
+}
+
+/// the report as the values the template refers to
+fn context_of(report: &Report) -> Value {
+    let columns = report.metadata.columns();
+    let rows = report.rows();
+    let subnets: Vec<Value> = rows
+        .iter()
+        .map(|row| {
+            let addrs: Vec<Option<Subnet>> = row
+                .addresses
+                .iter()
+                .map(|address| Subnet::from_str(address).ok())
+                .collect();
+            let ips: Vec<_> = addrs
+                .iter()
+                .flatten()
+                .filter_map(|addr| report.metadata.ip_metadata_of(addr))
+                .collect();
+            let asns: Vec<String> = ips
+                .iter()
+                .filter_map(|ip| ip.asn.map(|asn| asn.to_string()))
+                .collect();
+            let addresses: Vec<Value> = row
+                .addresses
+                .iter()
+                .zip(&addrs)
+                .map(|(address, addr)| {
+                    let metadata: Map<String, Value> = addr
+                        .as_ref()
+                        .and_then(|addr| report.metadata.row_of(addr))
+                        .map(|values| {
+                            columns
+                                .iter()
+                                .zip(values)
+                                .filter(|(_, value)| !value.is_empty())
+                                .map(|(column, value)| (column.to_string(), json!(value)))
+                                .collect()
+                        })
+                        .unwrap_or_default();
+                    json!({
+                        "address": address,
+                        "class": addr.as_ref().and_then(Subnet::address_class).map(|class| class.name()),
+                        "hits": report.address_hits.and_then(|hits| hits.get(address.as_str())),
+                        "metadata": metadata,
+                    })
+                })
+                .collect();
+            json!({
+                "subnet": row.name,
+                "label": row.label,
+                "kind": row.kind.map(|kind| kind.to_string()),
+                "class": row.class.map(|class| class.name()),
+                "size": row.density.map(|density| density.size),
+                "density": row.density.map(|density| density.ratio()),
+                "count": row.addresses.len(),
+                "hits": row.hits,
+                "score": row.score,
+                "first_seen": row.seen.map(|seen| seen.first),
+                "last_seen": row.seen.map(|seen| seen.last),
+                "sources": row.sources,
+                "metadata": {
+                    "asn": most_common(asns.iter().map(String::as_str)),
+                    "country": most_common(ips.iter().filter_map(|ip| ip.country.as_deref())),
+                    "org": most_common(ips.iter().filter_map(|ip| ip.org.as_deref())),
+                },
+                "addresses": addresses,
+            })
+        })
+        .collect();
+    json!({
+        "generator": concat!("ipv4-classify ", env!("CARGO_PKG_VERSION")),
+        "columns": columns,
+        "count": rows.iter().map(|row| row.addresses.len()).sum::<usize>(),
+        "subnets": subnets,
+    })
+}
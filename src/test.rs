@@ -1,5 +1,9 @@
 use crate::*;
-use std::{net::Ipv4Addr, ops::Sub, str::FromStr};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    path::PathBuf,
+    str::FromStr,
+};
 
 #[test]
 fn subnet_root() {
@@ -37,9 +41,11 @@ fn subnet_from_str() {
 }
 
 #[test]
-#[should_panic]
 fn subnet_from_str_wrong_mask() {
-    let s = Subnet::from_str("1.2.3.4/35").unwrap();
+    assert!(matches!(
+        Subnet::from_str("1.2.3.4/35"),
+        Err(Error::MaskTooLong(35))
+    ));
 }
 
 #[test]
@@ -53,7 +59,7 @@ fn subnet_from_str_ip() {
 #[test]
 fn subnet_from_str_too_many_slash() {
     assert_eq!(
-        "there are more than 1 / in the address",
+        "unable to parse \"1/2.3/7\": there are more than 1 / in the address",
         Subnet::from_str("1/2.3/7").err().unwrap().to_string()
     );
 }
@@ -61,7 +67,7 @@ fn subnet_from_str_too_many_slash() {
 #[test]
 fn subnet_from_str_too_big_mask() {
     assert_eq!(
-        "can't parse netmask from 1.2.3.7/300",
+        "unable to parse \"1.2.3.7/300\": can't parse netmask",
         Subnet::from_str("1.2.3.7/300").err().unwrap().to_string()
     );
 }
@@ -69,7 +75,7 @@ fn subnet_from_str_too_big_mask() {
 #[test]
 fn subnet_from_str_wrong_octets_cnt() {
     assert_eq!(
-        "address 1.2.3.7.8 doesn't have 4 dot-separated octets",
+        "unable to parse \"1.2.3.7.8\": doesn't have 4 dot-separated octets",
         Subnet::from_str("1.2.3.7.8").err().unwrap().to_string()
     );
 }
@@ -112,3 +118,2283 @@ fn subnet_common_of_2_subnets_extending_subnet_outside_limit() {
     let s2 = Subnet::new(10, 0, 2, 0, 24).unwrap();
     assert_eq!(None, Subnet::common_of(&s1, &s2, Some(16)));
 }
+
+#[test]
+fn subnet_addr_at_and_index_of() {
+    let s = Subnet::new(10, 0, 0, 0, 24).unwrap();
+    let addr = Subnet::new(10, 0, 0, 5, 32).unwrap();
+    assert_eq!(Some(addr.clone()), s.addr_at(5));
+    assert_eq!(Some(5), s.index_of(&addr));
+    assert_eq!(Some(255), s.index_of(&s.addr_at(255).unwrap()));
+    assert_eq!(None, s.addr_at(256));
+    assert_eq!(None, s.index_of(&Subnet::new(10, 0, 1, 0, 32).unwrap()));
+    assert_eq!(
+        Some(u32::MAX as u64),
+        Subnet::root().index_of(&"255.255.255.255".parse().unwrap())
+    );
+}
+
+#[test]
+fn subnet_and_ipv4_addr() {
+    let addr = std::net::Ipv4Addr::new(10, 0, 0, 5);
+    assert_eq!(Subnet::new(10, 0, 0, 5, 32).unwrap(), addr);
+    assert_eq!(addr, Subnet::new(10, 0, 0, 5, 32).unwrap());
+    assert_ne!(Subnet::new(10, 0, 0, 5, 31).unwrap(), addr);
+    assert!(Subnet::new(10, 0, 0, 0, 24).unwrap().contains_addr(&addr));
+    assert!(!Subnet::new(10, 0, 1, 0, 24).unwrap().contains_addr(&addr));
+    assert!(Subnet::root().contains_addr(&addr));
+}
+
+#[test]
+fn subnet_bit_string() {
+    let s = Subnet::from_str("10.1.0.0/16").unwrap();
+    assert_eq!("00001010 00000001/16", s.to_bit_string());
+    assert_eq!(s, Subnet::from_bit_string("00001010 00000001/16").unwrap());
+    let s = Subnet::from_str("10.1.240.0/20").unwrap();
+    assert_eq!("00001010 00000001 1111/20", s.to_bit_string());
+    assert_eq!(s, Subnet::from_bit_string(&s.to_bit_string()).unwrap());
+    assert_eq!("/0", Subnet::root().to_bit_string());
+    assert_eq!(Subnet::root(), Subnet::from_bit_string("/0").unwrap());
+    assert!(Subnet::from_bit_string("0002/8").is_err());
+    assert_eq!(
+        Subnet::from_str("128.0.0.0/1").unwrap(),
+        Subnet::from_bit_string("1/1").unwrap()
+    );
+    assert!(Subnet::from_bit_string("101/1").is_err());
+    assert!(Subnet::from_bit_string("1/0").is_err());
+}
+
+#[test]
+fn subnet_const() {
+    const LAN: Subnet = Subnet::from_parts([192, 168, 1, 7], 24);
+    assert_eq!(Subnet::from_str("192.168.1.0/24").unwrap(), LAN);
+    assert!(Subnet::PRIVATE[2].contains(&LAN));
+    assert_eq!(
+        Some("multicast"),
+        Subnet::from_str("239.1.2.3").unwrap().special_range()
+    );
+}
+
+#[test]
+fn rank_subnets_biggest_first() {
+    let subnets = HashMap::from([
+        ("10.0.0.0/24".to_string(), vec!["10.0.0.1/32".to_string()]),
+        (
+            "10.1.0.0/24".to_string(),
+            vec!["10.1.0.1/32".to_string(), "10.1.0.2/32".to_string()],
+        ),
+        ("10.2.0.0/24".to_string(), vec!["10.2.0.1/32".to_string()]),
+    ]);
+    let ranked: Vec<&String> = rank_subnets(&subnets).into_iter().map(|(s, _)| s).collect();
+    assert_eq!(vec!["10.1.0.0/24", "10.0.0.0/24", "10.2.0.0/24"], ranked);
+}
+
+#[test]
+fn group_by_from_str() {
+    assert_eq!(GroupBy::Subnet, GroupBy::from_str("subnet").unwrap());
+    assert_eq!(GroupBy::Class, GroupBy::from_str("class").unwrap());
+    assert_eq!(GroupBy::Prefix(8), GroupBy::from_str("/8").unwrap());
+    assert_eq!(
+        GroupBy::column("asn"),
+        GroupBy::from_str("column:asn").unwrap()
+    );
+    assert!(GroupBy::from_str("column:").is_err());
+    assert_eq!(
+        "unable to parse \"/33\": unknown grouping",
+        GroupBy::from_str("/33").err().unwrap().to_string()
+    );
+}
+
+#[test]
+fn group_by_prefix_and_class() {
+    let addr = Subnet::new(172, 16, 5, 4, 32).unwrap();
+    assert_eq!("172.0.0.0/8", GroupBy::Prefix(8).group_of(&addr));
+    assert_eq!("private", GroupBy::Class.group_of(&addr));
+    assert_eq!(
+        "public",
+        GroupBy::Class.group_of(&Subnet::new(8, 8, 8, 8, 32).unwrap())
+    );
+    let net = Subnet::new(10, 0, 0, 0, 8).unwrap();
+    assert_eq!("10.0.0.0/8", GroupBy::Prefix(16).group_of(&net));
+    // spanning private and public addresses
+    let net = Subnet::new(0, 0, 0, 0, 0).unwrap();
+    assert_eq!("0.0.0.0/0", GroupBy::Class.group_of(&net));
+}
+
+#[test]
+fn group_by_metadata_column() {
+    let metadata =
+        Metadata::from_lines(["ip,asn", "10.0.0.1,64500", "192.168.0.1,64500", "10.0.0.2,"])
+            .unwrap();
+    let group_by = GroupBy::column("ASN").joined(&metadata);
+    assert_eq!("column:ASN", group_by.to_string());
+    let mut tree = AddressTree::new();
+    for addr in ["10.0.0.1", "10.0.0.2", "192.168.0.1"] {
+        tree.push(Subnet::from_str(addr).unwrap()).unwrap();
+    }
+    let groups = tree.get_groups_map(&group_by);
+    assert_eq!(2, groups.len());
+    assert_eq!(2, groups["64500"].len());
+    assert_eq!(vec!["10.0.0.2/32".to_string()], groups["no ASN"]);
+    // the values are joined only once asked to
+    assert_eq!(
+        3,
+        tree.get_groups_map(&GroupBy::column("asn"))["no asn"].len()
+    );
+}
+
+#[test]
+fn generate_clustered() {
+    let addrs = generate(3, 5, 28, 42).unwrap();
+    assert_eq!(15, addrs.len());
+    assert_eq!(addrs, generate(3, 5, 28, 42).unwrap());
+    let clusters: HashSet<String> = addrs
+        .iter()
+        .map(|addr| GroupBy::Prefix(28).group_of(&Subnet::from_str(addr).unwrap()))
+        .collect();
+    assert_eq!(3, clusters.len());
+}
+
+#[test]
+fn generate_too_many_hosts() {
+    assert_eq!(
+        "/30 can't fit 5 hosts",
+        generate(1, 5, 30, 0).err().unwrap().to_string()
+    );
+}
+
+#[test]
+fn subnet_new_zero_mask() {
+    assert_eq!(Subnet::root(), Subnet::new(1, 2, 3, 4, 0).unwrap());
+}
+
+#[test]
+fn normalize_list() {
+    let (clean, malformed) = normalize_lines(
+        "10.0.0.7/24\n\n1.2.3.4\n10.0.0.0/24\n1.2.3.4/33\n 1.2.3.4 \n1.2.3\n".split('\n'),
+    );
+    assert_eq!(vec!["1.2.3.4/32", "10.0.0.0/24"], clean);
+    assert_eq!(
+        vec![
+            "line 5: mask len 33 is > 32",
+            "line 7: unable to parse \"1.2.3\": doesn't have 4 dot-separated octets"
+        ],
+        malformed
+    );
+}
+
+#[test]
+fn config_builder() {
+    let config = Config::builder()
+        .file("tests/res/invalid_ips.csv")
+        .top(Some(3))
+        .group_by(GroupBy::Class)
+        .build()
+        .unwrap();
+    assert_eq!(
+        vec![PathBuf::from("tests/res/invalid_ips.csv")],
+        config.file_names
+    );
+    assert_eq!(Some(3), config.top);
+    assert_eq!(GroupBy::Class, config.group_by);
+}
+
+#[test]
+fn config_builder_defers_checks() {
+    let builder = Config::builder().file("non.file").top(Some(0));
+    assert!(matches!(builder.build(), Err(Error::FileNotFound(_))));
+    let builder = Config::builder().top(Some(0));
+    assert!(matches!(builder.build(), Err(Error::InvalidArgument(_))));
+    let builder = Config::builder().min_count(Some(0));
+    assert!(matches!(builder.build(), Err(Error::InvalidArgument(_))));
+    let builder = Config::builder().group_by(GroupBy::column("asn"));
+    assert!(matches!(builder.build(), Err(Error::InvalidArgument(_))));
+    let builder = Config::builder().template(Some("non.hbs".into()));
+    assert!(matches!(builder.build(), Err(Error::FileNotFound(_))));
+    // a template writes to a file or stdout whatever the format
+    let template = Some(PathBuf::from("tests/res/valid_ips.csv"));
+    let builder = Config::builder()
+        .template(template.clone())
+        .output(Some("report.txt".into()));
+    assert!(builder.build().is_ok());
+    let builder = Config::builder()
+        .template(template)
+        .output_format(OutputFormat::Json);
+    assert!(matches!(builder.build(), Err(Error::InvalidArgument(_))));
+}
+
+#[test]
+fn tree_merge() {
+    let mut t1 = AddressTree::new();
+    t1.push(Subnet::from_str("10.0.0.1").unwrap()).unwrap();
+    t1.push(Subnet::from_str("10.0.0.2").unwrap()).unwrap();
+    let mut t2 = AddressTree::new();
+    t2.push(Subnet::from_str("10.0.0.3").unwrap()).unwrap();
+    t2.push(Subnet::from_str("192.168.0.1").unwrap()).unwrap();
+    t1.merge(t2).unwrap();
+    let groups = t1.get_groups_map(&GroupBy::Prefix(24));
+    assert_eq!(3, groups["10.0.0.0/24"].len());
+    assert_eq!(1, groups["192.168.0.0/24"].len());
+}
+
+#[test]
+fn tree_extend_sorted() {
+    let mut addrs = generate(20, 30, 24, 7).unwrap();
+    addrs.sort_by_key(|addr| Subnet::from_str(addr).unwrap().bits);
+    // out-of-order tail takes the regular path
+    addrs.push("0.0.0.1".to_string());
+    let mut expected = AddressTree::new();
+    for addr in &addrs {
+        expected.push(Subnet::from_str(addr).unwrap()).unwrap();
+    }
+    let mut sorted = AddressTree::new();
+    sorted
+        .extend_sorted(addrs.iter().map(|addr| Subnet::from_str(addr).unwrap()))
+        .unwrap();
+    assert_eq!(expected.to_string(), sorted.to_string());
+}
+
+#[test]
+fn subnet_display() {
+    for text in [
+        "0.0.0.0/0",
+        "255.255.255.255/32",
+        "10.0.100.9/32",
+        "192.168.0.0/16",
+    ] {
+        assert_eq!(text, Subnet::from_str(text).unwrap().to_string());
+    }
+}
+
+#[test]
+fn tree_subnets_iter() {
+    let mut tree = AddressTree::new();
+    for addr in ["10.0.0.1", "10.0.0.2", "10.0.1.5"] {
+        tree.push(Subnet::from_str(addr).unwrap()).unwrap();
+    }
+    let subnets: Vec<(String, Vec<String>)> = tree
+        .subnets_iter()
+        .map(|(subnet, addrs)| (subnet.to_string(), addrs.map(|a| a.to_string()).collect()))
+        .collect();
+    assert_eq!(
+        vec![(
+            "10.0.0.0/23".to_string(),
+            vec![
+                "10.0.0.1/32".to_string(),
+                "10.0.0.2/32".to_string(),
+                "10.0.1.5/32".to_string()
+            ]
+        )],
+        subnets
+    );
+}
+
+#[test]
+fn tree_capacity_is_a_hint() {
+    let mut tree = AddressTree::with_capacity(usize::MAX);
+    tree.reserve(10);
+    tree.push(Subnet::from_str("10.0.0.1").unwrap()).unwrap();
+    assert_eq!(1, tree.get_subnets_map().len());
+}
+
+#[test]
+fn overlap_of_two_sources() {
+    let overlaps = overlap_lines(
+        [
+            ("ours", vec!["10.0.0.1", "10.0.0.2", "192.168.0.1"]),
+            ("theirs", vec!["10.0.0.3", "10.0.0.2,7", "172.16.0.1"]),
+        ],
+        &GroupBy::Prefix(24),
+    )
+    .unwrap();
+    assert_eq!(
+        vec![Overlap {
+            group: "10.0.0.0/24".to_string(),
+            counts: vec![("ours".to_string(), 2), ("theirs".to_string(), 2)],
+        }],
+        overlaps
+    );
+    assert!(overlap_lines([("alone", vec!["10.0.0.1"])], &GroupBy::Subnet).is_err());
+}
+
+#[test]
+fn subnet_set_algebra() {
+    let ours = SubnetSet::from_lines(["10.0.0.0/24", "192.168.0.1"]).unwrap();
+    let theirs = SubnetSet::from_lines(["10.0.0.128/25", "10.0.1.0/24"]).unwrap();
+    let texts =
+        |set: SubnetSet| -> Vec<String> { set.subnets().iter().map(|s| s.to_string()).collect() };
+    assert_eq!(
+        vec!["10.0.0.0/23", "192.168.0.1/32"],
+        texts(SetOp::Union.apply(&ours, &theirs))
+    );
+    assert_eq!(vec!["10.0.0.128/25"], texts(ours.intersect(&theirs)));
+    assert_eq!(
+        vec!["10.0.0.0/25", "192.168.0.1/32"],
+        texts(ours.subtract(&theirs))
+    );
+    assert!(SetOp::from_str("xor").is_err());
+}
+
+#[test]
+fn subnet_set_odd_ranges() {
+    let all = SubnetSet::from_lines(["0.0.0.0/0"]).unwrap();
+    let hole = SubnetSet::from_lines(["0.0.0.1"]).unwrap();
+    let rest = all.subtract(&hole);
+    assert_eq!(32, rest.subnets().len());
+    assert!(!rest.contains(&Subnet::from_str("0.0.0.0/31").unwrap()));
+    assert!(rest.contains(&Subnet::from_str("255.255.255.255").unwrap()));
+    assert_eq!(all, rest.union(&hole));
+}
+
+#[test]
+fn density_of_groups() {
+    let addrs = vec![
+        "10.0.0.1/32".to_string(),
+        "10.0.0.128/25".to_string(),
+        "10.0.0.130/32".to_string(),
+    ];
+    let density = Density::of("10.0.0.0/24", &addrs).unwrap();
+    assert_eq!(129, density.present);
+    assert_eq!(256, density.size);
+    assert_eq!("129/256 addresses (50.4%)", density.to_string());
+    assert_eq!(
+        (1 << 24) + (1 << 20) + (1 << 16),
+        Density::of("private", &addrs).unwrap().size
+    );
+    let sizes: u64 = AddressClass::ALL.iter().map(AddressClass::size).sum();
+    assert_eq!(1 << 32, sizes);
+    assert!(Density::of("nonsense", &addrs).is_none());
+}
+
+#[test]
+fn block_rules_within_limits() {
+    let mut tree = AddressTree::new();
+    for addr in [
+        "10.0.0.0",
+        "10.0.0.1",
+        "10.0.0.2",
+        "10.0.0.3",
+        "10.0.0.8",
+        "192.168.0.1",
+    ] {
+        tree.push(Subnet::from_str(addr).unwrap()).unwrap();
+    }
+    let texts = |plan: &Plan| -> Vec<String> { plan.rules.iter().map(|s| s.to_string()).collect() };
+
+    let plan = tree.block_rules(&Target {
+        max_rules: Some(2),
+        max_collateral: 11,
+        ..Default::default()
+    });
+    assert_eq!(vec!["10.0.0.0/28", "192.168.0.1/32"], texts(&plan));
+    assert_eq!((6, 6, 11), (plan.covered, plan.observed, plan.collateral));
+
+    let plan = tree.block_rules(&Target {
+        min_coverage: Some(0.8),
+        ..Default::default()
+    });
+    assert_eq!(vec!["10.0.0.0/30", "192.168.0.1/32"], texts(&plan));
+    assert_eq!(0, plan.collateral);
+    assert!(plan.coverage() >= 0.8);
+}
+
+#[test]
+fn block_rules_of_nested_leafs() {
+    let mut tree = AddressTree::new();
+    for addr in ["10.0.0.1", "10.0.0.0/24"] {
+        tree.push(Subnet::from_str(addr).unwrap()).unwrap();
+    }
+    let plan = tree.block_rules(&Target::default());
+    assert_eq!(vec![Subnet::from_str("10.0.0.0/24").unwrap()], plan.rules);
+    assert_eq!(
+        (256, 256, 0),
+        (plan.covered, plan.observed, plan.collateral)
+    );
+}
+
+#[test]
+fn fail2ban_exports() {
+    let subnets = [
+        Subnet::from_str("10.0.0.0/24").unwrap(),
+        Subnet::from_str("192.168.0.1").unwrap(),
+    ];
+    assert_eq!(
+        "fail2ban-client set sshd banip 10.0.0.0/24\nfail2ban-client set sshd banip 192.168.0.1/32\n",
+        fail2ban_banip("sshd", &subnets).unwrap()
+    );
+    assert!(fail2ban_banip("sshd; rm -rf /", &subnets).is_err());
+    assert_eq!(
+        "[DEFAULT]\nignoreip = 127.0.0.1/8 ::1\n           10.0.0.0/24\n           192.168.0.1/32\n",
+        fail2ban_ignoreip(&subnets)
+    );
+}
+
+#[test]
+fn whitelist_splits_groups() {
+    let mut tree = AddressTree::new();
+    for addr in ["10.0.0.1", "10.0.0.2", "10.0.1.5", "10.0.2.0/30"] {
+        tree.push(Subnet::from_str(addr).unwrap()).unwrap();
+    }
+    let whitelist = SubnetSet::from_lines(["10.0.1.0/24"]).unwrap();
+    let subnets = tree.get_groups_map_except(&GroupBy::Subnet, &whitelist);
+    // 10.0.0.0/22 would enclose the whitelist
+    assert_eq!(
+        HashSet::from(["10.0.0.0/30", "10.0.1.5/32", "10.0.2.0/30"]),
+        subnets.keys().map(String::as_str).collect()
+    );
+    assert_eq!(vec!["10.0.1.5/32"], subnets["10.0.1.5/32"]);
+    let groups = tree.get_groups_map_except(&GroupBy::Prefix(16), &whitelist);
+    assert_eq!(4, groups.len());
+}
+
+#[test]
+fn set_compacted_to_max_entries() {
+    let set =
+        SubnetSet::from_lines(["10.0.0.0/30", "10.0.0.8", "10.0.0.9", "192.168.0.0/24"]).unwrap();
+    let plan = set.compact(2);
+    let texts: Vec<String> = plan.rules.iter().map(Subnet::to_string).collect();
+    assert_eq!(vec!["10.0.0.0/28", "192.168.0.0/24"], texts);
+    assert_eq!(
+        (262, 262, 10),
+        (plan.covered, plan.observed, plan.collateral)
+    );
+    assert_eq!(set.subnets(), set.compact(3).rules);
+    let plan = set.compact(1);
+    assert_eq!((1, 262), (plan.rules.len(), plan.covered));
+}
+
+#[test]
+fn block_rules_keep_off_whitelist() {
+    let mut tree = AddressTree::new();
+    for addr in ["10.0.0.0", "10.0.0.1", "10.0.0.2", "10.0.0.3"] {
+        tree.push(Subnet::from_str(addr).unwrap()).unwrap();
+    }
+    let plan = tree.block_rules(&Target {
+        max_collateral: 100,
+        whitelist: SubnetSet::from_lines(["10.0.0.3"]).unwrap(),
+        ..Default::default()
+    });
+    let rules: Vec<String> = plan.rules.iter().map(|s| s.to_string()).collect();
+    assert_eq!(vec!["10.0.0.0/31", "10.0.0.2/32"], rules);
+    assert_eq!(3, plan.covered);
+}
+
+#[test]
+fn tree_summary() {
+    let mut tree = AddressTree::new();
+    for addr in ["10.0.0.1", "10.0.0.2", "10.0.1.5", "192.168.0.0/24"] {
+        tree.push(Subnet::from_str(addr).unwrap()).unwrap();
+    }
+    let summary = tree.summary();
+    assert_eq!(4, summary.addresses);
+    assert_eq!(BTreeMap::from([(24, 1), (32, 3)]), summary.prefix_lengths);
+    assert_eq!(
+        vec![(8, 2), (16, 2), (24, 3)],
+        summary
+            .buckets
+            .iter()
+            .map(|b| (b.prefix, b.networks))
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(
+        Some(("10.0.0.0/24".to_string(), 2)),
+        summary.buckets[2].largest
+    );
+}
+
+#[test]
+fn subnet_set_gaps() {
+    let present = SubnetSet::from_lines(["10.0.0.0/25", "10.0.0.200"]).unwrap();
+    let gaps: Vec<String> = present
+        .gaps_in(&Subnet::from_str("10.0.0.0/24").unwrap())
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+    assert_eq!(
+        vec![
+            "10.0.0.128/26",
+            "10.0.0.192/29",
+            "10.0.0.201/32",
+            "10.0.0.202/31",
+            "10.0.0.204/30",
+            "10.0.0.208/28",
+            "10.0.0.224/27"
+        ],
+        gaps
+    );
+}
+
+#[test]
+fn labels_longest_prefix_match() {
+    let labels = Labels::from_lines([
+        "# networks",
+        "10.0.0.0/8 = corp",
+        "10.1.0.0/16 = lab",
+        "203.0.113.0/24 = partner-X",
+    ])
+    .unwrap();
+    let label_of = |s: &str| labels.label_of(&Subnet::from_str(s).unwrap());
+    assert_eq!(Some("corp"), label_of("10.2.0.1"));
+    assert_eq!(Some("lab"), label_of("10.1.2.0/24"));
+    assert_eq!(Some("corp"), label_of("10.0.0.0/8"));
+    assert_eq!(None, label_of("10.0.0.0/7"));
+    assert_eq!(None, label_of("192.168.0.1"));
+    assert!(Labels::from_lines(["10.0.0.0/8 corp"]).is_err());
+}
+
+#[test]
+fn compare_with_baseline() {
+    let grown = compare_lines(
+        ["10.0.0.1", "192.168.0.1", "192.168.0.2"],
+        [
+            "10.0.0.1",
+            "10.0.0.2",
+            "10.0.0.3",
+            "192.168.0.1",
+            "192.168.0.3",
+            "172.16.0.1",
+        ],
+        &GroupBy::Prefix(24),
+        2.0,
+    )
+    .unwrap();
+    assert_eq!(
+        vec![
+            Growth {
+                group: "172.16.0.0/24".to_string(),
+                baseline: 0,
+                current: 1
+            },
+            Growth {
+                group: "10.0.0.0/24".to_string(),
+                baseline: 1,
+                current: 3
+            },
+        ],
+        grown
+    );
+    assert!(compare_lines(["10.0.0.1"], ["10.0.0.1"], &GroupBy::Subnet, 0.5).is_err());
+}
+
+#[test]
+fn timed_lines_parsing() {
+    let (timestamp, addr) = parse_timed_line("2024-05-01T13:45:10Z 10.0.0.1").unwrap();
+    assert_eq!(1714571110, timestamp);
+    assert_eq!(Subnet::from_str("10.0.0.1").unwrap(), addr);
+    assert_eq!(
+        1714571110,
+        parse_timed_line("2024-05-01 13:45:10,10.0.0.1").unwrap().0
+    );
+    assert_eq!(
+        1714521600,
+        parse_timed_line("1714521600;10.0.0.1").unwrap().0
+    );
+    assert!(parse_timed_line("10.0.0.1").is_err());
+    assert!(parse_timed_line("2024-13-01 10.0.0.1").is_err());
+    assert!(parse_timed_line("2024-02-31 10.0.0.1").is_err());
+    assert!(parse_timed_line("2024-04-31 10.0.0.1").is_err());
+    assert!(parse_timed_line("2024-05-00 10.0.0.1").is_err());
+    assert!(parse_timed_line("2023-02-29 10.0.0.1").is_err());
+    assert!(parse_timed_line("1900-02-29 10.0.0.1").is_err());
+    assert!(parse_timed_line("2000-02-29 10.0.0.1").is_ok());
+    assert!(parse_timed_line("2024-02-29 10.0.0.1").is_ok());
+    assert_eq!("2024-02-29 23:00", TimeBucket::Hour.name_of(1709251199));
+    assert_eq!("1969-12-31", TimeBucket::Day.name_of(-1));
+}
+
+#[test]
+fn timed_lines_bucketing() {
+    let buckets = group_timed_lines(
+        [
+            "2024-05-01T13:45:00Z 10.0.0.1",
+            "2024-05-01T14:05:00Z 10.0.0.2",
+            "2024-05-02T01:00:00Z 192.168.0.1",
+        ],
+        &TimeBucket::Day,
+        &GroupBy::Prefix(24),
+    )
+    .unwrap();
+    assert_eq!(
+        vec!["2024-05-01", "2024-05-02"],
+        buckets.keys().collect::<Vec<_>>()
+    );
+    assert_eq!(2, buckets["2024-05-01"]["10.0.0.0/24"].len());
+}
+
+#[test]
+fn weighted_lines_parsing() {
+    let (addr, hits) = parse_weighted_line("10.0.0.1, 573").unwrap();
+    assert_eq!(Subnet::from_str("10.0.0.1").unwrap(), addr);
+    assert_eq!(573, hits);
+    assert_eq!(1, parse_weighted_line("10.0.0.1").unwrap().1);
+    assert!(parse_weighted_line("10.0.0.1,lots").is_err());
+    assert!(parse_weighted_line("10.0.0,5").is_err());
+}
+
+#[test]
+fn subnets_ranked_by_hits() {
+    let (subnets, weights) = weigh_lines(
+        [
+            "10.0.0.1,1",
+            "10.0.0.2,2",
+            "10.0.0.3",
+            "192.168.0.1,500",
+            "192.168.0.2",
+            "192.168.0.1,4",
+        ],
+        &GroupBy::Prefix(24),
+    )
+    .unwrap();
+    assert_eq!(4, weights["10.0.0.0/24"]);
+    assert_eq!(505, weights["192.168.0.0/24"]);
+    // repeated addresses add up their hits, but stay a single address
+    assert_eq!(2, subnets["192.168.0.0/24"].len());
+    assert_eq!(
+        vec!["10.0.0.0/24", "192.168.0.0/24"],
+        rank_subnets(&subnets)
+            .into_iter()
+            .map(|(s, _)| s)
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(
+        vec!["192.168.0.0/24", "10.0.0.0/24"],
+        rank_weighted(&subnets, &weights)
+            .into_iter()
+            .map(|(s, _)| s)
+            .collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn metadata_csv_join() {
+    let metadata = Metadata::from_lines([
+        "ip,username,device",
+        "# exported from the inventory",
+        "10.0.0.1, alice, \"laptop, black\"",
+        "10.0.0.2,,\"the \"\"server\"\"\"",
+        "10.0.0.3,bob",
+    ])
+    .unwrap();
+    let describe = |s: &str| metadata.describe(&Subnet::from_str(s).unwrap());
+    assert_eq!(["username", "device"], metadata.columns());
+    assert_eq!(
+        Some("username=alice, device=laptop, black".to_string()),
+        describe("10.0.0.1")
+    );
+    assert_eq!(
+        Some("device=the \"server\"".to_string()),
+        describe("10.0.0.2")
+    );
+    assert_eq!(Some("username=bob".to_string()), describe("10.0.0.3"));
+    assert_eq!(None, describe("10.0.0.4"));
+    assert_eq!(None, describe("10.0.0.0/24"));
+    assert!(Metadata::from_lines(["ip,user", "10.0.0.0/24,alice"]).is_err());
+    assert!(Metadata::from_lines(["ip,user", "10.0.0.1,alice,laptop"]).is_err());
+}
+
+#[test]
+fn ndjson_subnet_lines() {
+    let subnets = group_lines(
+        ["10.0.0.1", "10.0.0.2", "192.168.0.1"],
+        &GroupBy::Prefix(24),
+    )
+    .unwrap();
+    let labels = Labels::from_lines(["10.0.0.0/8 = \"corp\" net"]).unwrap();
+    let metadata = Metadata::new();
+    let report = Report::new(&subnets, &labels, &metadata).top(Some(1));
+    let mut out = vec![];
+    write_ndjson_subnets(&report, &mut out).unwrap();
+    assert_eq!(
+        "{\"subnet\":\"10.0.0.0/24\",\"label\":\"\\\"corp\\\" net\",\"kind\":null,\"class\":\"private\",\"count\":2,\"hits\":null,\"score\":null,\"sources\":null,\"addresses\":[\"10.0.0.1/32\",\"10.0.0.2/32\"]}\n",
+        String::from_utf8(out).unwrap()
+    );
+}
+
+#[test]
+fn json_document_nests_subnets() {
+    let mut subnets = group_lines(["10.0.1.1", "10.0.1.2"], &GroupBy::Prefix(24)).unwrap();
+    subnets.insert("10.0.0.0/16".to_string(), vec!["10.0.2.1/32".to_string()]);
+    let metadata = Metadata::from_lines(["ip,asn", "10.0.1.1,64500", "10.0.1.2,64500"]).unwrap();
+    let address_hits = WeightsMap::from([("10.0.1.1/32".to_string(), 3)]);
+    let labels = Labels::new();
+    let report = Report::new(&subnets, &labels, &metadata).address_hits(Some(&address_hits));
+    let mut out = vec![];
+    write_json(&report, &mut out).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    assert!(out.starts_with(&format!(
+        "{{\"version\":{},\"generator\":\"ipv4-classify {}\",\"columns\":[\"asn\"],\"count\":3,\"subnets\":[{{\"subnet\":\"10.0.0.0/16\"",
+        JSON_VERSION,
+        env!("CARGO_PKG_VERSION")
+    )));
+    assert!(out.contains(
+        "\"addresses\":[{\"address\":\"10.0.2.1/32\",\"class\":\"private\",\"hits\":null,\"sources\":null,\"metadata\":null}],\"subnets\":[{\"subnet\":\"10.0.1.0/24\""
+    ));
+    assert!(out.contains("\"metadata\":{\"asn\":\"64500\",\"country\":null,\"org\":null}"));
+    assert!(out.contains(
+        "{\"address\":\"10.0.1.1/32\",\"class\":\"private\",\"hits\":3,\"sources\":null,\"metadata\":{\"asn\":\"64500\"}}"
+    ));
+    assert!(out.ends_with("\"subnets\":[]}]}]}\n"));
+}
+
+#[cfg(feature = "schema")]
+#[test]
+fn json_schema_matches_outputs() {
+    let subnets = group_lines(["10.0.0.1", "10.0.0.2"], &GroupBy::Prefix(24)).unwrap();
+    let (labels, metadata) = (Labels::new(), Metadata::new());
+    let report = Report::new(&subnets, &labels, &metadata);
+    let keys = |value: &serde_json::Value| {
+        let mut keys: Vec<String> = value.as_object().unwrap().keys().cloned().collect();
+        keys.sort();
+        keys
+    };
+    let required = |schema: &serde_json::Value| {
+        let mut required: Vec<String> = serde_json::from_value(schema["required"].clone()).unwrap();
+        required.sort();
+        required
+    };
+    let schema: serde_json::Value =
+        serde_json::from_str(&json_schema(&OutputFormat::Json).unwrap()).unwrap();
+    let mut out = vec![];
+    write_json(&report, &mut out).unwrap();
+    let document: serde_json::Value = serde_json::from_slice(&out).unwrap();
+    assert_eq!(required(&schema), keys(&document));
+    let subnet = &document["subnets"][0];
+    assert_eq!(required(&schema["$defs"]["JsonSubnet"]), keys(subnet));
+    assert_eq!(
+        required(&schema["$defs"]["JsonAddress"]),
+        keys(&subnet["addresses"][0])
+    );
+
+    let schema: serde_json::Value =
+        serde_json::from_str(&json_schema(&OutputFormat::NdjsonSubnets).unwrap()).unwrap();
+    let mut out = vec![];
+    write_ndjson_subnets(&report, &mut out).unwrap();
+    let line: serde_json::Value = serde_json::from_slice(&out).unwrap();
+    assert_eq!(required(&schema), keys(&line));
+    assert!(json_schema(&OutputFormat::Text).is_err());
+}
+
+#[test]
+fn exporters_registered_by_name() {
+    struct Count;
+    impl Exporter for Count {
+        fn name(&self) -> &str {
+            "count"
+        }
+
+        fn write(&self, report: &Report, out: &mut dyn std::io::Write) -> Result<(), Error> {
+            Ok(writeln!(out, "{}", report.subnets.len())?)
+        }
+    }
+
+    let subnets = group_lines(["10.0.0.1", "192.168.0.1"], &GroupBy::Prefix(24)).unwrap();
+    let (labels, metadata) = (Labels::new(), Metadata::new());
+    let report = Report::new(&subnets, &labels, &metadata);
+    let mut exporters = Exporters::new();
+    assert!(exporters
+        .names()
+        .any(|name| name == OutputFormat::Geojson.name()));
+    assert!(exporters.get("count").is_none());
+    exporters.register(Count);
+    let mut out = vec![];
+    exporters.write("count", &report, &mut out).unwrap();
+    assert_eq!("2\n", String::from_utf8(out).unwrap());
+
+    let mut out = vec![];
+    exporters
+        .write(OutputFormat::NdjsonSubnets.name(), &report, &mut out)
+        .unwrap();
+    let mut expected = vec![];
+    write_ndjson_subnets(&report, &mut expected).unwrap();
+    assert_eq!(expected, out);
+    assert!(exporters.write("xml", &report, &mut vec![]).is_err());
+}
+
+#[test]
+fn country_summary_table() {
+    let subnets = group_lines(
+        ["10.0.0.1", "10.0.0.2", "10.0.1.1", "192.168.0.1"],
+        &GroupBy::Prefix(24),
+    )
+    .unwrap();
+    let metadata =
+        Metadata::from_lines(["ip,country", "10.0.0.1,NL", "10.0.0.2,NL", "10.0.1.1,NL"]).unwrap();
+    let labels = Labels::new();
+    let report = Report::new(&subnets, &labels, &metadata).top(Some(1));
+    let summary = report.country_summary();
+    assert_eq!(
+        vec![(Some("NL".to_string()), 3, 2), (None, 1, 1)],
+        summary
+            .countries
+            .iter()
+            .map(|c| (c.name.clone(), c.addresses, c.subnets))
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(
+        "country       addresses  subnets   share\n\
+         NL                    3        2   75.0%\n\
+         unknown               1        1   25.0%\n\
+         total                 4        3  100.0%",
+        summary.to_string()
+    );
+}
+
+#[test]
+fn subnets_clustered_by_org() {
+    assert_eq!("Example", normalize_org(" Example, Inc. "));
+    assert_eq!("Hoster Group", normalize_org("Hoster  Group B.V."));
+    assert_eq!("Co", normalize_org("Co"));
+    let subnets = group_lines(
+        ["10.0.0.1", "10.0.0.2", "10.0.1.1", "192.168.0.1"],
+        &GroupBy::Prefix(24),
+    )
+    .unwrap();
+    let metadata = Metadata::from_lines([
+        "ip,org",
+        "10.0.0.1,\"Example, Inc.\"",
+        "10.0.0.2,Example Inc",
+        "10.0.1.1,EXAMPLE LLC",
+    ])
+    .unwrap();
+    let labels = Labels::new();
+    let report = Report::new(&subnets, &labels, &metadata);
+    let orgs: Vec<String> = report.org_clusters().iter().map(Org::to_string).collect();
+    assert_eq!(
+        vec![
+            "Example: 3 addresses in 2 subnets\n\t10.0.0.0/24\n\t10.0.1.0/24",
+            "unknown: 1 addresses in 1 subnets\n\t192.168.0.0/24",
+        ],
+        orgs
+    );
+}
+
+#[test]
+fn geojson_point_per_located_subnet() {
+    let subnets = group_lines(
+        ["10.0.0.1", "10.0.0.2", "192.168.0.1"],
+        &GroupBy::Prefix(24),
+    )
+    .unwrap();
+    let metadata = Metadata::from_lines([
+        "ip,lat,lon,asn,org,asn_type",
+        "10.0.0.1,52,4,64500,\"Example, Inc\",hosting",
+        "10.0.0.2,53,5,64500,,isp",
+    ])
+    .unwrap();
+    let labels = Labels::new();
+    let report = Report::new(&subnets, &labels, &metadata);
+    let mut out = vec![];
+    geojson::write_geojson(&report, &mut out).unwrap();
+    assert_eq!(
+        "{\"type\":\"FeatureCollection\",\"features\":[\n\
+         {\"type\":\"Feature\",\"geometry\":{\"type\":\"Point\",\"coordinates\":[4.5,52.5]},\
+         \"properties\":{\"cidr\":\"10.0.0.0/24\",\"count\":2,\"asn\":\"64500\",\"org\":\"Example, Inc\",\"kind\":\"hosting\",\"class\":\"private\",\"score\":null}}\n]}\n",
+        String::from_utf8(out).unwrap()
+    );
+}
+
+#[test]
+fn network_kind_of_addresses() {
+    let metadata = Metadata::from_lines([
+        "ip,privacy.hosting,mobile,company.type",
+        "10.0.0.1,true,,isp",
+        "10.0.0.2,,yes,",
+        "10.0.0.3,false,,ISP",
+        "10.0.0.4,,,edu",
+        "10.0.0.5,,,",
+    ])
+    .unwrap();
+    let kinds: Vec<Option<NetworkKind>> = (1..=5)
+        .map(|i| NetworkKind::of(&metadata, &Subnet::new(10, 0, 0, i, 32).unwrap()))
+        .collect();
+    assert_eq!(
+        vec![
+            Some(NetworkKind::Hosting),
+            Some(NetworkKind::Mobile),
+            Some(NetworkKind::Residential),
+            Some(NetworkKind::Business),
+            None
+        ],
+        kinds
+    );
+    let addrs = ["10.0.0.5", "10.0.0.3", "10.0.0.1", "10.0.0.3"].map(String::from);
+    assert_eq!(
+        Some(NetworkKind::Residential),
+        NetworkKind::of_most(&metadata, &addrs)
+    );
+}
+
+#[test]
+fn abuse_reports_by_contact() {
+    let mut contacts = AbuseContacts::from_lines([
+        "# from the last incident",
+        "203.0.113.0/24 = Example Org <abuse@example.org>",
+        "203.0.113.128/25 = Example Hosting <noc@example.net>",
+    ])
+    .unwrap();
+    let abuse = contacts
+        .report_lines(
+            [
+                "203.0.113.200",
+                "203.0.113.5,10",
+                "198.51.100.1",
+                "203.0.113.5",
+            ],
+            [
+                "sshd: Failed password for root from 203.0.113.5 port 22",
+                "sshd: Accepted publickey for admin from 192.168.0.10",
+            ],
+        )
+        .unwrap();
+    assert_eq!(
+        vec![Subnet::from_str("198.51.100.1").unwrap()],
+        abuse.unresolved
+    );
+    assert_eq!(2, abuse.reports.len());
+    let template = "To: {email}\n{count} of {netblock} ({org}):\n{addresses}\n{evidence}";
+    assert_eq!(
+        "To: abuse@example.org\n1 of 203.0.113.0/24 (Example Org):\n203.0.113.5/32\nsshd: Failed password for root from 203.0.113.5 port 22",
+        abuse.reports[0].render(template)
+    );
+    assert_eq!(
+        "To: noc@example.net\n1 of 203.0.113.128/25 (Example Hosting):\n203.0.113.200/32\n",
+        abuse.reports[1].render(template)
+    );
+    assert!(AbuseContacts::from_lines(["203.0.113.0/24 = no email"]).is_err());
+}
+
+#[cfg(feature = "whois")]
+#[test]
+fn abuse_contact_of_whois_answer() {
+    let ripe = "inetnum:        193.0.0.0 - 193.0.7.255
+netname:        RIPE-NCC
+descr:          RIPE Network Coordination Centre
+% Abuse contact for '193.0.0.0 - 193.0.7.255' is 'abuse@ripe.net'
+";
+    assert_eq!(
+        Some(AbuseContact {
+            netblock: Subnet::from_str("193.0.0.0/21").unwrap(),
+            org: "RIPE Network Coordination Centre".to_string(),
+            email: "abuse@ripe.net".to_string(),
+        }),
+        abuse::parse_whois(ripe, &Subnet::from_str("193.0.0.1").unwrap())
+    );
+    // an unaligned range is split into the blocks within it
+    let unaligned = "inetnum:        185.1.2.0 - 185.1.5.255
+descr:          Example Hosting
+abuse-mailbox:  abuse@example.net
+";
+    let netblock_of = |addr: &str| {
+        abuse::parse_whois(unaligned, &Subnet::from_str(addr).unwrap()).map(|c| c.netblock)
+    };
+    assert_eq!(
+        Subnet::from_str("185.1.2.0/23").ok(),
+        netblock_of("185.1.3.9")
+    );
+    assert_eq!(
+        Subnet::from_str("185.1.4.0/23").ok(),
+        netblock_of("185.1.4.7")
+    );
+    assert_eq!(None, netblock_of("185.1.6.1"));
+    // nothing is sent to the server with no quota, the address is left without a contact
+    let whois = Whois::new("127.0.0.1:1").with_rate_limit(RateLimit::from_str("0/day").unwrap());
+    let mut contacts = AbuseContacts::new().with_whois(whois);
+    assert_eq!(
+        None,
+        contacts
+            .contact_of(&Subnet::from_str("193.0.0.1").unwrap())
+            .unwrap()
+    );
+}
+
+#[cfg(feature = "whois")]
+#[test]
+fn whois_network_fills_metadata() {
+    let addr = Subnet::from_str("193.0.0.1").unwrap();
+    let ripe = "inetnum:        193.0.0.0 - 193.0.7.255
+netname:        RIPE-NCC
+descr:          RIPE Network Coordination Centre
+country:        NL
+";
+    assert_eq!(
+        Some(WhoisNetwork {
+            netblock: Subnet::from_str("193.0.0.0/21").unwrap(),
+            netname: Some("RIPE-NCC".to_string()),
+            country: Some("NL".to_string()),
+            org: Some("RIPE Network Coordination Centre".to_string()),
+        }),
+        whois::parse_network(ripe, &addr)
+    );
+    assert_eq!(
+        None,
+        whois::parse_network(ripe, &Subnet::from_str("10.0.0.1").unwrap())
+    );
+    // only the aligned block is remembered, not the range's supernet
+    let unaligned = "NetRange:       185.1.2.0 - 185.1.5.255
+NetName:        EXAMPLE
+";
+    assert_eq!(
+        Subnet::from_str("185.1.4.0/23").ok(),
+        whois::parse_network(unaligned, &Subnet::from_str("185.1.5.1").unwrap())
+            .map(|network| network.netblock)
+    );
+
+    let mut metadata = Metadata::from_lines(["ip,country", "10.0.0.1,DE"]).unwrap();
+    metadata.set(&addr, "country", "NL");
+    metadata.set(&addr, "netname", "RIPE-NCC");
+    metadata.set(&Subnet::from_str("10.0.0.0/8").unwrap(), "country", "US");
+    assert_eq!(["country", "netname"], metadata.columns());
+    assert_eq!(Some("NL"), metadata.value_of(&addr, "country"));
+    assert_eq!(Some("RIPE-NCC"), metadata.value_of(&addr, "netname"));
+    let other = Subnet::from_str("10.0.0.1").unwrap();
+    assert_eq!(Some("DE"), metadata.value_of(&other, "country"));
+    assert_eq!(None, metadata.value_of(&other, "netname"));
+}
+
+#[test]
+fn ip_metadata_of_columns() {
+    let addr = Subnet::from_str("203.0.113.5").unwrap();
+    let mut metadata = Metadata::from_lines([
+        "ip,asn,country_code,ISP,privacy.hosting,vpn",
+        "203.0.113.5,AS64500 Example,NL,Example Hosting,true,false",
+    ])
+    .unwrap();
+    assert_eq!(
+        Some(IpMetadata {
+            asn: Some(64500),
+            country: Some("NL".to_string()),
+            org: Some("Example Hosting".to_string()),
+            privacy: Privacy {
+                hosting: true,
+                ..Privacy::default()
+            },
+            ..IpMetadata::default()
+        }),
+        metadata.ip_metadata_of(&addr)
+    );
+    assert_eq!(
+        None,
+        metadata.ip_metadata_of(&Subnet::from_str("10.0.0.1").unwrap())
+    );
+
+    metadata.fill(
+        &addr,
+        &IpMetadata {
+            country: Some("DE".to_string()),
+            city: Some("Amsterdam".to_string()),
+            is_anycast: true,
+            ..IpMetadata::default()
+        },
+    );
+    let ip = metadata.ip_metadata_of(&addr).unwrap();
+    assert_eq!(Some("NL".to_string()), ip.country);
+    assert_eq!(Some("Amsterdam".to_string()), ip.city);
+    assert!(ip.is_anycast);
+}
+
+#[test]
+fn prefix_cache_answers_for_the_prefix() {
+    let mut cache = PrefixCache::new();
+    let addr = |s: &str| Subnet::from_str(s).unwrap();
+    assert!(!cache.insert(&IpMetadata::default()));
+    assert!(cache.insert(&IpMetadata {
+        asn: Some(3333),
+        announced_prefix: Some(addr("193.0.0.0/21")),
+        hostname: Some("www.ripe.net".to_string()),
+        ..IpMetadata::default()
+    }));
+    assert_eq!(1, cache.len());
+
+    let ip = cache.get(&addr("193.0.7.1")).unwrap();
+    assert_eq!(Some(3333), ip.asn);
+    assert_eq!(None, ip.hostname);
+    assert_eq!(None, cache.get(&addr("193.0.8.1")));
+    assert_eq!((1, 1), (cache.hits(), cache.misses()));
+}
+
+#[test]
+fn rate_limits_of_providers() {
+    let limits =
+        RateLimits::from_lines(["# paid plan", "RIPEstat = 20/s 2/day", "whois = 0.5/s"]).unwrap();
+    let limit = limits.get("ripestat");
+    assert_eq!("20/s 2/day", limit.to_string());
+    assert_eq!(Some(0.5), limits.get("whois").per_second);
+    assert_eq!(RateLimit::default(), limits.get("ipinfo"));
+    assert!(RateLimits::from_lines(["whois 1/s"]).is_err());
+    assert!(RateLimits::from_lines(["whois = 0/s"]).is_err());
+    assert!(RateLimits::from_lines(["whois = 1/min"]).is_err());
+    // the wait between requests would overflow
+    assert!(RateLimit::from_str("1e-300/s").is_err());
+    assert!(RateLimit::from_str("5e-324/s").is_err());
+    assert!(RateLimit::from_str("1e-6/s").is_ok());
+
+    #[cfg(any(feature = "whois", feature = "ripestat"))]
+    {
+        let mut limiter = rate::Limiter::new("ripestat", limit);
+        let started = std::time::Instant::now();
+        limiter.acquire().unwrap();
+        limiter.acquire().unwrap();
+        assert!(started.elapsed() >= std::time::Duration::from_millis(50));
+        assert!(matches!(
+            limiter.acquire(),
+            Err(Error::QuotaExceeded(provider)) if provider == "ripestat"
+        ));
+        let by_hand = RateLimit {
+            per_second: Some(1e-300),
+            per_day: None,
+        };
+        let mut limiter = rate::Limiter::new("whois", by_hand);
+        limiter.acquire().unwrap();
+        assert!(matches!(limiter.acquire(), Err(Error::InvalidArgument(_))));
+    }
+}
+
+#[cfg(feature = "whois")]
+#[test]
+fn enrichment_decisions_logged_as_json() {
+    let addr = |s: &str| Subnet::from_str(s).unwrap();
+    let mut metadata =
+        Metadata::from_lines(["ip,netname,country,org", "193.0.0.1,RIPE-NCC,NL,RIPE NCC"]).unwrap();
+    // nothing is sent to the server with no quota
+    let mut whois = Whois::new("whois.invalid").with_rate_limit(RateLimit {
+        per_day: Some(0),
+        ..RateLimit::default()
+    });
+    let mut out = vec![];
+    let mut log = JsonLog::new(&mut out);
+    let addrs = [addr("193.0.0.1"), addr("10.0.0.1"), addr("10.0.0.2")];
+    whois
+        .enrich_observed(&mut metadata, &addrs, &mut log)
+        .unwrap();
+    log.finish().unwrap();
+    assert_eq!(
+        r#"{"provider":"whois","address":"193.0.0.1/32","cached":false,"verdict":"known","error":null}
+{"provider":"whois","address":"10.0.0.1/32","cached":false,"verdict":"quota-exceeded","error":null}
+"#,
+        String::from_utf8(out).unwrap()
+    );
+}
+
+#[test]
+fn rpz_rules_of_subnets() {
+    let rpz = |lines: &[&str]| {
+        let subnets = group_lines(lines, &GroupBy::Subnet).unwrap();
+        let (labels, metadata) = (Labels::new(), Metadata::new());
+        let report = Report::new(&subnets, &labels, &metadata);
+        let mut out = vec![];
+        rpz::write_rpz(&report, 7, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    };
+    let header = "$TTL 300
+@ IN SOA localhost. root.localhost. (7 3600 600 86400 300)
+  IN NS localhost.
+";
+    assert_eq!(
+        format!(
+            "{}; 203.0.113.0/28, 2 addresses, bogon\n28.0.113.0.203.rpz-ip CNAME .\n",
+            header
+        ),
+        rpz(&["203.0.113.1", "203.0.113.9"])
+    );
+    // scattered addresses make up 0.0.0.0/0, which must not sinkhole everything
+    assert_eq!(
+        format!(
+            "{}; 0.0.0.0/0, 2 addresses\n32.1.0.0.10.rpz-ip CNAME .\n32.1.113.0.203.rpz-ip CNAME .\n",
+            header
+        ),
+        rpz(&["10.0.0.1", "203.0.113.1"])
+    );
+}
+
+#[test]
+fn routing_table_longest_prefix_match() {
+    let table = RoutingTable::from_lines([
+        "# core",
+        "10.0.0.0/8 via gw1",
+        "10.1.0.0/16",
+        "10.1.0.0/16",
+        "192.168.0.0/24",
+    ])
+    .unwrap();
+    assert_eq!(3, table.len());
+    let routed = table
+        .route_lines(["10.1.2.3", "10.2.0.1", "10.1.0.1", "172.16.0.1", "10.1.2.3"])
+        .unwrap();
+    let prefix = |s: &str| Subnet::from_str(s).unwrap();
+    assert_eq!(
+        vec![
+            (prefix("10.0.0.0/8"), vec![prefix("10.2.0.1")]),
+            (
+                prefix("10.1.0.0/16"),
+                vec![prefix("10.1.0.1"), prefix("10.1.2.3")]
+            ),
+        ],
+        routed.prefixes
+    );
+    assert_eq!(vec![prefix("172.16.0.1")], routed.unmatched);
+    assert!(RoutingTable::from_lines(["10.0.0.0/33"]).is_err());
+}
+
+#[test]
+fn routing_table_of_mrt_dump() {
+    let record = |kind: u16, subtype: u16, body: &[u8]| {
+        let mut record = vec![0, 0, 0, 0];
+        record.extend(kind.to_be_bytes());
+        record.extend(subtype.to_be_bytes());
+        record.extend((body.len() as u32).to_be_bytes());
+        record.extend(body);
+        record
+    };
+    let mut dump = record(13, 1, &[1, 2, 3, 4]); // peer index table, skipped
+                                                 // sequence number, 193.0.0.0/21 in 3 octets, no entries
+    dump.extend(record(13, 2, &[0, 0, 0, 1, 21, 193, 0, 0, 0, 0]));
+    // view, sequence number, 10.0.0.0/8, status, ...
+    dump.extend(record(12, 1, &[0, 0, 0, 1, 10, 0, 0, 0, 8, 1, 0, 0]));
+    let table = RoutingTable::from_mrt(dump.as_slice()).unwrap();
+    assert_eq!(2, table.len());
+    let prefix = |s: &str| Subnet::from_str(s).unwrap();
+    assert_eq!(
+        Some(prefix("193.0.0.0/21")),
+        table.lookup(&prefix("193.0.7.1"))
+    );
+    assert_eq!(
+        Some(prefix("10.0.0.0/8")),
+        table.lookup(&prefix("10.1.2.3"))
+    );
+
+    dump.truncate(dump.len() - 1);
+    assert!(RoutingTable::from_mrt(dump.as_slice()).is_err());
+}
+
+#[test]
+fn subnets_below_min_count_dropped() {
+    let mut subnets = group_lines(
+        ["10.0.0.1", "10.0.0.2", "10.0.0.3", "192.168.0.1"],
+        &GroupBy::Prefix(24),
+    )
+    .unwrap();
+    retain_min_count(&mut subnets, 2);
+    assert_eq!(vec!["10.0.0.0/24"], subnets.keys().collect::<Vec<_>>());
+}
+
+#[test]
+fn special_ranges_kept_apart() {
+    // 100.64.0.0/10 would otherwise merge with 100.0.0.0/30 into 100.0.0.0/9
+    let subnets = classify_lines(["100.0.0.1", "100.0.0.2", "100.64.0.1", "100.64.0.2"]).unwrap();
+    assert_eq!(
+        HashSet::from(["100.0.0.0/30", "100.64.0.0/30"]),
+        subnets.keys().map(String::as_str).collect()
+    );
+    let groups = group_lines(
+        ["100.0.0.1", "100.64.0.1", "224.0.0.1", "224.1.0.1"],
+        &GroupBy::Prefix(8),
+    )
+    .unwrap();
+    assert_eq!(vec!["100.0.0.1/32"], groups["100.0.0.0/8"]);
+    assert_eq!(vec!["100.64.0.1/32"], groups["100.64.0.0/10"]);
+    assert_eq!(2, groups["224.0.0.0/8"].len());
+    assert_eq!(
+        Some("multicast"),
+        Subnet::from_str("239.1.2.3").unwrap().special_range()
+    );
+    assert_eq!(
+        None,
+        Subnet::from_str("100.128.0.1").unwrap().special_range()
+    );
+}
+
+#[test]
+fn special_address_splits_its_subnet() {
+    let mut tree = AddressTree::new();
+    tree.extend_sorted(
+        ["10.0.0.1", "10.0.0.2", "224.0.0.1"]
+            .into_iter()
+            .map(|s| Subnet::from_str(s).unwrap()),
+    )
+    .unwrap();
+    let subnets = tree.get_subnets_map();
+    assert_eq!(vec!["224.0.0.1/32"], subnets["224.0.0.1/32"]);
+    assert_eq!(2, subnets["10.0.0.0/30"].len());
+}
+
+#[test]
+fn boundaries_never_spanned() {
+    let subnet = |s: &str| Subnet::from_str(s).unwrap();
+    let mut tree = AddressTree::with_boundaries([subnet("10.0.0.0/25")]);
+    for addr in ["10.0.0.1", "10.0.0.2", "10.0.0.200", "10.0.0.201"] {
+        tree.push(subnet(addr)).unwrap();
+    }
+    let subnets = tree.get_subnets_map();
+    assert_eq!(
+        HashSet::from(["10.0.0.0/30", "10.0.0.200/31"]),
+        subnets.keys().map(String::as_str).collect()
+    );
+    let groups = tree.get_groups_map(&GroupBy::Prefix(24));
+    assert_eq!(2, groups["10.0.0.0/25"].len());
+    assert_eq!(2, groups["10.0.0.0/24"].len());
+}
+
+#[cfg(feature = "serve")]
+#[test]
+fn http_api_requests() {
+    use tiny_http::Method;
+
+    let mut tree = AddressTree::new();
+    let mut metrics = serve::Metrics::default();
+    let (status, body) = serve::respond(
+        &mut tree,
+        &mut metrics,
+        &Method::Post,
+        "/addresses",
+        r#"["10.0.0.1", "10.0.0.2,5"]"#,
+    );
+    assert_eq!((200, 2), (status, body["added"].as_u64().unwrap()));
+    let (status, body) = serve::respond(
+        &mut tree,
+        &mut metrics,
+        &Method::Get,
+        "/lookup/10.0.0.3",
+        "",
+    );
+    assert_eq!(200, status);
+    assert_eq!("10.0.0.0/30", body["subnet"]);
+    assert_eq!(false, body["known"]);
+    let (_, body) = serve::respond(&mut tree, &mut metrics, &Method::Get, "/subnets", "");
+    assert_eq!(2, body["10.0.0.0/30"].as_array().unwrap().len());
+    // a bad address rejects the whole list
+    let (status, _) = serve::respond(
+        &mut tree,
+        &mut metrics,
+        &Method::Post,
+        "/addresses",
+        r#"["10.0.0.9", "x"]"#,
+    );
+    assert_eq!(400, status);
+    assert!(!tree.contains(&Subnet::from_str("10.0.0.9").unwrap()));
+    assert_eq!(
+        404,
+        serve::respond(&mut tree, &mut metrics, &Method::Delete, "/subnets", "").0
+    );
+    let body = "1".repeat(serve::MAX_BODY_LEN as usize);
+    assert_eq!(Ok(body.clone()), serve::read_body(body.as_bytes()));
+    let body = body + "1";
+    assert_eq!(413, serve::read_body(body.as_bytes()).err().unwrap().0);
+    let text = metrics.render(&tree);
+    assert!(text.contains("\nipv4_classify_addresses_ingested_total 2\n"));
+    assert!(text.contains("\nipv4_classify_lookups_served_total 1\n"));
+    assert!(text.contains("\nipv4_classify_requests_failed_total 2\n"));
+    assert!(text.contains("\nipv4_classify_subnets 1\n"));
+}
+
+#[cfg(feature = "ripestat")]
+#[test]
+fn ripestat_network_info() {
+    let body = r#"{"status": "ok", "data": {"asns": ["3333"], "prefix": "193.0.0.0/21"}}"#;
+    assert_eq!(
+        Some(Announcement {
+            prefix: Subnet::from_str("193.0.0.0/21").unwrap(),
+            origins: vec![3333],
+        }),
+        ripestat::parse_network_info(body).unwrap()
+    );
+    let body = r#"{"status": "ok", "data": {"asns": [], "prefix": ""}}"#;
+    assert_eq!(None, ripestat::parse_network_info(body).unwrap());
+    assert!(ripestat::parse_network_info(r#"{"data": {}}"#).is_err());
+}
+
+#[cfg(feature = "serve")]
+#[test]
+fn syslog_messages_feed_addresses() {
+    use std::{
+        io::Write,
+        net::{TcpStream, UdpSocket},
+        thread,
+        time::{Duration, Instant},
+    };
+
+    let addrs = |message| {
+        extract_addresses(message)
+            .iter()
+            .map(Subnet::to_string)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        vec!["203.0.113.5/32", "10.0.0.7/32"],
+        addrs("<134>Oct  7 10:00:00 192.168.1.1 asa: Deny tcp src outside:203.0.113.5/4431 dst inside:10.0.0.7/22.")
+    );
+    assert_eq!(
+        vec!["198.51.100.2/32"],
+        addrs("<165>1 2024-10-11T22:14:15.003Z 10.1.1.1 sshd 4123 - - Failed password from 198.51.100.2 port 22")
+    );
+    assert!(addrs("version 1.2.3.4.5 and 300.1.1.1").is_empty());
+
+    let token = CancelToken::new();
+    let listener = SyslogListener::bind("127.0.0.1:0", &token).unwrap();
+    let udp = UdpSocket::bind("127.0.0.1:0").unwrap();
+    udp.send_to(b"drop from 10.0.0.1", listener.local_addr())
+        .unwrap();
+    let mut tcp = TcpStream::connect(listener.local_addr()).unwrap();
+    tcp.write_all(b"drop from 10.0.0.2\nno address\ndrop from 10.0.0.3\n")
+        .unwrap();
+    // the head of a too long line is a message, the rest is dropped
+    let long_line = format!("drop from 10.0.0.4 {} 10.0.0.5\n", "x".repeat(1 << 17));
+    tcp.write_all(long_line.as_bytes()).unwrap();
+    drop(tcp);
+    let mut received = vec![];
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while received.len() < 5 && Instant::now() < deadline {
+        received.extend(listener.received());
+        thread::sleep(Duration::from_millis(10));
+    }
+    token.cancel();
+    received.sort_by_key(|addrs| addrs.first().map(Subnet::to_string));
+    assert_eq!(
+        vec![
+            vec![],
+            vec![Subnet::from_str("10.0.0.1").unwrap()],
+            vec![Subnet::from_str("10.0.0.2").unwrap()],
+            vec![Subnet::from_str("10.0.0.3").unwrap()],
+            vec![Subnet::from_str("10.0.0.4").unwrap()],
+        ],
+        received
+    );
+}
+
+#[test]
+fn first_and_last_seen_across_runs() {
+    const DAY: i64 = 86_400;
+    let addr = |s: &str| Subnet::from_str(s).unwrap();
+    let mut tree = AddressTree::new();
+    tree.push(addr("10.0.0.1")).unwrap();
+    tree.track_seen(10 * DAY);
+    tree.push(addr("10.0.0.2")).unwrap();
+    #[cfg(feature = "persist")]
+    let mut tree = AddressTree::from_bytes(&tree.to_bytes()).unwrap();
+    tree.track_seen(13 * DAY);
+    tree.push(addr("10.0.0.2")).unwrap();
+    tree.push(addr("192.168.0.1")).unwrap();
+
+    let seen = tree.get_seen_map(&GroupBy::Prefix(24));
+    assert_eq!(
+        Seen {
+            first: 10 * DAY,
+            last: 13 * DAY
+        },
+        seen["10.0.0.0/24"]
+    );
+    assert_eq!(3, seen["10.0.0.0/24"].age_days());
+    assert_eq!(0, seen["192.168.0.0/24"].age_days());
+    // addresses pushed before tracking started have no stamps
+    assert!(!tree
+        .get_seen_map(&GroupBy::Subnet)
+        .contains_key("10.0.0.1/32"));
+}
+
+#[test]
+fn tree_coarsened_within_memory_limit() {
+    let mut tree = AddressTree::new();
+    let empty = tree.memory_used();
+    tree.limit_memory(empty * 200);
+    for i in 0..1024u32 {
+        let [_, _, o3, o4] = i.to_be_bytes();
+        tree.push(Subnet::new(10, 0, o3, o4, 32).unwrap()).unwrap();
+    }
+    assert!(tree.is_coarsened());
+    assert!(tree.memory_used() <= empty * 200);
+    assert_eq!(1024, tree.get_hits_map().values().sum::<u64>());
+
+    // a saved tree keeps its coarse leafs
+    #[cfg(feature = "persist")]
+    {
+        let mut loaded = AddressTree::from_bytes(&tree.to_bytes()).unwrap();
+        assert!(loaded.is_coarsened());
+        let addr = Subnet::from_str("10.0.0.1").unwrap();
+        let subnet = loaded.subnet_of(&addr).unwrap().clone();
+        loaded.push(addr).unwrap();
+        assert_eq!(1025, loaded.get_hits_map().values().sum::<u64>());
+        assert_eq!(1, loaded.get_subnets_map()[&subnet.to_string()].len());
+    }
+    // a coarse leaf takes the addresses within instead of growing the tree back
+    let used = tree.memory_used();
+    let addr = Subnet::from_str("10.0.0.1").unwrap();
+    let subnet = tree.subnet_of(&addr).unwrap().clone();
+    assert!(subnet.mask_len < 32);
+    tree.push(addr).unwrap();
+    assert_eq!(used, tree.memory_used());
+    assert_eq!(1025, tree.get_hits_map().values().sum::<u64>());
+    assert_eq!(1, tree.get_subnets_map()[&subnet.to_string()].len());
+}
+
+#[test]
+fn tree_over_memory_limit_with_nothing_to_collapse() {
+    let mut tree = AddressTree::new();
+    let empty = tree.memory_used();
+    tree.limit_memory(empty * 50);
+    // every address is a child of the root, so there are no subtrees to collapse
+    tree.set_merge_policy(MergeLimits {
+        min_mask_len: 33,
+        ..MergeLimits::default()
+    });
+    for i in 0..256u32 {
+        tree.push(Subnet::new(10, 0, (i >> 8) as u8, i as u8, 32).unwrap())
+            .unwrap();
+    }
+    assert!(!tree.is_coarsened());
+    assert!(tree.memory_used() > empty * 50);
+
+    // once there is something to collapse, it's collapsed as the tree grows
+    tree.set_merge_policy(MergeLimits::default());
+    for i in 0..256u32 {
+        tree.push(Subnet::new(10, 1, (i >> 8) as u8, i as u8, 32).unwrap())
+            .unwrap();
+    }
+    assert!(tree.is_coarsened());
+    assert_eq!(512, tree.get_hits_map().values().sum::<u64>());
+}
+
+#[test]
+fn views_in_one_pass() {
+    let mut tree = AddressTree::new();
+    for addr in ["10.0.0.1", "10.0.0.2", "10.1.0.0/24", "10.1.2.0/24"] {
+        tree.push(Subnet::from_str(addr).unwrap()).unwrap();
+    }
+    let views = [GroupBy::Prefix(8), GroupBy::Prefix(16), GroupBy::Subnet];
+    let maps = tree.get_views_map(&views);
+    for (view, map) in views.iter().zip(&maps) {
+        assert_eq!(&tree.get_groups_map(view), map, "{} view", view);
+    }
+    assert_eq!(4, maps[0]["10.0.0.0/8"].len());
+    // subnets pushed next to each other are reported on their own
+    assert_eq!(vec!["10.1.2.0/24"], maps[2]["10.1.2.0/24"]);
+}
+
+#[test]
+fn tags_and_untagged_remainder() {
+    let mut tree = AddressTree::new();
+    tree.tag(&Subnet::from_str("10.0.0.0/24").unwrap(), "known-scanner");
+    for addr in ["10.0.0.1", "10.0.0.2", "10.0.1.5", "192.168.0.1"] {
+        tree.push(Subnet::from_str(addr).unwrap()).unwrap();
+    }
+    assert_eq!(
+        Some("known-scanner"),
+        tree.tag_of(&Subnet::from_str("10.0.0.2").unwrap())
+    );
+    assert_eq!(
+        SubnetsMap::from([(
+            "known-scanner".to_string(),
+            vec!["10.0.0.1/32".to_string(), "10.0.0.2/32".to_string()]
+        )]),
+        tree.get_tags_map()
+    );
+
+    let mut subnets = tree.get_groups_map(&GroupBy::Prefix(16));
+    tree.retain_untagged(&mut subnets);
+    assert_eq!(
+        SubnetsMap::from([
+            ("10.0.0.0/16".to_string(), vec!["10.0.1.5/32".to_string()]),
+            (
+                "192.168.0.0/16".to_string(),
+                vec!["192.168.0.1/32".to_string()]
+            ),
+        ]),
+        subnets
+    );
+}
+
+#[test]
+fn merge_policy_limits_subnets() {
+    let addrs = ["10.0.0.1", "10.0.0.2", "10.0.200.1"].map(|addr| Subnet::from_str(addr).unwrap());
+    // the last address isn't merged into 10.0.0.0/16
+    let expected = "0.0.0.0/0=>[10.0.0.0/30=>[10.0.0.1/32;10.0.0.2/32;];10.0.200.1/32;];";
+    let mut tree = AddressTree::new();
+    tree.set_merge_policy(MergeLimits {
+        min_mask_len: 24,
+        min_density: 0.0,
+    });
+    for addr in addrs.clone() {
+        tree.push(addr).unwrap();
+    }
+    assert_eq!(expected, tree.to_string());
+
+    // half of the space taken at least
+    let mut tree = AddressTree::new();
+    tree.set_merge_policy(MergeLimits {
+        min_mask_len: 0,
+        min_density: 0.5,
+    });
+    tree.extend_sorted(addrs).unwrap();
+    assert_eq!(expected, tree.to_string());
+}
+
+#[test]
+fn redundant_entries() {
+    let redundant = redundant_lines([
+        ("a", vec!["10.0.0.0/24", "", "10.0.0.5", "192.168.0.1"]),
+        ("b", vec!["# comment", "192.168.0.1", "10.0.0.0/16"]),
+    ])
+    .unwrap();
+    let lines: Vec<String> = redundant.iter().map(Redundant::to_string).collect();
+    assert_eq!(
+        vec![
+            "a:1 10.0.0.0/24 shadowed by b:3 10.0.0.0/16",
+            "a:3 10.0.0.5/32 shadowed by b:3 10.0.0.0/16",
+            "b:2 192.168.0.1/32 duplicates a:4 192.168.0.1/32",
+        ],
+        lines
+    );
+}
+
+#[test]
+fn complete_prefix_reported_as_parent() {
+    let mut tree = AddressTree::new();
+    for addr in [
+        "10.0.0.0",
+        "10.0.0.1",
+        "10.0.0.2",
+        "10.0.0.3",
+        "10.0.2.0/24",
+        "10.0.3.0/24",
+    ] {
+        tree.push(Subnet::from_str(addr).unwrap()).unwrap();
+    }
+    let subnets = tree.get_subnets_map();
+    assert_eq!(4, subnets["10.0.0.0/30"].len());
+    assert_eq!(vec!["10.0.2.0/24", "10.0.3.0/24"], subnets["10.0.2.0/23"]);
+    assert!(Density::of("10.0.2.0/23", &subnets["10.0.2.0/23"])
+        .unwrap()
+        .is_full());
+    let addr = |s: &str| Subnet::from_str(s).unwrap();
+    assert_eq!(
+        Some(&addr("10.0.2.0/23")),
+        tree.subnet_of(&addr("10.0.3.1"))
+    );
+
+    let mut sorted = AddressTree::new();
+    sorted
+        .extend_sorted(tree.leafs().cloned().collect::<Vec<_>>())
+        .unwrap();
+    sorted.limit_children(2);
+    assert_eq!(subnets, sorted.get_subnets_map());
+
+    // dropping a half takes its addresses back
+    tree.push(addr("10.0.2.0/24")).unwrap();
+    tree.decay(0.5);
+    assert_eq!(
+        Some(&addr("10.0.2.0/24")),
+        tree.subnet_of(&addr("10.0.2.1"))
+    );
+    assert_eq!(None, tree.subnet_of(&addr("10.0.3.1")));
+}
+
+#[test]
+fn pipeline_normalizes_lines() {
+    let pipeline: Pipeline = ["trim", "strip-port", "strip-brackets", "unmap-ipv6"]
+        .iter()
+        .map(|step| step.parse::<PipelineStep>().unwrap())
+        .collect();
+    assert_eq!("1.2.3.4", pipeline.apply(" [::FFFF:1.2.3.4]:443 "));
+    assert_eq!("1.2.3.4", pipeline.apply("1.2.3.4:80"));
+    assert_eq!("1.2.3.4,5", pipeline.apply("1.2.3.4,5"));
+    assert!("rot13".parse::<PipelineStep>().is_err());
+}
+
+#[cfg(feature = "regex")]
+#[test]
+fn pipeline_extracts_by_regex() {
+    let pipeline = Pipeline::new()
+        .then(PipelineStep::from_str(r"extract=client=(\S+)").unwrap())
+        .then(PipelineStep::StripPort);
+    assert_eq!("1.2.3.4", pipeline.apply("GET / client=1.2.3.4:5000 200"));
+    assert_eq!("", pipeline.apply("no client here"));
+}
+
+#[test]
+fn tree_rooted_at_subnet() {
+    let root = Subnet::from_str("10.0.0.0/8").unwrap();
+    let mut tree = AddressTree::rooted_at(root.clone(), []);
+    tree.push(Subnet::from_str("10.1.2.3").unwrap()).unwrap();
+    tree.push(Subnet::from_str("10.1.2.4").unwrap()).unwrap();
+    let outside = Subnet::from_str("11.0.0.1").unwrap();
+    let rejected = tree.push(outside.clone()).unwrap_err();
+    assert_eq!(&outside, rejected.subnet());
+    assert_eq!(
+        "11.0.0.1/32 is outside of the tree's root 10.0.0.0/8",
+        rejected.to_string()
+    );
+    assert_eq!(&root, tree.root());
+    assert!(tree.get_subnets_map().contains_key("10.1.2.0/29"));
+    #[cfg(feature = "persist")]
+    assert_eq!(
+        &root,
+        AddressTree::from_bytes(&tree.to_bytes()).unwrap().root()
+    );
+}
+
+#[test]
+fn children_split_up_over_limit() {
+    // the policy keeps /24s apart, so they all end up as children of the root
+    let addrs: Vec<Subnet> = (0..64u8)
+        .flat_map(|i| [3, 9].map(|o4| Subnet::new(i * 4, 1, 2, o4, 32).unwrap()))
+        .collect();
+    let limits = MergeLimits {
+        min_mask_len: 24,
+        min_density: 0.0,
+    };
+    let mut flat = AddressTree::new();
+    flat.set_merge_policy(limits);
+    let mut split = AddressTree::new();
+    split.set_merge_policy(limits);
+    split.limit_children(4);
+    let mut sorted = AddressTree::new();
+    sorted.set_merge_policy(limits);
+    sorted.limit_children(4);
+    for addr in &addrs {
+        flat.push(addr.clone()).unwrap();
+        split.push(addr.clone()).unwrap();
+    }
+    sorted.extend_sorted(addrs.clone()).unwrap();
+    assert!(split.to_string().starts_with("0.0.0.0/0=>[0.0.0.0/1=>["));
+    assert_eq!(64, flat.get_subnets_map().len());
+    assert_eq!(flat.get_subnets_map(), split.get_subnets_map());
+    assert_eq!(flat.get_subnets_map(), sorted.get_subnets_map());
+    assert_eq!(flat.subnet_of(&addrs[5]), split.subnet_of(&addrs[5]));
+    assert!(split.contains(&addrs[127]));
+}
+
+#[test]
+fn tree_collected_and_extended() {
+    let mut tree: AddressTree = ["10.0.0.1", "10.0.0.2"]
+        .iter()
+        .map(|addr| Subnet::from_str(addr).unwrap())
+        .collect();
+    tree.extend([Subnet::from_str("10.0.0.3").unwrap()]);
+    assert_eq!(3, tree.get_subnets_map()["10.0.0.0/30"].len());
+
+    let mut rooted = AddressTree::rooted_at(Subnet::from_str("10.0.0.0/8").unwrap(), []);
+    rooted.extend(["10.0.0.1", "11.0.0.1"].map(|addr| Subnet::from_str(addr).unwrap()));
+    assert!(rooted.contains(&Subnet::from_str("10.0.0.1").unwrap()));
+    assert!(!rooted.contains(&Subnet::from_str("11.0.0.1").unwrap()));
+}
+
+#[test]
+fn push_unique_refuses_duplicates() {
+    let addr = Subnet::from_str("10.0.0.1").unwrap();
+    let mut tree = AddressTree::new();
+    tree.push_unique(addr.clone()).unwrap();
+    assert_eq!(
+        Err(PushError::Duplicate {
+            subnet: addr.clone()
+        }),
+        tree.push_unique(addr.clone())
+    );
+    assert_eq!(1, tree.get_hits_map()["10.0.0.1/32"]);
+    assert!(matches!(
+        Error::from(tree.push_unique(addr.clone()).unwrap_err()),
+        Error::PushError(PushError::Duplicate { .. })
+    ));
+}
+
+#[cfg(feature = "dns")]
+#[test]
+fn dns_cache_saved_as_lines() {
+    let cache = DnsCache::from_lines([
+        "# answers of the last run",
+        "a WWW.example.org 192.0.2.2 192.0.2.1",
+        "ptr 192.0.2.1 www.example.org",
+        "ptr 192.0.2.3",
+    ])
+    .unwrap();
+    let addr = Subnet::from_str("192.0.2.1").unwrap();
+    assert_eq!(Some(Some("www.example.org")), cache.hostname_of(&addr));
+    assert_eq!(
+        Some(None),
+        cache.hostname_of(&Subnet::from_str("192.0.2.3").unwrap())
+    );
+    assert_eq!(
+        None,
+        cache.hostname_of(&Subnet::from_str("192.0.2.4").unwrap())
+    );
+    assert_eq!(2, cache.addrs_of("www.EXAMPLE.org").unwrap().len());
+    assert_eq!(
+        "ptr 192.0.2.1 www.example.org\nptr 192.0.2.3\na www.example.org 192.0.2.2 192.0.2.1\n",
+        cache.to_string()
+    );
+    assert_eq!(
+        cache,
+        DnsCache::from_lines(cache.to_string().lines()).unwrap()
+    );
+    assert!(DnsCache::from_lines(["mx example.org"]).is_err());
+}
+
+#[test]
+fn sources_read_generically() {
+    let lines = LineSource::new(
+        "feed",
+        std::io::Cursor::new("10.0.0.1\n\n10.0.0.2,5\n192.168.0.1\n"),
+    );
+    let mut capture = vec![0xd4, 0xc3, 0xb2, 0xa1, 2, 0, 4, 0];
+    capture.extend([0; 12]);
+    capture.extend(1u32.to_le_bytes()); // Ethernet
+    let mut packet = vec![0; 12];
+    packet.extend([0x81, 0x00, 0, 5, 0x08, 0x00]); // VLAN 5, IPv4
+    packet.extend([
+        0x45, 0, 0, 20, 0, 0, 0, 0, 64, 6, 0, 0, 10, 0, 0, 3, 192, 0, 2, 1,
+    ]);
+    for packet in [&packet[..], &[0; 14][..]] {
+        capture.extend([0; 8]);
+        capture.extend((packet.len() as u32).to_le_bytes());
+        capture.extend((packet.len() as u32).to_le_bytes());
+        capture.extend(packet);
+    }
+    capture.extend([0; 10]); // cut off
+    let pcap = PcapSource::new("capture", std::io::Cursor::new(capture)).unwrap();
+    let sources: Vec<Box<dyn AddressSource>> = vec![Box::new(lines), Box::new(pcap)];
+    let subnets = find_groups_in(sources, &GroupBy::Prefix(24)).unwrap();
+    assert_eq!(
+        vec!["10.0.0.1/32", "10.0.0.2/32", "10.0.0.3/32"],
+        subnets["10.0.0.0/24"]
+    );
+    assert!(!subnets.contains_key("192.0.2.0/24"));
+
+    let mut bad = LineSource::new("feed", std::io::Cursor::new("10.0.0.1\n10.0.0.300\n"));
+    match bad.next_batch() {
+        Err(Error::LineError {
+            line_no, offset, ..
+        }) => assert_eq!((2, 9), (line_no, offset)),
+        other => panic!("unexpected result {:?}", other),
+    }
+    assert!(PcapSource::new("capture", std::io::Cursor::new([0x0a, 0x0d, 0x0d, 0x0a])).is_err());
+}
+
+#[test]
+fn classifier_runs_end_to_end() {
+    struct Country(&'static str);
+    impl EnrichmentProvider for Country {
+        fn enrich(&mut self, metadata: &mut Metadata, addrs: &[Subnet]) -> Result<(), Error> {
+            for addr in addrs {
+                let ip = IpMetadata {
+                    country: Some(self.0.to_string()),
+                    ..IpMetadata::default()
+                };
+                metadata.fill(addr, &ip);
+            }
+            Ok(())
+        }
+    }
+
+    let feed = "10.0.0.1\n198.51.100.1\n198.51.100.2\n198.51.100.1\n203.0.113.7\n";
+    let classified = Classifier::new()
+        .source(LineSource::new("feed", std::io::Cursor::new(feed)))
+        .exclude_private()
+        .exclude(Subnet::from_str("203.0.113.0/24").unwrap())
+        .group_by(GroupBy::Prefix(24))
+        .enrich(Country("NL"))
+        .enrich(Country("DE"))
+        .run()
+        .unwrap();
+    assert_eq!(
+        vec!["198.51.100.0/24"],
+        classified.subnets.keys().collect::<Vec<_>>()
+    );
+    let addr = Subnet::from_str("198.51.100.1").unwrap();
+    assert_eq!(Some("NL"), classified.metadata.value_of(&addr, "country"));
+    let mut out = vec![];
+    classified.export("ndjson-subnets", &mut out).unwrap();
+    assert!(String::from_utf8(out)
+        .unwrap()
+        .contains("\"count\":2,\"hits\":null"));
+    assert_eq!(
+        Some(&2),
+        classified
+            .report()
+            .address_hits
+            .unwrap()
+            .get("198.51.100.1/32")
+    );
+}
+
+#[cfg(feature = "manifest")]
+#[test]
+fn manifest_has_input_hashes() {
+    let mut manifest = Manifest::new(["-f", "tests/res/valid_ips.csv"]);
+    manifest.add_input("tests/res/valid_ips.csv").unwrap();
+    manifest.addresses = 3;
+    manifest.subnets = 1;
+    assert_eq!(51, manifest.inputs[0].size);
+    assert_eq!(
+        "afe303bb4bffab0a0e92616fefedea86e8a313f59d86353b773ae28ab09c4c56",
+        manifest.inputs[0].sha256
+    );
+    let mut out = vec![];
+    manifest.write_json(&mut out).unwrap();
+    let json = String::from_utf8(out).unwrap();
+    assert!(json.contains("\"inputs\":[{\"path\":\"tests/res/valid_ips.csv\",\"size\":51,"));
+    assert!(json.ends_with(",\"addresses\":3,\"subnets\":1,\"failures\":0}\n"));
+    assert!(manifest.add_input("no/such/file").is_err());
+}
+
+#[test]
+fn aggregator_decays_between_snapshots() {
+    // 10.0.0.1 is seen twice before the first snapshot, so it outlives the decay once
+    let feed = "10.0.0.1\n10.0.0.1\n10.0.0.2\n10.0.0.3\n10.0.0.4\n10.0.0.5\n10.0.0.6\n";
+    let mut aggregator = Aggregator::new(AddressTree::new())
+        .every_addresses(3)
+        .decay(0.5);
+    let mut snapshots: Vec<Vec<String>> = vec![];
+    aggregator
+        .run(
+            LineSource::new("feed", std::io::Cursor::new(feed)),
+            &CancelToken::new(),
+            |address_tree| {
+                let mut addrs: Vec<String> = address_tree.get_hits_map().into_keys().collect();
+                addrs.sort();
+                snapshots.push(addrs);
+                Ok(())
+            },
+        )
+        .unwrap();
+    assert_eq!(
+        vec![
+            vec!["10.0.0.1/32", "10.0.0.2/32"],
+            vec!["10.0.0.1/32", "10.0.0.3/32", "10.0.0.4/32", "10.0.0.5/32"],
+            vec!["10.0.0.6/32"],
+        ],
+        snapshots
+    );
+    assert_eq!(1, aggregator.tree().get_hits_map().len());
+    // the subnets of the dropped leafs go with them
+    let mut address_tree = AddressTree::new();
+    for addr in [
+        "10.0.0.1", "10.0.0.1", "10.0.0.2", "10.0.0.3", "10.0.0.3", "10.0.1.1",
+    ] {
+        address_tree.push(Subnet::from_str(addr).unwrap()).unwrap();
+    }
+    assert_eq!(2, address_tree.decay(0.5));
+    assert_eq!(
+        HashMap::from([(
+            "10.0.0.0/30".to_string(),
+            vec!["10.0.0.1/32".to_string(), "10.0.0.3/32".to_string()]
+        )]),
+        address_tree.get_subnets_map()
+    );
+    assert_eq!(2, address_tree.decay(0.0));
+    assert!(address_tree.get_hits_map().is_empty());
+}
+
+#[test]
+fn leafs_expire_after_ttl() {
+    let mut address_tree = AddressTree::new();
+    address_tree.track_seen(100);
+    for addr in ["10.0.0.1", "10.0.0.2"] {
+        address_tree.push(Subnet::from_str(addr).unwrap()).unwrap();
+    }
+    address_tree.track_seen(200);
+    for addr in ["10.0.0.2", "10.0.1.1"] {
+        address_tree.push(Subnet::from_str(addr).unwrap()).unwrap();
+    }
+    assert_eq!(0, address_tree.expire(100));
+    assert_eq!(1, address_tree.expire(150));
+    assert!(!address_tree.contains(&Subnet::from_str("10.0.0.1").unwrap()));
+    assert_eq!(
+        Some(&Seen {
+            first: 100,
+            last: 200
+        }),
+        address_tree
+            .get_seen_map(&GroupBy::Subnet)
+            .get("10.0.0.0/23")
+    );
+    assert_eq!(2, address_tree.expire(201));
+    assert!(address_tree.get_seen_map(&GroupBy::Subnet).is_empty());
+}
+
+#[test]
+fn datasets_crosstabulated() {
+    let mut datasets = Datasets::new();
+    datasets
+        .add_lines("dc1", ["10.0.0.1", "10.0.0.2", "192.168.1.1"])
+        .unwrap();
+    datasets
+        .add_lines("dc2", ["10.0.0.2", "10.0.0.3", "172.16.0.1"])
+        .unwrap();
+    // weighted lines count as their addresses
+    datasets.add_lines("dc3", ["10.0.0.1,5"]).unwrap();
+    assert!(datasets.add_lines("bad", ["10.0.0.256"]).is_err());
+    let crosstab = datasets.crosstab(&GroupBy::Prefix(8));
+    assert_eq!(vec!["dc1", "dc2", "dc3"], crosstab.labels);
+    assert_eq!(
+        vec![
+            CrossRow {
+                group: "10.0.0.0/8".to_string(),
+                counts: vec![2, 2, 1]
+            },
+            CrossRow {
+                group: "172.0.0.0/8".to_string(),
+                counts: vec![0, 1, 0]
+            },
+            CrossRow {
+                group: "192.0.0.0/8".to_string(),
+                counts: vec![1, 0, 0]
+            },
+        ],
+        crosstab.rows
+    );
+    assert!(crosstab
+        .to_string()
+        .starts_with("subnet\tdc1\tdc2\tdc3\tdatasets\n10.0.0.0/8\t2\t2\t1\t3\n"));
+}
+
+#[test]
+fn firewall_rules_carry_evidence() {
+    let mut address_tree = AddressTree::new();
+    let mut provenance = Provenance::new();
+    for (file_name, addrs) in [
+        ("feeds/abuse.txt", vec!["203.0.113.1", "203.0.113.2"]),
+        ("scan.txt", vec!["203.0.113.3"]),
+    ] {
+        provenance.on_file_started(std::path::Path::new(file_name));
+        for addr in addrs {
+            let addr = Subnet::from_str(addr).unwrap();
+            address_tree.push(addr.clone()).unwrap();
+            provenance.on_address_classified(&addr, &address_tree);
+        }
+    }
+    let subnets = address_tree.get_subnets_map();
+    let seen = SeenMap::from([(
+        "203.0.113.0/30".to_string(),
+        Seen {
+            first: 1_704_067_200,
+            last: 1_706_745_600,
+        },
+    )]);
+    let (labels, metadata) = (Labels::new(), Metadata::new());
+    let report = Report::new(&subnets, &labels, &metadata)
+        .seen(Some(&seen))
+        .sources(Some(&provenance));
+    let evidence = "\"subnet=203.0.113.0/30 addresses=3 class=bogon first_seen=2024-01-01 last_seen=2024-02-01 source=abuse.txt\"";
+    let exporters = Exporters::new();
+    for (format, rule) in [
+        (
+            "nft",
+            "add rule inet ipv4_classify input ip saddr 203.0.113.0/30 drop comment ",
+        ),
+        (
+            "iptables",
+            "-A IPV4_CLASSIFY -s 203.0.113.0/30 -m comment --comment ",
+        ),
+        ("ipset", "add ipv4_classify 203.0.113.0/30 comment "),
+    ] {
+        let mut out = vec![];
+        exporters.write(format, &report, &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains(&format!("{}{}", rule, evidence)), "{}", out);
+    }
+
+    // a metadata value as the group can't break out of the comment
+    let metadata = Metadata::from_lines([
+        "ip,owner",
+        "203.0.113.1,\"x\"\" accept comment \"\"pwn\"",
+        "203.0.113.2,\"x\"\" accept comment \"\"pwn\"",
+    ])
+    .unwrap();
+    let groups = address_tree.get_groups_map(&GroupBy::column("owner").joined(&metadata));
+    let report = Report::new(&groups, &labels, &metadata);
+    for format in ["nft", "iptables", "ipset"] {
+        let mut out = vec![];
+        exporters.write(format, &report, &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(
+            out.contains("\"subnet=x__accept_comment__pwn addresses=2"),
+            "{}",
+            out
+        );
+        assert!(out
+            .lines()
+            .filter(|line| line.contains("subnet="))
+            .all(|line| line.matches('"').count() == 2));
+    }
+}
+
+#[test]
+fn address_classes_summarized() {
+    let class_of = |src: &str| Subnet::from_str(src).unwrap().address_class();
+    assert_eq!(Some(AddressClass::Private), class_of("172.20.1.1"));
+    assert_eq!(Some(AddressClass::Cgnat), class_of("100.64.0.0/10"));
+    assert_eq!(Some(AddressClass::Multicast), class_of("239.1.1.1"));
+    assert_eq!(Some(AddressClass::Bogon), class_of("198.51.100.7"));
+    assert_eq!(Some(AddressClass::Public), class_of("8.8.0.0/16"));
+    assert_eq!(None, class_of("0.0.0.0/0"));
+    let addrs = [
+        Subnet::from_str("10.0.0.1").unwrap(),
+        Subnet::from_str("192.168.0.1").unwrap(),
+    ];
+    assert_eq!(Some(AddressClass::Private), AddressClass::of_all(&addrs));
+    let mixed = [addrs[0].clone(), Subnet::from_str("1.1.1.1").unwrap()];
+    assert_eq!(None, AddressClass::of_all(&mixed));
+
+    let subnets = group_lines(["100.64.0.1", "100.64.0.2"], &GroupBy::Prefix(24)).unwrap();
+    let (labels, metadata) = (Labels::new(), Metadata::new());
+    let report = Report::new(&subnets, &labels, &metadata);
+    let mut out = vec![];
+    write_json(&report, &mut out).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    assert!(out.contains("\"kind\":null,\"class\":\"cgnat\""), "{}", out);
+    assert!(
+        out.contains("{\"address\":\"100.64.0.1/32\",\"class\":\"cgnat\""),
+        "{}",
+        out
+    );
+}
+
+#[test]
+fn subnets_ranked_by_score() {
+    let scoring = Scoring::from_str("count=0, hits=0, recency=0").unwrap();
+    assert_eq!(0.0, scoring.hits);
+    assert_eq!(7.0, scoring.half_life_days);
+    assert!(Scoring::from_str("severity=1").is_err());
+    assert!(Scoring::from_str("half-life=0").is_err());
+    let full = Density::of("10.0.0.0/31", &["10.0.0.0/32".into(), "10.0.0.1/32".into()]);
+    assert_eq!(100.0, scoring.score(full, 2, None, None, 0));
+    let seen = Seen { first: 0, last: 0 };
+    let stale = Scoring::from_str("density=0,count=0,hits=0").unwrap();
+    assert_eq!(50.0, stale.score(None, 1, None, Some(seen), 7 * 86_400));
+
+    // 3 scattered addresses outnumber 2 adjacent ones, but the dense pair is worse
+    let subnets = group_lines(
+        [
+            "10.0.0.1",
+            "10.0.5.9",
+            "10.0.9.1",
+            "192.168.0.0",
+            "192.168.0.1",
+        ],
+        &GroupBy::Prefix(16),
+    )
+    .unwrap();
+    let subnets: SubnetsMap = subnets
+        .into_iter()
+        .map(|(name, addrs)| match name.as_str() {
+            "192.168.0.0/16" => ("192.168.0.0/31".to_string(), addrs),
+            _ => (name, addrs),
+        })
+        .collect();
+    let (labels, metadata) = (Labels::new(), Metadata::new());
+    let scoring = Scoring::default();
+    let report = Report::new(&subnets, &labels, &metadata)
+        .top(Some(1))
+        .scoring(Some(&scoring));
+    let mut out = vec![];
+    write_ndjson_subnets(&report, &mut out).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    assert!(out.starts_with("{\"subnet\":\"192.168.0.0/31\""), "{}", out);
+    assert!(out.contains("\"score\":55.6,"), "{}", out);
+    assert_eq!(1, out.lines().count());
+}
+
+#[test]
+fn explainer_replays_decisions() {
+    let mut address_tree = AddressTree::new();
+    address_tree.set_merge_policy(MergeLimits {
+        min_mask_len: 24,
+        min_density: 0.0,
+    });
+    let mut explainer = Explainer::new(Subnet::from_str("10.0.0.0/30").unwrap());
+    for addr in ["10.0.0.1", "10.0.0.2", "10.0.1.7", "192.168.0.1"] {
+        address_tree
+            .push_observed(Subnet::from_str(addr).unwrap(), &mut explainer)
+            .unwrap();
+    }
+    assert_eq!(
+        vec![
+            "10.0.0.1/32 became a child of 0.0.0.0/0",
+            "10.0.0.2/32 joined 10.0.0.1/32 into 10.0.0.0/30, their longest common prefix",
+            "10.0.1.7/32 stays apart from 10.0.0.0/30, the merge policy refused 10.0.0.0/23",
+            "192.168.0.1/32 stays apart from 10.0.0.0/30, they share fewer than 1 leading bits",
+        ],
+        explainer.steps()
+    );
+    let boundary = Subnet::from_str("10.0.0.0/24").unwrap();
+    let mut address_tree = AddressTree::with_boundaries([boundary]);
+    let mut explainer = Explainer::new(Subnet::from_str("10.0.1.0/24").unwrap());
+    for addr in ["10.0.0.1", "10.0.1.1"] {
+        address_tree
+            .push_observed(Subnet::from_str(addr).unwrap(), &mut explainer)
+            .unwrap();
+    }
+    assert_eq!(
+        "1. 10.0.1.1/32 stays apart from 10.0.0.1/32, 10.0.0.0/23 would span across the boundary 10.0.0.0/24\n\
+         2. 10.0.1.1/32 became a child of 0.0.0.0/0",
+        explainer.to_string()
+    );
+}
+
+#[test]
+fn free_blocks_allocated() {
+    let used = SubnetSet::from_lines(["10.0.0.5", "10.0.1.0/24", "10.0.3.0/25"]).unwrap();
+    let pool = Subnet::from_str("10.0.0.0/22").unwrap();
+    let allocate = |mask_len, fit| used.allocate(&pool, mask_len, fit).map(|s| s.to_string());
+    assert_eq!(Some("10.0.0.128/25".into()), allocate(25, Fit::First));
+    assert_eq!(Some("10.0.2.0/24".into()), allocate(24, Fit::First));
+    assert_eq!(Some("10.0.0.64/26".into()), allocate(26, Fit::Best));
+    assert_eq!(Some("10.0.0.0/30".into()), allocate(30, Fit::Best));
+    assert_eq!(None, allocate(23, Fit::First));
+    assert_eq!(None, allocate(21, Fit::First));
+    assert_eq!(Fit::Best, Fit::from_str("best").unwrap());
+
+    let mut address_tree = AddressTree::new();
+    for line in ["10.0.0.5", "10.0.1.0/24", "10.0.3.0/25", "192.168.0.1"] {
+        address_tree.push(Subnet::from_str(line).unwrap()).unwrap();
+    }
+    let block = address_tree.allocate(&pool, 25, Fit::Best).unwrap();
+    assert_eq!("10.0.0.128/25", block.to_string());
+    address_tree.push(block).unwrap();
+    let block = address_tree.allocate(&pool, 25, Fit::Best).unwrap();
+    assert_eq!("10.0.3.128/25", block.to_string());
+}
+
+#[test]
+fn subnet_index_looks_addresses_up() {
+    let mut subnets = group_lines(["10.0.1.1", "10.0.1.2"], &GroupBy::Subnet).unwrap();
+    subnets.insert("10.0.0.0/16".to_string(), vec!["10.0.2.1/32".to_string()]);
+    let (labels, metadata) = (Labels::new(), Metadata::new());
+    let report = Report::new(&subnets, &labels, &metadata).top(Some(1));
+    let index = report.subnet_index();
+    let subnet_of = |addr: &str| index.subnet_of(&Subnet::from_str(addr).unwrap());
+    assert_eq!(Some("10.0.1.0/30"), subnet_of("10.0.1.3"));
+    assert_eq!(Some("10.0.0.0/16"), subnet_of("10.0.2.1"));
+    assert_eq!(Some("10.0.0.0/16"), subnet_of("10.0.1.0/24"));
+    assert_eq!(None, subnet_of("192.168.0.1"));
+
+    let classes = group_lines(["10.0.0.1", "192.168.0.1"], &GroupBy::Class).unwrap();
+    let index = SubnetIndex::of(&classes);
+    assert_eq!(
+        Some("private"),
+        index.subnet_of(&Subnet::from_str("192.168.0.1").unwrap())
+    );
+    assert_eq!(
+        None,
+        index.subnet_of(&Subnet::from_str("192.168.0.2").unwrap())
+    );
+}
+
+#[cfg(feature = "template")]
+#[test]
+fn report_rendered_through_template() {
+    let subnets = group_lines(["10.0.0.1", "10.0.0.2", "10.0.0.3"], &GroupBy::Subnet).unwrap();
+    let (labels, metadata) = (Labels::new(), Metadata::new());
+    let report = Report::new(&subnets, &labels, &metadata);
+    let mut exporters = Exporters::new();
+    exporters.register(
+        Template::new(
+            "{{#each subnets}}{{subnet}} {{class}}: {{#each addresses}}{{address}} {{/each}}\n{{/each}}total {{count}} <&>\n",
+        )
+        .unwrap(),
+    );
+    let mut out = vec![];
+    exporters.write("template", &report, &mut out).unwrap();
+    assert_eq!(
+        "10.0.0.0/30 private: 10.0.0.1/32 10.0.0.2/32 10.0.0.3/32 \ntotal 3 <&>\n",
+        String::from_utf8(out).unwrap()
+    );
+    assert!(Template::new("{{#each subnets}").is_err());
+}
@@ -0,0 +1,191 @@
+#[cfg(feature = "fs")]
+use std::path::Path;
+use std::{collections::BTreeMap, str::FromStr};
+
+#[cfg(feature = "fs")]
+use crate::content::FileContent;
+use crate::{AddressTree, Error, GroupBy, Subnet, SubnetsMap};
+
+const SECONDS_IN_HOUR: i64 = 60 * 60;
+const SECONDS_IN_DAY: i64 = 24 * SECONDS_IN_HOUR;
+
+/// time span the timestamped addresses are reported by
+#[derive(Debug, Default, Clone, PartialEq)]
+pub enum TimeBucket {
+    Hour,
+    #[default]
+    Day,
+}
+
+impl TimeBucket {
+    /// human-readable start of the bucket the timestamp (unix seconds, UTC) belongs to,
+    /// names of consecutive buckets sort in time order
+    pub fn name_of(&self, timestamp: i64) -> String {
+        let (year, month, day) = civil_from_days(timestamp.div_euclid(SECONDS_IN_DAY));
+        match self {
+            TimeBucket::Hour => {
+                let hour = timestamp.rem_euclid(SECONDS_IN_DAY) / SECONDS_IN_HOUR;
+                format!("{:04}-{:02}-{:02} {:02}:00", year, month, day, hour)
+            }
+            TimeBucket::Day => format!("{:04}-{:02}-{:02}", year, month, day),
+        }
+    }
+}
+
+impl FromStr for TimeBucket {
+    type Err = Error;
+
+    /// parse one of: hour, day
+    fn from_str(src: &str) -> Result<Self, Self::Err> {
+        match src {
+            "hour" => Ok(TimeBucket::Hour),
+            "day" => Ok(TimeBucket::Day),
+            _ => Err(Error::ParseError {
+                input: src.to_string(),
+                reason: "unknown time bucket".to_string(),
+            }),
+        }
+    }
+}
+
+/// # split a line into its timestamp and address
+/// the address is the last field separated by a comma, a semicolon or whitespaces,
+/// the timestamp is everything before it: unix seconds or ISO 8601 date and time e.g. 2024-05-01T13:45:00Z,
+/// time zone offsets are ignored, so times are expected in UTC
+pub fn parse_timed_line(line: &str) -> Result<(i64, Subnet), Error> {
+    let parse_error = |reason: &str| Error::ParseError {
+        input: line.to_string(),
+        reason: reason.to_string(),
+    };
+    let Some((timestamp, addr)) = line.rsplit_once([',', ';', ' ', '\t']) else {
+        return Err(parse_error("there is no timestamp before the address"));
+    };
+    let timestamp = timestamp.trim_end_matches([',', ';', ' ', '\t']);
+    match parse_timestamp(timestamp) {
+        Some(timestamp) => Ok((timestamp, Subnet::from_str(addr)?)),
+        None => Err(parse_error("can't parse the timestamp")),
+    }
+}
+
+/// unix seconds or the date and time parts of ISO 8601
+fn parse_timestamp(src: &str) -> Option<i64> {
+    if let Ok(seconds) = src.parse::<i64>() {
+        return Some(seconds);
+    }
+    let field = |from: usize, len: usize| -> Option<i64> {
+        let digits = src.get(from..from + len)?;
+        digits
+            .bytes()
+            .all(|b| b.is_ascii_digit())
+            .then(|| digits.parse().ok())?
+    };
+    let (year, month, day) = (field(0, 4)?, field(5, 2)?, field(8, 2)?);
+    if src.get(4..5) != Some("-") || src.get(7..8) != Some("-") || !(1..=12).contains(&month) {
+        return None;
+    }
+    // otherwise e.g. 02-31 would be a day in March
+    if !(1..=days_in_month(year, month)).contains(&day) {
+        return None;
+    }
+    let (hour, minute, second) = match src.get(10..11) {
+        None => (0, 0, 0),
+        Some("T") | Some(" ") => (
+            field(11, 2)?,
+            field(14, 2)?,
+            field(17, 2).unwrap_or_default(),
+        ),
+        Some(_) => return None,
+    };
+    Some(
+        days_from_civil(year, month, day) * SECONDS_IN_DAY
+            + hour * SECONDS_IN_HOUR
+            + minute * 60
+            + second,
+    )
+}
+
+/// days in the month of the year in the proleptic Gregorian calendar, month is 1 to 12
+fn days_in_month(year: i64, month: i64) -> i64 {
+    let is_leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    match month {
+        2 if is_leap => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// days since 1970-01-01 of the date in the proleptic Gregorian calendar
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+/// date of the day since 1970-01-01, the inverse of days_from_civil
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_from_march = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_from_march + 2) / 5 + 1;
+    let month = if month_from_march < 10 {
+        month_from_march + 3
+    } else {
+        month_from_march - 9
+    };
+    let year = year_of_era + era * 400;
+    (if month <= 2 { year + 1 } else { year }, month, day)
+}
+
+/// # classify timestamped addresses separately for every time bucket
+/// lines look like "2024-05-01T13:45:00Z 1.2.3.4" or "1714571100,1.2.3.4", see parse_timed_line
+/// # returns
+/// subnets (or groups) of every bucket, buckets are in time order
+/// Err - if some line can't be parsed
+pub fn group_timed_lines(
+    lines: impl IntoIterator<Item = impl AsRef<str>>,
+    bucket: &TimeBucket,
+    group_by: &GroupBy,
+) -> Result<BTreeMap<String, SubnetsMap>, Error> {
+    let mut trees: BTreeMap<String, AddressTree> = BTreeMap::new();
+    for line in lines {
+        let line = line.as_ref().trim();
+        if line.is_empty() {
+            continue;
+        }
+        let (timestamp, addr) = parse_timed_line(line)?;
+        trees
+            .entry(bucket.name_of(timestamp))
+            .or_default()
+            .push(addr)?;
+    }
+    Ok(trees
+        .into_iter()
+        .map(|(name, tree)| (name, tree.get_groups_map(group_by)))
+        .collect())
+}
+
+/// same as group_timed_lines for lines of the files
+#[cfg(feature = "fs")]
+pub fn find_timed_groups(
+    file_names: impl IntoIterator<Item = impl AsRef<Path>>,
+    bucket: &TimeBucket,
+    group_by: &GroupBy,
+) -> Result<BTreeMap<String, SubnetsMap>, Error> {
+    let mut contents = vec![];
+    for file_name in file_names {
+        contents.push(FileContent::read(file_name.as_ref())?);
+    }
+    group_timed_lines(
+        contents.iter().flat_map(|content| content.split('\n')),
+        bucket,
+        group_by,
+    )
+}
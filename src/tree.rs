@@ -0,0 +1,1467 @@
+use std::{
+    cmp,
+    collections::HashMap,
+    fmt::{Debug, Display, Formatter},
+    iter,
+    mem::{replace, size_of, take},
+    str::FromStr,
+};
+
+use smallvec::{smallvec, SmallVec};
+
+use crate::{
+    Decision, Error, Fit, Labels, Merge, MergePolicy, Metadata, Observer, Subnet, SubnetSet,
+};
+
+/// human-readable subnets (or groups) with all their addresses
+pub type SubnetsMap = HashMap<String, Vec<String>>;
+
+/// human-readable subnets (or groups) with their total hits
+pub type WeightsMap = HashMap<String, u64>;
+
+/// human-readable subnets (or groups) with the times their addresses were seen
+pub type SeenMap = HashMap<String, Seen>;
+
+/// when an address (or a group of them) was pushed the first and the last time, unix seconds
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Seen {
+    pub first: i64,
+    pub last: i64,
+}
+
+impl Seen {
+    /// days between the first and the last time, 0 for a one-off source
+    pub fn age_days(&self) -> i64 {
+        (self.last - self.first) / 86_400
+    }
+
+    /// the earliest first and the latest last of both
+    fn join(&self, other: &Seen) -> Seen {
+        Seen {
+            first: cmp::min(self.first, other.first),
+            last: cmp::max(self.last, other.last),
+        }
+    }
+}
+
+/// top-level grouping dimension of the report
+#[derive(Debug, Default, Clone, PartialEq)]
+pub enum GroupBy {
+    /// hierarchical subnets as discovered by the tree
+    #[default]
+    Subnet,
+    /// fixed-size blocks of the given prefix length, e.g. /8
+    Prefix(u8),
+    /// special-purpose class of the addresses, see AddressClass
+    Class,
+    /// a column of the metadata, e.g. asn or country, along with its values joined by GroupBy::joined
+    Column(String, Labels),
+}
+
+impl GroupBy {
+    /// grouping by the metadata column, its values are to be joined by GroupBy::joined
+    pub fn column(name: impl Into<String>) -> Self {
+        GroupBy::Column(name.into(), Labels::new())
+    }
+
+    /// # the grouping with the values of its column taken from the metadata
+    /// the other groupings are returned as they are
+    pub fn joined(&self, metadata: &Metadata) -> Self {
+        match self {
+            GroupBy::Column(name, _) => {
+                let mut values = Labels::new();
+                for (addr, value) in metadata.values_of(name) {
+                    values.insert(&addr, value);
+                }
+                GroupBy::Column(name.clone(), values)
+            }
+            _ => self.clone(),
+        }
+    }
+
+    /// name of the group an address belongs to
+    /// not applicable to GroupBy::Subnet, as those groups come from the tree itself
+    pub(crate) fn group_of(&self, addr: &Subnet) -> String {
+        match self {
+            GroupBy::Subnet => panic!("subnet grouping is done by the tree"),
+            GroupBy::Prefix(mask_len) => {
+                let [o1, o2, o3, o4] = addr.bits.to_be_bytes();
+                // addresses with a shorter mask than the block are groups on their own
+                Subnet::new(o1, o2, o3, o4, cmp::min(*mask_len, addr.mask_len))
+                    .unwrap()
+                    .to_string()
+            }
+            // subnets spanning several classes are groups on their own
+            GroupBy::Class => addr
+                .address_class()
+                .map_or_else(|| addr.to_string(), |class| class.name().to_string()),
+            GroupBy::Column(name, values) => match values.label_of(addr) {
+                Some(value) => value.to_string(),
+                None => format!("no {}", name),
+            },
+        }
+    }
+}
+
+impl GroupBy {
+    /// address space of the group an address belongs to
+    /// not applicable to GroupBy::Subnet, as those groups come from the tree itself
+    pub(crate) fn range_of(&self, addr: &Subnet) -> Subnet {
+        let [o1, o2, o3, o4] = addr.bits.to_be_bytes();
+        match self {
+            GroupBy::Subnet => panic!("subnet grouping is done by the tree"),
+            GroupBy::Prefix(mask_len) => {
+                Subnet::new(o1, o2, o3, o4, cmp::min(*mask_len, addr.mask_len)).unwrap()
+            }
+            // public addresses are spread all over the address space
+            GroupBy::Class => addr
+                .class_range()
+                .unwrap_or(Subnet::from_parts([0, 0, 0, 0], 0)),
+            // addresses of a column value don't make a range, so it's the address alone
+            GroupBy::Column(..) => addr.clone(),
+        }
+    }
+}
+
+/// the way FromStr takes it, e.g. /8
+impl Display for GroupBy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+        match self {
+            GroupBy::Subnet => f.write_str("subnet"),
+            GroupBy::Prefix(mask_len) => write!(f, "/{}", mask_len),
+            GroupBy::Class => f.write_str("class"),
+            GroupBy::Column(name, _) => write!(f, "column:{}", name),
+        }
+    }
+}
+
+impl FromStr for GroupBy {
+    type Err = Error;
+
+    /// parse one of: subnet, class, /N (e.g. /8), column:NAME (e.g. column:asn)
+    fn from_str(src: &str) -> Result<Self, Self::Err> {
+        match src {
+            "subnet" => Ok(GroupBy::Subnet),
+            "class" => Ok(GroupBy::Class),
+            _ if src.starts_with("column:") => match &src["column:".len()..] {
+                "" => Err(Error::ParseError {
+                    input: src.to_string(),
+                    reason: "no column name".to_string(),
+                }),
+                name => Ok(GroupBy::column(name)),
+            },
+            _ => match src.strip_prefix('/').map(|mask_len| mask_len.parse::<u8>()) {
+                Some(Ok(mask_len)) if mask_len <= 32 => Ok(GroupBy::Prefix(mask_len)),
+                _ => Err(Error::ParseError {
+                    input: src.to_string(),
+                    reason: "unknown grouping".to_string(),
+                }),
+            },
+        }
+    }
+}
+
+/// tree of subnets, leafs are the addresses (or subnets) pushed into it
+/// nodes live in a single arena and refer to their children by index, the root is the first one
+#[derive(Debug)]
+pub struct AddressTree {
+    nodes: Vec<Node>,
+    boundaries: Vec<Subnet>, // no subnet made up by the tree spans across any of them
+    now: Option<i64>,        // time to stamp pushed leafs with, see track_seen
+    seen: HashMap<(u32, u8), Seen>, // stamps of the leafs by their subnets
+    memory_limit: Option<usize>, // bytes the tree may take before it's coarsened, see limit_memory
+    coarsened: bool,         // whether some leafs were collapsed into their parents
+    coarsen_at: usize, // bytes to grow to before coarsening is tried again after it failed, see fit_memory
+    tags: Labels,      // labels of the subnets, their leafs share them, see tag
+    merge_policy: Option<Box<dyn MergePolicy>>, // everything is merged without one
+    max_children: Option<usize>, // children a node may have before they're split, see limit_children
+}
+
+#[derive(Debug)]
+struct Node {
+    subnet: Subnet,
+    children: SmallVec<[usize; 2]>, // empty for leafs
+    hits: u64,                      // number of times a leaf was pushed, e.g. its traffic volume
+    coarse: bool,                   // a collapsed subtree, it takes all the addresses within
+    structural: bool, // made to split up children of its parent, it's never reported on its own
+    covered: u64, // addresses of the leafs below, saturated if a coarse one is among them, see is_complete
+}
+
+pub(crate) const ROOT: usize = 0;
+
+/// why the tree refused a subnet, the subnet is handed back
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum PushError {
+    #[error("{subnet} is outside of the tree's root {root}")]
+    OutsideRoot { subnet: Subnet, root: Subnet },
+    #[error("{subnet} is in the tree already")]
+    Duplicate { subnet: Subnet },
+}
+
+impl PushError {
+    /// the refused subnet
+    pub fn subnet(&self) -> &Subnet {
+        match self {
+            PushError::OutsideRoot { subnet, .. } | PushError::Duplicate { subnet } => subnet,
+        }
+    }
+
+    pub fn into_subnet(self) -> Subnet {
+        match self {
+            PushError::OutsideRoot { subnet, .. } | PushError::Duplicate { subnet } => subnet,
+        }
+    }
+}
+
+/// what to do with a node while walking subnets
+enum Visit {
+    Subnet,   // report the node as a subnet
+    Children, // look into the children
+}
+
+impl AddressTree {
+    /// make a new empty tree starting from 0.0.0.0/0
+    /// multicast and CGNAT addresses are never merged with their neighbours, see Subnet::special_range
+    pub fn new() -> Self {
+        Self {
+            nodes: vec![Node::of(Subnet::root(), 0)],
+            boundaries: Subnet::special_ranges().collect(),
+            now: None,
+            seen: HashMap::new(),
+            memory_limit: None,
+            coarsened: false,
+            coarsen_at: 0,
+            tags: Labels::new(),
+            merge_policy: None,
+            max_children: None,
+        }
+    }
+
+    /// # decide with the policy which subnets to merge from now on, e.g. MergeLimits
+    /// the policy isn't saved along with the tree
+    pub fn set_merge_policy(&mut self, policy: impl MergePolicy + 'static) {
+        self.merge_policy = Some(Box::new(policy));
+    }
+
+    /// # attach the label to the subnet, every leaf within gets it, including ones pushed later
+    /// a leaf within several tagged subnets gets the label of the most specific one,
+    /// the last label wins for the same subnet, tags aren't saved along with the tree
+    pub fn tag(&mut self, subnet: &Subnet, label: impl Into<String>) {
+        self.tags.insert(subnet, label);
+    }
+
+    /// the tags as labels, e.g. to print them along with the report
+    pub fn tags(&self) -> &Labels {
+        &self.tags
+    }
+
+    /// label of the most specific tagged subnet containing the address (or subnet), see tag
+    pub fn tag_of(&self, addr: &Subnet) -> Option<&str> {
+        self.tags.label_of(addr)
+    }
+
+    /// # split up children of a node into halves of its subnet once there are more than max of them,
+    /// e.g. addresses the merge policy keeps apart, so lookups don't scan thousands of children
+    /// the halves don't change the report, the limit isn't saved along with the tree
+    /// # Panics
+    /// if max is less than 2
+    pub fn limit_children(&mut self, max: usize) {
+        assert!(max >= 2, "a node should be allowed at least 2 children");
+        self.max_children = Some(max);
+        for at in 0..self.nodes.len() {
+            self.rebalance(at);
+        }
+    }
+
+    /// split up the node's children if there are too many of them, see limit_children
+    fn rebalance(&mut self, at: usize) {
+        let max = self.max_children.unwrap_or(usize::MAX);
+        let node = &mut self.nodes[at];
+        if node.children.len() <= max {
+            return;
+        }
+        let children = take(&mut node.children);
+        let subnet = node.subnet.clone();
+        let mut halves: [(u32, Vec<usize>); 2] = [
+            (subnet.bits, vec![]),
+            (subnet.bits | 1 << (31 - subnet.mask_len), vec![]),
+        ];
+        for ch in children {
+            let half = self.nodes[ch].subnet.bits >> (31 - subnet.mask_len) & 1;
+            halves[half as usize].1.push(ch);
+        }
+        for (bits, half) in halves {
+            match half.len() {
+                0 => {}
+                1 => self.nodes[at].children.push(half[0]),
+                _ => {
+                    let half_subnet = Subnet::from_parts(bits.to_be_bytes(), subnet.mask_len + 1);
+                    let node = self.add(half_subnet, 0);
+                    self.nodes[node].structural = true;
+                    self.nodes[node].children = half.into();
+                    self.nodes[at].children.push(node);
+                    self.rebalance(node);
+                    self.recount(node);
+                }
+            }
+        }
+    }
+
+    /// children of the node as if there were no structural nodes, see limit_children
+    fn real_children(&self, at: usize) -> impl Iterator<Item = usize> + '_ {
+        let mut stack: Vec<usize> = self.nodes[at].children.iter().rev().copied().collect();
+        iter::from_fn(move || {
+            while let Some(ch) = stack.pop() {
+                if !self.nodes[ch].structural {
+                    return Some(ch);
+                }
+                stack.extend(self.nodes[ch].children.iter().rev());
+            }
+            None
+        })
+    }
+
+    /// shortest mask of a subnet made up by joining children of the node
+    fn min_mask_at(&self, at: usize) -> u8 {
+        let node = &self.nodes[at];
+        // a structural node stands for its parent, so its very subnet may be made up
+        node.subnet.mask_len + u8::from(!node.structural)
+    }
+
+    /// # keep the tree within roughly the given number of bytes instead of running out of memory
+    /// once it grows bigger, the deepest subtrees are collapsed into their parents keeping the hits,
+    /// so the report gets less granular, but the input can be arbitrarily large
+    /// the limit isn't saved along with the tree
+    pub fn limit_memory(&mut self, bytes: usize) {
+        self.memory_limit = Some(bytes);
+        self.coarsen_at = 0;
+        self.fit_memory(&mut ());
+    }
+
+    /// estimated number of bytes taken by the tree, see limit_memory
+    pub fn memory_used(&self) -> usize {
+        memory_of(self.nodes.len(), self.seen.len())
+    }
+
+    /// whether addresses were collapsed into coarser subnets to stay within the memory limit
+    pub fn is_coarsened(&self) -> bool {
+        self.coarsened
+    }
+
+    /// # remember when leafs are pushed from now on, the time is e.g. the start of the run in unix seconds
+    /// a saved tree keeps the stamps, so they span the runs, see get_seen_map
+    pub fn track_seen(&mut self, now: i64) {
+        self.now = Some(now);
+    }
+
+    /// stamp the leaf with the current time if it's tracked
+    fn stamp(&mut self, subnet: &Subnet) {
+        if let Some(now) = self.now {
+            // a coarse leaf takes the stamps of its addresses
+            let subnet = match self.coarse_leaf_of(subnet) {
+                Some(at) => &self.nodes[at].subnet,
+                None => subnet,
+            };
+            self.seen
+                .entry((subnet.bits, subnet.mask_len))
+                .and_modify(|seen| seen.last = cmp::max(seen.last, now))
+                .or_insert(Seen {
+                    first: now,
+                    last: now,
+                });
+        }
+    }
+
+    #[cfg(feature = "persist")]
+    pub(crate) fn seen(&self) -> &HashMap<(u32, u8), Seen> {
+        &self.seen
+    }
+
+    #[cfg(feature = "persist")]
+    pub(crate) fn set_seen(&mut self, subnet: &Subnet, seen: Seen) {
+        self.seen.insert((subnet.bits, subnet.mask_len), seen);
+    }
+
+    /// # make a new empty tree which never merges addresses across the boundaries
+    /// e.g. RIR allocations or sites, the special ranges are boundaries anyway
+    pub fn with_boundaries(boundaries: impl IntoIterator<Item = Subnet>) -> Self {
+        Self::rooted_at(Subnet::root(), boundaries)
+    }
+
+    /// # same as with_boundaries, but the tree starts from the given subnet instead of 0.0.0.0/0
+    /// e.g. to analyze a single allocation, addresses outside of it are rejected by push
+    pub fn rooted_at(root: Subnet, boundaries: impl IntoIterator<Item = Subnet>) -> Self {
+        let mut tree = Self::new();
+        tree.nodes[ROOT].subnet = root;
+        tree.boundaries.extend(boundaries);
+        tree
+    }
+
+    /// the subnet every address of the tree is within, see rooted_at
+    pub fn root(&self) -> &Subnet {
+        &self.nodes[ROOT].subnet
+    }
+
+    /// the error of a subnet the tree doesn't take
+    pub(crate) fn rejected(&self, subnet: Subnet) -> PushError {
+        PushError::OutsideRoot {
+            subnet,
+            root: self.root().clone(),
+        }
+    }
+
+    /// make a new empty tree with room for the given number of addresses
+    pub fn with_capacity(addresses: usize) -> Self {
+        let mut tree = Self::new();
+        tree.reserve(addresses);
+        tree
+    }
+
+    /// make room for the given number of addresses more
+    /// it's only a hint, the tree still grows on demand if the memory can't be reserved upfront,
+    /// no more is reserved than the memory limit leaves room for, see limit_memory
+    pub fn reserve(&mut self, addresses: usize) {
+        // every address adds a leaf and at most one intermediate subnet
+        let mut nodes = addresses.saturating_mul(2);
+        if let Some(limit) = self.memory_limit {
+            let room = limit.saturating_sub(self.memory_used()) / memory_of(1, 0);
+            nodes = cmp::min(nodes, room);
+        }
+        let _ = self.nodes.try_reserve(nodes);
+    }
+
+    /// number of nodes, node indices are below it
+    pub(crate) fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub(crate) fn subnet_at(&self, at: usize) -> &Subnet {
+        &self.nodes[at].subnet
+    }
+
+    /// children of the node, structural ones are skipped, see limit_children
+    pub(crate) fn children_at(&self, at: usize) -> Vec<usize> {
+        self.real_children(at).collect()
+    }
+
+    #[cfg(feature = "persist")]
+    pub(crate) fn hits_at(&self, at: usize) -> u64 {
+        self.nodes[at].hits
+    }
+
+    #[cfg(feature = "persist")]
+    pub(crate) fn is_coarse_at(&self, at: usize) -> bool {
+        self.nodes[at].coarse
+    }
+
+    /// mark the node as a collapsed subtree, e.g. a coarse leaf of a saved tree
+    #[cfg(feature = "persist")]
+    pub(crate) fn set_coarse_at(&mut self, at: usize) {
+        self.nodes[at].coarse = true;
+        self.coarsened = true;
+    }
+
+    #[cfg(feature = "persist")]
+    pub(crate) fn boundaries(&self) -> &[Subnet] {
+        &self.boundaries
+    }
+
+    /// add a node as the last child of the one at the index, nothing is checked
+    /// # returns
+    /// index of the new node
+    #[cfg(feature = "persist")]
+    pub(crate) fn add_child(&mut self, at: usize, subnet: Subnet, hits: u64) -> usize {
+        let ch = self.add(subnet, hits);
+        self.nodes[at].children.push(ch);
+        ch
+    }
+
+    /// add a detached node to the arena
+    fn add(&mut self, subnet: Subnet, hits: u64) -> usize {
+        self.nodes.push(Node::of(subnet, hits));
+        self.nodes.len() - 1
+    }
+
+    /// try to place the supplied subnet in the tree
+    /// # Returns
+    /// Ok(()) - address was adopted by the tree
+    /// Err(PushError::OutsideRoot) - supplied subnet doesn't belong to the current tree
+    pub fn push(&mut self, new_subnet: Subnet) -> Result<(), PushError> {
+        self.push_observed(new_subnet, &mut ())
+    }
+
+    /// same as push, but an address (or subnet) the tree has already is refused instead of counted once more
+    /// # Returns
+    /// Err(PushError::Duplicate) - the tree has the subnet as a leaf
+    pub fn push_unique(&mut self, new_subnet: Subnet) -> Result<(), PushError> {
+        if self.contains(&new_subnet) {
+            return Err(PushError::Duplicate { subnet: new_subnet });
+        }
+        self.push(new_subnet)
+    }
+
+    /// same as push, but reports subnets created along the way to the observer
+    pub fn push_observed(
+        &mut self,
+        new_subnet: Subnet,
+        observer: &mut dyn Observer,
+    ) -> Result<(), PushError> {
+        self.push_weighted(new_subnet, 1, observer)
+    }
+
+    /// same as push_observed, but the subnet counts as pushed hits times, e.g. to rank subnets by traffic
+    pub fn push_weighted(
+        &mut self,
+        new_subnet: Subnet,
+        hits: u64,
+        observer: &mut dyn Observer,
+    ) -> Result<(), PushError> {
+        self.stamp(&new_subnet);
+        self.push_at(ROOT, new_subnet, hits, observer)
+            .map_err(|subnet| self.rejected(subnet))?;
+        self.fit_memory(observer);
+        Ok(())
+    }
+
+    /// coarsen the tree if it outgrew the memory limit, a quarter of the limit is freed
+    /// at once, so it isn't done again on the very next push,
+    /// if the tree stays over the limit, e.g. all the leafs are children of the root,
+    /// it isn't tried again before the tree grows by another quarter of the limit
+    fn fit_memory(&mut self, observer: &mut dyn Observer) {
+        let Some(limit) = self.memory_limit else {
+            return;
+        };
+        let used = self.memory_used();
+        if used <= limit || used < self.coarsen_at {
+            return;
+        }
+        if let Some(mask_len) = self.coarsen(limit - limit / 4) {
+            observer.on_tree_coarsened(mask_len);
+        }
+        let used = self.memory_used();
+        self.coarsen_at = if used > limit { used + limit / 4 } else { 0 };
+    }
+
+    /// # collapse the deepest subtrees into coarse leafs until the tree takes at most the given bytes
+    /// # returns
+    /// None - if nothing could be collapsed, e.g. all the leafs are children of the root
+    /// Some(mask_len) - the shortest mask of the collapsed subtrees
+    fn coarsen(&mut self, bytes: usize) -> Option<u8> {
+        let mut shortest = None;
+        let mut nodes = self.nodes.len();
+        while memory_of(nodes, self.seen.len()) > bytes {
+            let twigs = self.twigs();
+            let Some(&deepest) = twigs.first() else {
+                break;
+            };
+            let mask_len = self.nodes[deepest].subnet.mask_len;
+            for at in twigs {
+                if self.nodes[at].subnet.mask_len != mask_len
+                    || memory_of(nodes, self.seen.len()) <= bytes
+                {
+                    break;
+                }
+                nodes -= self.collapse(at);
+            }
+            shortest = Some(mask_len);
+            self.compact();
+        }
+        if shortest.is_some() {
+            self.recount_all();
+        }
+        shortest
+    }
+
+    /// nodes (but the root) with nothing but leafs as children, the longest masks first
+    fn twigs(&self) -> Vec<usize> {
+        let mut twigs: Vec<usize> = (ROOT + 1..self.nodes.len())
+            .filter(|&at| {
+                let children = &self.nodes[at].children;
+                !children.is_empty()
+                    && children
+                        .iter()
+                        .all(|&ch| self.nodes[ch].children.is_empty())
+            })
+            .collect();
+        twigs.sort_by_key(|&at| cmp::Reverse(self.nodes[at].subnet.mask_len));
+        twigs
+    }
+
+    /// # turn the node into a coarse leaf with the hits and stamps of its children
+    /// the children are left in the arena until it's compacted
+    /// # returns
+    /// number of the nodes dropped
+    fn collapse(&mut self, at: usize) -> usize {
+        let children = take(&mut self.nodes[at].children);
+        let key = (self.nodes[at].subnet.bits, self.nodes[at].subnet.mask_len);
+        let mut seen = None::<Seen>;
+        for &ch in &children {
+            let child = &self.nodes[ch].subnet;
+            if let Some(stamp) = self.seen.remove(&(child.bits, child.mask_len)) {
+                seen = Some(seen.map_or(stamp, |seen| seen.join(&stamp)));
+            }
+        }
+        if let Some(seen) = seen {
+            self.seen.insert(key, seen);
+        }
+        let hits = children.iter().map(|&ch| self.nodes[ch].hits).sum();
+        let node = &mut self.nodes[at];
+        node.hits = hits;
+        node.coarse = true;
+        node.structural = false;
+        self.coarsened = true;
+        children.len()
+    }
+
+    /// drop the nodes no longer reachable from the root, the order of the rest is kept
+    fn compact(&mut self) {
+        fn move_node(old: &mut [Node], at: usize, new: &mut Vec<Node>) -> usize {
+            let mut node = replace(&mut old[at], Node::of(Subnet::root(), 0));
+            let children = take(&mut node.children);
+            new.push(node);
+            let moved = new.len() - 1;
+            new[moved].children = children.iter().map(|&ch| move_node(old, ch, new)).collect();
+            moved
+        }
+        let mut old = take(&mut self.nodes);
+        move_node(&mut old, ROOT, &mut self.nodes);
+    }
+
+    /// the coarse leaf containing the subnet, if the tree was coarsened
+    fn coarse_leaf_of(&self, subnet: &Subnet) -> Option<usize> {
+        if !self.coarsened {
+            return None;
+        }
+        let mut at = ROOT;
+        loop {
+            let node = &self.nodes[at];
+            if node.coarse {
+                return Some(at);
+            }
+            at = *node
+                .children
+                .iter()
+                .find(|&&ch| self.nodes[ch].subnet.contains(subnet))?;
+        }
+    }
+
+    fn push_at(
+        &mut self,
+        at: usize,
+        mut new_subnet: Subnet,
+        hits: u64,
+        observer: &mut dyn Observer,
+    ) -> Result<(), Subnet> {
+        if !self.nodes[at].subnet.contains(&new_subnet) {
+            return Err(new_subnet);
+        }
+        if self.add_hits(at, &new_subnet, hits) {
+            observer.on_decision(&Decision::Counted {
+                new_subnet: &new_subnet,
+                leaf: &self.nodes[at].subnet,
+            });
+            return Ok(());
+        }
+        let min_mask = self.min_mask_at(at);
+        for i in 0..self.nodes[at].children.len() {
+            let ch = self.nodes[at].children[i];
+            match self.push_at(ch, new_subnet, hits, observer) {
+                Ok(_) => {
+                    // address found its place, only its addresses are to be counted here
+                    self.recount(at);
+                    return Ok(());
+                }
+                // a half of the node's subnet isn't joined with anything outside of it
+                Err(not_consumed) if self.nodes[ch].structural => new_subnet = not_consumed,
+                Err(not_consumed) => {
+                    // it wasn't consumed - try to adopt
+                    match self.common_within(ch, &not_consumed, min_mask, observer) {
+                        Some(new_intermediate) => {
+                            observer.on_decision(&Decision::Merged {
+                                new_subnet: &not_consumed,
+                                neighbour: &self.nodes[ch].subnet,
+                                common: &new_intermediate,
+                            });
+                            observer.on_subnet_created(&new_intermediate);
+                            self.stepdown(ch, new_intermediate, not_consumed, hits);
+                            self.recount(at);
+                            return Ok(());
+                        }
+                        None => new_subnet = not_consumed,
+                    }
+                }
+            }
+        }
+        observer.on_decision(&Decision::Adopted {
+            new_subnet: &new_subnet,
+            parent: &self.nodes[at].subnet,
+        });
+        let new_node = self.add(new_subnet, hits);
+        self.nodes[at].children.push(new_node);
+        self.rebalance(at);
+        self.recount(at);
+        Ok(())
+    }
+
+    /// # common subnet of the node and the new subnet to join them into,
+    /// the observer is told why they aren't joined if they aren't
+    /// # returns
+    /// None - if there is none within min_mask, it would span across a boundary or the merge policy refuses it
+    fn common_within(
+        &self,
+        at: usize,
+        new_subnet: &Subnet,
+        min_mask: u8,
+        observer: &mut dyn Observer,
+    ) -> Option<Subnet> {
+        let neighbour = &self.nodes[at].subnet;
+        let Some(common) = Subnet::common_of(neighbour, new_subnet, Some(min_mask)) else {
+            observer.on_decision(&Decision::Diverged {
+                new_subnet,
+                neighbour,
+                min_mask,
+            });
+            return None;
+        };
+        if let Some(boundary) = self.spanned(&common, neighbour, new_subnet) {
+            observer.on_decision(&Decision::Bounded {
+                new_subnet,
+                neighbour,
+                common: &common,
+                boundary,
+            });
+            return None;
+        }
+        if !self.may_merge(at, &common, new_subnet) {
+            observer.on_decision(&Decision::Refused {
+                new_subnet,
+                neighbour,
+                common: &common,
+            });
+            return None;
+        }
+        Some(common)
+    }
+
+    /// whether the merge policy lets the new subnet join the node into the common subnet
+    fn may_merge(&self, at: usize, common: &Subnet, new_subnet: &Subnet) -> bool {
+        self.merge_policy
+            .as_ref()
+            .is_none_or(|policy| policy.should_merge(&Merge::new(self, at, common, new_subnet)))
+    }
+
+    /// # the boundary merging the subnets into their common one spans across,
+    /// i.e. one of them is inside a boundary and the common subnet is bigger than it
+    /// # returns
+    /// None - if the common subnet stays within the boundaries
+    fn spanned(&self, common: &Subnet, s1: &Subnet, s2: &Subnet) -> Option<&Subnet> {
+        self.boundaries.iter().find(|b| {
+            common.mask_len < b.mask_len && common.contains(b) && (b.contains(s1) || b.contains(s2))
+        })
+    }
+
+    /// the most specific boundary containing the subnet, if any
+    fn boundary_of(&self, subnet: &Subnet) -> Option<&Subnet> {
+        self.boundaries
+            .iter()
+            .filter(|b| b.contains(subnet))
+            .max_by_key(|b| b.mask_len)
+    }
+
+    /// whether the node mixes leafs from inside of a boundary with ones from outside of it
+    fn crosses_boundary(&self, at: usize) -> bool {
+        let node = &self.nodes[at];
+        self.boundaries.iter().any(|b| {
+            node.subnet.mask_len < b.mask_len
+                && node.subnet.contains(b)
+                && self.real_children(at).any(|ch| {
+                    let child = &self.nodes[ch].subnet;
+                    b.contains(child) || (child.contains(b) && self.crosses_boundary(ch))
+                })
+        })
+    }
+
+    /// a leaf pushed once more only gets more hits, so does a coarse leaf for the addresses within
+    /// # returns
+    /// true - if the node is a leaf of the same subnet or a coarse one containing it
+    fn add_hits(&mut self, at: usize, new_subnet: &Subnet, hits: u64) -> bool {
+        let node = &mut self.nodes[at];
+        let takes = node.subnet == *new_subnet || (node.coarse && node.subnet.contains(new_subnet));
+        if at == ROOT || !node.children.is_empty() || !takes {
+            return false;
+        }
+        node.hits += hits;
+        true
+    }
+
+    /// replace the node's subnet with new_subnet, the node's old content and the neighbour become its children
+    fn stepdown(&mut self, at: usize, new_subnet: Subnet, neighbour: Subnet, neighbour_hits: u64) {
+        let old_subnet = replace(&mut self.nodes[at].subnet, new_subnet);
+        let old_children = take(&mut self.nodes[at].children);
+        let old_hits = take(&mut self.nodes[at].hits);
+        let old_coarse = take(&mut self.nodes[at].coarse);
+        let old_me = self.add(old_subnet, old_hits);
+        self.nodes[old_me].children = old_children;
+        self.nodes[old_me].coarse = old_coarse;
+        self.recount(old_me);
+        let neighbour = self.add(neighbour, neighbour_hits);
+        self.nodes[at].children = smallvec![old_me, neighbour];
+        self.recount(at);
+    }
+
+    /// lazily walk "subnets" - subnets that contain at least one tree leaf (IP address)
+    /// subnets overlapping the whitelist are split into their children down to single leafs
+    fn subnet_nodes<'a>(
+        &'a self,
+        whitelist: Option<&'a SubnetSet>,
+    ) -> impl Iterator<Item = usize> + 'a {
+        let mut stack = vec![ROOT];
+        iter::from_fn(move || {
+            while let Some(at) = stack.pop() {
+                match self.visit(at, whitelist) {
+                    Visit::Subnet => return Some(at),
+                    Visit::Children => stack.extend(self.nodes[at].children.iter().rev()),
+                }
+            }
+            None
+        })
+    }
+
+    /// whether the node is reported as a subnet
+    fn visit(&self, at: usize, whitelist: Option<&SubnetSet>) -> Visit {
+        let node = &self.nodes[at];
+        if node.structural {
+            // its children are visited as the parent's ones
+            return Visit::Children;
+        }
+        if at != ROOT && node.children.is_empty() {
+            // a leaf which isn't chopped along with its parent is a subnet on its own,
+            // e.g. one of a split subnet or a collapsed one
+            return Visit::Subnet;
+        }
+        let split =
+            whitelist.is_some_and(|w| w.overlaps(&node.subnet)) || self.crosses_boundary(at);
+        if !split
+            && self
+                .real_children(at)
+                .any(|ch| self.nodes[ch].subnet.mask_len == 32)
+        {
+            // chop the subtree at the first IP address in it
+            return Visit::Subnet;
+        }
+        if !split && self.is_complete(at) {
+            // e.g. both halves of a /23 make the /23 rather than two subnets
+            return Visit::Subnet;
+        }
+        Visit::Children
+    }
+
+    /// whether the leafs under the node cover all of its address space,
+    /// a coarse leaf doesn't as it may miss some of its addresses
+    fn is_complete(&self, at: usize) -> bool {
+        let node = &self.nodes[at];
+        !node.children.is_empty() && node.covered == node.subnet.size()
+    }
+
+    /// count the addresses of the leafs below the node from the counts of its children
+    fn recount(&mut self, at: usize) {
+        let node = &self.nodes[at];
+        let covered = match (node.children.is_empty(), node.coarse) {
+            (true, true) => u64::MAX,
+            (true, false) => node.subnet.size(),
+            (false, _) => node
+                .children
+                .iter()
+                .map(|&ch| self.nodes[ch].covered)
+                .fold(0, u64::saturating_add),
+        };
+        self.nodes[at].covered = covered;
+    }
+
+    /// count the addresses of the leafs below every node, children first, e.g. once the tree is rebuilt
+    pub(crate) fn recount_all(&mut self) {
+        let mut order = vec![ROOT];
+        let mut i = 0;
+        while i < order.len() {
+            order.extend(self.nodes[order[i]].children.iter().copied());
+            i += 1;
+        }
+        for at in order.into_iter().rev() {
+            self.recount(at);
+        }
+    }
+
+    /// # find the reported subnet the address (or subnet) falls into, see subnets_iter
+    /// the address itself doesn't need to be in the tree
+    /// # returns
+    /// None - if no reported subnet contains it
+    pub fn subnet_of(&self, addr: &Subnet) -> Option<&Subnet> {
+        let mut at = ROOT;
+        loop {
+            match self.visit(at, None) {
+                Visit::Subnet => return Some(&self.nodes[at].subnet),
+                Visit::Children => {
+                    at = *self.nodes[at]
+                        .children
+                        .iter()
+                        .find(|&&ch| self.nodes[ch].subnet.contains(addr))?;
+                }
+            }
+        }
+    }
+
+    /// whether the tree has the address (or subnet) as a leaf
+    pub fn contains(&self, addr: &Subnet) -> bool {
+        let mut at = ROOT;
+        loop {
+            let node = &self.nodes[at];
+            if at != ROOT && node.children.is_empty() {
+                return node.subnet == *addr;
+            }
+            match node
+                .children
+                .iter()
+                .find(|&&ch| self.nodes[ch].subnet.contains(addr))
+            {
+                Some(&ch) => at = ch,
+                None => return false,
+            }
+        }
+    }
+
+    /// # a /mask_len block of the pool none of the leafs is in, the tree being the used addresses, see SubnetSet::allocate
+    /// # returns
+    /// None - if there is no free block that big in the pool
+    pub fn allocate(&self, pool: &Subnet, mask_len: u8, fit: Fit) -> Option<Subnet> {
+        let used: SubnetSet = self
+            .leafs()
+            .filter(|leaf| pool.contains(leaf) || leaf.contains(pool))
+            .cloned()
+            .collect();
+        used.allocate(pool, mask_len, fit)
+    }
+
+    /// lazily walk leafs under the node in the tree's order, a leaf yields itself
+    pub(crate) fn leafs_of(&self, at: usize) -> impl Iterator<Item = &Subnet> {
+        self.leafs_from(vec![at])
+            .map(|leaf| &self.nodes[leaf].subnet)
+    }
+
+    /// lazily walk leafs under the node in the tree's order
+    fn leaf_nodes(&self, at: usize) -> impl Iterator<Item = usize> + '_ {
+        self.leafs_from(self.nodes[at].children.iter().rev().copied().collect())
+    }
+
+    /// lazily walk leafs of the nodes on the stack, a leaf on the stack yields itself
+    fn leafs_from(&self, mut stack: Vec<usize>) -> impl Iterator<Item = usize> + '_ {
+        iter::from_fn(move || {
+            while let Some(at) = stack.pop() {
+                let children = &self.nodes[at].children;
+                if children.is_empty() {
+                    return Some(at);
+                }
+                stack.extend(children.iter().rev());
+            }
+            None
+        })
+    }
+
+    /// lazily walk all the addresses (or subnets) pushed into the tree
+    pub(crate) fn leafs(&self) -> impl Iterator<Item = &Subnet> {
+        self.leaf_nodes(ROOT).map(|leaf| &self.nodes[leaf].subnet)
+    }
+
+    /// stream subnets along with their addresses without building a map of strings,
+    /// e.g. to export a huge report straight to disk
+    pub fn subnets_iter(&self) -> impl Iterator<Item = (&Subnet, impl Iterator<Item = &Subnet>)> {
+        self.subnets_walk(None)
+    }
+
+    /// same as subnets_iter, but no subnet encloses any of the whitelisted ranges,
+    /// subnets are split as needed, down to single addresses
+    pub fn subnets_iter_except<'a>(
+        &'a self,
+        whitelist: &'a SubnetSet,
+    ) -> impl Iterator<Item = (&'a Subnet, impl Iterator<Item = &'a Subnet>)> {
+        self.subnets_walk(Some(whitelist))
+    }
+
+    fn subnets_walk<'a>(
+        &'a self,
+        whitelist: Option<&'a SubnetSet>,
+    ) -> impl Iterator<Item = (&'a Subnet, impl Iterator<Item = &'a Subnet>)> {
+        self.subnet_groups(whitelist).map(|(s, leafs)| {
+            (
+                &self.nodes[s].subnet,
+                leafs.map(|leaf| &self.nodes[leaf].subnet),
+            )
+        })
+    }
+
+    /// same as subnets_walk, but with node indices
+    fn subnet_groups<'a>(
+        &'a self,
+        whitelist: Option<&'a SubnetSet>,
+    ) -> impl Iterator<Item = (usize, impl Iterator<Item = usize> + 'a)> {
+        self.subnet_nodes(whitelist).map(|s| {
+            // the node itself is the only address of a split leaf
+            let stack = if self.nodes[s].children.is_empty() {
+                vec![s]
+            } else {
+                self.nodes[s].children.iter().rev().copied().collect()
+            };
+            (s, self.leafs_from(stack))
+        })
+    }
+
+    /// take the tree apart into its leafs along with their hits
+    fn into_leafs(mut self) -> Vec<(Subnet, u64)> {
+        let leafs: Vec<usize> = self.leaf_nodes(ROOT).collect();
+        leafs
+            .into_iter()
+            .map(|leaf| {
+                let node = &mut self.nodes[leaf];
+                (replace(&mut node.subnet, Subnet::root()), node.hits)
+            })
+            .collect()
+    }
+
+    /// move all leafs of the other tree into this one
+    /// # Returns
+    /// Err - the first leaf which doesn't belong to the current tree
+    pub fn merge(&mut self, mut other: AddressTree) -> Result<(), PushError> {
+        for (key, seen) in take(&mut other.seen) {
+            let joined = self.seen.get(&key).map_or(seen, |own| own.join(&seen));
+            self.seen.insert(key, joined);
+        }
+        for (leaf, hits) in other.into_leafs() {
+            self.push_weighted(leaf, hits, &mut ())?;
+        }
+        Ok(())
+    }
+
+    /// # multiply hits of every leaf by the factor, rounding down, leafs left without hits are dropped
+    /// e.g. 0.5 after every snapshot of an endless stream, so addresses not seen again fade out
+    /// # returns
+    /// number of the leafs dropped
+    /// # Panics
+    /// if the factor isn't within 0.0..=1.0
+    pub fn decay(&mut self, factor: f64) -> usize {
+        assert!(
+            (0.0..=1.0).contains(&factor),
+            "a decay factor should be within 0.0..=1.0"
+        );
+        self.retain_leafs(|_, hits, _| {
+            *hits = (*hits as f64 * factor) as u64;
+            *hits > 0
+        })
+    }
+
+    /// # drop the leafs last seen before the time, e.g. now minus a TTL to keep only the recent addresses
+    /// the stamps come from track_seen, which is to be called with the current time as the input goes,
+    /// leafs pushed without it are kept
+    /// # returns
+    /// number of the leafs dropped
+    pub fn expire(&mut self, before: i64) -> usize {
+        self.retain_leafs(|_, _, seen| seen.is_none_or(|seen| seen.last >= before))
+    }
+
+    /// # keep the leafs the predicate tells to, it may change their hits
+    /// subnets left with a single child are replaced by it, as if the dropped leafs were never pushed
+    /// # returns
+    /// number of the leafs dropped
+    pub(crate) fn retain_leafs(
+        &mut self,
+        mut keep: impl FnMut(&Subnet, &mut u64, Option<&Seen>) -> bool,
+    ) -> usize {
+        let leafs = self.leaf_nodes(ROOT).count();
+        if self.retain_at(ROOT, &mut keep).is_none() {
+            self.nodes[ROOT].children.clear();
+        }
+        self.compact();
+        self.recount_all();
+        let kept: Vec<(u32, u8)> = self
+            .leafs()
+            .map(|leaf| (leaf.bits, leaf.mask_len))
+            .collect();
+        if kept.len() < leafs {
+            let mut seen = HashMap::with_capacity(kept.len());
+            for key in kept {
+                if let Some(stamp) = self.seen.remove(&key) {
+                    seen.insert(key, stamp);
+                }
+            }
+            self.seen = seen;
+        }
+        leafs - self.leaf_nodes(ROOT).count()
+    }
+
+    /// # drop the leafs under the node the predicate refuses, the dropped nodes stay in the arena
+    /// # returns
+    /// None - if nothing is left under the node
+    /// Some(at) - the node to take its place, e.g. its only child left
+    fn retain_at(
+        &mut self,
+        at: usize,
+        keep: &mut impl FnMut(&Subnet, &mut u64, Option<&Seen>) -> bool,
+    ) -> Option<usize> {
+        let children = take(&mut self.nodes[at].children);
+        if children.is_empty() {
+            if at == ROOT {
+                return Some(ROOT);
+            }
+            let node = &mut self.nodes[at];
+            let seen = self.seen.get(&(node.subnet.bits, node.subnet.mask_len));
+            return keep(&node.subnet, &mut node.hits, seen).then_some(at);
+        }
+        let mut kept: SmallVec<[usize; 2]> = SmallVec::new();
+        for ch in children {
+            match self.retain_at(ch, keep) {
+                // a half without a pair doesn't split anything up any longer
+                Some(ch) if self.nodes[ch].structural && !self.nodes[at].structural => {
+                    kept.extend(take(&mut self.nodes[ch].children))
+                }
+                Some(ch) => kept.push(ch),
+                None => {}
+            }
+        }
+        match kept.len() {
+            0 if at != ROOT => None,
+            1 if at != ROOT => Some(kept[0]),
+            _ => {
+                self.nodes[at].children = kept;
+                self.rebalance(at);
+                Some(at)
+            }
+        }
+    }
+
+    /// push subnets sorted by address, e.g. from a sorted export
+    /// every subnet is only compared against the rightmost path of the tree instead of all the children,
+    /// out-of-order subnets are still accepted, they just take the regular push
+    /// # Returns
+    /// Err - the first subnet which doesn't belong to the current tree
+    pub fn extend_sorted(
+        &mut self,
+        subnets: impl IntoIterator<Item = Subnet>,
+    ) -> Result<(), PushError> {
+        let mut last_bits = 0;
+        for subnet in subnets {
+            if subnet.bits < last_bits {
+                self.push(subnet)?;
+            } else {
+                last_bits = subnet.bits;
+                self.stamp(&subnet);
+                self.push_rightmost(ROOT, subnet)
+                    .map_err(|subnet| self.rejected(subnet))?;
+                self.fit_memory(&mut ());
+            }
+        }
+        Ok(())
+    }
+
+    /// same as push, but only the last child is considered, which is enough for sorted input
+    fn push_rightmost(&mut self, at: usize, new_subnet: Subnet) -> Result<(), Subnet> {
+        if !self.nodes[at].subnet.contains(&new_subnet) {
+            return Err(new_subnet);
+        }
+        if self.add_hits(at, &new_subnet, 1) {
+            return Ok(());
+        }
+        match self.nodes[at].children.last().copied() {
+            Some(last) if self.nodes[last].subnet.contains(&new_subnet) => {
+                self.push_rightmost(last, new_subnet)?;
+            }
+            Some(last) if !self.nodes[last].structural => {
+                let min_mask = Some(self.min_mask_at(at));
+                let last_subnet = &self.nodes[last].subnet;
+                match Subnet::common_of(last_subnet, &new_subnet, min_mask) {
+                    Some(common) if self.spanned(&common, last_subnet, &new_subnet).is_some() => {
+                        // an earlier child may still adopt it without spanning a boundary
+                        return self.push_at(at, new_subnet, 1, &mut ());
+                    }
+                    Some(new_intermediate)
+                        if self.may_merge(last, &new_intermediate, &new_subnet) =>
+                    {
+                        self.stepdown(last, new_intermediate, new_subnet, 1)
+                    }
+                    _ => {
+                        let new_node = self.add(new_subnet, 1);
+                        self.nodes[at].children.push(new_node);
+                        self.rebalance(at);
+                    }
+                }
+            }
+            _ => {
+                let new_node = self.add(new_subnet, 1);
+                self.nodes[at].children.push(new_node);
+                self.rebalance(at);
+            }
+        }
+        self.recount(at);
+        Ok(())
+    }
+
+    /// make a human-readable map of subnets to all their addresses
+    pub fn get_subnets_map(&self) -> SubnetsMap {
+        self.subnets_iter()
+            .map(|(subnet, leafs)| (subnet.to_string(), leafs.map(Subnet::to_string).collect()))
+            .collect()
+    }
+
+    /// make a human-readable map of groups to all their addresses
+    pub fn get_groups_map(&self, group_by: &GroupBy) -> SubnetsMap {
+        if *group_by == GroupBy::Subnet {
+            return self.get_subnets_map();
+        }
+        to_strings(self.groups(group_by, None))
+    }
+
+    /// same as get_groups_map, but no group encloses any of the whitelisted ranges
+    /// addresses of the groups which would enclose one are reported on their own
+    pub fn get_groups_map_except(&self, group_by: &GroupBy, whitelist: &SubnetSet) -> SubnetsMap {
+        to_strings(self.groups(group_by, Some(whitelist)))
+    }
+
+    /// # make several maps of the same addresses at once, e.g. /8, /16, /24 and the subnets of the tree
+    /// the tree is walked only once whatever the number of views
+    /// # returns
+    /// a map per view in the same order, see get_groups_map
+    pub fn get_views_map(&self, views: &[GroupBy]) -> Vec<SubnetsMap> {
+        self.views(views, None)
+    }
+
+    /// same as get_views_map, but no group of any view encloses any of the whitelisted ranges
+    pub fn get_views_map_except(
+        &self,
+        views: &[GroupBy],
+        whitelist: &SubnetSet,
+    ) -> Vec<SubnetsMap> {
+        self.views(views, Some(whitelist))
+    }
+
+    fn views(&self, views: &[GroupBy], whitelist: Option<&SubnetSet>) -> Vec<SubnetsMap> {
+        let mut maps = vec![SubnetsMap::new(); views.len()];
+        for (s, leafs) in self.subnet_groups(whitelist) {
+            let subnet = self.nodes[s].subnet.to_string();
+            for leaf in leafs {
+                let addr = &self.nodes[leaf].subnet;
+                let name = addr.to_string();
+                for (view, map) in views.iter().zip(&mut maps) {
+                    let group = match view {
+                        GroupBy::Subnet => subnet.clone(),
+                        _ => self.group_name(view, addr, whitelist),
+                    };
+                    map.entry(group).or_default().push(name.clone());
+                }
+            }
+        }
+        maps
+    }
+
+    /// total hits of every group, see push_weighted
+    pub fn get_weights_map(&self, group_by: &GroupBy) -> WeightsMap {
+        self.weights(group_by, None)
+    }
+
+    /// same as get_weights_map for the groups of get_groups_map_except
+    pub fn get_weights_map_except(&self, group_by: &GroupBy, whitelist: &SubnetSet) -> WeightsMap {
+        self.weights(group_by, Some(whitelist))
+    }
+
+    /// # hits of every address (or subnet) pushed into the tree
+    /// # returns
+    /// map of address -> hits
+    pub fn get_hits_map(&self) -> WeightsMap {
+        self.leaf_nodes(ROOT)
+            .map(|leaf| (self.nodes[leaf].subnet.to_string(), self.nodes[leaf].hits))
+            .collect()
+    }
+
+    /// # leafs of every tag, see tag
+    /// # returns
+    /// map of label -> addresses, untagged addresses are left out
+    pub fn get_tags_map(&self) -> SubnetsMap {
+        let mut res = SubnetsMap::new();
+        for leaf in self.leafs() {
+            if let Some(label) = self.tag_of(leaf) {
+                res.entry(label.to_string())
+                    .or_default()
+                    .push(leaf.to_string());
+            }
+        }
+        res
+    }
+
+    /// drop tagged addresses from the groups made of this tree's leafs, e.g. by get_groups_map,
+    /// so only the untagged remainder is left, groups left empty are dropped too
+    pub fn retain_untagged(&self, subnets: &mut SubnetsMap) {
+        if self.tags.is_empty() {
+            return;
+        }
+        for addrs in subnets.values_mut() {
+            addrs.retain(|addr| {
+                Subnet::from_str(addr).map_or(true, |addr| self.tag_of(&addr).is_none())
+            });
+        }
+        subnets.retain(|_, addrs| !addrs.is_empty());
+    }
+
+    /// # first and last time the addresses of every group were seen, see track_seen
+    /// # returns
+    /// map of group -> seen, groups without any stamped address are left out
+    pub fn get_seen_map(&self, group_by: &GroupBy) -> SeenMap {
+        self.seen_groups(group_by, None)
+    }
+
+    /// same as get_seen_map for the groups of get_groups_map_except
+    pub fn get_seen_map_except(&self, group_by: &GroupBy, whitelist: &SubnetSet) -> SeenMap {
+        self.seen_groups(group_by, Some(whitelist))
+    }
+
+    fn seen_groups(&self, group_by: &GroupBy, whitelist: Option<&SubnetSet>) -> SeenMap {
+        self.group_nodes(group_by, whitelist)
+            .into_iter()
+            .filter_map(|(group, leafs)| {
+                let seen = leafs
+                    .iter()
+                    .filter_map(|&leaf| {
+                        let subnet = &self.nodes[leaf].subnet;
+                        self.seen.get(&(subnet.bits, subnet.mask_len))
+                    })
+                    .copied()
+                    .reduce(|s1, s2| s1.join(&s2))?;
+                Some((group, seen))
+            })
+            .collect()
+    }
+
+    fn weights(&self, group_by: &GroupBy, whitelist: Option<&SubnetSet>) -> WeightsMap {
+        self.group_nodes(group_by, whitelist)
+            .into_iter()
+            .map(|(group, leafs)| (group, leafs.iter().map(|&leaf| self.nodes[leaf].hits).sum()))
+            .collect()
+    }
+
+    /// same as get_groups_map, but addresses are left as they are
+    pub(crate) fn groups<'a>(
+        &'a self,
+        group_by: &GroupBy,
+        whitelist: Option<&'a SubnetSet>,
+    ) -> HashMap<String, Vec<&'a Subnet>> {
+        self.group_nodes(group_by, whitelist)
+            .into_iter()
+            .map(|(group, leafs)| {
+                let leafs = leafs.iter().map(|&leaf| &self.nodes[leaf].subnet).collect();
+                (group, leafs)
+            })
+            .collect()
+    }
+
+    /// groups with indices of their leafs
+    fn group_nodes(
+        &self,
+        group_by: &GroupBy,
+        whitelist: Option<&SubnetSet>,
+    ) -> HashMap<String, Vec<usize>> {
+        if *group_by == GroupBy::Subnet {
+            return self
+                .subnet_groups(whitelist)
+                .map(|(s, leafs)| (self.nodes[s].subnet.to_string(), leafs.collect()))
+                .collect();
+        }
+        let mut res: HashMap<String, Vec<usize>> = HashMap::new();
+        for leaf in self.leaf_nodes(ROOT) {
+            let group = self.group_name(group_by, &self.nodes[leaf].subnet, whitelist);
+            res.entry(group).or_default().push(leaf);
+        }
+        res
+    }
+
+    /// group of the address unless it's whitelisted or the group would span across a boundary,
+    /// not applicable to GroupBy::Subnet
+    fn group_name(
+        &self,
+        group_by: &GroupBy,
+        addr: &Subnet,
+        whitelist: Option<&SubnetSet>,
+    ) -> String {
+        let range = group_by.range_of(addr);
+        match (whitelist, self.boundary_of(addr)) {
+            (Some(w), _) if w.overlaps(&range) => addr.to_string(),
+            // the group can't be bigger than the boundary its address belongs to
+            (_, Some(b)) if range.mask_len < b.mask_len => b.to_string(),
+            _ => group_by.group_of(addr),
+        }
+    }
+
+    fn fmt_node(&self, at: usize, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+        let node = &self.nodes[at];
+        write!(f, "{}", node.subnet)?;
+        if !node.children.is_empty() {
+            f.write_str("=>[")?;
+            for &ch in &node.children {
+                self.fmt_node(ch, f)?;
+            }
+            f.write_str("]")?;
+        }
+        f.write_str(";")
+    }
+}
+
+/// see AddressTree::memory_used, a node may take one more index in its parent's spilled children
+fn memory_of(nodes: usize, stamps: usize) -> usize {
+    nodes * (size_of::<Node>() + size_of::<usize>())
+        + stamps * (size_of::<(u32, u8)>() + size_of::<Seen>())
+}
+
+fn to_strings(groups: HashMap<String, Vec<&Subnet>>) -> SubnetsMap {
+    groups
+        .into_iter()
+        .map(|(group, addrs)| (group, addrs.into_iter().map(Subnet::to_string).collect()))
+        .collect()
+}
+
+impl Node {
+    fn of(subnet: Subnet, hits: u64) -> Self {
+        Self {
+            covered: subnet.size(),
+            subnet,
+            children: SmallVec::new(),
+            hits,
+            coarse: false,
+            structural: false,
+        }
+    }
+}
+
+impl Default for AddressTree {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// push the subnets into the tree, ones outside of its root are skipped, see rooted_at
+impl Extend<Subnet> for AddressTree {
+    fn extend<I: IntoIterator<Item = Subnet>>(&mut self, subnets: I) {
+        for subnet in subnets {
+            let _ = self.push(subnet);
+        }
+    }
+}
+
+/// a tree from 0.0.0.0/0 takes any subnet, so nothing is rejected
+impl FromIterator<Subnet> for AddressTree {
+    fn from_iter<I: IntoIterator<Item = Subnet>>(subnets: I) -> Self {
+        let subnets = subnets.into_iter();
+        let mut tree = Self::with_capacity(subnets.size_hint().0);
+        tree.extend(subnets);
+        tree
+    }
+}
+
+impl Display for AddressTree {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+        self.fmt_node(ROOT, f)
+    }
+}
@@ -0,0 +1,261 @@
+use std::{
+    io::{self, ErrorKind, Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    str::FromStr,
+    time::Duration,
+};
+
+use crate::{
+    metadata::enrich_with,
+    prefix_map::PrefixMap,
+    rate::{Limiter, RateLimit},
+    subnet::mask_of,
+    EnrichmentProvider, Error, IpMetadata, Metadata, Observer, Subnet,
+};
+
+/// where whois lookups start, it refers to the registry responsible for the address
+pub const IANA_WHOIS: &str = "whois.iana.org";
+/// a registry may refer to another one, e.g. ARIN to RIPE for legacy ranges
+const MAX_REFERRALS: usize = 3;
+const WHOIS_TIMEOUT: Duration = Duration::from_secs(10);
+/// answers are a few KiB, a server sending more isn't a whois server
+const MAX_RESPONSE_LEN: u64 = 1 << 20;
+
+/// what the registry responsible for an address tells about its network
+#[derive(Debug, Clone, PartialEq)]
+pub struct WhoisNetwork {
+    pub netblock: Subnet, // the most specific network of the answer
+    pub netname: Option<String>,
+    pub country: Option<String>,
+    pub org: Option<String>,
+}
+
+impl From<WhoisNetwork> for IpMetadata {
+    fn from(network: WhoisNetwork) -> Self {
+        Self {
+            country: network.country,
+            org: network.org,
+            netname: network.netname,
+            ..Self::default()
+        }
+    }
+}
+
+/// plain whois (port 43) client, a fallback enrichment when there is no CSV to join or it lacks addresses
+#[derive(Debug)]
+pub struct Whois {
+    server: String,                 // server to start lookups from
+    known: PrefixMap<WhoisNetwork>, // answers by their networks
+    limiter: Limiter,
+}
+
+impl Whois {
+    /// lookups start at the server, e.g. IANA_WHOIS
+    pub fn new(server: impl Into<String>) -> Self {
+        Self {
+            server: server.into(),
+            known: PrefixMap::default(),
+            limiter: Limiter::new("whois", RateLimit::default()),
+        }
+    }
+
+    /// keep the lookups within the limit, a lookup counts once with all its referrals
+    pub fn with_rate_limit(mut self, limit: RateLimit) -> Self {
+        self.limiter = Limiter::new("whois", limit);
+        self
+    }
+
+    /// # network of the address, answers are remembered, so the rest of the network is never looked up again
+    /// # returns
+    /// None - if the registry doesn't know the network
+    /// Err - if the whois server can't be reached or the daily quota is used up
+    pub fn network_of(&mut self, addr: &Subnet) -> Result<Option<WhoisNetwork>, Error> {
+        if let Some((_, network)) = self.known.lookup(addr) {
+            return Ok(Some(network.clone()));
+        }
+        self.limiter.acquire()?;
+        let network =
+            query(&self.server, addr)?.and_then(|response| parse_network(&response, addr));
+        if let Some(network) = &network {
+            self.known.insert(&network.netblock, network.clone());
+        }
+        Ok(network)
+    }
+
+    /// # raw answer of the registry knowing the address, within the rate limit and never remembered
+    /// # returns
+    /// None - if there are too many referrals
+    /// Err - if the whois server can't be reached or the daily quota is used up
+    pub(crate) fn answer_of(&mut self, addr: &Subnet) -> Result<Option<String>, Error> {
+        self.limiter.acquire()?;
+        query(&self.server, addr)
+    }
+
+    /// # fill netname, country and org columns of the addresses in, values the metadata has already are kept
+    /// once the daily quota is used up, the rest of the addresses are left as they are
+    /// # returns
+    /// Err - if the whois server can't be reached
+    pub fn enrich<'a>(
+        &mut self,
+        metadata: &mut Metadata,
+        addrs: impl IntoIterator<Item = &'a Subnet>,
+    ) -> Result<(), Error> {
+        self.enrich_observed(metadata, addrs, &mut ())
+    }
+
+    /// same as enrich, but every decision is told to the observer, see Observer::on_enrichment
+    pub fn enrich_observed<'a>(
+        &mut self,
+        metadata: &mut Metadata,
+        addrs: impl IntoIterator<Item = &'a Subnet>,
+        observer: &mut impl Observer,
+    ) -> Result<(), Error> {
+        let is_complete =
+            |ip: &IpMetadata| ip.netname.is_some() && ip.country.is_some() && ip.org.is_some();
+        enrich_with("whois", metadata, addrs, observer, is_complete, |addr| {
+            let cached = self.known.lookup(addr).is_some();
+            (
+                cached,
+                self.network_of(addr).map(|found| found.map(Into::into)),
+            )
+        })
+    }
+}
+
+impl EnrichmentProvider for Whois {
+    fn enrich(&mut self, metadata: &mut Metadata, addrs: &[Subnet]) -> Result<(), Error> {
+        Whois::enrich(self, metadata, addrs)
+    }
+}
+
+/// # answer of the registry knowing the address, the referrals from the server are followed
+/// # returns
+/// None - if there are too many referrals
+pub(crate) fn query(server: &str, addr: &Subnet) -> Result<Option<String>, Error> {
+    let query = addr.to_string();
+    let query = query.trim_end_matches("/32");
+    let mut server = server.to_string();
+    for _ in 0..=MAX_REFERRALS {
+        let response = whois(&server, query)?;
+        match referral_of(&response) {
+            Some(referral) if referral != server => server = referral,
+            _ => return Ok(Some(response)),
+        }
+    }
+    Ok(None)
+}
+
+fn whois(server: &str, query: &str) -> Result<String, Error> {
+    let mut stream = connect(server)?;
+    stream.set_read_timeout(Some(WHOIS_TIMEOUT))?;
+    stream.set_write_timeout(Some(WHOIS_TIMEOUT))?;
+    stream.write_all(format!("{}\r\n", query).as_bytes())?;
+    let mut response = vec![];
+    stream
+        .take(MAX_RESPONSE_LEN + 1)
+        .read_to_end(&mut response)?;
+    if response.len() as u64 > MAX_RESPONSE_LEN {
+        return Err(io::Error::new(
+            ErrorKind::InvalidData,
+            format!("{} answered more than {} bytes", server, MAX_RESPONSE_LEN),
+        )
+        .into());
+    }
+    Ok(String::from_utf8_lossy(&response).into_owned())
+}
+
+/// # connect to the first address of the server which answers within WHOIS_TIMEOUT
+/// # returns
+/// Err - of the last address tried, if none answers
+fn connect(server: &str) -> Result<TcpStream, io::Error> {
+    let mut last_error = None;
+    for addr in (server, 43).to_socket_addrs()? {
+        match TcpStream::connect_timeout(&addr, WHOIS_TIMEOUT) {
+            Ok(stream) => return Ok(stream),
+            Err(e) => last_error = Some(e),
+        }
+    }
+    Err(last_error.unwrap_or_else(|| {
+        io::Error::new(ErrorKind::NotFound, format!("{} has no addresses", server))
+    }))
+}
+
+/// # network of a registry's answer, the formats of ARIN, RIPE, APNIC, AFRINIC and LACNIC are understood
+/// # returns
+/// None - if the answer has no network containing the address
+pub(crate) fn parse_network(response: &str, addr: &Subnet) -> Option<WhoisNetwork> {
+    let value = |names: &[&str]| first_field(response, names).map(String::from);
+    Some(WhoisNetwork {
+        netblock: netblock_of(response, addr)?,
+        netname: value(&["netname", "NetName"]),
+        country: value(&["country", "Country"]),
+        org: value(&["OrgName", "org-name", "owner", "descr"]),
+    })
+}
+
+/// values of the whois field, e.g. "OrgName:  Example" in the order they appear
+fn fields<'a>(response: &'a str, name: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+    response.lines().filter_map(move |line| {
+        let (field, value) = line.split_once(':')?;
+        (field.trim().eq_ignore_ascii_case(name) && !value.trim().is_empty()).then(|| value.trim())
+    })
+}
+
+pub(crate) fn first_field<'a>(response: &'a str, names: &[&'a str]) -> Option<&'a str> {
+    names.iter().find_map(|name| fields(response, name).next())
+}
+
+/// whois server the response refers to, e.g. "refer: whois.ripe.net" or "ReferralServer: whois://whois.ripe.net:43"
+fn referral_of(response: &str) -> Option<String> {
+    let referral = first_field(response, &["refer", "ReferralServer"])?;
+    let referral = referral.strip_prefix("whois://").unwrap_or(referral);
+    Some(referral.split(':').next().unwrap_or(referral).to_string())
+}
+
+/// the most specific network of the answer containing the address
+pub(crate) fn netblock_of(response: &str, addr: &Subnet) -> Option<Subnet> {
+    let cidrs = fields(response, "CIDR").flat_map(|value| value.split(','));
+    let ranges = ["inetnum", "NetRange"]
+        .iter()
+        .flat_map(|name| fields(response, name))
+        .filter_map(|range| {
+            let (first, last) = range.split_once('-')?;
+            let first = Subnet::from_str(first.trim()).ok()?;
+            let last = Subnet::from_str(last.trim()).ok()?;
+            block_within(first.bits, last.bits, addr)
+        });
+    cidrs
+        .filter_map(|cidr| Subnet::from_str(cidr.trim()).ok())
+        .chain(ranges)
+        .filter(|netblock| netblock.contains(addr))
+        .max_by_key(|netblock| netblock.mask_len)
+}
+
+/// # the largest CIDR block within the range of first to last addresses containing the address,
+/// ranges aren't always aligned, e.g. 185.1.2.0 - 185.1.5.255 has 185.1.2.0/23 and 185.1.4.0/23
+/// # returns
+/// None - if the range doesn't contain the address
+fn block_within(first: u32, last: u32, addr: &Subnet) -> Option<Subnet> {
+    let block = |mask_len: u8| {
+        let mask = mask_of(mask_len);
+        (addr.bits & mask, addr.bits | !mask)
+    };
+    let (start, end) = block(addr.mask_len);
+    if start < first || end > last {
+        return None;
+    }
+    let mask_len = (0..addr.mask_len)
+        .rev()
+        .take_while(|&mask_len| {
+            let (start, end) = block(mask_len);
+            first <= start && end <= last
+        })
+        .last()
+        .unwrap_or(addr.mask_len);
+    let mask = mask_of(mask_len);
+    Some(Subnet {
+        bits: addr.bits & mask,
+        mask_len,
+        mask,
+    })
+}
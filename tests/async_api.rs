@@ -0,0 +1,51 @@
+#![cfg(feature = "async")]
+
+use ipv4_classify::Error;
+
+#[tokio::test]
+async fn async_file_has_wrong_addr() {
+    match ipv4_classify::find_subnets_async(vec!["tests/res/invalid_ips.csv".to_string()]).await {
+        Err(Error::LineError {
+            line_no, reason, ..
+        }) => {
+            assert_eq!(2, line_no);
+            assert!(matches!(*reason, Error::ParseError { input, .. } if input == "256.0.1.2"));
+        }
+        other => panic!("unexpected result {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn async_file_does_not_exist() {
+    match ipv4_classify::find_subnets_async(vec!["non.file".to_string()]).await {
+        Err(Error::IoError(e)) => assert_eq!(std::io::ErrorKind::NotFound, e.kind()),
+        other => panic!("unexpected result {:?}", other),
+    }
+}
+
+#[cfg(feature = "dns")]
+#[tokio::test]
+async fn hostnames_resolved_from_the_cache() {
+    use ipv4_classify::{DnsCache, Resolver};
+
+    let cache = DnsCache::from_lines([
+        "a www.example.org 192.0.2.1 192.0.2.2",
+        "a gone.example.org",
+    ])
+    .unwrap();
+    let resolver = Resolver::new(4).with_cache(cache);
+    let lines = resolver
+        .resolve_lines(["www.example.org", "198.51.100.1", "gone.example.org", ""])
+        .await
+        .unwrap();
+    assert_eq!(
+        vec![
+            "192.0.2.1",
+            "192.0.2.2",
+            "198.51.100.1",
+            "gone.example.org",
+            ""
+        ],
+        lines
+    );
+}
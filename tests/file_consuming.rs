@@ -1,17 +1,348 @@
-use ipv4_classify;
+#![cfg(feature = "fs")]
+
+use ipv4_classify::Error;
 
 #[test]
-#[should_panic(
-    expected = "called `Result::unwrap()` on an `Err` value: \"unable to parse \\\"256.0.1.2\\\": ParseIntError { kind: PosOverflow }\""
-)]
 fn file_has_wrong_addr() {
-    ipv4_classify::find_subnets(vec!["tests/res/invalid_ips.csv".to_string()]).unwrap();
+    match ipv4_classify::find_subnets(vec!["tests/res/invalid_ips.csv".to_string()]) {
+        Err(Error::LineError {
+            file_name,
+            line_no,
+            offset,
+            line,
+            reason,
+        }) => {
+            assert_eq!(std::path::Path::new("tests/res/invalid_ips.csv"), file_name);
+            assert_eq!(2, line_no);
+            assert_eq!(10, offset);
+            assert_eq!("256.0.1.2", line);
+            match *reason {
+                Error::ParseError { input, reason } => {
+                    assert_eq!("256.0.1.2", input);
+                    assert_eq!("ParseIntError { kind: PosOverflow }", reason);
+                }
+                other => panic!("unexpected reason {:?}", other),
+            }
+        }
+        other => panic!("unexpected result {:?}", other),
+    }
 }
 
 #[test]
-#[should_panic(
-    expected = "called `Result::unwrap()` on an `Err` value: Os { code: 2, kind: NotFound, message: \"No such file or directory\" }"
-)]
 fn file_does_not_exist() {
-    ipv4_classify::find_subnets(vec!["non.file".to_string()]).unwrap();
+    match ipv4_classify::find_subnets(vec!["non.file".to_string()]) {
+        Err(Error::IoError(e)) => assert_eq!(std::io::ErrorKind::NotFound, e.kind()),
+        other => panic!("unexpected result {:?}", other),
+    }
+}
+
+#[test]
+fn lines_in_memory() {
+    let lines = vec!["10.0.0.1", " 10.0.0.2 ", "", "10.0.0.3"];
+    let subnets = ipv4_classify::classify_lines(lines).unwrap();
+    assert_eq!(1, subnets.len());
+    assert_eq!(3, subnets["10.0.0.0/30"].len());
+}
+
+#[test]
+fn lenient_keeps_going() {
+    let (subnets, failures) = ipv4_classify::find_groups_lenient(
+        ["tests/res/invalid_ips.csv", "non.file"],
+        &ipv4_classify::GroupBy::Subnet,
+    );
+    assert_eq!(1, subnets.len());
+    assert_eq!(2, failures.len());
+    assert_eq!(Some(2), failures[0].line_no);
+    assert_eq!(Some(10), failures[0].offset);
+    assert_eq!(Some("256.0.1.2"), failures[0].line.as_deref());
+    assert!(failures[0]
+        .to_string()
+        .starts_with("tests/res/invalid_ips.csv:2 (byte 10): "));
+    assert!(matches!(failures[0].reason, Error::ParseError { .. }));
+    assert_eq!(None, failures[1].line_no);
+    assert!(matches!(failures[1].reason, Error::IoError(_)));
+}
+
+#[test]
+fn cancelled_before_start() {
+    let token = ipv4_classify::CancelToken::new();
+    token.cancel();
+    let (subnets, failures) = ipv4_classify::find_groups_cancellable(
+        ["tests/res/invalid_ips.csv"],
+        &ipv4_classify::GroupBy::Subnet,
+        false,
+        &token,
+    )
+    .unwrap();
+    assert!(subnets.is_empty());
+    assert!(failures.is_empty());
+}
+
+#[derive(Default)]
+struct Counter {
+    parsed: usize,
+    created: usize,
+    files_done: usize,
+}
+
+impl ipv4_classify::Observer for Counter {
+    fn on_address_parsed(&mut self, _addr: &ipv4_classify::Subnet) {
+        self.parsed += 1;
+    }
+
+    fn on_subnet_created(&mut self, _subnet: &ipv4_classify::Subnet) {
+        self.created += 1;
+    }
+
+    fn on_file_done(&mut self, _file_name: &std::path::Path, _addresses: usize) {
+        self.files_done += 1;
+    }
+}
+
+#[test]
+fn observer_sees_progress() {
+    let mut counter = Counter::default();
+    ipv4_classify::find_groups_observed(
+        ["tests/res/invalid_ips.csv"],
+        &ipv4_classify::GroupBy::Subnet,
+        true,
+        &ipv4_classify::CancelToken::new(),
+        &mut counter,
+    )
+    .unwrap();
+    assert_eq!(1, counter.parsed);
+    assert_eq!(0, counter.created);
+    assert_eq!(1, counter.files_done);
+}
+
+#[cfg(feature = "parallel")]
+#[test]
+fn parallel_matches_sequential() {
+    let files = ["tests/res/valid_ips.csv", "tests/res/valid_ips.csv"];
+    let group_by = ipv4_classify::GroupBy::Prefix(8);
+    let sorted = |mut groups: ipv4_classify::SubnetsMap| {
+        groups.values_mut().for_each(|addrs| addrs.sort());
+        groups
+    };
+    assert_eq!(
+        sorted(ipv4_classify::find_groups(files, &group_by).unwrap()),
+        sorted(ipv4_classify::find_groups_parallel(files, &group_by).unwrap())
+    );
+}
+
+#[cfg(feature = "dedup")]
+#[test]
+fn dedup_drops_repeats() {
+    use std::str::FromStr;
+
+    let group_by = ipv4_classify::GroupBy::Subnet;
+    let mut filter = ipv4_classify::DuplicateFilter::new();
+    let groups = ipv4_classify::find_groups_dedup(
+        ["tests/res/valid_ips.csv", "tests/res/valid_ips.csv"],
+        &group_by,
+        &mut filter,
+    )
+    .unwrap();
+    assert_eq!(
+        ipv4_classify::find_groups(["tests/res/valid_ips.csv"], &group_by).unwrap(),
+        groups
+    );
+    let addr = ipv4_classify::Subnet::from_str("10.0.0.1").unwrap();
+    assert_eq!(2, filter.count(&addr));
+}
+
+#[cfg(feature = "persist")]
+#[test]
+fn tree_saved_and_loaded() {
+    use ipv4_classify::{AddressTree, Subnet};
+    use std::str::FromStr;
+
+    let boundary = Subnet::from_str("10.0.0.0/25").unwrap();
+    let mut tree = AddressTree::with_boundaries([boundary]);
+    for addr in ["10.0.0.1", "10.0.0.2", "10.0.0.200", "10.0.0.1"] {
+        tree.push(Subnet::from_str(addr).unwrap()).unwrap();
+    }
+    let file_name = std::env::temp_dir().join("ipv4_classify_saved.tree");
+    tree.save(&file_name).unwrap();
+    let mut loaded = AddressTree::load(&file_name).unwrap();
+    std::fs::remove_file(&file_name).unwrap();
+    assert_eq!(tree.to_string(), loaded.to_string());
+    assert_eq!(tree.to_bytes(), loaded.to_bytes());
+    // the boundary still holds for addresses pushed after loading
+    loaded
+        .push(Subnet::from_str("10.0.0.201").unwrap())
+        .unwrap();
+    assert!(loaded.get_subnets_map().contains_key("10.0.0.200/31"));
+
+    let bytes = tree.to_bytes();
+    assert!(AddressTree::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    assert!(matches!(
+        AddressTree::load("non.tree"),
+        Err(Error::FileNotFound(_))
+    ));
+}
+
+#[cfg(feature = "sqlite")]
+#[test]
+fn sqlite_export() {
+    use ipv4_classify::{GroupBy, Labels, Metadata, Report};
+
+    let subnets = ipv4_classify::group_lines(
+        ["10.0.0.1", "10.0.0.2", "192.168.0.1"],
+        &GroupBy::Prefix(24),
+    )
+    .unwrap();
+    let labels = Labels::from_lines(["10.0.0.0/8 = corp"]).unwrap();
+    let metadata = Metadata::from_lines(["ip,user", "10.0.0.1,alice"]).unwrap();
+    let report = Report::new(&subnets, &labels, &metadata);
+    let file_name = std::env::temp_dir().join("ipv4_classify_export.db");
+    // the second export replaces the first one
+    ipv4_classify::export_sqlite(&report, &file_name).unwrap();
+    ipv4_classify::export_sqlite(&report, &file_name).unwrap();
+
+    let connection = rusqlite::Connection::open(&file_name).unwrap();
+    let (name, label, size, addresses): (String, Option<String>, i64, i64) = connection
+        .query_row(
+            "SELECT name, label, size, addresses FROM subnets WHERE id = 1",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+        )
+        .unwrap();
+    assert_eq!(
+        ("10.0.0.0/24".to_string(), Some("corp".to_string()), 256, 2),
+        (name, label, size, addresses)
+    );
+    let user: String = connection
+        .query_row(
+            "SELECT e.value FROM enrichment e JOIN addresses a ON a.address = e.address
+             WHERE a.subnet_id = 1 AND e.name = 'user'",
+            [],
+            |row| row.get(0),
+        )
+        .unwrap();
+    assert_eq!("alice", user);
+    let count: i64 = connection
+        .query_row("SELECT count(*) FROM addresses", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(3, count);
+    drop(connection);
+    std::fs::remove_file(&file_name).unwrap();
+}
+
+#[cfg(feature = "parquet")]
+#[test]
+fn parquet_export() {
+    use ipv4_classify::{GroupBy, Labels, Metadata, Report, WeightsMap};
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+
+    let subnets = ipv4_classify::group_lines(
+        ["10.0.0.1", "10.0.0.2", "192.168.0.1"],
+        &GroupBy::Prefix(24),
+    )
+    .unwrap();
+    let labels = Labels::new();
+    let metadata = Metadata::from_lines(["ip,ASN,country", "10.0.0.1,64500,NL"]).unwrap();
+    let address_hits = WeightsMap::from([("10.0.0.1/32".to_string(), 7)]);
+    let report = Report::new(&subnets, &labels, &metadata).address_hits(Some(&address_hits));
+    let file_name = std::env::temp_dir().join("ipv4_classify_export.parquet");
+    ipv4_classify::export_parquet(&report, &file_name).unwrap();
+
+    let reader = SerializedFileReader::new(std::fs::File::open(&file_name).unwrap()).unwrap();
+    let rows: Vec<String> = reader
+        .get_row_iter(None)
+        .unwrap()
+        .map(|row| row.unwrap().to_string())
+        .collect();
+    assert_eq!(
+        vec![
+            r#"{address: "10.0.0.1/32", subnet: "10.0.0.0/24", count: 7, asn: "64500", country: "NL", kind: null, class: "private", score: null}"#,
+            r#"{address: "10.0.0.2/32", subnet: "10.0.0.0/24", count: 1, asn: null, country: null, kind: null, class: "private", score: null}"#,
+            r#"{address: "192.168.0.1/32", subnet: "192.168.0.0/24", count: 1, asn: null, country: null, kind: null, class: "private", score: null}"#,
+        ],
+        rows
+    );
+    std::fs::remove_file(&file_name).unwrap();
+}
+
+#[test]
+fn ndjson_streamed_while_reading() {
+    let mut out = vec![];
+    let mut sink = ipv4_classify::NdjsonSink::new(&mut out);
+    ipv4_classify::read_into_tree_observed(
+        &mut ipv4_classify::AddressTree::new(),
+        ["tests/res/valid_ips.csv"],
+        false,
+        &ipv4_classify::CancelToken::new(),
+        &mut sink,
+    )
+    .unwrap();
+    sink.finish().unwrap();
+    let lines: Vec<&str> = std::str::from_utf8(&out).unwrap().lines().collect();
+    assert_eq!(5, lines.len());
+    assert_eq!(
+        r#"{"address":"10.0.0.2/32","class":"private","subnet":"10.0.0.0/30","source":"tests/res/valid_ips.csv"}"#,
+        lines[1]
+    );
+    assert_eq!(
+        r#"{"address":"192.168.1.7/32","class":"private","subnet":"192.168.1.0/29","source":"tests/res/valid_ips.csv"}"#,
+        lines[4]
+    );
+}
+
+#[test]
+fn provenance_of_addresses() {
+    let mut address_tree = ipv4_classify::AddressTree::new();
+    let mut provenance = ipv4_classify::Provenance::new();
+    let failures = ipv4_classify::read_into_tree_observed(
+        &mut address_tree,
+        ["tests/res/valid_ips.csv", "tests/res/invalid_ips.csv"],
+        true,
+        &ipv4_classify::CancelToken::new(),
+        &mut provenance,
+    )
+    .unwrap();
+    assert_eq!(1, failures.len());
+    let addr = |text: &str| text.parse::<ipv4_classify::Subnet>().unwrap();
+    assert_eq!(
+        vec!["tests/res/valid_ips.csv"],
+        provenance.sources_of(&addr("10.0.0.1"))
+    );
+    assert_eq!(
+        vec!["tests/res/invalid_ips.csv"],
+        provenance.sources_of(&addr("102.1.2.3"))
+    );
+    let sources = provenance
+        .get_sources_map(&address_tree.get_groups_map(&ipv4_classify::GroupBy::Prefix(0)));
+    assert_eq!(
+        vec!["tests/res/valid_ips.csv", "tests/res/invalid_ips.csv"],
+        sources["0.0.0.0/0"]
+    );
+}
+
+#[cfg(all(feature = "cli", feature = "template"))]
+#[test]
+fn template_written_to_output() {
+    let template = std::env::temp_dir().join("ipv4_classify_report.hbs");
+    let output = std::env::temp_dir().join("ipv4_classify_report.txt");
+    std::fs::write(
+        &template,
+        "{{#each subnets}}{{subnet}} {{count}}\n{{/each}}",
+    )
+    .unwrap();
+    let status = std::process::Command::new(env!("CARGO_BIN_EXE_ipv4-classify"))
+        .args(["-f", "tests/res/valid_ips.csv", "--template"])
+        .arg(&template)
+        .arg("--output")
+        .arg(&output)
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .status()
+        .unwrap();
+    assert!(status.success());
+    assert_eq!(
+        "10.0.0.0/23 3\n192.168.1.0/29 2\n",
+        std::fs::read_to_string(&output).unwrap()
+    );
+    std::fs::remove_file(&template).unwrap();
+    std::fs::remove_file(&output).unwrap();
 }